@@ -1,51 +1,1079 @@
 // Nu Language AST Definitions
 // 这些将用于Nu2Rust转换器
+//
+// 由 parser::Parser 生成；每个节点都带有 Span，指向 Nu 源码位置。
 
+// AST 节点按值内嵌，变体大小差异是预期的
+#![allow(clippy::large_enum_variant)]
+
+use std::fmt;
+use std::sync::Arc;
+
+/// 源码位置（行列从 1 开始，len 为覆盖的字节长度）
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Span {
+    /// 源文件路径；解析字符串时为 None
+    pub file: Option<Arc<str>>,
+    pub line: usize,
+    pub col: usize,
+    pub len: usize,
+}
+
+impl Span {
+    pub fn new(line: usize, col: usize, len: usize) -> Self {
+        Self {
+            file: None,
+            line,
+            col,
+            len,
+        }
+    }
+
+    pub fn with_file(mut self, file: Option<Arc<str>>) -> Self {
+        self.file = file;
+        self
+    }
+
+    /// 渲染该位置所在行及下方的插入符（rustc 风格片段，不含标题行）
+    pub fn snippet(&self, source: &str) -> String {
+        let Some(text) = source.lines().nth(self.line.saturating_sub(1)) else {
+            return String::new();
+        };
+        let text = text.trim_end_matches('\r');
+        let prefix: String = text.chars().take(self.col.saturating_sub(1)).collect();
+        let mut width = 0;
+        let mut bytes = 0;
+        for c in text[prefix.len()..].chars() {
+            if bytes >= self.len {
+                break;
+            }
+            bytes += c.len_utf8();
+            width += 1;
+        }
+        // 保留制表符，使插入符与源码对齐
+        let indent: String = prefix
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let gutter = self.line.to_string();
+        let pad = " ".repeat(gutter.len());
+        format!(
+            "{pad} |\n{gutter} | {text}\n{pad} | {indent}{}",
+            "^".repeat(width.max(1))
+        )
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{}:{}:{}", file, self.line, self.col),
+            None => write!(f, "{}:{}", self.line, self.col),
+        }
+    }
+}
+
+// ============ 顶级结构 ============
+
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct NuFile {
+    /// 文件级属性：#![...] 与 //! 文档
+    pub attrs: Vec<NuAttr>,
     pub items: Vec<NuItem>,
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub enum NuItem {
     Fn(NuFn),
     Struct(NuStruct),
+    /// UN / un
+    Union(NuUnion),
     Enum(NuEnum),
     Trait(NuTrait),
+    /// TR Name = Bound + Bound;
+    TraitAlias(NuTraitAlias),
     Impl(NuImpl),
     Use(NuUse),
     Mod(NuMod),
+    /// C / CP
+    Const(NuConst),
+    /// ST / SP / SM / SMP
+    Static(NuStatic),
+    /// t Name = Type
+    TypeAlias(NuTypeAlias),
+    /// EXT "C" { ... }
+    Extern(NuExtern),
+    /// EXT crate name [as alias];
+    ExternCrate(NuExternCrate),
+    /// macro_rules! 定义或条目位置的宏调用
+    Macro(NuMacroItem),
+}
+
+impl NuItem {
+    pub fn span(&self) -> &Span {
+        match self {
+            NuItem::Fn(i) => &i.span,
+            NuItem::Struct(i) => &i.span,
+            NuItem::Union(i) => &i.span,
+            NuItem::Enum(i) => &i.span,
+            NuItem::Trait(i) => &i.span,
+            NuItem::TraitAlias(i) => &i.span,
+            NuItem::Impl(i) => &i.span,
+            NuItem::Use(i) => &i.span,
+            NuItem::Mod(i) => &i.span,
+            NuItem::Const(i) => &i.span,
+            NuItem::Static(i) => &i.span,
+            NuItem::TypeAlias(i) => &i.span,
+            NuItem::Extern(i) => &i.span,
+            NuItem::ExternCrate(i) => &i.span,
+            NuItem::Macro(i) => &i.span,
+        }
+    }
+}
+
+/// 可见性
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Visibility {
+    #[default]
+    Private,
+    Public,
+    /// pub(crate)
+    Crate,
+    /// pub(super)
+    Super,
+    /// pub(self)
+    SelfMod,
+    /// pub(in path)
+    In(String),
+}
+
+impl Visibility {
+    pub fn is_public(&self) -> bool {
+        !matches!(self, Visibility::Private)
+    }
+}
+
+// ============ 属性 ============
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NuAttr {
+    pub kind: AttrKind,
+    /// #![...] / //! 为内部属性
+    pub is_inner: bool,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttrKind {
+    /// #D(Debug, Clone)
+    Derive(Vec<String>),
+    /// #I
+    Inline,
+    /// /// 或 //! 文档注释（不含前缀）
+    Doc(String),
+    /// #[...] 的原始内容，如 `cfg(test)`
+    Meta(String),
+}
+
+// ============ 泛型 ============
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct NuGenerics {
+    pub params: Vec<GenericParam>,
+    pub where_clause: Vec<WherePredicate>,
+}
+
+impl NuGenerics {
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty() && self.where_clause.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GenericParam {
+    /// 'a: 'b
+    Lifetime {
+        name: String,
+        bounds: Vec<String>,
+        span: Span,
+    },
+    /// T: Clone = Default
+    Type {
+        name: String,
+        bounds: Vec<TypeBound>,
+        default: Option<NuType>,
+        span: Span,
+    },
+    /// const N: usize
+    Const {
+        name: String,
+        ty: NuType,
+        default: Option<NuExpr>,
+        span: Span,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeBound {
+    /// 'a
+    Lifetime(String),
+    /// Clone / ?Sized / for<'a> Fn(&'a T)
+    Trait {
+        path: NuPath,
+        maybe: bool,
+        for_lifetimes: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WherePredicate {
+    /// T: Clone + Send
+    Type {
+        ty: NuType,
+        bounds: Vec<TypeBound>,
+        for_lifetimes: Vec<String>,
+        span: Span,
+    },
+    /// 'a: 'b
+    Lifetime {
+        name: String,
+        bounds: Vec<String>,
+        span: Span,
+    },
+}
+
+// ============ 路径 ============
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NuPath {
+    /// 以 :: 开头
+    pub leading_colon: bool,
+    pub segments: Vec<PathSegment>,
+    pub span: Span,
+}
+
+impl NuPath {
+    /// 单段路径的标识符
+    pub fn as_ident(&self) -> Option<&str> {
+        match self.segments.as_slice() {
+            [seg] if !self.leading_colon && seg.args.is_none() => Some(&seg.ident),
+            _ => None,
+        }
+    }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathSegment {
+    pub ident: String,
+    pub args: Option<GenericArgs>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GenericArgs {
+    /// <T, 'a, Item = U>；turbofish 表示是否带 ::
+    AngleBracketed {
+        args: Vec<GenericArg>,
+        turbofish: bool,
+    },
+    /// Fn(A, B) -> C
+    Parenthesized {
+        inputs: Vec<NuType>,
+        output: Option<Box<NuType>>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GenericArg {
+    Lifetime(String),
+    Type(NuType),
+    /// 常量参数 { N + 1 } 或字面量
+    Const(NuExpr),
+    /// Item = T
+    Binding {
+        name: String,
+        ty: NuType,
+    },
+    /// Item: Bound
+    Constraint {
+        name: String,
+        bounds: Vec<TypeBound>,
+    },
+}
+
+// ============ 类型 ============
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NuType {
+    pub kind: TypeKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeKind {
+    /// V<T>、std::io::Result<()>（缩写保持原样，由后端展开）
+    Path(NuPath),
+    /// <T as Trait>::Assoc
+    QSelf {
+        ty: Box<NuType>,
+        trait_path: Option<NuPath>,
+        rest: Vec<PathSegment>,
+    },
+    /// &'a T / &!T
+    Ref {
+        lifetime: Option<String>,
+        mutable: bool,
+        ty: Box<NuType>,
+    },
+    /// *const T / *!T
+    Ptr {
+        mutable: bool,
+        ty: Box<NuType>,
+    },
+    Tuple(Vec<NuType>),
+    Slice(Box<NuType>),
+    Array {
+        ty: Box<NuType>,
+        len: Box<NuExpr>,
+    },
+    /// fn(A) -> B
+    Fn {
+        is_unsafe: bool,
+        abi: Option<String>,
+        inputs: Vec<NuType>,
+        output: Option<Box<NuType>>,
+    },
+    /// impl Trait
+    ImplTrait(Vec<TypeBound>),
+    /// dyn Trait
+    DynTrait(Vec<TypeBound>),
+    /// !
+    Never,
+    /// _
+    Infer,
+    /// 类型位置的宏
+    Macro(NuMacroCall),
+}
+
+// ============ 条目 ============
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct NuFn {
+    pub attrs: Vec<NuAttr>,
     pub name: String,
+    /// F = pub fn, f = fn；受限可见性（pub(crate) f）单独记录
     pub is_public: bool,
+    pub vis: Visibility,
     pub is_async: bool,
+    pub is_unsafe: bool,
+    pub is_const: bool,
+    /// EXT "C" f / extern "C" fn；无显式 ABI 时为 Some("")
+    pub abi: Option<String>,
+    pub generics: NuGenerics,
+    pub self_param: Option<SelfParam>,
+    pub params: Vec<Param>,
+    /// EXT 块中的可变参数：f printf(fmt: *const u8, ...)
+    pub variadic: bool,
+    pub ret: Option<NuType>,
+    /// trait 中无默认实现的方法为 None
+    pub body: Option<NuBlock>,
+    pub span: Span,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelfParam {
+    /// self / !self (mut self)
+    Value { mutable: bool },
+    /// &self / &'a self / &!self
+    Ref {
+        lifetime: Option<String>,
+        mutable: bool,
+    },
+    /// self: Box<Self>
+    Typed { mutable: bool, ty: NuType },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub attrs: Vec<NuAttr>,
+    pub pat: NuPat,
+    pub ty: NuType,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct NuStruct {
+    pub attrs: Vec<NuAttr>,
     pub name: String,
+    /// S = pub struct, s = struct
     pub is_public: bool,
+    pub vis: Visibility,
+    pub generics: NuGenerics,
+    pub fields: Fields,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fields {
+    /// { a: T, b: U }
+    Named(Vec<Field>),
+    /// (T, U)
+    Tuple(Vec<Field>),
+    Unit,
 }
 
+impl Fields {
+    pub fn len(&self) -> usize {
+        match self {
+            Fields::Named(f) | Fields::Tuple(f) => f.len(),
+            Fields::Unit => 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub attrs: Vec<NuAttr>,
+    /// 源码中显式写出的可见性（nu2rust 旧路径会为 S 的字段补 pub）
+    pub vis: Visibility,
+    /// 元组字段为 None
+    pub name: Option<String>,
+    pub ty: NuType,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NuUnion {
+    pub attrs: Vec<NuAttr>,
+    pub name: String,
+    /// UN = pub union, un = union
+    pub is_public: bool,
+    pub vis: Visibility,
+    pub generics: NuGenerics,
+    pub fields: Vec<Field>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct NuEnum {
+    pub attrs: Vec<NuAttr>,
     pub name: String,
+    /// 可见性由标识符首字母决定（Go风格）
     pub is_public: bool,
+    pub vis: Visibility,
+    pub generics: NuGenerics,
+    pub variants: Vec<Variant>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variant {
+    pub attrs: Vec<NuAttr>,
+    pub name: String,
+    pub fields: Fields,
+    /// A = 1
+    pub discriminant: Option<NuExpr>,
+    pub span: Span,
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct NuTrait {
+    pub attrs: Vec<NuAttr>,
     pub name: String,
+    /// TR = pub trait, tr = trait
     pub is_public: bool,
+    pub vis: Visibility,
+    pub is_unsafe: bool,
+    pub generics: NuGenerics,
+    pub supertraits: Vec<TypeBound>,
+    pub items: Vec<TraitItem>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NuTraitAlias {
+    pub attrs: Vec<NuAttr>,
+    pub name: String,
+    /// TR = pub trait, tr = trait
+    pub is_public: bool,
+    pub vis: Visibility,
+    pub generics: NuGenerics,
+    pub bounds: Vec<TypeBound>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TraitItem {
+    Fn(NuFn),
+    /// C N: usize [= 3];
+    Const(NuConst),
+    /// t Item[: Bound] [= Default];
+    Type(NuTypeAlias),
+    Macro(NuMacroItem),
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct NuImpl {
+    pub attrs: Vec<NuAttr>,
+    /// 实现目标类型的源码形式（兼容旧接口）
     pub type_name: String,
+    pub is_unsafe: bool,
+    pub generics: NuGenerics,
+    /// I Trait for Type
+    pub trait_ref: Option<NuPath>,
+    /// I !Trait for Type
+    pub negative: bool,
+    pub self_ty: NuType,
+    pub items: Vec<ImplItem>,
+    pub span: Span,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImplItem {
+    Fn(NuFn),
+    Const(NuConst),
+    Type(NuTypeAlias),
+    Macro(NuMacroItem),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NuConst {
+    pub attrs: Vec<NuAttr>,
+    pub name: String,
+    /// CP = pub const, C = const
+    pub is_public: bool,
+    pub vis: Visibility,
+    pub ty: NuType,
+    /// trait 中无默认值时为 None
+    pub value: Option<NuExpr>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NuStatic {
+    pub attrs: Vec<NuAttr>,
+    pub name: String,
+    /// SP / SMP = pub
+    pub is_public: bool,
+    pub vis: Visibility,
+    /// SM / SMP = static mut
+    pub mutable: bool,
+    pub ty: NuType,
+    /// EXT 块中的声明为 None
+    pub value: Option<NuExpr>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NuTypeAlias {
+    pub attrs: Vec<NuAttr>,
+    pub name: String,
+    pub is_public: bool,
+    pub vis: Visibility,
+    pub generics: NuGenerics,
+    /// 关联类型约束：t Item: Clone;
+    pub bounds: Vec<TypeBound>,
+    /// 关联类型声明（无默认值）与 EXT 块中的不透明类型为 None
+    pub ty: Option<NuType>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NuExtern {
+    pub attrs: Vec<NuAttr>,
+    pub is_unsafe: bool,
+    /// EXT "C" { } 的 ABI 字符串（含引号的原始文本）
+    pub abi: Option<String>,
+    pub items: Vec<ForeignItem>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NuExternCrate {
+    pub attrs: Vec<NuAttr>,
+    pub vis: Visibility,
+    /// crate 名或 `self`
+    pub name: String,
+    /// EXT crate foo as bar;
+    pub rename: Option<String>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ForeignItem {
+    Fn(NuFn),
+    Static(NuStatic),
+    Type(NuTypeAlias),
+    Macro(NuMacroItem),
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct NuUse {
+    pub attrs: Vec<NuAttr>,
+    /// use 树的源码形式，如 `std::io::{self, Write}`
     pub path: String,
+    /// U = pub use
     pub is_public: bool,
+    pub vis: Visibility,
+    pub tree: UseTree,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UseTree {
+    /// std::io
+    Path { ident: String, tree: Box<UseTree> },
+    /// HashMap / self
+    Name(String),
+    /// Foo as Bar
+    Rename { ident: String, rename: String },
+    /// *
+    Glob,
+    /// {a, b}
+    Group(Vec<UseTree>),
+}
+
+impl UseTree {
+    /// 展开为所有叶子路径，如 `std::io::{self, Write}` → [std::io::self, std::io::Write]
+    pub fn flatten(&self) -> Vec<String> {
+        match self {
+            UseTree::Path { ident, tree } => tree
+                .flatten()
+                .into_iter()
+                .map(|rest| format!("{}::{}", ident, rest))
+                .collect(),
+            UseTree::Name(name) => vec![name.clone()],
+            UseTree::Rename { ident, .. } => vec![ident.clone()],
+            UseTree::Glob => vec!["*".to_string()],
+            UseTree::Group(trees) => trees.iter().flat_map(|t| t.flatten()).collect(),
+        }
+    }
+
+    /// 引入作用域的名称，如 `a::{self, B as C, D}` → [a, C, D]；glob 不计
+    pub fn bound_names(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        self.collect_bound_names(None, &mut names);
+        names
+    }
+
+    fn collect_bound_names(&self, parent: Option<&str>, names: &mut Vec<String>) {
+        match self {
+            UseTree::Path { ident, tree } => tree.collect_bound_names(Some(ident), names),
+            UseTree::Name(name) if name == "self" => names.extend(parent.map(String::from)),
+            UseTree::Name(name) => names.push(name.clone()),
+            UseTree::Rename { rename, .. } if rename != "_" => names.push(rename.clone()),
+            UseTree::Rename { .. } | UseTree::Glob => {}
+            UseTree::Group(trees) => {
+                for tree in trees {
+                    tree.collect_bound_names(parent, names);
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for UseTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UseTree::Path { ident, tree } => write!(f, "{}::{}", ident, tree),
+            UseTree::Name(name) => write!(f, "{}", name),
+            UseTree::Rename { ident, rename } => write!(f, "{} as {}", ident, rename),
+            UseTree::Glob => write!(f, "*"),
+            UseTree::Group(trees) => {
+                let parts: Vec<String> = trees.iter().map(|t| t.to_string()).collect();
+                write!(f, "{{{}}}", parts.join(", "))
+            }
+        }
+    }
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct NuMod {
+    pub attrs: Vec<NuAttr>,
     pub name: String,
+    /// DM = pub mod, D = mod
     pub is_public: bool,
+    pub vis: Visibility,
+    /// D name { ... } 为 Some；D name（外部文件）为 None
+    pub items: Option<Vec<NuItem>>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NuMacroItem {
+    pub attrs: Vec<NuAttr>,
+    pub mac: NuMacroCall,
+    /// macro_rules! name { ... } 中的 name
+    pub name: Option<String>,
+    pub span: Span,
+}
+
+/// 宏调用：宏体按源码原样保存
+#[derive(Debug, Clone, PartialEq)]
+pub struct NuMacroCall {
+    pub path: NuPath,
+    pub delimiter: MacroDelimiter,
+    /// 分隔符内部的原始源码
+    pub tokens: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MacroDelimiter {
+    Paren,
+    Bracket,
+    Brace,
+}
+
+// ============ 语句 / 块 ============
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NuBlock {
+    pub stmts: Vec<NuStmt>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NuStmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StmtKind {
+    /// l / v
+    Let(NuLocal),
+    Item(Box<NuItem>),
+    /// 表达式；has_semi 表示是否以 ; 结尾
+    Expr {
+        expr: NuExpr,
+        has_semi: bool,
+    },
+    /// 单独的 ;
+    Empty,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NuLocal {
+    pub attrs: Vec<NuAttr>,
+    /// v = let mut
+    pub is_mut: bool,
+    pub pat: NuPat,
+    pub ty: Option<NuType>,
+    pub init: Option<NuExpr>,
+    /// let-else
+    pub else_block: Option<NuBlock>,
+}
+
+// ============ 模式 ============
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NuPat {
+    pub kind: PatKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatKind {
+    /// _
+    Wild,
+    /// ..
+    Rest,
+    /// ref mut x @ sub
+    Ident {
+        by_ref: bool,
+        mutable: bool,
+        name: String,
+        sub: Option<Box<NuPat>>,
+    },
+    Lit(Box<NuExpr>),
+    /// 1..=5
+    Range {
+        start: Option<Box<NuExpr>>,
+        end: Option<Box<NuExpr>>,
+        inclusive: bool,
+    },
+    Path(NuPath),
+    /// Some(x)
+    TupleStruct {
+        path: NuPath,
+        elems: Vec<NuPat>,
+    },
+    /// P { x, y: 0, .. }
+    Struct {
+        path: NuPath,
+        fields: Vec<FieldPat>,
+        rest: bool,
+    },
+    Tuple(Vec<NuPat>),
+    Slice(Vec<NuPat>),
+    /// &x / &!x
+    Ref {
+        mutable: bool,
+        pat: Box<NuPat>,
+    },
+    /// a | b
+    Or(Vec<NuPat>),
+    Macro(NuMacroCall),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldPat {
+    pub name: String,
+    /// 简写 `x` 时为 None
+    pub pat: Option<NuPat>,
+    pub span: Span,
+}
+
+// ============ 表达式 ============
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NuExpr {
+    pub attrs: Vec<NuAttr>,
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    /// 字面量原始文本
+    Lit(Lit),
+    Path(NuPath),
+    /// <T as Trait>::f
+    QPath(NuType),
+    Unary {
+        op: UnOp,
+        expr: Box<NuExpr>,
+    },
+    Binary {
+        op: BinOp,
+        lhs: Box<NuExpr>,
+        rhs: Box<NuExpr>,
+    },
+    Assign {
+        lhs: Box<NuExpr>,
+        rhs: Box<NuExpr>,
+    },
+    /// a += b
+    AssignOp {
+        op: BinOp,
+        lhs: Box<NuExpr>,
+        rhs: Box<NuExpr>,
+    },
+    /// &x / &!x
+    Ref {
+        mutable: bool,
+        expr: Box<NuExpr>,
+    },
+    Call {
+        func: Box<NuExpr>,
+        args: Vec<NuExpr>,
+    },
+    MethodCall {
+        receiver: Box<NuExpr>,
+        method: String,
+        turbofish: Option<GenericArgs>,
+        args: Vec<NuExpr>,
+    },
+    Field {
+        expr: Box<NuExpr>,
+        name: String,
+    },
+    Index {
+        expr: Box<NuExpr>,
+        index: Box<NuExpr>,
+    },
+    /// 后缀 ! (Nu) 或 ? (Rust)
+    Try(Box<NuExpr>),
+    /// .~
+    Await(Box<NuExpr>),
+    /// x a T
+    Cast {
+        expr: Box<NuExpr>,
+        ty: NuType,
+    },
+    Tuple(Vec<NuExpr>),
+    Array(Vec<NuExpr>),
+    /// [x; n]
+    Repeat {
+        expr: Box<NuExpr>,
+        len: Box<NuExpr>,
+    },
+    /// P { a: 1, ..base }
+    Struct {
+        path: NuPath,
+        fields: Vec<FieldInit>,
+        base: Option<Box<NuExpr>>,
+    },
+    Range {
+        start: Option<Box<NuExpr>>,
+        end: Option<Box<NuExpr>>,
+        inclusive: bool,
+    },
+    Paren(Box<NuExpr>),
+    Block {
+        label: Option<String>,
+        block: NuBlock,
+    },
+    Unsafe(NuBlock),
+    /// ~{ } / ~$ { }
+    Async {
+        is_move: bool,
+        block: NuBlock,
+    },
+    If {
+        cond: Box<NuExpr>,
+        then_branch: NuBlock,
+        else_branch: Option<Box<NuExpr>>,
+    },
+    /// if let 条件中的 `let pat = expr`
+    Let {
+        pat: NuPat,
+        expr: Box<NuExpr>,
+    },
+    /// M expr { arms }
+    Match {
+        expr: Box<NuExpr>,
+        arms: Vec<MatchArm>,
+    },
+    /// L { }
+    Loop {
+        label: Option<String>,
+        body: NuBlock,
+    },
+    /// L pat: iter { } / L pat in iter { }
+    ForLoop {
+        label: Option<String>,
+        pat: NuPat,
+        iter: Box<NuExpr>,
+        body: NuBlock,
+    },
+    While {
+        label: Option<String>,
+        cond: Box<NuExpr>,
+        body: NuBlock,
+    },
+    /// |x| / $|x|
+    Closure {
+        is_move: bool,
+        is_async: bool,
+        params: Vec<ClosureParam>,
+        ret: Option<NuType>,
+        body: Box<NuExpr>,
+    },
+    /// < expr
+    Return(Option<Box<NuExpr>>),
+    /// br 'label expr
+    Break {
+        label: Option<String>,
+        expr: Option<Box<NuExpr>>,
+    },
+    /// ct 'label
+    Continue {
+        label: Option<String>,
+    },
+    Macro(NuMacroCall),
+    /// @ expr：tokio::spawn(async move { expr })
+    Spawn(Box<NuExpr>),
+    /// @@ expr：thread::spawn(move || expr)
+    Thread(Box<NuExpr>),
+    /// << rx：rx.recv()
+    Recv(Box<NuExpr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Lit {
+    Int(String),
+    Float(String),
+    Str(String),
+    ByteStr(String),
+    Char(String),
+    Byte(String),
+    Bool(bool),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnOp {
+    /// -
+    Neg,
+    /// !
+    Not,
+    /// *
+    Deref,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    And,
+    Or,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl BinOp {
+    /// Rust 运算符文本
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+            BinOp::Rem => "%",
+            BinOp::And => "&&",
+            BinOp::Or => "||",
+            BinOp::BitAnd => "&",
+            BinOp::BitOr => "|",
+            BinOp::BitXor => "^",
+            BinOp::Shl => "<<",
+            BinOp::Shr => ">>",
+            BinOp::Eq => "==",
+            BinOp::Ne => "!=",
+            BinOp::Lt => "<",
+            BinOp::Le => "<=",
+            BinOp::Gt => ">",
+            BinOp::Ge => ">=",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldInit {
+    /// 命名字段或元组下标（如 `0`）
+    pub name: String,
+    /// 简写 `P { x }` 时为 None
+    pub expr: Option<NuExpr>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchArm {
+    pub attrs: Vec<NuAttr>,
+    pub pat: NuPat,
+    pub guard: Option<NuExpr>,
+    pub body: NuExpr,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClosureParam {
+    pub pat: NuPat,
+    pub ty: Option<NuType>,
+    pub span: Span,
 }
@@ -3,9 +3,11 @@
 
 use anyhow::{Context, Result};
 use clap::Parser;
+use nu_compiler::project::{NuPackage, NuProject, ProjectManager};
+use nu_compiler::workspace::WorkspaceConfig;
+use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
-use toml::Value;
 
 #[derive(Parser, Debug)]
 #[command(name = "nu2cmake")]
@@ -32,28 +34,22 @@ fn main() -> Result<()> {
         println!("Input: {:?}", args.input);
     }
 
-    let nu_toml_path = args.input.join("Nu.toml");
-    if !nu_toml_path.exists() {
+    if !args.input.join("Nu.toml").exists() {
         anyhow::bail!("Nu.toml not found in {:?}", args.input);
     }
-
-    // 读取 Nu.toml
-    let toml_content = fs::read_to_string(&nu_toml_path)
-        .with_context(|| format!("Failed to read Nu.toml: {:?}", nu_toml_path))?;
-
-    let toml_value: Value =
-        toml::from_str(&toml_content).with_context(|| "Failed to parse Nu.toml")?;
+    let project = ProjectManager::new()
+        .load(&args.input)
+        .with_context(|| format!("Failed to load Nu project: {:?}", args.input))?;
 
     // 确定输出目录
     let output_dir = args.output.as_ref().unwrap_or(&args.input);
 
-    // 检查是否是 workspace
-    if toml_value.get("W").is_some() || toml_value.get("workspace").is_some() {
+    if let Some(workspace) = &project.workspace {
         // Workspace 项目
-        convert_workspace(&toml_value, &args.input, output_dir, args.verbose)?;
-    } else {
+        convert_workspace(&project, workspace, output_dir, args.verbose)?;
+    } else if let Some(package) = &project.package {
         // 单项目
-        convert_single_project(&toml_value, output_dir, args.verbose)?;
+        convert_single_project(package, output_dir, args.verbose)?;
     }
 
     if args.verbose {
@@ -63,8 +59,8 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn convert_single_project(toml: &Value, output_dir: &Path, verbose: bool) -> Result<()> {
-    let cmake_content = generate_cmake_for_project(toml, None)?;
+fn convert_single_project(package: &NuPackage, output_dir: &Path, verbose: bool) -> Result<()> {
+    let cmake_content = generate_cmake_for_project(package, false);
 
     let output_path = output_dir.join("CMakeLists.txt");
     fs::write(&output_path, cmake_content)
@@ -78,13 +74,13 @@ fn convert_single_project(toml: &Value, output_dir: &Path, verbose: bool) -> Res
 }
 
 fn convert_workspace(
-    toml: &Value,
-    input_dir: &Path,
+    project: &NuProject,
+    workspace: &WorkspaceConfig,
     output_dir: &Path,
     verbose: bool,
 ) -> Result<()> {
     // 生成根 CMakeLists.txt
-    let root_cmake = generate_workspace_root_cmake(toml)?;
+    let root_cmake = generate_workspace_root_cmake(project, workspace);
     let root_cmake_path = output_dir.join("CMakeLists.txt");
 
     fs::create_dir_all(output_dir)?;
@@ -95,33 +91,33 @@ fn convert_workspace(
         println!("✓ Generated: {:?}", root_cmake_path);
     }
 
-    // 获取成员列表
-    let members = get_workspace_members(toml)?;
-
     // 为每个成员生成 CMakeLists.txt
-    for member in members {
-        let member_toml_path = input_dir.join(&member).join("Nu.toml");
-        if member_toml_path.exists() {
-            let member_toml_content = fs::read_to_string(&member_toml_path)?;
-            let member_toml: Value = toml::from_str(&member_toml_content)?;
-
-            let member_cmake = generate_cmake_for_project(&member_toml, Some(toml))?;
-            let member_output_dir = output_dir.join(&member);
-            fs::create_dir_all(&member_output_dir)?;
+    for member in &project.members {
+        let member_cmake = generate_cmake_for_project(member, true);
+        let member_output_dir = output_dir.join(member_dir(project, member));
+        fs::create_dir_all(&member_output_dir)?;
 
-            let member_cmake_path = member_output_dir.join("CMakeLists.txt");
-            fs::write(&member_cmake_path, member_cmake)?;
+        let member_cmake_path = member_output_dir.join("CMakeLists.txt");
+        fs::write(&member_cmake_path, member_cmake)?;
 
-            if verbose {
-                println!("✓ Generated: {:?}", member_cmake_path);
-            }
+        if verbose {
+            println!("✓ Generated: {:?}", member_cmake_path);
         }
     }
 
     Ok(())
 }
 
-fn generate_workspace_root_cmake(toml: &Value) -> Result<String> {
+/// 成员相对工作区根目录的路径
+fn member_dir(project: &NuProject, member: &NuPackage) -> PathBuf {
+    member
+        .dir
+        .strip_prefix(&project.root)
+        .unwrap_or(&member.dir)
+        .to_path_buf()
+}
+
+fn generate_workspace_root_cmake(project: &NuProject, workspace: &WorkspaceConfig) -> String {
     let mut cmake = String::new();
 
     cmake.push_str("cmake_minimum_required(VERSION 3.15)\n");
@@ -130,48 +126,36 @@ fn generate_workspace_root_cmake(toml: &Value) -> Result<String> {
     cmake.push_str("set(CMAKE_CXX_STANDARD_REQUIRED ON)\n\n");
 
     // Workspace 共享依赖
-    if let Some(workspace_deps) = toml
-        .get("W")
-        .and_then(|w| w.get("D"))
-        .or_else(|| toml.get("workspace").and_then(|w| w.get("dependencies")))
-    {
+    if !workspace.dependencies.is_empty() {
         cmake.push_str("# Workspace shared dependencies\n");
-        if let Value::Table(deps) = workspace_deps {
-            for (name, _version) in deps {
-                let pkg_name = dependency_to_cmake_package(name);
-                cmake.push_str(&format!("find_package({} REQUIRED)\n", pkg_name));
-            }
+        for name in sorted_keys(&workspace.dependencies) {
+            let pkg_name = dependency_to_cmake_package(name);
+            cmake.push_str(&format!("find_package({} REQUIRED)\n", pkg_name));
         }
         cmake.push('\n');
     }
 
     // 添加子项目
-    let members = get_workspace_members(toml)?;
     cmake.push_str("# Add subdirectories\n");
-    for member in members {
-        cmake.push_str(&format!("add_subdirectory({})\n", member));
+    for member in &project.members {
+        cmake.push_str(&format!(
+            "add_subdirectory({})\n",
+            member_dir(project, member).display()
+        ));
     }
 
-    Ok(cmake)
+    cmake
 }
 
-fn generate_cmake_for_project(toml: &Value, workspace_toml: Option<&Value>) -> Result<String> {
+fn generate_cmake_for_project(package: &NuPackage, is_member: bool) -> String {
     let mut cmake = String::new();
 
     // 项目信息
-    let package = toml.get("P").or_else(|| toml.get("package"));
-    let project_name = package
-        .and_then(|p| p.get("id").or_else(|| p.get("n")).or_else(|| p.get("name")))
-        .and_then(|v| v.as_str())
-        .unwrap_or("nu_project");
-
-    let version = package
-        .and_then(|p| p.get("v").or_else(|| p.get("version")))
-        .and_then(|v| v.as_str())
-        .unwrap_or("0.1.0");
+    let project_name = package.name.as_str();
+    let version = package.version().unwrap_or("0.1.0");
 
     // 如果不是 workspace 成员，生成完整的项目头
-    if workspace_toml.is_none() {
+    if !is_member {
         cmake.push_str("cmake_minimum_required(VERSION 3.15)\n");
         cmake.push_str(&format!(
             "project({} VERSION {} LANGUAGES CXX)\n\n",
@@ -187,23 +171,14 @@ fn generate_cmake_for_project(toml: &Value, workspace_toml: Option<&Value>) -> R
     }
 
     // 依赖
-    let dependencies = toml.get("D").or_else(|| toml.get("dependencies"));
-    if let Some(Value::Table(deps)) = dependencies {
+    let dependencies = sorted_keys(&package.dependencies);
+    if !dependencies.is_empty() {
         cmake.push_str("# Dependencies\n");
-        for (name, spec) in deps {
-            // 跳过 workspace 依赖（已在根级处理）
-            if let Value::Table(spec_table) = spec {
-                if spec_table
-                    .get("workspace")
-                    .and_then(|v| v.as_bool())
-                    .unwrap_or(false)
-                {
-                    continue;
-                }
-                if spec_table.get("path").is_some() {
-                    // 路径依赖，不需要 find_package
-                    continue;
-                }
+        for name in &dependencies {
+            let spec = &package.dependencies[*name];
+            // 跳过 workspace 依赖（已在根级处理）与路径依赖（不需要 find_package）
+            if spec.workspace || spec.path.is_some() {
+                continue;
             }
 
             let pkg_name = dependency_to_cmake_package(name);
@@ -217,18 +192,24 @@ fn generate_cmake_for_project(toml: &Value, workspace_toml: Option<&Value>) -> R
     cmake.push_str("file(GLOB_RECURSE SOURCES \"src/*.cpp\")\n");
     cmake.push_str("file(GLOB_RECURSE HEADERS \"src/*.hpp\")\n\n");
 
-    // 可执行文件或库
+    // 可执行文件或库：只有 lib 目标时生成库，[L] ct 决定库的类型
     cmake.push_str("# Target\n");
-    let is_library = package
-        .and_then(|p| p.get("crate-type"))
-        .and_then(|v| v.as_array())
-        .map(|arr| arr.iter().any(|v| v.as_str() == Some("lib")))
-        .unwrap_or(false);
-
-    if is_library {
+    if package.is_lib_only() {
+        let crate_types = package
+            .lib
+            .as_ref()
+            .map(|lib| lib.crate_type.as_slice())
+            .unwrap_or_default();
+        let kind = if crate_types.iter().any(|t| t == "cdylib" || t == "dylib") {
+            " SHARED"
+        } else if crate_types.iter().any(|t| t == "staticlib") {
+            " STATIC"
+        } else {
+            ""
+        };
         cmake.push_str(&format!(
-            "add_library({} ${{SOURCES}} ${{HEADERS}})\n\n",
-            project_name
+            "add_library({}{} ${{SOURCES}} ${{HEADERS}})\n\n",
+            project_name, kind
         ));
     } else {
         cmake.push_str(&format!(
@@ -238,27 +219,23 @@ fn generate_cmake_for_project(toml: &Value, workspace_toml: Option<&Value>) -> R
     }
 
     // 链接库
-    if let Some(Value::Table(deps)) = dependencies {
-        if !deps.is_empty() {
-            cmake.push_str("# Link libraries\n");
-            cmake.push_str(&format!("target_link_libraries({}\n", project_name));
-            cmake.push_str("    PRIVATE\n");
-
-            for (name, spec) in deps {
-                if let Value::Table(spec_table) = spec {
-                    if spec_table.get("path").is_some() {
-                        // 路径依赖 - 直接使用依赖名
-                        cmake.push_str(&format!("    {}\n", name));
-                        continue;
-                    }
-                }
-
-                let link_name = dependency_to_cmake_link(name);
-                cmake.push_str(&format!("    {}\n", link_name));
+    if !dependencies.is_empty() {
+        cmake.push_str("# Link libraries\n");
+        cmake.push_str(&format!("target_link_libraries({}\n", project_name));
+        cmake.push_str("    PRIVATE\n");
+
+        for name in &dependencies {
+            if package.dependencies[*name].path.is_some() {
+                // 路径依赖 - 直接使用依赖名
+                cmake.push_str(&format!("    {}\n", name));
+                continue;
             }
 
-            cmake.push_str(")\n\n");
+            let link_name = dependency_to_cmake_link(name);
+            cmake.push_str(&format!("    {}\n", link_name));
         }
+
+        cmake.push_str(")\n\n");
     }
 
     // 包含目录
@@ -268,21 +245,14 @@ fn generate_cmake_for_project(toml: &Value, workspace_toml: Option<&Value>) -> R
     cmake.push_str("    ${CMAKE_CURRENT_SOURCE_DIR}/src\n");
     cmake.push_str(")\n");
 
-    Ok(cmake)
+    cmake
 }
 
-fn get_workspace_members(toml: &Value) -> Result<Vec<String>> {
-    let members = toml
-        .get("W")
-        .and_then(|w| w.get("m"))
-        .or_else(|| toml.get("workspace").and_then(|w| w.get("members")))
-        .and_then(|v| v.as_array())
-        .ok_or_else(|| anyhow::anyhow!("Workspace members not found"))?;
-
-    Ok(members
-        .iter()
-        .filter_map(|v| v.as_str().map(String::from))
-        .collect())
+/// 依赖名排序，保证生成结果稳定
+fn sorted_keys<V>(map: &HashMap<String, V>) -> Vec<&str> {
+    let mut keys: Vec<&str> = map.keys().map(String::as_str).collect();
+    keys.sort();
+    keys
 }
 
 fn dependency_to_cmake_package(name: &str) -> String {
@@ -2,11 +2,12 @@
 // 将 Nu 代码转换为 C++ 代码
 
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use std::fs;
 use std::path::{Path, PathBuf};
 
 use nu_compiler::nu2cpp::{CppCodegen, Nu2CppConverter, NuToCppAstConverter, SourceMap};
+use nu_compiler::sourcemap::{relative_path, SourceMapV3};
 
 #[derive(Parser, Debug)]
 #[command(name = "nu2cpp")]
@@ -36,11 +37,24 @@ struct Args {
     #[arg(short = 'm', long)]
     sourcemap: bool,
 
+    /// Source map format (implies --sourcemap): nu (line table JSON) or v3 (standard Source Map v3)
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    sourcemap_format: Option<SourceMapFormat>,
+
     /// Use new AST-based converter (experimental, incomplete)
     #[arg(long = "use-ast")]
     use_ast: bool,
 }
 
+/// .cpp.map 的格式
+#[derive(Clone, Copy, PartialEq, Eq, Debug, ValueEnum)]
+enum SourceMapFormat {
+    /// 行映射 JSON
+    Nu,
+    /// 标准 Source Map v3
+    V3,
+}
+
 fn main() -> Result<()> {
     let args = Args::parse();
 
@@ -109,7 +123,7 @@ fn convert_file(converter: &Nu2CppConverter, args: &Args) -> Result<()> {
         if args.verbose {
             println!("Using string-based converter (stable)");
         }
-        let mut sourcemap = if args.sourcemap {
+        let mut sourcemap = if args.sourcemap || args.sourcemap_format.is_some() {
             let source_name = input_path
                 .file_name()
                 .and_then(|n| n.to_str())
@@ -136,8 +150,17 @@ fn convert_file(converter: &Nu2CppConverter, args: &Args) -> Result<()> {
             let mut map_path = output_path.clone();
             map_path.set_extension("cpp.map");
 
-            sm.save_to_file(&map_path)
-                .with_context(|| format!("Failed to write source map: {:?}", map_path))?;
+            match args.sourcemap_format.unwrap_or(SourceMapFormat::Nu) {
+                SourceMapFormat::Nu => sm.save_to_file(&map_path),
+                SourceMapFormat::V3 => {
+                    let map_dir = map_path.parent().unwrap_or(Path::new("."));
+                    SourceMapV3::from_table(&sm)
+                        .with_source(relative_path(map_dir, input_path))
+                        .with_content(nu_code.as_str())
+                        .save_to_file(&map_path)
+                }
+            }
+            .with_context(|| format!("Failed to write source map: {:?}", map_path))?;
 
             if args.verbose {
                 println!(
@@ -197,6 +220,7 @@ fn convert_directory(converter: &Nu2CppConverter, args: &Args) -> Result<()> {
         force: true,     // 目录转换默认force，避免文件覆盖问题
         verbose: args.verbose,
         sourcemap: args.sourcemap,
+        sourcemap_format: args.sourcemap_format,
         use_ast: args.use_ast,
     };
 
@@ -232,6 +256,7 @@ fn convert_directory_recursive(
                     force: args.force,
                     verbose: args.verbose,
                     sourcemap: args.sourcemap,
+                    sourcemap_format: args.sourcemap_format,
                     use_ast: args.use_ast,
                 };
 
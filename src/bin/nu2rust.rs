@@ -2,10 +2,21 @@
 // 将Nu代码转换回标准Rust代码
 
 use anyhow::{Context, Result};
-use clap::Parser;
-use nu_compiler::nu2rust::{LazySourceMap, Nu2RustConverter};
+use clap::{Parser, ValueEnum};
+use nu_compiler::codegen::CodeGenerator;
+use nu_compiler::diagnostics::check_project;
+use nu_compiler::nu2rust::{
+    LazySourceMap, Nu2RustConverter, Nu2RustOptions, StrictError, Strictness,
+};
+use nu_compiler::parser::Parser as NuParser;
+use nu_compiler::profile::CompressionProfile;
+use nu_compiler::project::ProjectManager;
+use nu_compiler::sourcemap::{relative_path, SourceMapV3};
+use nu_compiler::workspace::{ConversionDecision, FileChange, FileWatcher, IncrementalConverter};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
 use walkdir::WalkDir;
 
 #[derive(Parser)]
@@ -36,6 +47,73 @@ struct Cli {
     /// Generate source map file (.rs.map)
     #[arg(short = 's', long)]
     sourcemap: bool,
+
+    /// Source map format (implies --sourcemap): nu (line/column tables read by nuc and the
+    /// VS Code extension) or v3 (standard Source Map v3 with VLQ mappings)
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    sourcemap_format: Option<SourceMapFormat>,
+
+    /// Fail with a located error on Nu constructs the converter cannot classify, or whose
+    /// generated Rust does not parse, instead of passing them through
+    #[arg(long, conflicts_with = "warn")]
+    strict: bool,
+
+    /// Report Nu constructs the converter cannot classify as warnings
+    #[arg(long)]
+    warn: bool,
+
+    /// Compression profile the Nu sources were written with: minimal, default, max, or
+    /// key=value overrides such as "types=Vec,Option returns=false"; a `// nu:compress`
+    /// header on the first line of a file takes precedence
+    #[arg(long, value_name = "PROFILE", default_value = "default")]
+    profile: CompressionProfile,
+
+    /// Keep running and re-convert .nu files under INPUT (a directory) as they are added,
+    /// changed or deleted; generated .rs/.rs.map files are overwritten or removed
+    #[arg(long, conflicts_with = "check")]
+    watch: bool,
+
+    /// Polling interval for --watch, in milliseconds
+    #[arg(long, value_name = "MS", default_value_t = 500, requires = "watch")]
+    poll_interval: u64,
+
+    /// Conversion backend: legacy line-based converter or AST-driven generator
+    #[arg(long, value_enum, default_value_t = Backend::Legacy)]
+    backend: Backend,
+
+    /// Run `cargo check` on the generated project of the Nu project containing INPUT
+    /// and report diagnostics at .nu locations
+    #[arg(long)]
+    check: bool,
+
+    /// Diagnostic output for --check: human (rustc-style text) or json (cargo JSON with Nu spans)
+    #[arg(long, value_enum, value_name = "FORMAT", default_value_t = MessageFormat::Human, requires = "check")]
+    message_format: MessageFormat,
+}
+
+/// --check 的诊断输出格式
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum MessageFormat {
+    Human,
+    Json,
+}
+
+/// 转换后端：AST 生成器达到与逐行转换器同等覆盖前，两者并存
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Backend {
+    /// 逐行转换器（Nu2RustConverter）
+    Legacy,
+    /// 基于 AST 的生成器（Parser + CodeGenerator）
+    Ast,
+}
+
+/// .rs.map 的格式
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum SourceMapFormat {
+    /// LazySourceMap JSON（行映射 + 列级片段）
+    Nu,
+    /// 标准 Source Map v3
+    V3,
 }
 
 const ASCII_LOGO: &str = r#"
@@ -51,10 +129,49 @@ Nu-lang: Rust, Condensed. v1.6.5
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
+    if cli.check {
+        return check(&cli.input, cli.message_format);
+    }
+
     // 显示ASCII Logo
     println!("{}", ASCII_LOGO);
 
-    let converter = Nu2RustConverter::new();
+    let sourcemap = (cli.sourcemap || cli.sourcemap_format.is_some())
+        .then(|| cli.sourcemap_format.unwrap_or(SourceMapFormat::Nu));
+    if sourcemap.is_some() && cli.backend == Backend::Ast {
+        anyhow::bail!("--sourcemap is only supported by the legacy backend");
+    }
+
+    let strictness = if cli.strict {
+        Strictness::Strict
+    } else if cli.warn {
+        Strictness::Warn
+    } else {
+        Strictness::Lenient
+    };
+    if strictness != Strictness::Lenient && cli.backend == Backend::Ast {
+        anyhow::bail!("--strict and --warn are only supported by the legacy backend");
+    }
+    if cli.profile != CompressionProfile::default() && cli.backend == Backend::Ast {
+        anyhow::bail!("--profile is only supported by the legacy backend");
+    }
+
+    let converter = Nu2RustConverter::with_options(Nu2RustOptions {
+        strictness,
+        profile: cli.profile.clone(),
+    });
+
+    if cli.watch {
+        let settings = WatchSettings {
+            output_dir: cli.output.clone(),
+            recursive: cli.recursive,
+            verbose: cli.verbose,
+            sourcemap,
+            backend: cli.backend,
+            interval: Duration::from_millis(cli.poll_interval),
+        };
+        return watch(&converter, &cli.input, &settings);
+    }
 
     if cli.input.is_file() {
         // 单文件转换
@@ -64,7 +181,8 @@ fn main() -> Result<()> {
             cli.output.as_ref(),
             cli.force,
             cli.verbose,
-            cli.sourcemap,
+            sourcemap,
+            cli.backend,
         )?;
     } else if cli.input.is_dir() {
         // 目录转换
@@ -75,7 +193,8 @@ fn main() -> Result<()> {
                 cli.output.as_ref(),
                 cli.force,
                 cli.verbose,
-                cli.sourcemap,
+                sourcemap,
+                cli.backend,
             )?;
         } else {
             convert_directory(
@@ -84,7 +203,8 @@ fn main() -> Result<()> {
                 cli.output.as_ref(),
                 cli.force,
                 cli.verbose,
-                cli.sourcemap,
+                sourcemap,
+                cli.backend,
             )?;
         }
     } else {
@@ -94,13 +214,150 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+/// nu2rust --check：生成影子项目并运行 cargo check，诊断映射回 .nu
+fn check(input: &Path, format: MessageFormat) -> Result<()> {
+    let cwd = std::env::current_dir()?;
+    let start = if input.is_dir() {
+        input
+    } else {
+        input.parent().unwrap_or(Path::new("."))
+    };
+    let start = start.canonicalize().unwrap_or_else(|_| cwd.join(start));
+    let root = ProjectManager::find_root(&start)
+        .with_context(|| format!("Could not find Nu.toml for {}", input.display()))?;
+    let display_root = root.strip_prefix(&cwd).unwrap_or(&root);
+    let name = if display_root.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        display_root
+    };
+
+    let summary = check_project(&root, display_root, |value| match format {
+        MessageFormat::Json => println!("{}", value),
+        MessageFormat::Human => {
+            if value["reason"] == "compiler-message" {
+                if let Some(rendered) = value["message"]["rendered"].as_str() {
+                    eprintln!("{}", rendered);
+                }
+            }
+        }
+    })?;
+
+    if format == MessageFormat::Human {
+        eprintln!(
+            "Checked {}: {} error(s), {} warning(s)",
+            name.display(),
+            summary.errors,
+            summary.warnings
+        );
+    }
+    if !summary.success {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// --watch 的转换设置
+struct WatchSettings {
+    output_dir: Option<PathBuf>,
+    recursive: bool,
+    verbose: bool,
+    sourcemap: Option<SourceMapFormat>,
+    backend: Backend,
+    interval: Duration,
+}
+
+/// nu2rust --watch：轮询 .nu 文件，只重新转换新增/修改的文件，删除已移除文件的输出
+fn watch(converter: &Nu2RustConverter, input_dir: &Path, settings: &WatchSettings) -> Result<()> {
+    if !input_dir.is_dir() {
+        anyhow::bail!("--watch requires a directory: {}", input_dir.display());
+    }
+    let output_base = settings
+        .output_dir
+        .clone()
+        .unwrap_or_else(|| input_dir.to_path_buf());
+    let target = |nu_path: &Path| -> Result<PathBuf> {
+        let relative = nu_path.strip_prefix(input_dir)?;
+        Ok(output_base.join(relative).with_extension("rs"))
+    };
+
+    let mut watcher = FileWatcher::new(input_dir, "nu", settings.recursive);
+
+    // 启动时只转换输出缺失或比 .nu 旧的文件
+    let incremental = IncrementalConverter::new();
+    let files: Vec<PathBuf> = watcher.files().map(Path::to_path_buf).collect();
+    for nu_path in files {
+        let rs_path = target(&nu_path)?;
+        if incremental.should_convert(&nu_path, &rs_path) != ConversionDecision::Skip {
+            reconvert(converter, &nu_path, &rs_path, settings);
+        }
+    }
+    println!(
+        "Watching {} for changes (Ctrl+C to stop)",
+        input_dir.display()
+    );
+
+    loop {
+        thread::sleep(settings.interval);
+        for change in watcher.poll() {
+            let rs_path = target(change.path())?;
+            match change {
+                FileChange::Added(nu_path) | FileChange::Modified(nu_path) => {
+                    reconvert(converter, &nu_path, &rs_path, settings);
+                }
+                FileChange::Removed(nu_path) => {
+                    for path in [rs_path.with_extension("rs.map"), rs_path] {
+                        if path.exists() {
+                            match fs::remove_file(&path) {
+                                Ok(()) => println!("- {}", path.display()),
+                                Err(err) => eprintln!("✗ {}: {}", path.display(), err),
+                            }
+                        }
+                    }
+                    if settings.verbose {
+                        println!("Removed: {}", nu_path.display());
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 重新转换单个文件；失败只打印错误，监视继续
+fn reconvert(
+    converter: &Nu2RustConverter,
+    nu_path: &Path,
+    rs_path: &Path,
+    settings: &WatchSettings,
+) {
+    if let Some(parent) = rs_path.parent() {
+        if let Err(err) = fs::create_dir_all(parent) {
+            eprintln!("✗ {}: {}", parent.display(), err);
+            return;
+        }
+    }
+    let result = convert_file(
+        converter,
+        &nu_path.to_path_buf(),
+        Some(&rs_path.to_path_buf()),
+        true,
+        settings.verbose,
+        settings.sourcemap,
+        settings.backend,
+    );
+    if let Err(err) = result {
+        eprintln!("✗ {}: {:#}", nu_path.display(), err);
+    }
+}
+
 fn convert_file(
     converter: &Nu2RustConverter,
     input: &PathBuf,
     output: Option<&PathBuf>,
     force: bool,
     verbose: bool,
-    generate_sourcemap: bool,
+    sourcemap_format: Option<SourceMapFormat>,
+    backend: Backend,
 ) -> Result<()> {
     // 检查输入文件扩展名
     if input.extension().and_then(|s| s.to_str()) != Some("nu") {
@@ -137,7 +394,9 @@ fn convert_file(
         .with_context(|| format!("Failed to read input file: {}", input.display()))?;
 
     // 转换为Rust代码
-    let rust_code = if generate_sourcemap {
+    let rust_code = if backend == Backend::Ast {
+        convert_ast(input, &nu_code)?
+    } else if let Some(format) = sourcemap_format {
         // 创建 SourceMap
         let mut sourcemap = LazySourceMap::new(
             input.file_name().unwrap().to_string_lossy().to_string(),
@@ -149,15 +408,25 @@ fn convert_file(
         );
 
         // 使用 sourcemap 进行转换
-        let code = converter
-            .convert_with_sourcemap(&nu_code, Some(&mut sourcemap))
-            .with_context(|| format!("Failed to convert file: {}", input.display()))?;
+        let code = report_constructs(
+            converter,
+            input,
+            converter.convert_with_sourcemap(&nu_code, Some(&mut sourcemap)),
+        )?;
 
         // 保存 sourcemap 文件
         let map_path = output_path.with_extension("rs.map");
-        sourcemap
-            .save_to_file(&map_path)
-            .with_context(|| format!("Failed to write sourcemap file: {}", map_path.display()))?;
+        match format {
+            SourceMapFormat::Nu => sourcemap.save_to_file(&map_path),
+            SourceMapFormat::V3 => {
+                let map_dir = map_path.parent().unwrap_or(Path::new("."));
+                SourceMapV3::from_table(&sourcemap)
+                    .with_source(relative_path(map_dir, input))
+                    .with_content(nu_code.as_str())
+                    .save_to_file(&map_path)
+            }
+        }
+        .with_context(|| format!("Failed to write sourcemap file: {}", map_path.display()))?;
 
         if verbose {
             println!(
@@ -169,9 +438,7 @@ fn convert_file(
 
         code
     } else {
-        converter
-            .convert(&nu_code)
-            .with_context(|| format!("Failed to convert file: {}", input.display()))?
+        report_constructs(converter, input, converter.convert(&nu_code))?
     };
 
     // 写入输出文件
@@ -183,13 +450,64 @@ fn convert_file(
     Ok(())
 }
 
+/// AST 后端转换；AST 生成器只支持默认配置，首行记录了其他压缩配置时与 --profile 一样报错
+fn convert_ast(input: &Path, nu_code: &str) -> Result<String> {
+    let header = nu_code.lines().next().unwrap_or_default();
+    let profile = CompressionProfile::from_header(header)
+        .with_context(|| format!("Failed to read profile header: {}", input.display()))?;
+    if profile.is_some_and(|profile| profile != CompressionProfile::default()) {
+        anyhow::bail!(
+            "{}: the {} header is only supported by the legacy backend",
+            input.display(),
+            nu_compiler::profile::HEADER_PREFIX
+        );
+    }
+    let ast = NuParser::with_file(input.to_string_lossy())
+        .parse(nu_code)
+        .with_context(|| format!("Failed to parse file: {}", input.display()))?;
+    CodeGenerator::new()
+        .generate(&ast)
+        .with_context(|| format!("Failed to convert file: {}", input.display()))
+}
+
+/// 输出无法识别的 Nu 结构：--warn 下打印警告，--strict 下逐条打印错误后失败
+fn report_constructs(
+    converter: &Nu2RustConverter,
+    input: &Path,
+    result: Result<String>,
+) -> Result<String> {
+    let file = input.display().to_string();
+    match result {
+        Ok(code) => {
+            if converter.options().strictness == Strictness::Warn {
+                for construct in converter.unrecognized_constructs() {
+                    eprintln!("{}\n", construct.render("warning", &file));
+                }
+            }
+            Ok(code)
+        }
+        Err(err) => {
+            if let Some(strict) = err.downcast_ref::<StrictError>() {
+                eprintln!("{}\n", strict.render(&file));
+                anyhow::bail!(
+                    "Failed to convert file: {} ({} unrecognised construct(s))",
+                    file,
+                    strict.constructs.len()
+                );
+            }
+            Err(err).with_context(|| format!("Failed to convert file: {}", file))
+        }
+    }
+}
+
 fn convert_directory(
     converter: &Nu2RustConverter,
     input_dir: &PathBuf,
     output_dir: Option<&PathBuf>,
     force: bool,
     verbose: bool,
-    generate_sourcemap: bool,
+    sourcemap_format: Option<SourceMapFormat>,
+    backend: Backend,
 ) -> Result<()> {
     let output_base = output_dir.cloned().unwrap_or_else(|| input_dir.clone());
 
@@ -213,7 +531,8 @@ fn convert_directory(
                 Some(&output_path),
                 force,
                 verbose,
-                generate_sourcemap,
+                sourcemap_format,
+                backend,
             )?;
         }
     }
@@ -227,7 +546,8 @@ fn convert_directory_recursive(
     output_dir: Option<&PathBuf>,
     force: bool,
     verbose: bool,
-    generate_sourcemap: bool,
+    sourcemap_format: Option<SourceMapFormat>,
+    backend: Backend,
 ) -> Result<()> {
     let output_base = output_dir.cloned().unwrap_or_else(|| input_dir.clone());
 
@@ -255,9 +575,29 @@ fn convert_directory_recursive(
             Some(&output_path),
             force,
             verbose,
-            generate_sourcemap,
+            sourcemap_format,
+            backend,
         )?;
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_ast_profile_header() {
+        let input = Path::new("a.nu");
+        let err = convert_ast(input, "// nu:compress minimal\n").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("only supported by the legacy backend"));
+        assert!(convert_ast(input, "// nu:compress bogus=1\n").is_err());
+
+        // 默认配置的记录与没有记录一样
+        assert!(convert_ast(input, "// nu:compress default\n").is_ok());
+        assert!(convert_ast(input, "F f() {}\n").is_ok());
+    }
+}
@@ -8,8 +8,10 @@
 use anyhow::{Context, Result};
 use clap::Parser;
 use nu_compiler::nu2ts::{Nu2TsConverter, RuntimeMode, Target, TsConfig};
+use nu_compiler::project::ProjectManager;
+use nu_compiler::sourcemap::{relative_path, source_mapping_url, SourceMapV3};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
 #[derive(Parser)]
@@ -55,6 +57,10 @@ struct Cli {
     /// Project mode: convert entire Nu project to TypeScript project
     #[arg(short = 'P', long)]
     project: bool,
+
+    /// Generate a Source Map v3 file (.ts.map) and append a sourceMappingURL comment
+    #[arg(long)]
+    source_map: bool,
 }
 
 fn main() -> Result<()> {
@@ -79,7 +85,7 @@ fn main() -> Result<()> {
         target,
         strict: true,
         no_format: false,
-        source_map: false,
+        source_map: cli.source_map,
     };
 
     let mut converter = Nu2TsConverter::new(config.clone());
@@ -170,10 +176,38 @@ fn convert_file(
         .with_context(|| format!("Failed to read input file: {}", input.display()))?;
 
     // 转换为TypeScript代码
-    let ts_code = converter
-        .convert(&nu_code)
+    let (mut ts_code, mappings) = converter
+        .convert_with_sourcemap(&nu_code)
         .with_context(|| format!("Failed to convert file: {}", input.display()))?;
 
+    // 生成 source map，并在末尾追加 sourceMappingURL
+    if converter.config().source_map {
+        let map_path = output_path.with_extension("ts.map");
+        let file_name = |p: &Path| {
+            p.file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default()
+        };
+        let map_dir = map_path.parent().unwrap_or(Path::new("."));
+        SourceMapV3::new(
+            file_name(&output_path),
+            relative_path(map_dir, input),
+            &mappings,
+        )
+        .with_content(nu_code.as_str())
+        .save_to_file(&map_path)
+        .with_context(|| format!("Failed to write source map: {}", map_path.display()))?;
+
+        if !ts_code.ends_with('\n') {
+            ts_code.push('\n');
+        }
+        ts_code.push_str(&source_mapping_url(&file_name(&map_path)));
+        ts_code.push('\n');
+        if verbose {
+            println!("✓ {}", map_path.display());
+        }
+    }
+
     // 写入输出文件
     fs::write(&output_path, ts_code)
         .with_context(|| format!("Failed to write output file: {}", output_path.display()))?;
@@ -378,31 +412,30 @@ fn generate_config_files(
 
 fn generate_package_json(output_dir: &PathBuf, input_dir: &PathBuf) -> Result<()> {
     // 尝试从Nu.toml读取项目信息
-    let nu_toml = input_dir.join("Nu.toml");
-    let (name, version) = if nu_toml.exists() {
-        let content = fs::read_to_string(&nu_toml)?;
-        let name = content
-            .lines()
-            .find(|l| l.starts_with("name"))
-            .and_then(|l| l.split('=').nth(1))
-            .map(|s| s.trim().trim_matches('"'))
-            .unwrap_or("nu-project");
-        let version = content
-            .lines()
-            .find(|l| l.starts_with("version"))
-            .and_then(|l| l.split('=').nth(1))
-            .map(|s| s.trim().trim_matches('"'))
-            .unwrap_or("0.1.0");
-        (name.to_string(), version.to_string())
+    let package = if input_dir.join("Nu.toml").exists() {
+        ProjectManager::new().load(input_dir)?.package
     } else {
-        ("nu-project".to_string(), "0.1.0".to_string())
+        None
     };
+    let name = package.as_ref().map_or("nu-project", |p| p.name.as_str());
+    let version = package
+        .as_ref()
+        .and_then(|p| p.version())
+        .unwrap_or("0.1.0");
+    let description = package
+        .as_ref()
+        .and_then(|p| p.metadata.description.as_deref())
+        .unwrap_or("Converted from Nu project");
+    let license = package
+        .as_ref()
+        .and_then(|p| p.metadata.license.as_deref())
+        .unwrap_or("MIT");
 
     let package_json = format!(
         r#"{{
-  "name": "{}",
-  "version": "{}",
-  "description": "Converted from Nu project",
+  "name": {},
+  "version": {},
+  "description": {},
   "main": "dist/main.js",
   "scripts": {{
     "build": "tsc",
@@ -412,7 +445,7 @@ fn generate_package_json(output_dir: &PathBuf, input_dir: &PathBuf) -> Result<()
   }},
   "keywords": ["nu", "typescript"],
   "author": "",
-  "license": "MIT",
+  "license": {},
   "devDependencies": {{
     "@types/node": "^20.0.0",
     "typescript": "^5.0.0",
@@ -420,13 +453,21 @@ fn generate_package_json(output_dir: &PathBuf, input_dir: &PathBuf) -> Result<()
   }}
 }}
 "#,
-        name, version
+        json_string(name),
+        json_string(version),
+        json_string(description),
+        json_string(license)
     );
 
     fs::write(output_dir.join("package.json"), package_json)?;
     Ok(())
 }
 
+/// JSON 字符串字面量（含引号与转义）
+fn json_string(value: &str) -> String {
+    serde_json::Value::from(value).to_string()
+}
+
 fn generate_tsconfig_json(output_dir: &PathBuf) -> Result<()> {
     let tsconfig = r#"{
   "compilerOptions": {
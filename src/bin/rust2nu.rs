@@ -3,7 +3,8 @@
 
 use anyhow::{Context, Result};
 use clap::Parser;
-use nu_compiler::Rust2NuConverter;
+use nu_compiler::rust2nu::verify_round_trip;
+use nu_compiler::{CompressionProfile, Rust2NuConverter, Rust2NuOptions};
 use std::fs;
 use std::path::PathBuf;
 use walkdir::WalkDir;
@@ -31,6 +32,22 @@ struct Cli {
     /// Verbose output
     #[arg(short, long)]
     verbose: bool,
+
+    /// Keep the original formatting: copy unchanged source verbatim and only
+    /// rewrite keywords, type abbreviations, `?` and `mut`
+    #[arg(long)]
+    preserve: bool,
+
+    /// Compression profile: minimal, default, max, or key=value overrides such as
+    /// "types=Vec,Option returns=false"; non-default profiles are recorded in a
+    /// `// nu:compress` header so nu2rust converts the output back consistently
+    #[arg(long, value_name = "PROFILE", default_value = "default")]
+    profile: CompressionProfile,
+
+    /// Check that Rust -> Nu -> Rust round-trips to equivalent code instead of
+    /// writing output; reports the first differing item or statement per file
+    #[arg(long, conflicts_with = "output")]
+    verify: bool,
 }
 
 const ASCII_LOGO: &str = r#"
@@ -49,7 +66,16 @@ fn main() -> Result<()> {
     // 显示ASCII Logo
     println!("{}", ASCII_LOGO);
 
-    let converter = Rust2NuConverter::new();
+    let options = Rust2NuOptions {
+        preserve_syntax: cli.preserve,
+        profile: cli.profile.clone(),
+    };
+
+    if cli.verify {
+        return verify(&cli.input, options, cli.recursive);
+    }
+
+    let converter = Rust2NuConverter::with_options(options);
 
     if cli.input.is_file() {
         // 单文件转换
@@ -209,3 +235,56 @@ fn convert_directory_recursive(
 
     Ok(())
 }
+
+/// --verify：逐个文件做往返验证，有文件不等价时返回错误
+fn verify(input: &PathBuf, options: Rust2NuOptions, recursive: bool) -> Result<()> {
+    let files: Vec<PathBuf> = if input.is_file() {
+        vec![input.clone()]
+    } else if input.is_dir() {
+        let depth = if recursive { usize::MAX } else { 1 };
+        let mut files: Vec<PathBuf> = WalkDir::new(input)
+            .max_depth(depth)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .map(|e| e.into_path())
+            .filter(|p| p.is_file() && p.extension().and_then(|s| s.to_str()) == Some("rs"))
+            .collect();
+        files.sort();
+        files
+    } else {
+        anyhow::bail!("Input path does not exist: {}", input.display());
+    };
+
+    let mut failed = 0;
+    for path in &files {
+        let rust_code = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read input file: {}", path.display()))?;
+        match verify_round_trip(&rust_code, options.clone()) {
+            Ok(report) if report.is_equivalent() => println!("✓ {}", path.display()),
+            Ok(report) => {
+                failed += 1;
+                println!("✗ {}", path.display());
+                println!(
+                    "{}\n",
+                    report.render(&rust_code, &path.display().to_string())
+                );
+            }
+            Err(err) => {
+                failed += 1;
+                println!("✗ {}", path.display());
+                println!("error: {:#}\n", err);
+            }
+        }
+    }
+
+    println!(
+        "\n{} file(s) verified, {} equivalent, {} failed",
+        files.len(),
+        files.len() - failed,
+        failed
+    );
+    if failed > 0 {
+        anyhow::bail!("{} file(s) did not round-trip", failed);
+    }
+    Ok(())
+}
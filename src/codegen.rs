@@ -1,17 +1,45 @@
 // Nu Code Generator
 // 将AST生成为Rust代码
+//
+// 遍历 parser 产出的 AST 逐节点输出 Rust 源码，再经 syn 校验、prettyplease 格式化。
+// 与 nu2rust 的逐行字符串改写不同：字符串字面量原样输出，标识符只去掉不需要的 `r#`，
+// 类型缩写（V/O/R/A/X/B）只在 AST 的路径段上展开，且不会覆盖同名的泛型参数或本文件声明的类型。
+// 注释不在 AST 中，生成结果不保留普通注释（文档注释以 #[doc] 保留）。
 
-pub struct CodeGenerator {
-    // TODO: 实现CodeGenerator
-}
+use crate::ast::*;
+use crate::escape;
+use crate::profile::TYPE_ABBREVIATIONS;
+use anyhow::{anyhow, Result};
+use std::collections::HashSet;
+
+pub struct CodeGenerator {}
 
 impl CodeGenerator {
     pub fn new() -> Self {
         Self {}
     }
 
-    pub fn generate(&self, _ast: &crate::ast::NuFile) -> anyhow::Result<String> {
-        todo!("CodeGenerator implementation")
+    /// 生成格式化后的 Rust 源码；生成结果无法通过 syn 解析时返回错误
+    pub fn generate(&self, ast: &NuFile) -> Result<String> {
+        let raw = self.generate_unformatted(ast);
+        let file = syn::parse_file(&raw).map_err(|e| {
+            let start = e.span().start();
+            let line = raw.lines().nth(start.line.saturating_sub(1)).unwrap_or("");
+            anyhow!(
+                "生成的 Rust 代码无法解析 ({}:{}): {}\n  {}",
+                start.line,
+                start.column + 1,
+                e,
+                line.trim()
+            )
+        })?;
+        Ok(prettyplease::unparse(&file))
+    }
+
+    /// 未经格式化的 Rust 源码（调试用）
+    pub fn generate_unformatted(&self, ast: &NuFile) -> String {
+        let mut emitter = Emitter::new(ast);
+        emitter.file(ast)
     }
 }
 
@@ -20,3 +48,1637 @@ impl Default for CodeGenerator {
         Self::new()
     }
 }
+
+/// Nu 类型缩写 → Rust 类型名（与 nu2rust 共用同一张表）
+fn expand_abbreviation(ident: &str) -> Option<&'static str> {
+    TYPE_ABBREVIATIONS
+        .iter()
+        .find(|(abbr, _)| *abbr == ident)
+        .map(|(_, full)| *full)
+}
+
+/// 去掉 Nu 源码中为避开关键字而写的 `r#`（`r#l` -> `l`），与 nu2rust 旧路径一致
+fn ident(name: &str) -> &str {
+    escape::unescape(name)
+}
+
+/// 函数所在位置决定可见性的输出方式
+#[derive(Clone, Copy, PartialEq)]
+enum FnContext {
+    Free,
+    Trait,
+    TraitImpl,
+    InherentImpl,
+    Foreign,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum PathMode {
+    Type,
+    Expr,
+}
+
+// 输出时的表达式优先级（数值越大结合越紧）
+const P_JUMP: u8 = 1;
+const P_ASSIGN: u8 = 2;
+const P_RANGE: u8 = 3;
+const P_OR: u8 = 4;
+const P_AND: u8 = 5;
+const P_CMP: u8 = 6;
+const P_BIT_OR: u8 = 7;
+const P_BIT_XOR: u8 = 8;
+const P_BIT_AND: u8 = 9;
+const P_SHIFT: u8 = 10;
+const P_ADD: u8 = 11;
+const P_MUL: u8 = 12;
+const P_CAST: u8 = 13;
+const P_PREFIX: u8 = 14;
+const P_POSTFIX: u8 = 15;
+const P_PRIMARY: u8 = 16;
+
+fn binop_prec(op: BinOp) -> u8 {
+    match op {
+        BinOp::Or => P_OR,
+        BinOp::And => P_AND,
+        BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => P_CMP,
+        BinOp::BitOr => P_BIT_OR,
+        BinOp::BitXor => P_BIT_XOR,
+        BinOp::BitAnd => P_BIT_AND,
+        BinOp::Shl | BinOp::Shr => P_SHIFT,
+        BinOp::Add | BinOp::Sub => P_ADD,
+        BinOp::Mul | BinOp::Div | BinOp::Rem => P_MUL,
+    }
+}
+
+fn expr_prec(expr: &NuExpr) -> u8 {
+    match &expr.kind {
+        ExprKind::Closure { .. }
+        | ExprKind::Return(_)
+        | ExprKind::Break { .. }
+        | ExprKind::Continue { .. } => P_JUMP,
+        ExprKind::Assign { .. } | ExprKind::AssignOp { .. } => P_ASSIGN,
+        ExprKind::Range { .. } => P_RANGE,
+        ExprKind::Binary { op, .. } => binop_prec(*op),
+        ExprKind::Cast { .. } => P_CAST,
+        ExprKind::Unary { .. } | ExprKind::Ref { .. } => P_PREFIX,
+        ExprKind::Call { .. }
+        | ExprKind::MethodCall { .. }
+        | ExprKind::Field { .. }
+        | ExprKind::Index { .. }
+        | ExprKind::Try(_)
+        | ExprKind::Await(_)
+        | ExprKind::Recv(_)
+        | ExprKind::Spawn(_)
+        | ExprKind::Thread(_) => P_POSTFIX,
+        _ => P_PRIMARY,
+    }
+}
+
+/// 语句位置不需要 `;` 的块状表达式
+fn is_block_like(expr: &NuExpr) -> bool {
+    matches!(
+        expr.kind,
+        ExprKind::Block { .. }
+            | ExprKind::Unsafe(_)
+            | ExprKind::If { .. }
+            | ExprKind::Match { .. }
+            | ExprKind::Loop { .. }
+            | ExprKind::ForLoop { .. }
+            | ExprKind::While { .. }
+    )
+}
+
+fn vis_str(vis: &Visibility) -> String {
+    match vis {
+        Visibility::Private => String::new(),
+        Visibility::Public => "pub ".to_string(),
+        Visibility::Crate => "pub(crate) ".to_string(),
+        Visibility::Super => "pub(super) ".to_string(),
+        Visibility::SelfMod => "pub(self) ".to_string(),
+        Visibility::In(path) => format!("pub(in {}) ", path),
+    }
+}
+
+fn delimited(delimiter: MacroDelimiter, tokens: &str) -> String {
+    match delimiter {
+        MacroDelimiter::Paren => format!("({})", tokens),
+        MacroDelimiter::Bracket => format!("[{}]", tokens),
+        MacroDelimiter::Brace => format!("{{{}}}", tokens),
+    }
+}
+
+/// `v (a, b) = ...`：mut 作用于模式中的每个绑定
+fn mark_bindings_mut(pat: &mut NuPat) {
+    match &mut pat.kind {
+        PatKind::Ident {
+            by_ref,
+            mutable,
+            sub,
+            ..
+        } => {
+            if !*by_ref {
+                *mutable = true;
+            }
+            if let Some(sub) = sub {
+                mark_bindings_mut(sub);
+            }
+        }
+        PatKind::TupleStruct { elems, .. }
+        | PatKind::Tuple(elems)
+        | PatKind::Slice(elems)
+        | PatKind::Or(elems) => elems.iter_mut().for_each(mark_bindings_mut),
+        PatKind::Struct { fields, .. } => {
+            for field in fields {
+                match &mut field.pat {
+                    Some(p) => mark_bindings_mut(p),
+                    None => {
+                        field.pat = Some(NuPat {
+                            kind: PatKind::Ident {
+                                by_ref: false,
+                                mutable: true,
+                                name: field.name.clone(),
+                                sub: None,
+                            },
+                            span: field.span.clone(),
+                        })
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+struct Emitter {
+    /// 当前作用域内的泛型参数（遮蔽类型缩写）
+    generic_scopes: Vec<Vec<String>>,
+    /// 文件中声明或用 u 导入的类型名（遮蔽类型缩写）
+    declared: HashSet<String>,
+}
+
+impl Emitter {
+    fn new(file: &NuFile) -> Self {
+        let mut declared = HashSet::new();
+        collect_declared(&file.items, &mut declared);
+        Self {
+            generic_scopes: Vec::new(),
+            declared,
+        }
+    }
+
+    fn is_shadowed(&self, ident: &str) -> bool {
+        ident == "Self"
+            || self.declared.contains(ident)
+            || self
+                .generic_scopes
+                .iter()
+                .any(|scope| scope.iter().any(|name| name == ident))
+    }
+
+    fn push_generics(&mut self, generics: &NuGenerics) {
+        let names = generics
+            .params
+            .iter()
+            .filter_map(|p| match p {
+                GenericParam::Type { name, .. } | GenericParam::Const { name, .. } => {
+                    Some(name.clone())
+                }
+                GenericParam::Lifetime { .. } => None,
+            })
+            .collect();
+        self.generic_scopes.push(names);
+    }
+
+    fn pop_generics(&mut self) {
+        self.generic_scopes.pop();
+    }
+
+    // ============ 文件与条目 ============
+
+    fn file(&mut self, file: &NuFile) -> String {
+        let mut out = String::new();
+        for attr in &file.attrs {
+            out.push_str(&self.attr(attr));
+            out.push('\n');
+        }
+        for item in &file.items {
+            out.push_str(&self.item(item));
+            out.push('\n');
+        }
+        out
+    }
+
+    fn attr(&self, attr: &NuAttr) -> String {
+        let bang = if attr.is_inner { "!" } else { "" };
+        match &attr.kind {
+            AttrKind::Derive(derives) => format!("#{}[derive({})]", bang, derives.join(", ")),
+            AttrKind::Inline => format!("#{}[inline]", bang),
+            AttrKind::Doc(doc) => format!("#{}[doc = {:?}]", bang, doc),
+            AttrKind::Meta(meta) => format!("#{}[{}]", bang, meta),
+        }
+    }
+
+    fn attrs(&self, attrs: &[NuAttr]) -> String {
+        attrs
+            .iter()
+            .map(|a| format!("{}\n", self.attr(a)))
+            .collect()
+    }
+
+    fn item(&mut self, item: &NuItem) -> String {
+        match item {
+            NuItem::Fn(f) => self.function(f, FnContext::Free),
+            NuItem::Struct(s) => self.structure(s),
+            NuItem::Union(u) => self.union_def(u),
+            NuItem::Enum(e) => self.enumeration(e),
+            NuItem::Trait(t) => self.trait_def(t),
+            NuItem::TraitAlias(t) => self.trait_alias(t),
+            NuItem::Impl(i) => self.impl_block(i),
+            NuItem::Use(u) => format!(
+                "{}{}use {};",
+                self.attrs(&u.attrs),
+                vis_str(&u.vis),
+                use_tree(&u.tree)
+            ),
+            NuItem::Mod(m) => self.module(m),
+            NuItem::Const(c) => self.constant(c),
+            NuItem::Static(s) => self.static_item(s),
+            NuItem::TypeAlias(t) => self.type_alias(t),
+            NuItem::Extern(e) => self.extern_block(e),
+            NuItem::ExternCrate(c) => self.extern_crate(c),
+            NuItem::Macro(m) => self.macro_item(m),
+        }
+    }
+
+    fn function(&mut self, f: &NuFn, ctx: FnContext) -> String {
+        self.push_generics(&f.generics);
+        let mut out = self.attrs(&f.attrs);
+        // trait 与 trait 实现中的方法不能带可见性
+        if !matches!(ctx, FnContext::Trait | FnContext::TraitImpl) {
+            out.push_str(&vis_str(&f.vis));
+        }
+        if f.is_const {
+            out.push_str("const ");
+        }
+        if f.is_async {
+            out.push_str("async ");
+        }
+        if f.is_unsafe {
+            out.push_str("unsafe ");
+        }
+        if let (Some(abi), false) = (&f.abi, ctx == FnContext::Foreign) {
+            out.push_str("extern ");
+            if !abi.is_empty() {
+                out.push_str(abi);
+                out.push(' ');
+            }
+        }
+        out.push_str("fn ");
+        out.push_str(ident(&f.name));
+        out.push_str(&self.generic_params(&f.generics));
+
+        let mut params = Vec::new();
+        if let Some(self_param) = &f.self_param {
+            params.push(self.self_param(self_param));
+        }
+        for p in &f.params {
+            params.push(format!(
+                "{}{}: {}",
+                self.inline_attrs(&p.attrs),
+                self.pat(&p.pat),
+                self.ty(&p.ty)
+            ));
+        }
+        if f.variadic {
+            params.push("...".to_string());
+        }
+        out.push_str(&format!("({})", params.join(", ")));
+        if let Some(ret) = &f.ret {
+            out.push_str(" -> ");
+            out.push_str(&self.ty(ret));
+        }
+        out.push_str(&self.where_clause(&f.generics));
+        match &f.body {
+            Some(body) => {
+                out.push(' ');
+                out.push_str(&self.block(body));
+            }
+            None => out.push(';'),
+        }
+        self.pop_generics();
+        out
+    }
+
+    fn inline_attrs(&self, attrs: &[NuAttr]) -> String {
+        attrs.iter().map(|a| format!("{} ", self.attr(a))).collect()
+    }
+
+    fn self_param(&mut self, param: &SelfParam) -> String {
+        match param {
+            SelfParam::Value { mutable } => {
+                if *mutable {
+                    "mut self".to_string()
+                } else {
+                    "self".to_string()
+                }
+            }
+            SelfParam::Ref { lifetime, mutable } => {
+                let mut out = "&".to_string();
+                if let Some(l) = lifetime {
+                    out.push_str(l);
+                    out.push(' ');
+                }
+                if *mutable {
+                    out.push_str("mut ");
+                }
+                out.push_str("self");
+                out
+            }
+            SelfParam::Typed { mutable, ty } => {
+                let m = if *mutable { "mut " } else { "" };
+                format!("{}self: {}", m, self.ty(ty))
+            }
+        }
+    }
+
+    fn structure(&mut self, s: &NuStruct) -> String {
+        self.push_generics(&s.generics);
+        let mut out = self.attrs(&s.attrs);
+        out.push_str(&vis_str(&s.vis));
+        out.push_str("struct ");
+        out.push_str(ident(&s.name));
+        out.push_str(&self.generic_params(&s.generics));
+        match &s.fields {
+            Fields::Named(_) => {
+                out.push_str(&self.where_clause(&s.generics));
+                out.push(' ');
+                out.push_str(&self.fields(&s.fields, true));
+            }
+            Fields::Tuple(_) => {
+                out.push_str(&self.fields(&s.fields, false));
+                out.push_str(&self.where_clause(&s.generics));
+                out.push(';');
+            }
+            Fields::Unit => {
+                out.push_str(&self.where_clause(&s.generics));
+                out.push(';');
+            }
+        }
+        self.pop_generics();
+        out
+    }
+
+    fn union_def(&mut self, u: &NuUnion) -> String {
+        self.push_generics(&u.generics);
+        let mut out = self.attrs(&u.attrs);
+        out.push_str(&vis_str(&u.vis));
+        out.push_str("union ");
+        out.push_str(ident(&u.name));
+        out.push_str(&self.generic_params(&u.generics));
+        out.push_str(&self.where_clause(&u.generics));
+        out.push(' ');
+        out.push_str(&self.fields(&Fields::Named(u.fields.clone()), true));
+        self.pop_generics();
+        out
+    }
+
+    /// pub_named：与 nu2rust 一致，结构体与联合体的命名字段默认 pub
+    fn fields(&mut self, fields: &Fields, pub_named: bool) -> String {
+        match fields {
+            Fields::Named(fields) => {
+                let mut out = "{\n".to_string();
+                for field in fields {
+                    let vis = if pub_named && field.vis == Visibility::Private {
+                        "pub ".to_string()
+                    } else {
+                        vis_str(&field.vis)
+                    };
+                    out.push_str(&format!(
+                        "{}{}{}: {},\n",
+                        self.attrs(&field.attrs),
+                        vis,
+                        field.name.as_deref().map_or("_", ident),
+                        self.ty(&field.ty)
+                    ));
+                }
+                out.push('}');
+                out
+            }
+            Fields::Tuple(fields) => {
+                let parts: Vec<String> = fields
+                    .iter()
+                    .map(|f| {
+                        format!(
+                            "{}{}{}",
+                            self.inline_attrs(&f.attrs),
+                            vis_str(&f.vis),
+                            self.ty(&f.ty)
+                        )
+                    })
+                    .collect();
+                format!("({})", parts.join(", "))
+            }
+            Fields::Unit => String::new(),
+        }
+    }
+
+    fn enumeration(&mut self, e: &NuEnum) -> String {
+        self.push_generics(&e.generics);
+        let mut out = self.attrs(&e.attrs);
+        out.push_str(&vis_str(&e.vis));
+        out.push_str("enum ");
+        out.push_str(ident(&e.name));
+        out.push_str(&self.generic_params(&e.generics));
+        out.push_str(&self.where_clause(&e.generics));
+        out.push_str(" {\n");
+        for variant in &e.variants {
+            out.push_str(&self.attrs(&variant.attrs));
+            out.push_str(ident(&variant.name));
+            out.push_str(&self.fields(&variant.fields, false));
+            if let Some(d) = &variant.discriminant {
+                out.push_str(" = ");
+                out.push_str(&self.expr(d));
+            }
+            out.push_str(",\n");
+        }
+        out.push('}');
+        self.pop_generics();
+        out
+    }
+
+    fn trait_def(&mut self, t: &NuTrait) -> String {
+        self.push_generics(&t.generics);
+        let mut out = self.attrs(&t.attrs);
+        out.push_str(&vis_str(&t.vis));
+        if t.is_unsafe {
+            out.push_str("unsafe ");
+        }
+        out.push_str("trait ");
+        out.push_str(ident(&t.name));
+        out.push_str(&self.generic_params(&t.generics));
+        if !t.supertraits.is_empty() {
+            out.push_str(": ");
+            out.push_str(&self.bounds(&t.supertraits));
+        }
+        out.push_str(&self.where_clause(&t.generics));
+        out.push_str(" {\n");
+        for item in &t.items {
+            let code = match item {
+                TraitItem::Fn(f) => self.function(f, FnContext::Trait),
+                TraitItem::Const(c) => self.assoc_const(c),
+                TraitItem::Type(ty) => self.assoc_type(ty),
+                TraitItem::Macro(m) => self.macro_item(m),
+            };
+            out.push_str(&code);
+            out.push('\n');
+        }
+        out.push('}');
+        self.pop_generics();
+        out
+    }
+
+    fn trait_alias(&mut self, t: &NuTraitAlias) -> String {
+        self.push_generics(&t.generics);
+        let mut out = self.attrs(&t.attrs);
+        out.push_str(&vis_str(&t.vis));
+        out.push_str("trait ");
+        out.push_str(ident(&t.name));
+        out.push_str(&self.generic_params(&t.generics));
+        out.push_str(" = ");
+        out.push_str(&self.bounds(&t.bounds));
+        out.push_str(&self.where_clause(&t.generics));
+        out.push(';');
+        self.pop_generics();
+        out
+    }
+
+    fn impl_block(&mut self, i: &NuImpl) -> String {
+        self.push_generics(&i.generics);
+        let mut out = self.attrs(&i.attrs);
+        if i.is_unsafe {
+            out.push_str("unsafe ");
+        }
+        out.push_str("impl");
+        out.push_str(&self.generic_params(&i.generics));
+        out.push(' ');
+        if let Some(trait_ref) = &i.trait_ref {
+            if i.negative {
+                out.push('!');
+            }
+            out.push_str(&self.path(trait_ref, PathMode::Type));
+            out.push_str(" for ");
+        }
+        out.push_str(&self.ty(&i.self_ty));
+        out.push_str(&self.where_clause(&i.generics));
+        out.push_str(" {\n");
+        let ctx = if i.trait_ref.is_some() {
+            FnContext::TraitImpl
+        } else {
+            FnContext::InherentImpl
+        };
+        for item in &i.items {
+            let code = match item {
+                ImplItem::Fn(f) => self.function(f, ctx),
+                ImplItem::Const(c) if ctx == FnContext::TraitImpl => self.assoc_const(c),
+                ImplItem::Const(c) => self.constant(c),
+                ImplItem::Type(ty) => self.assoc_type(ty),
+                ImplItem::Macro(m) => self.macro_item(m),
+            };
+            out.push_str(&code);
+            out.push('\n');
+        }
+        out.push('}');
+        self.pop_generics();
+        out
+    }
+
+    fn module(&mut self, m: &NuMod) -> String {
+        let (inner, outer): (Vec<&NuAttr>, Vec<&NuAttr>) = m.attrs.iter().partition(|a| a.is_inner);
+        let mut out: String = outer
+            .iter()
+            .map(|a| format!("{}\n", self.attr(a)))
+            .collect();
+        out.push_str(&vis_str(&m.vis));
+        out.push_str("mod ");
+        out.push_str(ident(&m.name));
+        match &m.items {
+            Some(items) => {
+                out.push_str(" {\n");
+                for attr in inner {
+                    out.push_str(&self.attr(attr));
+                    out.push('\n');
+                }
+                for item in items {
+                    out.push_str(&self.item(item));
+                    out.push('\n');
+                }
+                out.push('}');
+            }
+            None => out.push(';'),
+        }
+        out
+    }
+
+    fn constant(&mut self, c: &NuConst) -> String {
+        let mut out = self.attrs(&c.attrs);
+        out.push_str(&vis_str(&c.vis));
+        out.push_str(&self.const_body(c));
+        out
+    }
+
+    /// trait 与 trait 实现中的关联常量不带可见性
+    fn assoc_const(&mut self, c: &NuConst) -> String {
+        format!("{}{}", self.attrs(&c.attrs), self.const_body(c))
+    }
+
+    fn const_body(&mut self, c: &NuConst) -> String {
+        let mut out = format!("const {}: {}", ident(&c.name), self.ty(&c.ty));
+        if let Some(value) = &c.value {
+            out.push_str(" = ");
+            out.push_str(&self.expr(value));
+        }
+        out.push(';');
+        out
+    }
+
+    fn static_item(&mut self, s: &NuStatic) -> String {
+        let mut out = self.attrs(&s.attrs);
+        out.push_str(&vis_str(&s.vis));
+        out.push_str("static ");
+        if s.mutable {
+            out.push_str("mut ");
+        }
+        out.push_str(&format!("{}: {}", ident(&s.name), self.ty(&s.ty)));
+        if let Some(value) = &s.value {
+            out.push_str(" = ");
+            out.push_str(&self.expr(value));
+        }
+        out.push(';');
+        out
+    }
+
+    fn type_alias(&mut self, t: &NuTypeAlias) -> String {
+        format!(
+            "{}{}{}",
+            self.attrs(&t.attrs),
+            vis_str(&t.vis),
+            self.type_alias_body(t)
+        )
+    }
+
+    fn assoc_type(&mut self, t: &NuTypeAlias) -> String {
+        format!("{}{}", self.attrs(&t.attrs), self.type_alias_body(t))
+    }
+
+    fn type_alias_body(&mut self, t: &NuTypeAlias) -> String {
+        self.push_generics(&t.generics);
+        let mut out = format!(
+            "type {}{}",
+            ident(&t.name),
+            self.generic_params(&t.generics)
+        );
+        if !t.bounds.is_empty() {
+            out.push_str(": ");
+            out.push_str(&self.bounds(&t.bounds));
+        }
+        out.push_str(&self.where_clause(&t.generics));
+        if let Some(ty) = &t.ty {
+            out.push_str(" = ");
+            out.push_str(&self.ty(ty));
+        }
+        out.push(';');
+        self.pop_generics();
+        out
+    }
+
+    fn extern_block(&mut self, e: &NuExtern) -> String {
+        let (inner, outer): (Vec<&NuAttr>, Vec<&NuAttr>) = e.attrs.iter().partition(|a| a.is_inner);
+        let mut out: String = outer
+            .iter()
+            .map(|a| format!("{}\n", self.attr(a)))
+            .collect();
+        if e.is_unsafe {
+            out.push_str("unsafe ");
+        }
+        out.push_str("extern ");
+        if let Some(abi) = &e.abi {
+            out.push_str(abi);
+            out.push(' ');
+        }
+        out.push_str("{\n");
+        for attr in inner {
+            out.push_str(&self.attr(attr));
+            out.push('\n');
+        }
+        for item in &e.items {
+            let code = match item {
+                ForeignItem::Fn(f) => self.function(f, FnContext::Foreign),
+                ForeignItem::Static(s) => self.static_item(s),
+                ForeignItem::Type(t) => self.type_alias(t),
+                ForeignItem::Macro(m) => self.macro_item(m),
+            };
+            out.push_str(&code);
+            out.push('\n');
+        }
+        out.push('}');
+        out
+    }
+
+    fn extern_crate(&mut self, c: &NuExternCrate) -> String {
+        let mut out = self.attrs(&c.attrs);
+        out.push_str(&vis_str(&c.vis));
+        out.push_str("extern crate ");
+        out.push_str(ident(&c.name));
+        if let Some(rename) = &c.rename {
+            out.push_str(" as ");
+            out.push_str(ident(rename));
+        }
+        out.push(';');
+        out
+    }
+
+    fn macro_item(&mut self, m: &NuMacroItem) -> String {
+        let mut out = self.attrs(&m.attrs);
+        match &m.name {
+            Some(name) => {
+                out.push_str(&format!(
+                    "macro_rules! {} {}",
+                    name,
+                    delimited(m.mac.delimiter, &m.mac.tokens)
+                ));
+                if m.mac.delimiter != MacroDelimiter::Brace {
+                    out.push(';');
+                }
+            }
+            None => {
+                out.push_str(&self.macro_call(&m.mac));
+                if m.mac.delimiter != MacroDelimiter::Brace {
+                    out.push(';');
+                }
+            }
+        }
+        out
+    }
+
+    /// 宏体原样输出；V! 还原为 vec!
+    fn macro_call(&mut self, mac: &NuMacroCall) -> String {
+        let path = match mac.path.as_ident() {
+            Some("V") => "vec".to_string(),
+            _ => self.path(&mac.path, PathMode::Expr),
+        };
+        format!("{}!{}", path, delimited(mac.delimiter, &mac.tokens))
+    }
+
+    // ============ 泛型 ============
+
+    fn generic_params(&mut self, generics: &NuGenerics) -> String {
+        if generics.params.is_empty() {
+            return String::new();
+        }
+        let parts: Vec<String> = generics
+            .params
+            .iter()
+            .map(|p| match p {
+                GenericParam::Lifetime { name, bounds, .. } => {
+                    if bounds.is_empty() {
+                        name.clone()
+                    } else {
+                        format!("{}: {}", name, bounds.join(" + "))
+                    }
+                }
+                GenericParam::Type {
+                    name,
+                    bounds,
+                    default,
+                    ..
+                } => {
+                    let mut out = ident(name).to_string();
+                    if !bounds.is_empty() {
+                        out.push_str(": ");
+                        out.push_str(&self.bounds(bounds));
+                    }
+                    if let Some(d) = default {
+                        out.push_str(" = ");
+                        out.push_str(&self.ty(d));
+                    }
+                    out
+                }
+                GenericParam::Const {
+                    name, ty, default, ..
+                } => {
+                    let mut out = format!("const {}: {}", ident(name), self.ty(ty));
+                    if let Some(d) = default {
+                        out.push_str(" = ");
+                        out.push_str(&self.expr(d));
+                    }
+                    out
+                }
+            })
+            .collect();
+        format!("<{}>", parts.join(", "))
+    }
+
+    fn where_clause(&mut self, generics: &NuGenerics) -> String {
+        if generics.where_clause.is_empty() {
+            return String::new();
+        }
+        let parts: Vec<String> = generics
+            .where_clause
+            .iter()
+            .map(|p| match p {
+                WherePredicate::Type {
+                    ty,
+                    bounds,
+                    for_lifetimes,
+                    ..
+                } => format!(
+                    "{}{}: {}",
+                    for_prefix(for_lifetimes),
+                    self.ty(ty),
+                    self.bounds(bounds)
+                ),
+                WherePredicate::Lifetime { name, bounds, .. } => {
+                    format!("{}: {}", name, bounds.join(" + "))
+                }
+            })
+            .collect();
+        format!(" where {}", parts.join(", "))
+    }
+
+    fn bounds(&mut self, bounds: &[TypeBound]) -> String {
+        let parts: Vec<String> = bounds
+            .iter()
+            .map(|b| match b {
+                TypeBound::Lifetime(l) => l.clone(),
+                TypeBound::Trait {
+                    path,
+                    maybe,
+                    for_lifetimes,
+                } => format!(
+                    "{}{}{}",
+                    for_prefix(for_lifetimes),
+                    if *maybe { "?" } else { "" },
+                    self.path(path, PathMode::Type)
+                ),
+            })
+            .collect();
+        parts.join(" + ")
+    }
+
+    // ============ 路径与类型 ============
+
+    /// 类型路径展开首段缩写（以及 io::R 这类模块路径末段）；
+    /// 表达式/模式路径只展开多段路径的非末段（V::new、O::Some）
+    fn path(&mut self, path: &NuPath, mode: PathMode) -> String {
+        let len = path.segments.len();
+        let first = path
+            .segments
+            .first()
+            .map(|s| s.ident.as_str())
+            .unwrap_or("");
+        let first_shadowed = self.is_shadowed(first);
+        let module_path = first.starts_with(|c: char| c.is_lowercase());
+
+        let mut out = String::new();
+        if path.leading_colon {
+            out.push_str("::");
+        }
+        for (i, seg) in path.segments.iter().enumerate() {
+            if i > 0 {
+                out.push_str("::");
+            }
+            let expand = !path.leading_colon
+                && !first_shadowed
+                && match mode {
+                    PathMode::Type => i == 0 || (i == len - 1 && module_path),
+                    PathMode::Expr => i + 1 < len,
+                };
+            match expand_abbreviation(&seg.ident) {
+                Some(full) if expand => out.push_str(full),
+                _ => out.push_str(ident(&seg.ident)),
+            }
+            if let Some(args) = &seg.args {
+                out.push_str(&self.generic_args(args, mode));
+            }
+        }
+        out
+    }
+
+    fn generic_args(&mut self, args: &GenericArgs, mode: PathMode) -> String {
+        match args {
+            GenericArgs::AngleBracketed { args, turbofish } => {
+                let parts: Vec<String> = args
+                    .iter()
+                    .map(|a| match a {
+                        GenericArg::Lifetime(l) => l.clone(),
+                        GenericArg::Type(t) => self.ty(t),
+                        GenericArg::Const(e) => self.expr(e),
+                        GenericArg::Binding { name, ty } => {
+                            format!("{} = {}", ident(name), self.ty(ty))
+                        }
+                        GenericArg::Constraint { name, bounds } => {
+                            format!("{}: {}", ident(name), self.bounds(bounds))
+                        }
+                    })
+                    .collect();
+                let colons = if *turbofish || mode == PathMode::Expr {
+                    "::"
+                } else {
+                    ""
+                };
+                format!("{}<{}>", colons, parts.join(", "))
+            }
+            GenericArgs::Parenthesized { inputs, output } => {
+                let parts: Vec<String> = inputs.iter().map(|t| self.ty(t)).collect();
+                let mut out = format!("({})", parts.join(", "));
+                if let Some(o) = output {
+                    out.push_str(" -> ");
+                    out.push_str(&self.ty(o));
+                }
+                out
+            }
+        }
+    }
+
+    fn ty(&mut self, ty: &NuType) -> String {
+        match &ty.kind {
+            TypeKind::Path(path) => self.path(path, PathMode::Type),
+            TypeKind::QSelf {
+                ty,
+                trait_path,
+                rest,
+            } => {
+                let mut out = format!("<{}", self.ty(ty));
+                if let Some(tr) = trait_path {
+                    out.push_str(" as ");
+                    out.push_str(&self.path(tr, PathMode::Type));
+                }
+                out.push('>');
+                for seg in rest {
+                    out.push_str("::");
+                    out.push_str(ident(&seg.ident));
+                    if let Some(args) = &seg.args {
+                        out.push_str(&self.generic_args(args, PathMode::Type));
+                    }
+                }
+                out
+            }
+            TypeKind::Ref {
+                lifetime,
+                mutable,
+                ty,
+            } => {
+                let mut out = "&".to_string();
+                if let Some(l) = lifetime {
+                    out.push_str(l);
+                    out.push(' ');
+                }
+                if *mutable {
+                    out.push_str("mut ");
+                }
+                out.push_str(&self.pointee(ty));
+                out
+            }
+            TypeKind::Ptr { mutable, ty } => {
+                let m = if *mutable { "*mut " } else { "*const " };
+                format!("{}{}", m, self.pointee(ty))
+            }
+            TypeKind::Tuple(elems) => {
+                let parts: Vec<String> = elems.iter().map(|t| self.ty(t)).collect();
+                if parts.len() == 1 {
+                    format!("({},)", parts[0])
+                } else {
+                    format!("({})", parts.join(", "))
+                }
+            }
+            TypeKind::Slice(inner) => format!("[{}]", self.ty(inner)),
+            TypeKind::Array { ty, len } => format!("[{}; {}]", self.ty(ty), self.expr(len)),
+            TypeKind::Fn {
+                is_unsafe,
+                abi,
+                inputs,
+                output,
+            } => {
+                let mut out = String::new();
+                if *is_unsafe {
+                    out.push_str("unsafe ");
+                }
+                if let Some(abi) = abi {
+                    out.push_str("extern ");
+                    if !abi.is_empty() {
+                        out.push_str(abi);
+                        out.push(' ');
+                    }
+                }
+                let parts: Vec<String> = inputs.iter().map(|t| self.ty(t)).collect();
+                out.push_str(&format!("fn({})", parts.join(", ")));
+                if let Some(o) = output {
+                    out.push_str(" -> ");
+                    out.push_str(&self.ty(o));
+                }
+                out
+            }
+            TypeKind::ImplTrait(bounds) => format!("impl {}", self.bounds(bounds)),
+            TypeKind::DynTrait(bounds) => format!("dyn {}", self.bounds(bounds)),
+            TypeKind::Never => "!".to_string(),
+            TypeKind::Infer => "_".to_string(),
+            TypeKind::Macro(mac) => self.macro_call(mac),
+        }
+    }
+
+    /// &(dyn A + B) 需要括号
+    fn pointee(&mut self, ty: &NuType) -> String {
+        let code = self.ty(ty);
+        match &ty.kind {
+            TypeKind::DynTrait(b) | TypeKind::ImplTrait(b) if b.len() > 1 => format!("({})", code),
+            _ => code,
+        }
+    }
+
+    // ============ 语句 ============
+
+    fn block(&mut self, block: &NuBlock) -> String {
+        let mut out = "{\n".to_string();
+        let last = block.stmts.len().saturating_sub(1);
+        for (i, stmt) in block.stmts.iter().enumerate() {
+            out.push_str(&self.stmt(stmt, i == last));
+            out.push('\n');
+        }
+        out.push('}');
+        out
+    }
+
+    fn stmt(&mut self, stmt: &NuStmt, is_last: bool) -> String {
+        match &stmt.kind {
+            StmtKind::Let(local) => self.local(local),
+            StmtKind::Item(item) => self.item(item),
+            StmtKind::Expr { expr, has_semi } => {
+                let mut out = self.attrs(&expr.attrs);
+                out.push_str(&self.expr(expr));
+                // Nu 允许以换行结束语句：非末尾的表达式语句补 `;`
+                if *has_semi || (!is_last && !is_block_like(expr)) {
+                    out.push(';');
+                }
+                out
+            }
+            StmtKind::Empty => String::new(),
+        }
+    }
+
+    fn local(&mut self, local: &NuLocal) -> String {
+        let mut out = self.attrs(&local.attrs);
+        out.push_str("let ");
+        let mut pat = local.pat.clone();
+        if local.is_mut {
+            mark_bindings_mut(&mut pat);
+        }
+        out.push_str(&self.pat(&pat));
+        if let Some(ty) = &local.ty {
+            out.push_str(": ");
+            out.push_str(&self.ty(ty));
+        }
+        if let Some(init) = &local.init {
+            out.push_str(" = ");
+            out.push_str(&self.expr(init));
+        }
+        if let Some(else_block) = &local.else_block {
+            out.push_str(" else ");
+            out.push_str(&self.block(else_block));
+        }
+        out.push(';');
+        out
+    }
+
+    // ============ 表达式 ============
+
+    /// 优先级低于 min 的子表达式加括号
+    fn operand(&mut self, expr: &NuExpr, min: u8) -> String {
+        let code = self.expr(expr);
+        if expr_prec(expr) < min {
+            format!("({})", code)
+        } else {
+            code
+        }
+    }
+
+    fn exprs(&mut self, exprs: &[NuExpr]) -> String {
+        let parts: Vec<String> = exprs.iter().map(|e| self.expr(e)).collect();
+        parts.join(", ")
+    }
+
+    fn label(label: &Option<String>) -> String {
+        match label {
+            Some(l) => format!("{}: ", l),
+            None => String::new(),
+        }
+    }
+
+    fn expr(&mut self, expr: &NuExpr) -> String {
+        match &expr.kind {
+            ExprKind::Lit(lit) => match lit {
+                Lit::Int(s)
+                | Lit::Float(s)
+                | Lit::Str(s)
+                | Lit::ByteStr(s)
+                | Lit::Char(s)
+                | Lit::Byte(s) => s.clone(),
+                Lit::Bool(b) => b.to_string(),
+            },
+            ExprKind::Path(path) => self.path(path, PathMode::Expr),
+            ExprKind::QPath(ty) => self.ty(ty),
+            ExprKind::Unary { op, expr } => {
+                let op = match op {
+                    UnOp::Neg => "-",
+                    UnOp::Not => "!",
+                    UnOp::Deref => "*",
+                };
+                format!("{}{}", op, self.operand(expr, P_PREFIX))
+            }
+            ExprKind::Binary { op, lhs, rhs } => {
+                let prec = binop_prec(*op);
+                // 比较运算符不可结合：两侧同级都要加括号
+                let lhs_min = if prec == P_CMP { prec + 1 } else { prec };
+                format!(
+                    "{} {} {}",
+                    self.operand(lhs, lhs_min),
+                    op.as_str(),
+                    self.operand(rhs, prec + 1)
+                )
+            }
+            ExprKind::Assign { lhs, rhs } => {
+                format!(
+                    "{} = {}",
+                    self.operand(lhs, P_RANGE),
+                    self.operand(rhs, P_ASSIGN)
+                )
+            }
+            ExprKind::AssignOp { op, lhs, rhs } => format!(
+                "{} {}= {}",
+                self.operand(lhs, P_RANGE),
+                op.as_str(),
+                self.operand(rhs, P_ASSIGN)
+            ),
+            ExprKind::Ref { mutable, expr } => {
+                let m = if *mutable { "&mut " } else { "&" };
+                format!("{}{}", m, self.operand(expr, P_PREFIX))
+            }
+            ExprKind::Call { func, args } => {
+                format!("{}({})", self.operand(func, P_POSTFIX), self.exprs(args))
+            }
+            ExprKind::MethodCall {
+                receiver,
+                method,
+                turbofish,
+                args,
+            } => {
+                let mut out = format!("{}.{}", self.operand(receiver, P_POSTFIX), ident(method));
+                if let Some(t) = turbofish {
+                    out.push_str(&self.generic_args(t, PathMode::Expr));
+                }
+                out.push_str(&format!("({})", self.exprs(args)));
+                out
+            }
+            ExprKind::Field { expr, name } => {
+                format!("{}.{}", self.operand(expr, P_POSTFIX), ident(name))
+            }
+            ExprKind::Index { expr, index } => {
+                format!("{}[{}]", self.operand(expr, P_POSTFIX), self.expr(index))
+            }
+            ExprKind::Try(inner) => format!("{}?", self.operand(inner, P_POSTFIX)),
+            ExprKind::Await(inner) => format!("{}.await", self.operand(inner, P_POSTFIX)),
+            ExprKind::Cast { expr, ty } => {
+                format!("{} as {}", self.operand(expr, P_CAST), self.ty(ty))
+            }
+            ExprKind::Tuple(elems) => {
+                if elems.len() == 1 {
+                    format!("({},)", self.expr(&elems[0]))
+                } else {
+                    format!("({})", self.exprs(elems))
+                }
+            }
+            ExprKind::Array(elems) => format!("[{}]", self.exprs(elems)),
+            ExprKind::Repeat { expr, len } => format!("[{}; {}]", self.expr(expr), self.expr(len)),
+            ExprKind::Struct { path, fields, base } => {
+                let mut parts: Vec<String> = fields
+                    .iter()
+                    .map(|f| match &f.expr {
+                        Some(e) => format!("{}: {}", ident(&f.name), self.expr(e)),
+                        None => ident(&f.name).to_string(),
+                    })
+                    .collect();
+                if let Some(base) = base {
+                    parts.push(format!("..{}", self.expr(base)));
+                }
+                format!(
+                    "{} {{ {} }}",
+                    self.path(path, PathMode::Expr),
+                    parts.join(", ")
+                )
+            }
+            ExprKind::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                let mut out = String::new();
+                if let Some(s) = start {
+                    out.push_str(&self.operand(s, P_RANGE + 1));
+                }
+                out.push_str(if *inclusive { "..=" } else { ".." });
+                if let Some(e) = end {
+                    out.push_str(&self.operand(e, P_RANGE + 1));
+                }
+                out
+            }
+            ExprKind::Paren(inner) => format!("({})", self.expr(inner)),
+            ExprKind::Block { label, block } => {
+                format!("{}{}", Self::label(label), self.block(block))
+            }
+            ExprKind::Unsafe(block) => format!("unsafe {}", self.block(block)),
+            ExprKind::Async { is_move, block } => {
+                let m = if *is_move { "move " } else { "" };
+                format!("async {}{}", m, self.block(block))
+            }
+            ExprKind::If {
+                cond,
+                then_branch,
+                else_branch,
+            } => {
+                let mut out = format!("if {} {}", self.expr(cond), self.block(then_branch));
+                if let Some(e) = else_branch {
+                    out.push_str(" else ");
+                    out.push_str(&self.expr(e));
+                }
+                out
+            }
+            ExprKind::Let { pat, expr } => {
+                format!("let {} = {}", self.pat(pat), self.operand(expr, P_AND + 1))
+            }
+            ExprKind::Match { expr, arms } => {
+                let mut out = format!("match {} {{\n", self.expr(expr));
+                for arm in arms {
+                    out.push_str(&self.attrs(&arm.attrs));
+                    out.push_str(&self.pat(&arm.pat));
+                    if let Some(g) = &arm.guard {
+                        out.push_str(" if ");
+                        out.push_str(&self.expr(g));
+                    }
+                    out.push_str(" => ");
+                    out.push_str(&self.expr(&arm.body));
+                    out.push_str(",\n");
+                }
+                out.push('}');
+                out
+            }
+            ExprKind::Loop { label, body } => {
+                format!("{}loop {}", Self::label(label), self.block(body))
+            }
+            ExprKind::ForLoop {
+                label,
+                pat,
+                iter,
+                body,
+            } => format!(
+                "{}for {} in {} {}",
+                Self::label(label),
+                self.pat(pat),
+                self.expr(iter),
+                self.block(body)
+            ),
+            ExprKind::While { label, cond, body } => format!(
+                "{}while {} {}",
+                Self::label(label),
+                self.expr(cond),
+                self.block(body)
+            ),
+            ExprKind::Closure {
+                is_move,
+                is_async,
+                params,
+                ret,
+                body,
+            } => {
+                let mut out = String::new();
+                if *is_async {
+                    out.push_str("async ");
+                }
+                if *is_move {
+                    out.push_str("move ");
+                }
+                let parts: Vec<String> = params
+                    .iter()
+                    .map(|p| match &p.ty {
+                        Some(ty) => format!("{}: {}", self.pat(&p.pat), self.ty(ty)),
+                        None => self.pat(&p.pat),
+                    })
+                    .collect();
+                out.push_str(&format!("|{}|", parts.join(", ")));
+                if let Some(ret) = ret {
+                    out.push_str(" -> ");
+                    out.push_str(&self.ty(ret));
+                }
+                out.push(' ');
+                out.push_str(&self.expr(body));
+                out
+            }
+            ExprKind::Return(value) => match value {
+                Some(v) => format!("return {}", self.expr(v)),
+                None => "return".to_string(),
+            },
+            ExprKind::Break { label, expr } => {
+                let mut out = "break".to_string();
+                if let Some(l) = label {
+                    out.push(' ');
+                    out.push_str(l);
+                }
+                if let Some(e) = expr {
+                    out.push(' ');
+                    out.push_str(&self.expr(e));
+                }
+                out
+            }
+            ExprKind::Continue { label } => match label {
+                Some(l) => format!("continue {}", l),
+                None => "continue".to_string(),
+            },
+            ExprKind::Macro(mac) => self.macro_call(mac),
+            ExprKind::Spawn(inner) => match &inner.kind {
+                ExprKind::Block { block, .. } => {
+                    format!("tokio::spawn(async move {})", self.block(block))
+                }
+                _ => format!("tokio::spawn({})", self.expr(inner)),
+            },
+            ExprKind::Thread(inner) => match &inner.kind {
+                ExprKind::Closure { .. } | ExprKind::Path(_) => {
+                    format!("std::thread::spawn({})", self.expr(inner))
+                }
+                _ => format!("std::thread::spawn(move || {})", self.expr(inner)),
+            },
+            ExprKind::Recv(inner) => format!("{}.recv()", self.operand(inner, P_POSTFIX)),
+        }
+    }
+
+    // ============ 模式 ============
+
+    fn pats(&mut self, pats: &[NuPat]) -> String {
+        let parts: Vec<String> = pats.iter().map(|p| self.pat(p)).collect();
+        parts.join(", ")
+    }
+
+    fn pat(&mut self, pat: &NuPat) -> String {
+        match &pat.kind {
+            PatKind::Wild => "_".to_string(),
+            PatKind::Rest => "..".to_string(),
+            PatKind::Ident {
+                by_ref,
+                mutable,
+                name,
+                sub,
+            } => {
+                let mut out = String::new();
+                if *by_ref {
+                    out.push_str("ref ");
+                }
+                if *mutable {
+                    out.push_str("mut ");
+                }
+                out.push_str(ident(name));
+                if let Some(sub) = sub {
+                    out.push_str(" @ ");
+                    out.push_str(&self.pat(sub));
+                }
+                out
+            }
+            PatKind::Lit(e) => self.expr(e),
+            PatKind::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                let mut out = String::new();
+                if let Some(s) = start {
+                    out.push_str(&self.expr(s));
+                }
+                out.push_str(if *inclusive { "..=" } else { ".." });
+                if let Some(e) = end {
+                    out.push_str(&self.expr(e));
+                }
+                out
+            }
+            PatKind::Path(path) => self.path(path, PathMode::Expr),
+            PatKind::TupleStruct { path, elems } => {
+                format!("{}({})", self.path(path, PathMode::Expr), self.pats(elems))
+            }
+            PatKind::Struct { path, fields, rest } => {
+                let mut parts: Vec<String> = fields
+                    .iter()
+                    .map(|f| match &f.pat {
+                        Some(p) => match &p.kind {
+                            // 简写绑定 ref x / mut x
+                            PatKind::Ident {
+                                name, sub: None, ..
+                            } if *name == f.name => self.pat(p),
+                            _ => format!("{}: {}", ident(&f.name), self.pat(p)),
+                        },
+                        None => ident(&f.name).to_string(),
+                    })
+                    .collect();
+                if *rest {
+                    parts.push("..".to_string());
+                }
+                format!(
+                    "{} {{ {} }}",
+                    self.path(path, PathMode::Expr),
+                    parts.join(", ")
+                )
+            }
+            PatKind::Tuple(elems) => {
+                if elems.len() == 1 && !matches!(elems[0].kind, PatKind::Rest) {
+                    format!("({},)", self.pat(&elems[0]))
+                } else {
+                    format!("({})", self.pats(elems))
+                }
+            }
+            PatKind::Slice(elems) => format!("[{}]", self.pats(elems)),
+            PatKind::Ref { mutable, pat } => {
+                let m = if *mutable { "&mut " } else { "&" };
+                let inner = self.pat(pat);
+                match pat.kind {
+                    PatKind::Range { .. } | PatKind::Or(_) => format!("{}({})", m, inner),
+                    _ => format!("{}{}", m, inner),
+                }
+            }
+            PatKind::Or(alts) => {
+                let parts: Vec<String> = alts.iter().map(|p| self.pat(p)).collect();
+                parts.join(" | ")
+            }
+            PatKind::Macro(mac) => self.macro_call(mac),
+        }
+    }
+}
+
+fn use_tree(tree: &UseTree) -> String {
+    match tree {
+        UseTree::Path { ident: name, tree } => format!("{}::{}", ident(name), use_tree(tree)),
+        UseTree::Name(name) => ident(name).to_string(),
+        UseTree::Rename {
+            ident: name,
+            rename,
+        } => {
+            format!("{} as {}", ident(name), ident(rename))
+        }
+        UseTree::Glob => "*".to_string(),
+        UseTree::Group(trees) => {
+            let parts: Vec<String> = trees.iter().map(use_tree).collect();
+            format!("{{{}}}", parts.join(", "))
+        }
+    }
+}
+
+fn for_prefix(lifetimes: &[String]) -> String {
+    if lifetimes.is_empty() {
+        String::new()
+    } else {
+        format!("for<{}> ", lifetimes.join(", "))
+    }
+}
+
+/// 收集文件中声明的类型名（含嵌套模块）
+fn collect_declared(items: &[NuItem], declared: &mut HashSet<String>) {
+    for item in items {
+        match item {
+            NuItem::Struct(s) => {
+                declared.insert(s.name.clone());
+            }
+            NuItem::Enum(e) => {
+                declared.insert(e.name.clone());
+            }
+            NuItem::Union(u) => {
+                declared.insert(u.name.clone());
+            }
+            NuItem::Trait(t) => {
+                declared.insert(t.name.clone());
+            }
+            NuItem::TraitAlias(t) => {
+                declared.insert(t.name.clone());
+            }
+            NuItem::TypeAlias(t) => {
+                declared.insert(t.name.clone());
+            }
+            NuItem::Use(u) => declared.extend(u.tree.bound_names()),
+            NuItem::Mod(NuMod {
+                items: Some(items), ..
+            }) => collect_declared(items, declared),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn gen(src: &str) -> String {
+        let ast = Parser::new().parse(src).unwrap();
+        CodeGenerator::new().generate(&ast).unwrap()
+    }
+
+    #[test]
+    fn test_generate_items() {
+        let rust = gen(r#"
+#D(Debug, Clone)
+S Point<T> {
+    x: T,
+    pub(crate) y: T
+}
+
+E Shape {
+    Circle(f64),
+    Empty = 3
+}
+
+I<T: Clone> Point<T> {
+    F new(x: T, y: T) -> Self { Point { x, y } }
+    f helper(&!self) {}
+}
+
+u std::collections::HashMap
+CP MAX: usize = 10;
+"#);
+        assert!(rust.contains("#[derive(Debug, Clone)]\npub struct Point<T> {\n    pub x: T,\n    pub(crate) y: T,\n}"));
+        assert!(rust.contains("pub enum Shape {\n    Circle(f64),\n    Empty = 3,\n}"));
+        assert!(rust.contains("impl<T: Clone> Point<T> {"));
+        assert!(rust.contains("pub fn new(x: T, y: T) -> Self {"));
+        assert!(rust.contains("fn helper(&mut self) {}"));
+        assert!(rust.contains("use std::collections::HashMap;"));
+        assert!(rust.contains("pub const MAX: usize = 10;"));
+    }
+
+    #[test]
+    fn test_generate_statements() {
+        let rust = gen(r#"
+f main() -> R<(), String> {
+    v list: V<i32> = V::new()
+    L i: 0..10 {
+        if i % 2 == 0 { ct }
+        list.push(i)
+    }
+    l total = M list.len() {
+        0 => < Ok(()),
+        n => n * 2,
+    };
+    l parsed = "5".parse::<i32>().map_err(|e| e.to_string())!;
+    println!("{} {}", total, parsed);
+    Ok(())
+}
+"#);
+        assert!(rust.contains("fn main() -> Result<(), String> {"));
+        assert!(rust.contains("let mut list: Vec<i32> = Vec::new();"));
+        assert!(rust.contains("for i in 0..10 {"));
+        assert!(rust.contains("continue;"));
+        assert!(rust.contains("0 => return Ok(()),"));
+        assert!(rust.contains("\"5\".parse::<i32>().map_err(|e| e.to_string())?;"));
+        assert!(rust.contains("println!(\"{} {}\", total, parsed);"));
+    }
+
+    #[test]
+    fn test_abbreviations_respect_identifiers_and_generics() {
+        let rust = gen(r#"
+f read_all<R: std::io::Read>(mut reader: R, VERSION: &str) -> std::io::R<V<u8>> {
+    l label = "V<O>";
+    l VALUE = V![1, 2];
+    < Ok(V::new())
+}
+"#);
+        // 泛型参数 R 不会被展开成 Result
+        assert!(rust.contains("fn read_all<R: std::io::Read>(mut reader: R, VERSION: &str)"));
+        assert!(rust.contains("-> std::io::Result<Vec<u8>>"));
+        assert!(rust.contains("let label = \"V<O>\";"));
+        assert!(rust.contains("let VALUE = vec![1, 2];"));
+    }
+
+    #[test]
+    fn test_imported_names_shadow_abbreviations() {
+        let rust = gen(r#"
+u crate::shapes::R
+u crate::geo::{self, Point as O}
+F area(r: R, p: O, all: V<R>) -> f64 {
+    r.area()
+}
+f parent(w: W<Node>) {}
+"#);
+        assert!(rust.contains("pub fn area(r: R, p: O, all: Vec<R>) -> f64 {"));
+        assert!(rust.contains("fn parent(w: Weak<Node>) {}"));
+    }
+
+    #[test]
+    fn test_async_and_concurrency() {
+        let rust = gen(r#"
+~F fetch(url: &str) -> R<String, E> {
+    l body = get(url).~!;
+    l task = @ { work().~ };
+    l handle = @@ $|| compute();
+    l msg = << rx;
+    Ok(body)
+}
+"#);
+        assert!(rust.contains("pub async fn fetch(url: &str) -> Result<String, E> {"));
+        assert!(rust.contains("get(url).await?"));
+        assert!(rust.contains("tokio::spawn(async move { work().await })"));
+        assert!(rust.contains("std::thread::spawn(move || compute())"));
+        assert!(rust.contains("let msg = rx.recv();"));
+    }
+
+    #[test]
+    fn test_raw_identifiers_unescaped() {
+        let rust = gen("S Foo { r#a: i32, r#type: u8 }\nf r#l(r#V: i32) -> i32 {\n    l r#u = Foo { r#a: r#V, r#type: 0 };\n    r#u.r#a\n}");
+        assert!(rust.contains("pub a: i32,"), "{}", rust);
+        assert!(rust.contains("pub r#type: u8,"), "{}", rust);
+        assert!(rust.contains("fn l(V: i32) -> i32 {"), "{}", rust);
+        assert!(
+            rust.contains("let u = Foo { a: V, r#type: 0 };"),
+            "{}",
+            rust
+        );
+        assert!(rust.contains("u.a"), "{}", rust);
+    }
+
+    #[test]
+    fn test_union_extern_crate_and_trait_alias() {
+        let rust = gen(r#"
+#[macro_use]
+EXT crate libc;
+EXT crate alloc as a2;
+
+#[repr(C)]
+UN Value {
+    i: i32,
+    f: f32
+}
+un Raw<T: Copy> { x: T }
+
+EXT "C" {
+    F cos(x: f64) -> f64;
+    f printf(fmt: *const u8, ...) -> i32;
+}
+
+TR Shape = Clone + Send;
+tr Local<T> = Into<T> wh T: Copy;
+"#);
+        assert!(rust.contains("#[macro_use]\nextern crate libc;"));
+        assert!(rust.contains("extern crate alloc as a2;"));
+        assert!(rust.contains("#[repr(C)]\npub union Value {\n    pub i: i32,\n    pub f: f32,\n}"));
+        assert!(rust.contains("union Raw<T: Copy> {"));
+        assert!(rust.contains("fn printf(fmt: *const u8, ...) -> i32;"));
+        assert!(rust.contains("pub trait Shape = Clone + Send;"));
+        assert!(rust.contains("trait Local<T> = Into<T>\nwhere\n    T: Copy;"));
+    }
+
+    #[test]
+    fn test_precedence_parentheses() {
+        let rust = gen("f f1(a: i32, b: i32) -> bool {\n    l x = (a + b) * 2;\n    l y = -(a - b) a i64;\n    x > (a < b) a i32\n}");
+        assert!(rust.contains("let x = (a + b) * 2;"));
+        assert!(rust.contains("let y = -(a - b) as i64;"));
+    }
+}
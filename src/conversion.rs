@@ -0,0 +1,214 @@
+// 转换结果与诊断
+// Rust2Nu / Nu2Rust / Nu2Ts / Nu2Cpp 共用的库 API：返回生成代码、source map 映射和结构化诊断，
+// 嵌入方（构建服务、编辑器插件）无需解析 stderr 即可展示警告
+
+use crate::ast::Span;
+use crate::sourcemap::Mapping;
+use std::fmt;
+
+/// 诊断级别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        })
+    }
+}
+
+/// 一条转换诊断
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// 稳定的诊断代码，如 `unrecognized-construct`
+    pub code: &'static str,
+    pub message: String,
+    /// 在输入源码中的位置（行、列从 1 开始）
+    pub span: Option<Span>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            code,
+            message: message.into(),
+            span: None,
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn error(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, code, message)
+    }
+
+    pub fn warning(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, code, message)
+    }
+
+    pub fn note(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(Severity::Note, code, message)
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// 渲染为 rustc 风格的文本，source 为输入源码（用于显示源码片段）
+    pub fn render(&self, source: &str) -> String {
+        let mut out = format!("{}[{}]: {}", self.severity, self.code, self.message);
+        if let Some(span) = &self.span {
+            out.push_str(&format!("\n --> {}\n{}", span, span.snippet(source)));
+        }
+        for note in &self.notes {
+            out.push_str(&format!("\n  = note: {}", note));
+        }
+        out
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(span) = &self.span {
+            write!(f, "{}: ", span)?;
+        }
+        write!(f, "{}[{}]: {}", self.severity, self.code, self.message)
+    }
+}
+
+/// 一次转换的结果
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConversionOutput {
+    /// 生成的代码；转换失败时为空
+    pub code: String,
+    /// 生成代码到输入源码的映射（后端不支持时为空）
+    pub sourcemap: Vec<Mapping>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ConversionOutput {
+    /// 转换失败：只有一条错误诊断
+    pub fn failed(diagnostic: Diagnostic) -> Self {
+        Self {
+            diagnostics: vec![diagnostic],
+            ..Self::default()
+        }
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|d| d.severity == Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Warning)
+    }
+}
+
+/// 返回 ConversionOutput 的转换器
+pub trait Transpile {
+    /// 转换源码；失败不返回 Err，而是以错误诊断的形式出现在结果中
+    fn transpile(&self, source: &str) -> ConversionOutput;
+}
+
+/// 生成代码中未翻译内容的占位注释
+const PLACEHOLDER_MARKER: &str = "/* TODO";
+
+/// 为生成代码中的 `/* TODO ... */` 占位注释生成警告，位置按映射回推到输入源码行
+pub(crate) fn placeholder_diagnostics(code: &str, mappings: &[Mapping]) -> Vec<Diagnostic> {
+    let mut sorted = mappings.to_vec();
+    sorted.sort();
+    let source_line = |generated_line: usize| {
+        sorted
+            .iter()
+            .rev()
+            .find(|m| m.generated_line <= generated_line)
+            .map(|m| m.source_line)
+    };
+
+    let mut diagnostics = Vec::new();
+    for (idx, line) in code.lines().enumerate() {
+        let mut rest = line;
+        while let Some(pos) = rest.find(PLACEHOLDER_MARKER) {
+            let after = &rest[pos + PLACEHOLDER_MARKER.len()..];
+            let end = after.find("*/").unwrap_or(after.len());
+            let what = after[..end].trim_start_matches(':').trim();
+            let mut diagnostic = Diagnostic::warning(
+                "untranslated",
+                if what.is_empty() {
+                    "生成代码中有未翻译的占位".to_string()
+                } else {
+                    format!("未翻译：{}", what)
+                },
+            )
+            .with_note(format!("生成代码第 {} 行：{}", idx + 1, line.trim()));
+            if let Some(nu_line) = source_line(idx + 1) {
+                diagnostic = diagnostic.with_span(Span::new(nu_line, 1, 0));
+            }
+            diagnostics.push(diagnostic);
+            rest = &after[end..];
+        }
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let diagnostic = Diagnostic::warning("macro-passthrough", "宏 `lazy_static!` 原样保留")
+            .with_span(Span::new(2, 1, 11))
+            .with_note("宏内部不做 Nu 转换");
+        assert_eq!(
+            diagnostic.render("fn a() {}\nlazy_static! {}\n"),
+            "warning[macro-passthrough]: 宏 `lazy_static!` 原样保留\n --> 2:1\n  |\n2 | lazy_static! {}\n  | ^^^^^^^^^^^\n  = note: 宏内部不做 Nu 转换"
+        );
+        assert_eq!(
+            diagnostic.to_string(),
+            "2:1: warning[macro-passthrough]: 宏 `lazy_static!` 原样保留"
+        );
+    }
+
+    #[test]
+    fn test_placeholder_diagnostics() {
+        let code = "const a = 1;\nconst b = /* TODO: complex expression */ null;\n";
+        let mappings = [
+            Mapping {
+                generated_line: 1,
+                generated_col: 1,
+                source_line: 3,
+                source_col: 1,
+            },
+            Mapping {
+                generated_line: 2,
+                generated_col: 1,
+                source_line: 7,
+                source_col: 1,
+            },
+        ];
+        let diagnostics = placeholder_diagnostics(code, &mappings);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "未翻译：complex expression");
+        assert_eq!(diagnostics[0].span.as_ref().map(|s| s.line), Some(7));
+    }
+}
@@ -0,0 +1,591 @@
+// Rustc Diagnostic Remapping
+// 将 rustc/cargo 诊断（--message-format=json）中的影子项目 .rs 位置映射回 .nu 源码
+
+use crate::nu2rust::LazySourceMap;
+use crate::project::{ProjectManager, SHADOW_DIR};
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// 映射后的 Nu 区间（1-based，列按字符计数，column_end 不含）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NuLocation {
+    /// 输出用路径（以 source_root 为前缀）
+    pub file: PathBuf,
+    /// 读取源码用的实际路径
+    pub source: PathBuf,
+    pub line_start: usize,
+    pub column_start: usize,
+    pub line_end: usize,
+    pub column_end: usize,
+}
+
+/// 将 rustc/cargo 诊断中的 `.rs` 位置改写为影子项目对应的 .nu 位置
+pub struct DiagnosticRemapper {
+    shadow: PathBuf,
+    source_root: PathBuf,
+    maps: HashMap<PathBuf, Option<LazySourceMap>>,
+    sources: HashMap<PathBuf, Option<NuSource>>,
+}
+
+/// 已读取的 .nu 源码及各行起始字节偏移
+struct NuSource {
+    text: String,
+    line_starts: Vec<usize>,
+}
+
+impl NuSource {
+    fn new(text: String) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .filter(|&i| i < text.len())
+            .collect();
+        Self { text, line_starts }
+    }
+
+    /// 第 line 行（1-based）的内容，不含换行符
+    fn line(&self, line: usize) -> Option<&str> {
+        let start = *self.line_starts.get(line.checked_sub(1)?)?;
+        let end = self
+            .line_starts
+            .get(line)
+            .copied()
+            .unwrap_or(self.text.len());
+        Some(self.text[start..end].trim_end_matches(['\n', '\r']))
+    }
+
+    /// (line, column) 对应的字节偏移；列按字符计数，超出行尾时取行尾
+    fn byte_offset(&self, line: usize, column: usize) -> Option<usize> {
+        let start = *self.line_starts.get(line.checked_sub(1)?)?;
+        let text = self.line(line)?;
+        let within = text
+            .char_indices()
+            .nth(column.saturating_sub(1))
+            .map_or(text.len(), |(i, _)| i);
+        Some(start + within)
+    }
+}
+
+impl DiagnosticRemapper {
+    /// shadow：影子项目根目录；source_root：输出 .nu 路径时使用的项目根目录前缀
+    pub fn new(shadow: impl Into<PathBuf>, source_root: impl Into<PathBuf>) -> Self {
+        Self {
+            shadow: shadow.into(),
+            source_root: source_root.into(),
+            maps: HashMap::new(),
+            sources: HashMap::new(),
+        }
+    }
+
+    /// 影子项目中的 .rs 路径 -> (输出用 .nu 路径, .nu 文件实际路径, source map)
+    fn lookup(&mut self, rust_path: &str) -> Option<(PathBuf, PathBuf, &LazySourceMap)> {
+        let path = Path::new(rust_path);
+        let relative = path.strip_prefix(&self.shadow).unwrap_or(path);
+        if relative.is_absolute() || relative.extension().and_then(|e| e.to_str()) != Some("rs") {
+            return None;
+        }
+        let nu_relative = relative.with_extension("nu");
+        // 影子项目位于 <root>/target/nu-gen 时从项目根目录读取 .nu 源码
+        let project_root = if self.shadow.ends_with(SHADOW_DIR) {
+            self.shadow
+                .ancestors()
+                .nth(Path::new(SHADOW_DIR).components().count())
+                .unwrap_or(&self.source_root)
+        } else {
+            &self.source_root
+        };
+        let nu_file = project_root.join(&nu_relative);
+        let nu_path = self.source_root.join(&nu_relative);
+        let map_path = self.shadow.join(relative).with_extension("rs.map");
+        let map = self
+            .maps
+            .entry(map_path.clone())
+            .or_insert_with(|| LazySourceMap::load_from_file(&map_path).ok());
+        map.as_ref().map(|m| (nu_path, nu_file, m))
+    }
+
+    /// 映射一个 rustc span（column_end 不含）；不属于影子项目或没有 .map 时返回 None
+    pub fn remap_span(
+        &mut self,
+        rust_path: &str,
+        (line_start, column_start): (usize, usize),
+        (line_end, column_end): (usize, usize),
+    ) -> Option<NuLocation> {
+        let (file, source, map) = self.lookup(rust_path)?;
+        let single_line = line_start == line_end;
+        let start = map.find_nu_span(
+            line_start,
+            column_start,
+            if single_line {
+                column_end.saturating_sub(column_start)
+            } else {
+                1
+            },
+        );
+        let location = match start {
+            Some(start) if single_line => NuLocation {
+                file,
+                source,
+                line_start: start.line,
+                column_start: start.col,
+                line_end: start.line,
+                column_end: start.col + start.len,
+            },
+            Some(start) => {
+                let end = map.find_nu_span(line_end, column_end.saturating_sub(1).max(1), 1);
+                let (line_end, column_end) = match end {
+                    Some(end) if end.line >= start.line => (end.line, end.col + end.len),
+                    _ => (start.line, start.col + start.len),
+                };
+                NuLocation {
+                    file,
+                    source,
+                    line_start: start.line,
+                    column_start: start.col,
+                    line_end,
+                    column_end,
+                }
+            }
+            // 旧版 .map 没有列信息：标出整行
+            None => {
+                let nu_line = map.find_nearest_nu_line(line_start)?;
+                let nu_line_end = map
+                    .find_nearest_nu_line(line_end)
+                    .filter(|&l| l >= nu_line)
+                    .unwrap_or(nu_line);
+                let column_end = self
+                    .source_line(&source, nu_line_end)
+                    .map_or(1, |text| text.chars().count() + 1);
+                NuLocation {
+                    file,
+                    source,
+                    line_start: nu_line,
+                    column_start: 1,
+                    line_end: nu_line_end,
+                    column_end,
+                }
+            }
+        };
+        Some(location)
+    }
+
+    /// 读取 .nu 源码（按路径缓存）
+    fn source(&mut self, file: &Path) -> Option<&NuSource> {
+        self.sources
+            .entry(file.to_path_buf())
+            .or_insert_with(|| fs::read_to_string(file).ok().map(NuSource::new))
+            .as_ref()
+    }
+
+    /// 读取 .nu 源码的第 line 行（1-based）
+    fn source_line(&mut self, file: &Path, line: usize) -> Option<String> {
+        self.source(file)?.line(line).map(str::to_string)
+    }
+
+    /// 改写一条 rustc JSON 诊断（cargo 输出中的 `message` 字段）：
+    /// spans 与 children 中的 span 换成 .nu 位置，`text` 换成 Nu 源码行，`rendered` 重新生成
+    pub fn remap_message(&mut self, message: &mut Value) {
+        if let Some(spans) = message.get_mut("spans").and_then(Value::as_array_mut) {
+            for span in spans {
+                self.remap_json_span(span);
+            }
+        }
+        if let Some(children) = message.get_mut("children").and_then(Value::as_array_mut) {
+            for child in children {
+                self.remap_message(child);
+            }
+        }
+        if message.get("rendered").is_some_and(|r| !r.is_null()) {
+            message["rendered"] = Value::String(render_message(message));
+        }
+    }
+
+    fn remap_json_span(&mut self, span: &mut Value) {
+        if let Some(expansion) = span.get_mut("expansion").filter(|e| !e.is_null()) {
+            if let Some(call_site) = expansion.get_mut("span") {
+                self.remap_json_span(call_site);
+            }
+        }
+        let field = |name: &str| span.get(name).and_then(Value::as_u64).map(|v| v as usize);
+        let (Some(file), Some(ls), Some(cs), Some(le), Some(ce)) = (
+            span.get("file_name")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            field("line_start"),
+            field("column_start"),
+            field("line_end"),
+            field("column_end"),
+        ) else {
+            return;
+        };
+        let Some(nu) = self.remap_span(&file, (ls, cs), (le, ce)) else {
+            return;
+        };
+
+        let text: Vec<Value> = (nu.line_start..=nu.line_end)
+            .map(|line| {
+                let source = self.source_line(&nu.source, line).unwrap_or_default();
+                let start = if line == nu.line_start {
+                    nu.column_start
+                } else {
+                    1
+                };
+                let end = if line == nu.line_end {
+                    nu.column_end
+                } else {
+                    source.chars().count() + 1
+                };
+                json!({ "text": source, "highlight_start": start, "highlight_end": end })
+            })
+            .collect();
+        span["file_name"] = Value::String(nu.file.display().to_string());
+        span["line_start"] = json!(nu.line_start);
+        span["column_start"] = json!(nu.column_start);
+        span["line_end"] = json!(nu.line_end);
+        span["column_end"] = json!(nu.column_end);
+        let (byte_start, byte_end) = self
+            .source(&nu.source)
+            .and_then(|src| {
+                Some((
+                    src.byte_offset(nu.line_start, nu.column_start)?,
+                    src.byte_offset(nu.line_end, nu.column_end)?,
+                ))
+            })
+            .unwrap_or((0, 0));
+        span["byte_start"] = json!(byte_start);
+        span["byte_end"] = json!(byte_end);
+        span["text"] = Value::Array(text);
+        // 针对生成 Rust 代码的修复建议不能直接应用到 .nu 文件
+        span["suggested_replacement"] = Value::Null;
+        span["suggestion_applicability"] = Value::Null;
+    }
+
+    /// 解析 `cargo --message-format=json` 输出的一行；`compiler-message` 就地改写后返回
+    pub fn remap_cargo_line(&mut self, line: &str) -> Option<Value> {
+        let mut value: Value = serde_json::from_str(line).ok()?;
+        if value.get("reason").and_then(Value::as_str) == Some("compiler-message") {
+            if let Some(message) = value.get_mut("message") {
+                self.remap_message(message);
+            }
+        }
+        Some(value)
+    }
+}
+
+/// 按 rustc 的文本格式渲染一条 JSON 诊断（使用 span 中的 `text` 作为源码片段）
+pub fn render_message(message: &Value) -> String {
+    let mut out = String::new();
+    let spans = json_spans(message);
+    let children = message
+        .get("children")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    let gutter = spans
+        .iter()
+        .chain(
+            children
+                .iter()
+                .flat_map(json_spans)
+                .collect::<Vec<_>>()
+                .iter(),
+        )
+        .filter_map(|s| s.get("line_end").and_then(Value::as_u64))
+        .max()
+        .unwrap_or(0)
+        .to_string()
+        .len();
+    render_header(message, &mut out);
+    render_spans(&spans, gutter, &mut out);
+
+    let mut noted = false;
+    for child in &children {
+        let child_spans = json_spans(child);
+        if child_spans.is_empty() {
+            if !noted && !spans.is_empty() {
+                out.push_str(&format!("{} |\n", " ".repeat(gutter)));
+            }
+            noted = true;
+            // 多行 note 的后续行与首行消息对齐
+            let level = str_field(child, "level");
+            let continuation = format!("\n{}", " ".repeat(gutter + level.len() + 5));
+            out.push_str(&format!(
+                "{} = {}: {}\n",
+                " ".repeat(gutter),
+                level,
+                str_field(child, "message").replace('\n', &continuation)
+            ));
+        } else {
+            render_header(child, &mut out);
+            render_spans(&child_spans, gutter, &mut out);
+        }
+    }
+    out
+}
+
+fn str_field<'a>(value: &'a Value, name: &str) -> &'a str {
+    value.get(name).and_then(Value::as_str).unwrap_or("")
+}
+
+fn json_spans(message: &Value) -> Vec<Value> {
+    let mut spans = message
+        .get("spans")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    // 主 span 在前
+    spans.sort_by_key(|s| {
+        !s.get("is_primary")
+            .and_then(Value::as_bool)
+            .unwrap_or(false)
+    });
+    spans
+}
+
+fn render_header(message: &Value, out: &mut String) {
+    let level = str_field(message, "level");
+    // rustc 直接输出 failure-note 的内容
+    if level == "failure-note" {
+        out.push_str(str_field(message, "message"));
+        out.push('\n');
+        return;
+    }
+    match message
+        .get("code")
+        .and_then(|c| c.get("code"))
+        .and_then(Value::as_str)
+    {
+        Some(code) => out.push_str(&format!("{}[{}]: ", level, code)),
+        None => out.push_str(&format!("{}: ", level)),
+    }
+    out.push_str(str_field(message, "message"));
+    out.push('\n');
+}
+
+fn render_spans(spans: &[Value], gutter: usize, out: &mut String) {
+    let pad = " ".repeat(gutter);
+    let mut current_file = None;
+    let mut last_line = None;
+    for span in spans {
+        let file = str_field(span, "file_name");
+        let line = span.get("line_start").and_then(Value::as_u64).unwrap_or(0);
+        let column = span
+            .get("column_start")
+            .and_then(Value::as_u64)
+            .unwrap_or(0);
+        if current_file != Some(file) {
+            let marker = if current_file.is_none() { "-->" } else { ":::" };
+            out.push_str(&format!("{}{} {}:{}:{}\n", pad, marker, file, line, column));
+            out.push_str(&format!("{} |\n", pad));
+            current_file = Some(file);
+        }
+
+        let primary = span
+            .get("is_primary")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        let mark = if primary { "^" } else { "-" };
+        let label = span.get("label").and_then(Value::as_str).unwrap_or("");
+        let text = span.get("text").and_then(Value::as_array);
+        for (offset, entry) in text.into_iter().flatten().enumerate() {
+            let source = str_field(entry, "text");
+            let field = |name: &str| entry.get(name).and_then(Value::as_u64).unwrap_or(1) as usize;
+            let (start, end) = (field("highlight_start"), field("highlight_end"));
+            // 同一行上的多个 span 只输出一次源码
+            let number = line as usize + offset;
+            if last_line != Some((file, number)) {
+                out.push_str(&format!(
+                    "{:>width$} | {}\n",
+                    number,
+                    source,
+                    width = gutter
+                ));
+            }
+            last_line = Some((file, number));
+            // 保留制表符，使标记与源码对齐
+            let indent: String = source
+                .chars()
+                .take(start.saturating_sub(1))
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let marks = mark.repeat(end.saturating_sub(start).max(1));
+            let is_last = offset + 1 == text.map_or(0, Vec::len);
+            if is_last && !label.is_empty() {
+                out.push_str(&format!("{} | {}{} {}\n", pad, indent, marks, label));
+            } else {
+                out.push_str(&format!("{} | {}{}\n", pad, indent, marks));
+            }
+        }
+    }
+}
+
+/// 一次 cargo 运行的诊断汇总
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CheckSummary {
+    pub success: bool,
+    pub errors: usize,
+    pub warnings: usize,
+}
+
+/// 生成影子项目并运行 `cargo check --message-format=json`，
+/// 每行 JSON 输出（compiler-message 已映射回 .nu）交给 `on_message`
+///
+/// source_root 为输出 .nu 路径时使用的项目根目录前缀。
+pub fn check_project(
+    root: &Path,
+    source_root: &Path,
+    on_message: impl FnMut(&Value),
+) -> Result<CheckSummary> {
+    let (shadow, _) = ProjectManager::new().generate_shadow(root)?;
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let mut cmd = Command::new(cargo);
+    cmd.arg("check")
+        .arg("--manifest-path")
+        .arg(shadow.join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(root.join("target"));
+    run_cargo_json(cmd, &shadow, source_root, on_message)
+}
+
+/// 以 `--message-format=json` 运行已配置好的 cargo 命令（影子项目 shadow），
+/// 每行 JSON 输出（compiler-message 已映射回 .nu）交给 `on_message`
+pub fn run_cargo_json(
+    mut cmd: Command,
+    shadow: &Path,
+    source_root: &Path,
+    mut on_message: impl FnMut(&Value),
+) -> Result<CheckSummary> {
+    let mut child = cmd
+        .arg("--message-format=json")
+        .stdout(Stdio::piped())
+        .spawn()
+        .context("无法运行 cargo")?;
+
+    let mut remapper = DiagnosticRemapper::new(shadow, source_root);
+    let mut summary = CheckSummary::default();
+    if let Some(stdout) = child.stdout.take() {
+        for line in BufReader::new(stdout).lines() {
+            let line = line?;
+            let Some(value) = remapper.remap_cargo_line(&line) else {
+                continue;
+            };
+            if value.get("reason").and_then(Value::as_str) == Some("compiler-message") {
+                match value["message"].get("level").and_then(Value::as_str) {
+                    Some("error") => summary.errors += 1,
+                    Some("warning") => summary.warnings += 1,
+                    _ => {}
+                }
+            }
+            on_message(&value);
+        }
+    }
+    summary.success = child.wait()?.success();
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::InitOptions;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_remap_json_message() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("app");
+        let manager = ProjectManager::new();
+        manager.init(&root, "app", &InitOptions::default()).unwrap();
+        fs::write(
+            root.join("src/main.nu"),
+            "f main() {\n    l total: V<i32> = \"x\";\n}\n",
+        )
+        .unwrap();
+        let (shadow, _) = manager.generate_shadow(&root).unwrap();
+        let rust = fs::read_to_string(shadow.join("src/main.rs")).unwrap();
+        let rust_line = rust.lines().nth(1).unwrap();
+        assert_eq!(rust_line, "    let total: Vec<i32> = \"x\";");
+
+        // 模拟 cargo check 对 `"x"` 与 `Vec<i32>` 的诊断
+        let col = |needle: &str| rust_line.find(needle).unwrap() + 1;
+        let line = json!({
+            "reason": "compiler-message",
+            "message": {
+                "message": "mismatched types",
+                "code": { "code": "E0308", "explanation": null },
+                "level": "error",
+                "spans": [
+                    {
+                        "file_name": "src/main.rs",
+                        "line_start": 2, "line_end": 2,
+                        "column_start": col("\"x\""), "column_end": col("\"x\"") + 3,
+                        "byte_start": 0, "byte_end": 0,
+                        "is_primary": true,
+                        "label": "expected `Vec<i32>`, found `&str`",
+                        "text": [], "suggested_replacement": null, "expansion": null
+                    },
+                    {
+                        "file_name": "src/main.rs",
+                        "line_start": 2, "line_end": 2,
+                        "column_start": col("Vec"), "column_end": col("Vec") + 8,
+                        "byte_start": 0, "byte_end": 0,
+                        "is_primary": false,
+                        "label": "expected due to this",
+                        "text": [], "suggested_replacement": null, "expansion": null
+                    }
+                ],
+                "children": [
+                    { "message": "expected struct `Vec<i32>`", "level": "note", "spans": [], "children": [], "code": null, "rendered": null }
+                ],
+                "rendered": "error[E0308]: mismatched types\n --> src/main.rs:2:29\n"
+            }
+        })
+        .to_string();
+
+        let mut remapper = DiagnosticRemapper::new(&shadow, "app");
+        let value = remapper.remap_cargo_line(&line).unwrap();
+        let primary = &value["message"]["spans"][0];
+        assert_eq!(primary["file_name"], "app/src/main.nu");
+        assert_eq!(primary["line_start"], 2);
+        assert_eq!(primary["column_start"], 23);
+        assert_eq!(primary["column_end"], 26);
+        assert_eq!(primary["text"][0]["text"], "    l total: V<i32> = \"x\";");
+        // 字节偏移指向 .nu 源码中的 `"x"`
+        let nu_source = fs::read_to_string(root.join("src/main.nu")).unwrap();
+        let (start, end) = (
+            primary["byte_start"].as_u64().unwrap() as usize,
+            primary["byte_end"].as_u64().unwrap() as usize,
+        );
+        assert_eq!(&nu_source[start..end], "\"x\"");
+        let secondary = &value["message"]["spans"][1];
+        assert_eq!(secondary["column_start"], 14);
+        assert_eq!(secondary["column_end"], 20);
+
+        let rendered = value["message"]["rendered"].as_str().unwrap();
+        assert_eq!(
+            rendered,
+            "error[E0308]: mismatched types\n\
+             \x20--> app/src/main.nu:2:23\n\
+             \x20 |\n\
+             2 |     l total: V<i32> = \"x\";\n\
+             \x20 |                       ^^^ expected `Vec<i32>`, found `&str`\n\
+             \x20 |              ------ expected due to this\n\
+             \x20 |\n\
+             \x20 = note: expected struct `Vec<i32>`\n"
+        );
+
+        // 非诊断行原样返回；非 JSON 行返回 None
+        let artifact = remapper
+            .remap_cargo_line(r#"{"reason":"build-finished","success":false}"#)
+            .unwrap();
+        assert_eq!(artifact["success"], false);
+        assert!(remapper.remap_cargo_line("Compiling app").is_none());
+        // 影子项目之外的 span 保持不变
+        assert!(remapper
+            .remap_span("/rustc/library/core/src/fmt.rs", (1, 1), (1, 2))
+            .is_none());
+    }
+}
@@ -0,0 +1,300 @@
+// 标识符转义
+// 与 Nu 关键字或类型缩写同名的 Rust 标识符（语句开头的变量 l、类型 V、变量 u 等）会被 nu2rust 误读为关键字。
+// rust2nu 把它们写成原始标识符 `r#l`，nu2rust 把 `r#` 标识符整体当作普通标识符并去掉不需要的前缀；
+// nu2ts、nu2cpp 同样去掉前缀，与目标语言关键字冲突时改名
+
+use crate::profile::CompressionProfile;
+use proc_macro2::{LineColumn, TokenStream, TokenTree};
+use std::collections::HashSet;
+use syn::visit::{self, Visit};
+
+/// 在行中任意位置都会被 nu2rust 读作关键字的单词（`u x`、`M x {`、`br;`）；
+/// 其余 Nu 关键字只在语句开头被误读
+const INLINE_KEYWORDS: &[&str] = &["D", "U", "u", "wh", "M", "br", "ct"];
+
+/// Rust 严格关键字与保留字：作为标识符时必须保留 `r#` 前缀
+pub const RUST_KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// name 是否会被 nu2rust 当作 Nu 关键字或本配置使用的类型缩写
+pub fn is_reserved(name: &str, profile: &CompressionProfile) -> bool {
+    crate::lexer::keyword(name).is_some() || profile.expansion(name).is_some()
+}
+
+/// name 在任意位置都需要转义；否则只有位于语句开头时才需要
+fn always_reserved(name: &str, profile: &CompressionProfile) -> bool {
+    INLINE_KEYWORDS.contains(&name) || profile.expansion(name).is_some()
+}
+
+/// offset 处的标识符是否位于语句开头：行首，或紧跟在 `;` `{` `}` 之后
+fn starts_statement(source: &str, offset: usize) -> bool {
+    let before = source[..offset].trim_end_matches([' ', '\t']);
+    before.is_empty() || before.ends_with(['\n', ';', '{', '}'])
+}
+
+/// 去掉原始标识符中不需要的 `r#`：`r#l` -> `l`，`r#type` 保持原样
+pub fn unescape(ident: &str) -> &str {
+    match ident.strip_prefix("r#") {
+        Some(name) if !RUST_KEYWORDS.contains(&name) => name,
+        _ => ident,
+    }
+}
+
+/// 把 Rust 源码中与 Nu 关键字或缩写同名的标识符改写为 `r#` 原始标识符
+///
+/// 泛型类型参数不转义（两个方向都按泛型作用域处理）；生命周期、属性和 macro_rules! 定义保持原样
+pub fn escape_identifiers(source: &str, profile: &CompressionProfile) -> syn::Result<String> {
+    let file = syn::parse_file(source)?;
+    let mut collector = Collector {
+        profile,
+        generics: Vec::new(),
+        positions: Vec::new(),
+    };
+    collector.visit_file(&file);
+    if collector.positions.is_empty() {
+        return Ok(source.to_string());
+    }
+
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    let mut offsets: Vec<usize> = collector
+        .positions
+        .iter()
+        .filter_map(|(pos, always)| {
+            let start = *line_starts.get(pos.line.checked_sub(1)?)?;
+            let offset = source[start..]
+                .char_indices()
+                .nth(pos.column)
+                .map(|(i, _)| start + i)?;
+            (*always || starts_statement(source, offset)).then_some(offset)
+        })
+        .collect();
+    offsets.sort_unstable();
+    offsets.dedup();
+
+    let mut escaped = String::with_capacity(source.len() + offsets.len() * 2);
+    let mut copied = 0;
+    for offset in offsets {
+        escaped.push_str(&source[copied..offset]);
+        escaped.push_str("r#");
+        copied = offset;
+    }
+    escaped.push_str(&source[copied..]);
+    Ok(escaped)
+}
+
+/// 把 Nu 源码中的 `r#` 原始标识符改写为普通标识符，供没有原始标识符语法的目标语言使用；
+/// rename 返回 Some 时（名字与目标语言关键字冲突）改用返回的名字
+pub fn unraw_identifiers(source: &str, rename: impl Fn(&str) -> Option<String>) -> String {
+    let (tokens, _) = crate::lexer::tokenize_with_errors(source);
+    let mut result = String::with_capacity(source.len());
+    let mut copied = 0;
+    for spanned in tokens {
+        let crate::lexer::Token::Ident(ident) = &spanned.token else {
+            continue;
+        };
+        let Some(name) = ident.strip_prefix("r#") else {
+            continue;
+        };
+        result.push_str(&source[copied..spanned.span.start]);
+        result.push_str(&rename(name).unwrap_or_else(|| name.to_string()));
+        copied = spanned.span.end;
+    }
+    result.push_str(&source[copied..]);
+    result
+}
+
+/// 收集需要转义的标识符位置
+struct Collector<'p> {
+    profile: &'p CompressionProfile,
+    /// 泛型类型参数作用域
+    generics: Vec<HashSet<String>>,
+    /// 标识符位置，以及是否不论位置都需要转义
+    positions: Vec<(LineColumn, bool)>,
+}
+
+impl Collector<'_> {
+    fn check(&mut self, ident: &proc_macro2::Ident) {
+        self.check_at(ident, false);
+    }
+
+    /// line_start：标识符在 Nu 中独占一行开头（字段、枚举变体），与语句开头同样处理
+    fn check_at(&mut self, ident: &proc_macro2::Ident, line_start: bool) {
+        let name = ident.to_string();
+        if !is_reserved(&name, self.profile)
+            || self.generics.iter().any(|scope| scope.contains(&name))
+        {
+            return;
+        }
+        let always = line_start || always_reserved(&name, self.profile);
+        self.positions.push((ident.span().start(), always));
+    }
+
+    fn scoped(&mut self, generics: &syn::Generics, visit: impl FnOnce(&mut Self)) {
+        let scope = generics
+            .type_params()
+            .map(|param| param.ident.to_string())
+            .collect();
+        self.generics.push(scope);
+        visit(self);
+        self.generics.pop();
+    }
+
+    /// 宏参数：跳过生命周期（`'a`）与宏变量（`$a`）
+    fn visit_tokens(&mut self, tokens: TokenStream) {
+        let mut prev: Option<TokenTree> = None;
+        for token in tokens {
+            match &token {
+                TokenTree::Group(group) => self.visit_tokens(group.stream()),
+                TokenTree::Ident(ident) => {
+                    let quoted = matches!(&prev, Some(TokenTree::Punct(p)) if matches!(p.as_char(), '\'' | '$'));
+                    if !quoted {
+                        self.check(ident);
+                    }
+                }
+                _ => {}
+            }
+            prev = Some(token);
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for Collector<'_> {
+    fn visit_ident(&mut self, ident: &'ast proc_macro2::Ident) {
+        self.check(ident);
+    }
+
+    fn visit_lifetime(&mut self, _: &'ast syn::Lifetime) {}
+
+    fn visit_field(&mut self, node: &'ast syn::Field) {
+        if let Some(ident) = &node.ident {
+            self.check_at(ident, true);
+        }
+        visit::visit_field(self, node);
+    }
+
+    fn visit_variant(&mut self, node: &'ast syn::Variant) {
+        self.check_at(&node.ident, true);
+        visit::visit_variant(self, node);
+    }
+
+    fn visit_attribute(&mut self, _: &'ast syn::Attribute) {}
+
+    fn visit_item_macro(&mut self, node: &'ast syn::ItemMacro) {
+        if !node.mac.path.is_ident("macro_rules") {
+            visit::visit_item_macro(self, node);
+        }
+    }
+
+    fn visit_macro(&mut self, node: &'ast syn::Macro) {
+        self.visit_path(&node.path);
+        self.visit_tokens(node.tokens.clone());
+    }
+
+    fn visit_item_fn(&mut self, node: &'ast syn::ItemFn) {
+        self.scoped(&node.sig.generics, |c| visit::visit_item_fn(c, node));
+    }
+
+    fn visit_impl_item_fn(&mut self, node: &'ast syn::ImplItemFn) {
+        self.scoped(&node.sig.generics, |c| visit::visit_impl_item_fn(c, node));
+    }
+
+    fn visit_trait_item_fn(&mut self, node: &'ast syn::TraitItemFn) {
+        self.scoped(&node.sig.generics, |c| visit::visit_trait_item_fn(c, node));
+    }
+
+    fn visit_foreign_item_fn(&mut self, node: &'ast syn::ForeignItemFn) {
+        self.scoped(&node.sig.generics, |c| {
+            visit::visit_foreign_item_fn(c, node)
+        });
+    }
+
+    fn visit_item_struct(&mut self, node: &'ast syn::ItemStruct) {
+        self.scoped(&node.generics, |c| visit::visit_item_struct(c, node));
+    }
+
+    fn visit_item_union(&mut self, node: &'ast syn::ItemUnion) {
+        self.scoped(&node.generics, |c| visit::visit_item_union(c, node));
+    }
+
+    fn visit_item_enum(&mut self, node: &'ast syn::ItemEnum) {
+        self.scoped(&node.generics, |c| visit::visit_item_enum(c, node));
+    }
+
+    fn visit_item_trait(&mut self, node: &'ast syn::ItemTrait) {
+        self.scoped(&node.generics, |c| visit::visit_item_trait(c, node));
+    }
+
+    fn visit_item_trait_alias(&mut self, node: &'ast syn::ItemTraitAlias) {
+        self.scoped(&node.generics, |c| visit::visit_item_trait_alias(c, node));
+    }
+
+    fn visit_item_impl(&mut self, node: &'ast syn::ItemImpl) {
+        self.scoped(&node.generics, |c| visit::visit_item_impl(c, node));
+    }
+
+    fn visit_item_type(&mut self, node: &'ast syn::ItemType) {
+        self.scoped(&node.generics, |c| visit::visit_item_type(c, node));
+    }
+
+    fn visit_impl_item_type(&mut self, node: &'ast syn::ImplItemType) {
+        self.scoped(&node.generics, |c| visit::visit_impl_item_type(c, node));
+    }
+
+    fn visit_trait_item_type(&mut self, node: &'ast syn::TraitItemType) {
+        self.scoped(&node.generics, |c| visit::visit_trait_item_type(c, node));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_identifiers() {
+        let profile = CompressionProfile::default();
+        let source = "struct V<S> { l: Vec<S> }\n\nfn f<'a>(u: &'a V<i32>) -> usize {\n    let M = u.l.len();\n    println!(\"{M}\", M = M);\n    M\n}\n\nmacro_rules! l {\n    ($l:expr) => { $l };\n}\n";
+        let escaped = escape_identifiers(source, &profile).unwrap();
+        assert_eq!(
+            escaped,
+            "struct r#V<S> { r#l: Vec<S> }\n\nfn f<'a>(r#u: &'a r#V<i32>) -> usize {\n    let r#M = r#u.l.len();\n    println!(\"{M}\", r#M = r#M);\n    r#M\n}\n\nmacro_rules! l {\n    ($l:expr) => { $l };\n}\n"
+        );
+
+        // 配置不使用的缩写不需要转义
+        let minimal = CompressionProfile::minimal();
+        assert_eq!(
+            escape_identifiers("struct V;\n", &minimal).unwrap(),
+            "struct V;\n"
+        );
+        assert!(is_reserved("V", &profile) && !is_reserved("V", &minimal));
+
+        // 其余 Nu 关键字只在语句开头转义
+        assert_eq!(
+            escape_identifiers(
+                "fn g(s: &str, a: u8) {\n    let v = s.len() + a as usize;\n    v = 1;\n}\n",
+                &profile
+            )
+            .unwrap(),
+            "fn g(s: &str, a: u8) {\n    let v = s.len() + a as usize;\n    r#v = 1;\n}\n"
+        );
+
+        assert_eq!(unescape("r#l"), "l");
+        assert_eq!(unescape("r#type"), "r#type");
+        assert_eq!(unescape("value"), "value");
+    }
+
+    #[test]
+    fn test_unraw_identifiers() {
+        let rename = |name: &str| (name == "new").then(|| format!("{}_", name));
+        assert_eq!(
+            unraw_identifiers("l r#l = r#new(r#V); // r#x\nl s = \"r#y\";\n", rename),
+            "l l = new_(V); // r#x\nl s = \"r#y\";\n"
+        );
+    }
+}
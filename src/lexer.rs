@@ -1,15 +1,39 @@
 // Nu Language Lexer
 // 使用logos进行词法分析
+//
+// 覆盖 v1.6.x 完整关键字集，输出带位置信息的 Token 流；
+// 无法识别的字符作为错误上报，而不是被静默丢弃。
 
-use logos::Logos;
+use logos::{FilterResult, Logos};
+use std::fmt;
+use std::ops::Range;
+use thiserror::Error;
+
+/// 词法错误类型
+#[derive(Debug, Clone, PartialEq, Eq, Default, Error)]
+pub enum LexErrorKind {
+    #[default]
+    #[error("无法识别的字符")]
+    UnknownChar,
+    #[error("块注释未闭合")]
+    UnterminatedBlockComment,
+    #[error("原始字符串未闭合")]
+    UnterminatedRawString,
+}
 
 #[derive(Logos, Debug, PartialEq, Clone)]
+#[logos(error = LexErrorKind)]
+#[logos(skip r"[ \t\r\n\f]+")]
 pub enum Token {
-    // 定义关键字
+    // ===== 定义关键字 =====
     #[token("S")]
     StructPub,
     #[token("s")]
     StructPriv,
+    #[token("UN")]
+    UnionPub,
+    #[token("un")]
+    UnionPriv,
     #[token("E")]
     EnumPub,
     #[token("e")]
@@ -26,8 +50,24 @@ pub enum Token {
     Impl,
     #[token("D")]
     Mod,
+    #[token("DM")]
+    ModPub,
+    #[token("C")]
+    Const,
+    #[token("CP")]
+    ConstPub,
+    #[token("ST")]
+    Static,
+    #[token("SP")]
+    StaticPub,
+    #[token("SM")]
+    StaticMut,
+    #[token("SMP")]
+    StaticMutPub,
+    #[token("EXT")]
+    Extern,
 
-    // 原子关键字
+    // ===== 原子关键字 =====
     #[token("l")]
     Let,
     #[token("v")]
@@ -36,36 +76,852 @@ pub enum Token {
     Use,
     #[token("U")]
     PubUse,
+    #[token("wh")]
+    Where,
+    #[token("a")]
+    As,
+    #[token("t")]
+    Type,
+    #[token("br")]
+    Break,
+    #[token("ct")]
+    Continue,
+    #[token("M")]
+    Match,
+    #[token("L")]
+    Loop,
+
+    // ===== 属性 =====
+    /// #D(...) = #[derive(...)]
+    #[token("#D")]
+    Derive,
+    /// #I = #[inline]
+    #[token("#I")]
+    Inline,
+    #[token("#")]
+    Pound,
+
+    // ===== 并发 / 异步 =====
+    /// ~ = async
+    #[token("~")]
+    Async,
+    /// .~ = .await
+    #[token(".~")]
+    Await,
+    /// @ = spawn
+    #[token("@")]
+    Spawn,
+    /// @@ = thread::spawn
+    #[token("@@")]
+    Thread,
+    /// << = channel send/recv（也可能是左移，由 parser 决定）
+    #[token("<<")]
+    Channel,
+    /// $ = move（$|x| ...）
+    #[token("$")]
+    Move,
+
+    // ===== 内存修饰 =====
+    /// &! = &mut
+    #[token("&!")]
+    RefMut,
+    /// *! = *mut
+    #[token("*!")]
+    PtrMut,
+    /// 后缀 ! = ?（由 classify_bangs 从 Bang 转换而来）
+    Try,
+    /// 前缀 !（逻辑非 / !self）以及宏调用的 !
+    #[token("!")]
+    Bang,
 
-    // 符号
+    // ===== 符号 =====
+    /// < 在语句开头表示 return，其他位置为小于
     #[token("<")]
     LessThanOrReturn,
     #[token(">")]
-    GreaterThanOrPrint,
+    GreaterThan,
+    #[token("<=")]
+    LessEq,
+    #[token(">=")]
+    GreaterEq,
+    #[token("==")]
+    EqEq,
+    #[token("!=")]
+    NotEq,
+    #[token("=")]
+    Eq,
     #[token("?")]
-    If,
-    #[token("M")]
-    Match,
-    #[token("L")]
-    Loop,
+    Question,
+    #[token("+")]
+    Plus,
+    #[token("-")]
+    Minus,
+    #[token("*")]
+    Star,
+    #[token("/")]
+    Slash,
+    #[token("%")]
+    Percent,
+    #[token("^")]
+    Caret,
+    #[token("&")]
+    Amp,
+    #[token("|")]
+    Pipe,
+    #[token("&&")]
+    AndAnd,
+    #[token("||")]
+    OrOr,
+    #[token("+=")]
+    PlusEq,
+    #[token("-=")]
+    MinusEq,
+    #[token("*=")]
+    StarEq,
+    #[token("/=")]
+    SlashEq,
+    #[token("%=")]
+    PercentEq,
+    #[token("^=")]
+    CaretEq,
+    #[token("&=")]
+    AndEq,
+    #[token("|=")]
+    OrEq,
+    #[token("<<=")]
+    ShlEq,
+    /// >> 右移；闭合嵌套泛型时由 parser 拆成两个 `>`
+    #[token(">>")]
+    Shr,
+    #[token(">>=")]
+    ShrEq,
+    #[token("->")]
+    Arrow,
+    #[token("=>")]
+    FatArrow,
+    #[token("::")]
+    PathSep,
+    #[token(":")]
+    Colon,
+    #[token(";")]
+    Semi,
+    #[token(",")]
+    Comma,
+    #[token(".")]
+    Dot,
+    #[token("..")]
+    DotDot,
+    #[token("..=")]
+    DotDotEq,
+    #[token("...")]
+    DotDotDot,
+    #[token("(")]
+    LParen,
+    #[token(")")]
+    RParen,
+    #[token("{")]
+    LBrace,
+    #[token("}")]
+    RBrace,
+    #[token("[")]
+    LBracket,
+    #[token("]")]
+    RBracket,
 
-    // 标识符
+    // ===== 标识符 =====
     #[regex(r"[a-zA-Z_][a-zA-Z0-9_]*", |lex| lex.slice().to_string())]
+    #[regex(r"r#[a-zA-Z_][a-zA-Z0-9_]*", |lex| lex.slice().to_string())]
     Ident(String),
 
-    // 字面量
-    #[regex(r#""([^"\\]|\\.)*""#, |lex| lex.slice().to_string())]
+    /// 'a, 'static
+    #[regex(r"'[a-zA-Z_][a-zA-Z0-9_]*", |lex| lex.slice().to_string())]
+    Lifetime(String),
+
+    // ===== 字面量（保留原始文本） =====
+    #[regex(r"[0-9][0-9_]*([iu](8|16|32|64|128|size))?", |lex| lex.slice().to_string())]
+    #[regex(r"0x[0-9a-fA-F_]+([iu](8|16|32|64|128|size))?", |lex| lex.slice().to_string())]
+    #[regex(r"0o[0-7_]+([iu](8|16|32|64|128|size))?", |lex| lex.slice().to_string())]
+    #[regex(r"0b[01_]+([iu](8|16|32|64|128|size))?", |lex| lex.slice().to_string())]
+    IntLit(String),
+
+    #[regex(r"[0-9][0-9_]*\.[0-9][0-9_]*([eE][+-]?[0-9_]+)?(f32|f64)?", |lex| lex.slice().to_string())]
+    #[regex(r"[0-9][0-9_]*[eE][+-]?[0-9_]+(f32|f64)?", |lex| lex.slice().to_string())]
+    #[regex(r"[0-9][0-9_]*(f32|f64)", |lex| lex.slice().to_string())]
+    FloatLit(String),
+
+    #[regex(r#""([^"\\]|\\(.|\n))*""#, |lex| lex.slice().to_string())]
     StringLit(String),
 
-    #[regex(r"\d+", |lex| lex.slice().parse::<i64>().ok())]
-    IntLit(Option<i64>),
+    /// r"..." / r#"..."#
+    #[regex(r##"r#*""##, lex_raw_string)]
+    RawStringLit(String),
+
+    /// b"..." / br"..." / br#"..."#
+    #[regex(r#"b"([^"\\]|\\(.|\n))*""#, |lex| lex.slice().to_string())]
+    #[regex(r##"br#*""##, lex_raw_string)]
+    ByteStringLit(String),
+
+    #[regex(r"'([^'\\\n]|[\u{80}-\u{10FFFF}]|\\.|\\u\{[0-9a-fA-F_]+\})'", |lex| lex.slice().to_string())]
+    CharLit(String),
+
+    #[regex(r"b'([^'\\\n]|\\.)'", |lex| lex.slice().to_string())]
+    ByteLit(String),
+
+    // ===== 注释 =====
+    /// /// 外部文档注释（内容不含前缀）
+    #[regex(r"///[^\n]*", lex_line_doc)]
+    #[token("/**", lex_block_doc)]
+    DocComment(String),
+
+    /// //! 内部文档注释（内容不含前缀）
+    #[regex(r"//![^\n]*", |lex| lex.slice()[3..].to_string())]
+    #[token("/*!", lex_block_inner_doc)]
+    InnerDocComment(String),
 
-    // 空白和注释
-    #[regex(r"[ \t\r\n]+", logos::skip)]
     #[regex(r"//[^\n]*", logos::skip)]
-    Whitespace,
+    #[token("/*", lex_block_comment)]
+    Comment,
+}
+
+/// `////...` 在 Rust 中是普通注释，不是文档注释
+fn lex_line_doc(lex: &mut logos::Lexer<Token>) -> FilterResult<String, LexErrorKind> {
+    let slice = lex.slice();
+    if slice.starts_with("////") {
+        FilterResult::Skip
+    } else {
+        FilterResult::Emit(slice[3..].to_string())
+    }
+}
+
+/// 扫描（可嵌套的）块注释剩余部分，depth 为已打开的层数；
+/// 返回注释体的字节长度（不含结尾 `*/`），未闭合时返回结尾处的嵌套深度
+pub(crate) fn scan_block_comment(rest: &str, mut depth: usize) -> Result<usize, usize> {
+    let bytes = rest.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'/' && bytes.get(i + 1) == Some(&b'*') {
+            depth += 1;
+            i += 2;
+        } else if bytes[i] == b'*' && bytes.get(i + 1) == Some(&b'/') {
+            depth -= 1;
+            if depth == 0 {
+                return Ok(i);
+            }
+            i += 2;
+        } else {
+            i += 1;
+        }
+    }
+    Err(depth)
+}
+
+fn lex_block_comment(lex: &mut logos::Lexer<Token>) -> FilterResult<(), LexErrorKind> {
+    match scan_block_comment(lex.remainder(), 1) {
+        Ok(len) => {
+            lex.bump(len + 2);
+            FilterResult::Skip
+        }
+        Err(_) => {
+            lex.bump(lex.remainder().len());
+            FilterResult::Error(LexErrorKind::UnterminatedBlockComment)
+        }
+    }
 }
 
-pub fn tokenize(input: &str) -> Vec<Token> {
-    Token::lexer(input).filter_map(|t| t.ok()).collect()
+fn lex_block_doc(lex: &mut logos::Lexer<Token>) -> FilterResult<String, LexErrorKind> {
+    // `/**/` 与 `/***...` 是普通注释
+    let rest = lex.remainder();
+    if rest.starts_with('/') {
+        lex.bump(1);
+        return FilterResult::Skip;
+    }
+    let is_plain = rest.starts_with('*');
+    match scan_block_comment(rest, 1) {
+        Ok(len) => {
+            let body = rest[..len].to_string();
+            lex.bump(len + 2);
+            if is_plain {
+                FilterResult::Skip
+            } else {
+                FilterResult::Emit(body)
+            }
+        }
+        Err(_) => {
+            lex.bump(rest.len());
+            FilterResult::Error(LexErrorKind::UnterminatedBlockComment)
+        }
+    }
+}
+
+fn lex_block_inner_doc(lex: &mut logos::Lexer<Token>) -> FilterResult<String, LexErrorKind> {
+    let rest = lex.remainder();
+    match scan_block_comment(rest, 1) {
+        Ok(len) => {
+            let body = rest[..len].to_string();
+            lex.bump(len + 2);
+            FilterResult::Emit(body)
+        }
+        Err(_) => {
+            lex.bump(rest.len());
+            FilterResult::Error(LexErrorKind::UnterminatedBlockComment)
+        }
+    }
+}
+
+/// 原始字符串：已匹配 `r#*"` / `br#*"`，向后查找 `"` + 相同数量的 `#`
+fn lex_raw_string(lex: &mut logos::Lexer<Token>) -> Result<String, LexErrorKind> {
+    let hashes = lex.slice().chars().filter(|&c| c == '#').count();
+    let terminator = format!("\"{}", "#".repeat(hashes));
+    match lex.remainder().find(&terminator) {
+        Some(pos) => {
+            lex.bump(pos + terminator.len());
+            Ok(lex.slice().to_string())
+        }
+        None => {
+            lex.bump(lex.remainder().len());
+            Err(LexErrorKind::UnterminatedRawString)
+        }
+    }
+}
+
+impl Token {
+    /// 关键字 / 符号的源码文本；携带数据的 Token 返回其原始文本
+    pub fn text(&self) -> &str {
+        match self {
+            Token::StructPub => "S",
+            Token::StructPriv => "s",
+            Token::UnionPub => "UN",
+            Token::UnionPriv => "un",
+            Token::EnumPub => "E",
+            Token::EnumPriv => "e",
+            Token::FnPub => "F",
+            Token::FnPriv => "f",
+            Token::TraitPub => "TR",
+            Token::TraitPriv => "tr",
+            Token::Impl => "I",
+            Token::Mod => "D",
+            Token::ModPub => "DM",
+            Token::Const => "C",
+            Token::ConstPub => "CP",
+            Token::Static => "ST",
+            Token::StaticPub => "SP",
+            Token::StaticMut => "SM",
+            Token::StaticMutPub => "SMP",
+            Token::Extern => "EXT",
+            Token::Let => "l",
+            Token::LetMut => "v",
+            Token::Use => "u",
+            Token::PubUse => "U",
+            Token::Where => "wh",
+            Token::As => "a",
+            Token::Type => "t",
+            Token::Break => "br",
+            Token::Continue => "ct",
+            Token::Match => "M",
+            Token::Loop => "L",
+            Token::Derive => "#D",
+            Token::Inline => "#I",
+            Token::Pound => "#",
+            Token::Async => "~",
+            Token::Await => ".~",
+            Token::Spawn => "@",
+            Token::Thread => "@@",
+            Token::Channel => "<<",
+            Token::Move => "$",
+            Token::RefMut => "&!",
+            Token::PtrMut => "*!",
+            Token::Try | Token::Bang => "!",
+            Token::LessThanOrReturn => "<",
+            Token::GreaterThan => ">",
+            Token::LessEq => "<=",
+            Token::GreaterEq => ">=",
+            Token::EqEq => "==",
+            Token::NotEq => "!=",
+            Token::Eq => "=",
+            Token::Question => "?",
+            Token::Plus => "+",
+            Token::Minus => "-",
+            Token::Star => "*",
+            Token::Slash => "/",
+            Token::Percent => "%",
+            Token::Caret => "^",
+            Token::Amp => "&",
+            Token::Pipe => "|",
+            Token::AndAnd => "&&",
+            Token::OrOr => "||",
+            Token::PlusEq => "+=",
+            Token::MinusEq => "-=",
+            Token::StarEq => "*=",
+            Token::SlashEq => "/=",
+            Token::PercentEq => "%=",
+            Token::CaretEq => "^=",
+            Token::AndEq => "&=",
+            Token::OrEq => "|=",
+            Token::ShlEq => "<<=",
+            Token::Shr => ">>",
+            Token::ShrEq => ">>=",
+            Token::Arrow => "->",
+            Token::FatArrow => "=>",
+            Token::PathSep => "::",
+            Token::Colon => ":",
+            Token::Semi => ";",
+            Token::Comma => ",",
+            Token::Dot => ".",
+            Token::DotDot => "..",
+            Token::DotDotEq => "..=",
+            Token::DotDotDot => "...",
+            Token::LParen => "(",
+            Token::RParen => ")",
+            Token::LBrace => "{",
+            Token::RBrace => "}",
+            Token::LBracket => "[",
+            Token::RBracket => "]",
+            Token::Ident(s)
+            | Token::Lifetime(s)
+            | Token::IntLit(s)
+            | Token::FloatLit(s)
+            | Token::StringLit(s)
+            | Token::RawStringLit(s)
+            | Token::ByteStringLit(s)
+            | Token::CharLit(s)
+            | Token::ByteLit(s)
+            | Token::DocComment(s)
+            | Token::InnerDocComment(s) => s,
+            Token::Comment => "",
+        }
+    }
+
+    /// Nu 的单词型关键字（S/f/l/wh/...）都是上下文相关的：
+    /// 在非关键字位置（如 `|a, s|`、`x.f`）可以作为普通标识符使用
+    pub fn as_ident(&self) -> Option<&str> {
+        match self {
+            Token::Ident(s) => Some(s),
+            t if t.is_word_keyword() => Some(t.text()),
+            _ => None,
+        }
+    }
+
+    /// 是否为单词型 Nu 关键字
+    pub fn is_word_keyword(&self) -> bool {
+        matches!(
+            self,
+            Token::StructPub
+                | Token::StructPriv
+                | Token::UnionPub
+                | Token::UnionPriv
+                | Token::EnumPub
+                | Token::EnumPriv
+                | Token::FnPub
+                | Token::FnPriv
+                | Token::TraitPub
+                | Token::TraitPriv
+                | Token::Impl
+                | Token::Mod
+                | Token::ModPub
+                | Token::Const
+                | Token::ConstPub
+                | Token::Static
+                | Token::StaticPub
+                | Token::StaticMut
+                | Token::StaticMutPub
+                | Token::Extern
+                | Token::Let
+                | Token::LetMut
+                | Token::Use
+                | Token::PubUse
+                | Token::Where
+                | Token::As
+                | Token::Type
+                | Token::Break
+                | Token::Continue
+                | Token::Match
+                | Token::Loop
+        )
+    }
+
+    /// 是否为字面量
+    pub fn is_literal(&self) -> bool {
+        matches!(
+            self,
+            Token::IntLit(_)
+                | Token::FloatLit(_)
+                | Token::StringLit(_)
+                | Token::RawStringLit(_)
+                | Token::ByteStringLit(_)
+                | Token::CharLit(_)
+                | Token::ByteLit(_)
+        )
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text())
+    }
+}
+
+/// 带位置信息的 Token
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedToken {
+    pub token: Token,
+    /// 字节范围
+    pub span: Range<usize>,
+    /// 行号（从 1 开始）
+    pub line: usize,
+    /// 列号（从 1 开始，按字符计）
+    pub col: usize,
+}
+
+/// 词法错误
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{line}:{col}: {kind} `{text}`")]
+pub struct LexError {
+    pub kind: LexErrorKind,
+    pub span: Range<usize>,
+    pub line: usize,
+    pub col: usize,
+    pub text: String,
+}
+
+/// 增量计算字节偏移对应的行列号
+struct LineIndex<'a> {
+    input: &'a str,
+    offset: usize,
+    line: usize,
+    line_start: usize,
+}
+
+impl<'a> LineIndex<'a> {
+    fn new(input: &'a str) -> Self {
+        Self {
+            input,
+            offset: 0,
+            line: 1,
+            line_start: 0,
+        }
+    }
+
+    /// offset 必须单调递增
+    fn locate(&mut self, offset: usize) -> (usize, usize) {
+        for (i, b) in self.input.as_bytes()[self.offset..offset].iter().enumerate() {
+            if *b == b'\n' {
+                self.line += 1;
+                self.line_start = self.offset + i + 1;
+            }
+        }
+        self.offset = offset;
+        let col = self.input[self.line_start..offset].chars().count() + 1;
+        (self.line, col)
+    }
+}
+
+/// 词法分析，同时返回 Token 与全部错误（供 parser 做错误恢复）
+pub fn tokenize_with_errors(input: &str) -> (Vec<SpannedToken>, Vec<LexError>) {
+    let mut tokens = Vec::new();
+    let mut errors = Vec::new();
+    let mut index = LineIndex::new(input);
+    let mut lexer = Token::lexer(input);
+
+    while let Some(result) = lexer.next() {
+        let span = lexer.span();
+        let (line, col) = index.locate(span.start);
+        match result {
+            Ok(token) => tokens.push(SpannedToken {
+                token,
+                span,
+                line,
+                col,
+            }),
+            Err(kind) => {
+                let text = match kind {
+                    LexErrorKind::UnknownChar => lexer.slice().to_string(),
+                    _ => lexer.slice().chars().take(8).collect(),
+                };
+                errors.push(LexError {
+                    kind,
+                    span,
+                    line,
+                    col,
+                    text,
+                });
+            }
+        }
+    }
+
+    classify_bangs(&mut tokens);
+    (tokens, errors)
+}
+
+/// 词法分析；任何未识别字符都会导致失败
+pub fn tokenize(input: &str) -> Result<Vec<SpannedToken>, Vec<LexError>> {
+    let (tokens, errors) = tokenize_with_errors(input);
+    if errors.is_empty() {
+        Ok(tokens)
+    } else {
+        Err(errors)
+    }
+}
+
+/// 单个单词对应的 Nu 关键字（`S`、`wh`、`UN` …）；不是关键字时返回 None
+pub fn keyword(word: &str) -> Option<Token> {
+    let mut lex = Token::lexer(word);
+    match (lex.next(), lex.next()) {
+        (Some(Ok(token)), None) if token.is_word_keyword() => Some(token),
+        _ => None,
+    }
+}
+
+/// 后面可以紧跟前缀表达式的 Rust 关键字（`if!x`、`return!ok`）
+const PREFIX_KEYWORDS: &[&str] = &[
+    "if", "while", "in", "return", "else", "match", "let", "mut", "break", "yield", "move",
+    "macro_rules",
+];
+
+/// 区分 `!` 的三种用法：
+/// - `func()!` / `x!`：紧贴表达式结尾 → Try
+/// - `println!(...)` / `macro_rules! name`：宏调用 → Bang
+/// - `!flag` / `!self` / `if!x`：前缀 → Bang
+fn classify_bangs(tokens: &mut [SpannedToken]) {
+    for i in 1..tokens.len() {
+        if tokens[i].token != Token::Bang {
+            continue;
+        }
+        let prev = &tokens[i - 1];
+        if prev.span.end != tokens[i].span.start {
+            continue;
+        }
+        let ends_expr = match &prev.token {
+            Token::Ident(name) => !PREFIX_KEYWORDS.contains(&name.as_str()),
+            Token::RParen | Token::RBracket | Token::Question | Token::Try | Token::Await => true,
+            t => t.is_literal(),
+        };
+        if !ends_expr {
+            continue;
+        }
+        let is_macro = matches!(prev.token, Token::Ident(_))
+            && matches!(
+                tokens.get(i + 1).map(|t| &t.token),
+                Some(Token::LParen | Token::LBracket | Token::LBrace)
+            );
+        if !is_macro {
+            tokens[i].token = Token::Try;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(input: &str) -> Vec<Token> {
+        tokenize(input)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.token)
+            .collect()
+    }
+
+    #[test]
+    fn test_definition_keywords() {
+        assert_eq!(
+            kinds("S s UN un E e F f TR tr I D DM C CP ST SP SM SMP EXT"),
+            vec![
+                Token::StructPub,
+                Token::StructPriv,
+                Token::UnionPub,
+                Token::UnionPriv,
+                Token::EnumPub,
+                Token::EnumPriv,
+                Token::FnPub,
+                Token::FnPriv,
+                Token::TraitPub,
+                Token::TraitPriv,
+                Token::Impl,
+                Token::Mod,
+                Token::ModPub,
+                Token::Const,
+                Token::ConstPub,
+                Token::Static,
+                Token::StaticPub,
+                Token::StaticMut,
+                Token::StaticMutPub,
+                Token::Extern,
+            ]
+        );
+        assert_eq!(
+            kinds("l v u U wh a t br ct M L"),
+            vec![
+                Token::Let,
+                Token::LetMut,
+                Token::Use,
+                Token::PubUse,
+                Token::Where,
+                Token::As,
+                Token::Type,
+                Token::Break,
+                Token::Continue,
+                Token::Match,
+                Token::Loop,
+            ]
+        );
+        assert_eq!(keyword("UN"), Some(Token::UnionPub));
+        assert_eq!(keyword("e"), Some(Token::EnumPriv));
+        assert_eq!(keyword("Self"), None);
+        assert_eq!(keyword("l x"), None);
+        // 关键字前缀不影响标识符
+        assert_eq!(
+            kinds("Self when self"),
+            vec![
+                Token::Ident("Self".to_string()),
+                Token::Ident("when".to_string()),
+                Token::Ident("self".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bang_classification() {
+        assert_eq!(
+            kinds("foo()!"),
+            vec![
+                Token::Ident("foo".to_string()),
+                Token::LParen,
+                Token::RParen,
+                Token::Try,
+            ]
+        );
+        assert_eq!(
+            kinds("println!(x)")[1],
+            Token::Bang,
+            "宏调用的 ! 不是 Try"
+        );
+        assert_eq!(kinds("x!")[1], Token::Try);
+        assert_eq!(kinds("!flag")[0], Token::Bang);
+        assert_eq!(kinds("&!self")[0], Token::RefMut);
+        assert_eq!(kinds("*!ptr")[0], Token::PtrMut);
+        assert_eq!(kinds("macro_rules! m {}")[1], Token::Bang);
+        assert_eq!(kinds("if!x {")[1], Token::Bang, "关键字后的 ! 是前缀");
+        assert_eq!(kinds("while!done {")[1], Token::Bang);
+        assert_eq!(kinds("a != b")[1], Token::NotEq);
+    }
+
+    #[test]
+    fn test_unicode_char_literal() {
+        assert_eq!(
+            kinds("'é' '…' '\\n'"),
+            vec![
+                Token::CharLit("'é'".to_string()),
+                Token::CharLit("'…'".to_string()),
+                Token::CharLit("'\\n'".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_concurrency_symbols() {
+        assert_eq!(
+            kinds("~F @ @@ tx << v x.~ $|x|"),
+            vec![
+                Token::Async,
+                Token::FnPub,
+                Token::Spawn,
+                Token::Thread,
+                Token::Ident("tx".to_string()),
+                Token::Channel,
+                Token::LetMut,
+                Token::Ident("x".to_string()),
+                Token::Await,
+                Token::Move,
+                Token::Pipe,
+                Token::Ident("x".to_string()),
+                Token::Pipe,
+            ]
+        );
+        assert_eq!(kinds("#D(Debug) #I #[test]")[0], Token::Derive);
+        assert_eq!(kinds("#I")[0], Token::Inline);
+    }
+
+    #[test]
+    fn test_shift_operators() {
+        let ident = |s: &str| Token::Ident(s.to_string());
+        assert_eq!(
+            kinds("x >>= 1 << y >> z <<= w"),
+            vec![
+                ident("x"),
+                Token::ShrEq,
+                Token::IntLit("1".to_string()),
+                Token::Channel,
+                ident("y"),
+                Token::Shr,
+                ident("z"),
+                Token::ShlEq,
+                ident("w"),
+            ]
+        );
+        assert_eq!(kinds("x > > y")[1], Token::GreaterThan);
+    }
+
+    #[test]
+    fn test_literals() {
+        assert_eq!(
+            kinds(r##"1 0xFFu8 1.5 2e10 3f32 'c' '\n' b'x' "s\"q" r#"raw"# b"bytes" br"rb" 'a"##),
+            vec![
+                Token::IntLit("1".to_string()),
+                Token::IntLit("0xFFu8".to_string()),
+                Token::FloatLit("1.5".to_string()),
+                Token::FloatLit("2e10".to_string()),
+                Token::FloatLit("3f32".to_string()),
+                Token::CharLit("'c'".to_string()),
+                Token::CharLit("'\\n'".to_string()),
+                Token::ByteLit("b'x'".to_string()),
+                Token::StringLit("\"s\\\"q\"".to_string()),
+                Token::RawStringLit("r#\"raw\"#".to_string()),
+                Token::ByteStringLit("b\"bytes\"".to_string()),
+                Token::ByteStringLit("br\"rb\"".to_string()),
+                Token::Lifetime("'a".to_string()),
+            ]
+        );
+        // 范围与方法调用不是浮点数
+        assert_eq!(
+            kinds("0..10 1.max(2)")[..3],
+            [
+                Token::IntLit("0".to_string()),
+                Token::DotDot,
+                Token::IntLit("10".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_comments_and_spans() {
+        let tokens =
+            tokenize("/// doc\n/* a /* nested */ b */ F main() {\n  //! inner\n  < 1\n}").unwrap();
+        assert_eq!(tokens[0].token, Token::DocComment(" doc".to_string()));
+        assert_eq!(tokens[1].token, Token::FnPub);
+        assert_eq!((tokens[1].line, tokens[1].col), (2, 24));
+        let inner = tokens
+            .iter()
+            .find(|t| matches!(t.token, Token::InnerDocComment(_)))
+            .unwrap();
+        assert_eq!((inner.line, inner.col), (3, 3));
+        let ret = tokens
+            .iter()
+            .find(|t| t.token == Token::LessThanOrReturn)
+            .unwrap();
+        assert_eq!((ret.line, ret.col), (4, 3));
+        assert_eq!(ret.span, 56..57);
+    }
+
+    #[test]
+    fn test_unknown_char_reported() {
+        let (tokens, errors) = tokenize_with_errors("l x = 1 ` 2\nl y = §");
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].kind, LexErrorKind::UnknownChar);
+        assert_eq!((errors[0].line, errors[0].col), (1, 9));
+        assert_eq!(errors[1].text, "§");
+        assert_eq!((errors[1].line, errors[1].col), (2, 7));
+        assert_eq!(tokens.len(), 8);
+        assert!(tokenize("/* open").is_err());
+        assert!(tokenize("r#\"open").is_err());
+    }
 }
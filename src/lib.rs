@@ -26,22 +26,29 @@
 
 pub mod ast;
 pub mod codegen;
+pub mod conversion;
+pub mod diagnostics;
+pub mod escape;
 pub mod lexer;
 pub mod module;
 pub mod nu2cpp;
 pub mod nu2rust;
 pub mod nu2ts;
 pub mod parser;
+pub mod profile;
 pub mod project;
 pub mod rust2nu;
+pub mod sourcemap;
 pub mod utils;
 pub mod workspace;
 
 pub use ast::*;
+pub use conversion::{ConversionOutput, Diagnostic, Severity, Transpile};
 pub use nu2cpp::Nu2CppConverter;
 pub use nu2rust::Nu2RustConverter;
 pub use nu2ts::Nu2TsConverter;
-pub use rust2nu::Rust2NuConverter;
+pub use profile::CompressionProfile;
+pub use rust2nu::{Rust2NuConverter, Rust2NuOptions};
 
 use anyhow::Result;
 
@@ -63,6 +70,26 @@ pub fn nu_to_ts(nu_code: &str) -> Result<String> {
     converter.convert(nu_code)
 }
 
+/// 将Rust代码转换为Nu代码，返回生成代码与诊断
+pub fn rust_to_nu_output(rust_code: &str) -> ConversionOutput {
+    Rust2NuConverter::new().transpile(rust_code)
+}
+
+/// 将Nu代码转换为Rust代码，返回生成代码、source map 映射与诊断
+pub fn nu_to_rust_output(nu_code: &str) -> ConversionOutput {
+    Nu2RustConverter::new().transpile(nu_code)
+}
+
+/// 将Nu代码转换为TypeScript代码，返回生成代码、source map 映射与诊断
+pub fn nu_to_ts_output(nu_code: &str) -> ConversionOutput {
+    Nu2TsConverter::with_default_config().transpile(nu_code)
+}
+
+/// 将Nu代码转换为C++代码，返回生成代码、source map 映射与诊断
+pub fn nu_to_cpp_output(nu_code: &str) -> ConversionOutput {
+    Nu2CppConverter::new().transpile(nu_code)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -106,4 +133,229 @@ F add(a: i32, b: i32) -> i32 {
         assert!(back_to_rust.contains("pub fn test"));
         assert!(back_to_rust.contains("return 42"));
     }
+
+    #[test]
+    fn test_round_trip_macros() {
+        let original_rust = "macro_rules! sq {\n    ($x:expr) => {\n        $x  *  $x // F S <\n    };\n}\n\nfn run() -> Result<(), String> {\n    assert_eq!(f()?, 1);\n    println!(\"{:?} < {}\", g()?, sq!(2));\n    Ok(())\n}\n";
+        let macro_rules = &original_rust[..original_rust.find("\n\n").unwrap()];
+
+        let nu_code = rust_to_nu(original_rust).unwrap();
+        assert!(nu_code.contains(macro_rules));
+        assert!(nu_code.contains("assert_eq!(f()!, 1);"));
+        assert!(nu_code.contains("println!(\"{:?} < {}\", g()!, sq!(2));"));
+
+        let back_to_rust = nu_to_rust(&nu_code).unwrap();
+        assert!(back_to_rust.contains(macro_rules));
+        assert!(back_to_rust.contains("assert_eq!(f()?, 1);"));
+        assert!(back_to_rust.contains("println!(\"{:?} < {}\", g()?, sq!(2));"));
+    }
+
+    #[test]
+    fn test_round_trip_profiles() {
+        let original_rust = "#[derive(Debug)]\npub struct Node {\n    pub items: Vec<Option<i32>>,\n    pub parent: Option<std::rc::Weak<Node>>,\n}\n\nimpl Node {\n    #[inline]\n    pub fn first(&self) -> Result<i32, String> {\n        for x in &self.items {\n            if let Some(v) = x {\n                return Ok(*v);\n            }\n        }\n        let v = vec![1];\n        Err(format!(\"{:?}\", v))\n    }\n}\n";
+
+        let convert = |profile: &str, preserve_syntax: bool| {
+            let options = Rust2NuOptions {
+                preserve_syntax,
+                profile: profile.parse().unwrap(),
+            };
+            Rust2NuConverter::with_options(options)
+                .convert(original_rust)
+                .unwrap()
+        };
+
+        // minimal：类型、属性、return 与循环保持 Rust 写法，关键字仍压缩
+        let nu_code = convert("minimal", false);
+        assert!(nu_code.starts_with("// nu:compress minimal\n"));
+        for expected in [
+            "#[derive(Debug)]",
+            "items: Vec<Option<i32>>",
+            "F first(&self) -> Result<i32, String>",
+            "for x in",
+            "return Ok",
+            "vec![1]",
+        ] {
+            assert!(nu_code.contains(expected), "{}", nu_code);
+        }
+
+        // max：Weak -> W、#[inline] -> #I
+        let nu_code = convert("max", true);
+        assert!(nu_code.starts_with("// nu:compress max\n"));
+        for expected in ["#D(Debug)", "parent: O<std::rc::W<Node>>", "    #I\n", "< Ok"] {
+            assert!(nu_code.contains(expected), "{}", nu_code);
+        }
+
+        // 首行记录的配置优先于 nu2rust 的选项
+        let rust_code = nu_to_rust(&convert("max", false)).unwrap();
+        assert!(!rust_code.contains("nu:compress"));
+        assert!(rust_code.contains("#[inline]"));
+        assert!(rust_code.contains("Option<std::rc::Weak<Node>>"));
+
+        // 无记录时按选项解释：minimal 下 V/O 不是缩写
+        let options = nu2rust::Nu2RustOptions {
+            profile: CompressionProfile::minimal(),
+            ..Default::default()
+        };
+        let rust_code = Nu2RustConverter::with_options(options)
+            .convert("F f(x: V) -> O {\n    x\n}\n")
+            .unwrap();
+        assert!(rust_code.contains("pub fn f(x: V) -> O {"), "{}", rust_code);
+
+        for profile in ["minimal", "default", "max", "types=Box returns=false"] {
+            for preserve_syntax in [false, true] {
+                let options = Rust2NuOptions {
+                    preserve_syntax,
+                    profile: profile.parse().unwrap(),
+                };
+                let report = rust2nu::verify_round_trip(original_rust, options).unwrap();
+                assert!(report.is_equivalent(), "{}: {:?}", profile, report.failure);
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_trip_keyword_identifiers() {
+        let original_rust = "pub struct V {\n    pub l: i32,\n    pub v: Vec<i32>,\n}\n\npub fn f(x: &V) -> i32 {\n    let mut l = x.l;\n    l = 1;\n    let u = v(l);\n    let M = u + x.v.len() as i32;\n    M\n}\n\nfn v(x: i32) -> i32 {\n    x\n}\n";
+
+        let nu_code = rust_to_nu(original_rust).unwrap();
+        for expected in ["S r#V {", "r#v: V<i32>", "F f(x: &r#V)", "    v l = x.l;", "r#l = 1;", "l r#M ="] {
+            assert!(nu_code.contains(expected), "{}", nu_code);
+        }
+
+        let rust_code = nu_to_rust(&nu_code).unwrap();
+        for expected in ["pub struct V {", "pub v: Vec<i32>", "let u = v(l);", "l = 1;"] {
+            assert!(rust_code.contains(expected), "{}", rust_code);
+        }
+        assert!(!rust_code.contains("r#"), "{}", rust_code);
+
+        for preserve_syntax in [false, true] {
+            let options = Rust2NuOptions {
+                preserve_syntax,
+                ..Default::default()
+            };
+            let report = rust2nu::verify_round_trip(original_rust, options).unwrap();
+            assert!(report.is_equivalent(), "{:?}", report.failure);
+        }
+    }
+
+    #[test]
+    fn test_round_trip_ffi_items() {
+        let original_rust = "#[macro_use]\nextern crate libc;\n\n#[repr(C)]\npub union Value {\n    pub i: i32,\n    pub f: f32,\n}\n\n#[link(name = \"m\")]\nextern \"C\" {\n    pub fn cos(x: f64) -> f64;\n    fn printf(fmt: *const u8, ...) -> i32;\n    pub static mut errno: i32;\n}\n\n#[no_mangle]\npub unsafe extern \"C\" fn callback(v: *mut Value) -> Option<i32> {\n    None\n}\n\npub trait Shape = Clone + Send;\n";
+
+        let nu_code = rust_to_nu(original_rust).unwrap();
+        for expected in [
+            "EXT crate libc;",
+            "UN Value {",
+            "EXT \"C\" {",
+            "    f printf(fmt: *const u8, ...) -> i32;",
+            "    SMP errno: i32;",
+            "unsafe EXT \"C\" F callback(v: *mut Value) -> O<i32> {",
+            "TR Shape = Clone + Send;",
+        ] {
+            assert!(nu_code.contains(expected), "{}", nu_code);
+        }
+
+        for preserve_syntax in [false, true] {
+            let report = rust2nu::verify_round_trip(
+                original_rust,
+                Rust2NuOptions {
+                    preserve_syntax,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            assert!(report.is_equivalent(), "{:?}", report.failure);
+        }
+    }
+
+    #[test]
+    fn test_round_trip_comments() {
+        let original_rust = "//! crate doc\n\n// header\nuse std::fmt;\n\n/// A point.\npub struct Point {\n    /// x coord\n    pub x: i32, // trailing field\n}\n\nfn run() -> Result<i32, String> {\n    // leading < wh\n    let x = g()?; // V<i32> < wh ? mut\n\n    /* block\n       comment */\n    Ok(x)\n}\n// eof\n";
+
+        let nu_code = rust_to_nu(original_rust).unwrap();
+        let back_to_rust = nu_to_rust(&nu_code).unwrap();
+        for expected in [
+            "//! crate doc\n\n// header\n",
+            "/// A point.\n",
+            "    /// x coord\n",
+            " // trailing field\n",
+            "    // leading < wh\n    let x = g()?; // V<i32> < wh ? mut\n\n    /* block\n       comment */\n    Ok(x)\n",
+            "}\n// eof\n",
+        ] {
+            assert!(back_to_rust.contains(expected), "{}", back_to_rust);
+        }
+        assert!(!back_to_rust.contains("#![doc"));
+    }
+
+    #[test]
+    fn test_round_trip_nested_comments() {
+        let original_rust = "fn sign(x: i32) -> i32 {\n    if x > 0 {\n        // positive\n        let y = x + 1;\n        return y;\n    } else {\n        // negative\n        return 0;\n    }\n}\n\nfn pick(x: Option<i32>) -> i32 {\n    /* lead */ let b = 2;\n    match x {\n        // some\n        Some(n) => {\n            // inside arm\n            n + b\n        }\n        None => 0, // none\n    }\n}\n";
+
+        let nu_code = rust_to_nu(original_rust).unwrap();
+        let back_to_rust = nu_to_rust(&nu_code).unwrap();
+        for expected in [
+            " {\n        // positive\n        let y = x + 1;\n",
+            "    } else {\n        // negative\n        return 0;\n",
+            "    /* lead */ let b = 2;\n",
+            "    match x {\n        // some\n        Some(n) => {\n            // inside arm\n            n + b\n",
+            "        None => 0, // none\n",
+        ] {
+            assert!(back_to_rust.contains(expected), "{}", back_to_rust);
+        }
+
+        let report = rust2nu::verify_round_trip(original_rust, Rust2NuOptions::default()).unwrap();
+        assert!(report.is_equivalent(), "{:?}", report.failure);
+    }
+
+    #[test]
+    fn test_round_trip_comments_in_closures_and_literals() {
+        let original_rust = "fn keep(items: Vec<String>, only: &[String]) -> Vec<String> {\n    let point = Point {\n        // x axis\n        x: 1,\n        y: 2,\n    };\n    items\n        .into_iter()\n        .filter(|m| {\n            // only matching\n            only.iter().any(|o| m.contains(o))\n        })\n        .collect()\n}\n\nconst LIMITS: [u8; 2] = [\n    1, // low\n    2,\n];\n";
+
+        let output = rust_to_nu_output(original_rust);
+        for expected in [
+            "    l point = Point {\n        // x axis\n        x: 1,\n",
+            "        .filter(|m| {\n            // only matching\n            only.iter()",
+        ] {
+            assert!(output.code.contains(expected), "{}", output.code);
+        }
+        // 无法留在原位的注释给出提示
+        let relocated: Vec<_> = output
+            .diagnostics
+            .iter()
+            .filter(|d| d.code == "relocated-comment")
+            .collect();
+        assert_eq!(relocated.len(), 1);
+        assert_eq!(relocated[0].span.as_ref().map(|s| s.line), Some(17));
+
+        let back_to_rust = nu_to_rust(&output.code).unwrap();
+        assert!(back_to_rust.contains("            // only matching\n"));
+        let report = rust2nu::verify_round_trip(original_rust, Rust2NuOptions::default()).unwrap();
+        assert!(report.is_equivalent(), "{:?}", report.failure);
+    }
+
+    #[test]
+    fn test_conversion_output() {
+        let output = rust_to_nu_output("lazy_static! { static ref N: u8 = 1; }\nfn a() {}\n");
+        assert!(output.code.contains("f a()"));
+        let diagnostic = output.warnings().next().unwrap();
+        assert_eq!(diagnostic.code, "macro-passthrough");
+        assert_eq!(diagnostic.span.as_ref().map(|s| (s.line, s.col)), Some((1, 1)));
+
+        let output = rust_to_nu_output("fn broken( {");
+        assert!(output.has_errors());
+        assert!(output.code.is_empty());
+
+        let output = nu_to_rust_output("F add(a: i32, b: i32) -> i32 {\n    < a + b\n}\n");
+        assert!(output.code.contains("pub fn add"));
+        assert!(output.diagnostics.is_empty());
+        assert!(output.sourcemap.iter().any(|m| m.source_line == 2));
+
+        let output = nu_to_cpp_output("F add(a: i32, b: i32) -> i32 {\n    < a + b\n}\n");
+        assert!(!output.has_errors());
+        assert!(!output.sourcemap.is_empty());
+
+        let output = nu_to_ts_output("F add(a: i32, b: i32) -> i32 {\n    < a + b\n}\n");
+        assert!(!output.has_errors());
+        assert!(output.code.contains("function add"));
+    }
 }
@@ -1,8 +1,19 @@
 // nuc - Nu Language Compiler CLI
 // Nu语言编译器命令行工具
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
+use nu_compiler::diagnostics::run_cargo_json;
+use nu_compiler::nu2rust::{LazySourceMap, Nu2RustConverter};
+use nu_compiler::parser::Parser as NuParser;
+use nu_compiler::project::{InitOptions, NuPackage, NuProject, ProjectKind, ProjectManager};
+use nu_compiler::rust2nu::{CompressionReport, TokenCounter};
+use nu_compiler::{Rust2NuConverter, Span};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+use walkdir::WalkDir;
 
 #[derive(Parser)]
 #[command(name = "nuc")]
@@ -19,6 +30,18 @@ enum Commands {
     Init {
         /// Project name
         name: String,
+
+        /// Create a library crate (src/lib.nu); combine with --bin for both targets
+        #[arg(long)]
+        lib: bool,
+
+        /// Create a binary crate (src/main.nu, default)
+        #[arg(long)]
+        bin: bool,
+
+        /// Create a workspace root with the crate under crates/<name>
+        #[arg(long)]
+        workspace: bool,
     },
 
     /// Build Nu project
@@ -30,25 +53,45 @@ enum Commands {
 
     /// Run Nu project
     Run {
+        /// Release mode
+        #[arg(short, long)]
+        release: bool,
+
         /// Arguments to pass to the program
-        #[arg(trailing_var_arg = true)]
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
         args: Vec<String>,
     },
 
     /// Compress Rust code to Nu
     Compress {
         /// Input Rust file or directory
-        input: String,
+        input: PathBuf,
 
-        /// Output Nu file or directory
+        /// Output Nu file or directory (omit to only print the report)
         #[arg(short, long)]
-        output: Option<String>,
+        output: Option<PathBuf>,
+
+        /// Overwrite existing output files
+        #[arg(short = 'f', long)]
+        force: bool,
+
+        /// BPE vocabulary file for token counts (vocab.json or one token per line)
+        #[arg(long, value_name = "FILE")]
+        vocab: Option<PathBuf>,
+
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
     },
 
     /// Check Nu syntax
     Check {
-        /// Nu file to check
-        file: String,
+        /// Nu file or directory to check
+        path: PathBuf,
+
+        /// Also translate to Rust in memory and validate the result with syn
+        #[arg(long)]
+        translate: bool,
     },
 }
 
@@ -56,28 +99,348 @@ fn main() -> Result<()> {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Init { name } => {
-            println!("Initializing new Nu project: {}", name);
-            println!("TODO: Implement project initialization");
+        Commands::Init {
+            name,
+            lib,
+            bin,
+            workspace,
+        } => init(&name, ProjectKind::from_flags(lib, bin), workspace)?,
+        Commands::Build { release } => build(release, None)?,
+        Commands::Run { release, args } => build(release, Some(&args))?,
+        Commands::Compress {
+            input,
+            output,
+            force,
+            vocab,
+            json,
+        } => compress(&input, output.as_deref(), force, vocab.as_deref(), json)?,
+        Commands::Check { path, translate } => check(&path, translate)?,
+    }
+
+    Ok(())
+}
+
+/// nuc init：在当前目录下创建 <name> 项目
+fn init(name: &str, kind: ProjectKind, workspace: bool) -> Result<()> {
+    let options = InitOptions { kind, workspace };
+    let root = PathBuf::from(name);
+    let created = ProjectManager::new().init(&root, name, &options)?;
+    for path in &created {
+        println!("  created {}", path.display());
+    }
+    println!("✓ Created {} `{}` project", kind.description(), name);
+    Ok(())
+}
+
+/// nuc build / nuc run：生成 target/nu-gen 影子项目并交给 cargo
+fn build(release: bool, run_args: Option<&[String]>) -> Result<()> {
+    let cwd = std::env::current_dir()?;
+    let root = ProjectManager::find_root(&cwd)
+        .context("Could not find Nu.toml in the current directory or any parent")?;
+    let manager = ProjectManager::new();
+    let project = manager.load(&root)?;
+    let (shadow, report) = manager.generate_shadow(&root)?;
+    if report.files_converted > 0 {
+        println!(
+            "  Generated {} file(s) in {}",
+            report.files_converted,
+            shadow.strip_prefix(&cwd).unwrap_or(&shadow).display()
+        );
+    }
+
+    if !cargo_build(&cwd, &root, &shadow, release)? {
+        std::process::exit(1);
+    }
+
+    if let Some(args) = run_args {
+        let mut cmd = cargo_command("run", &root, &shadow, release);
+        // 在工作区成员目录中运行时只运行该成员
+        if let Some(package) = current_member(&project, &cwd) {
+            cmd.arg("--package").arg(&package.name);
         }
-        Commands::Build { release } => {
-            println!("Building Nu project (release: {})", release);
-            println!("TODO: Implement project build");
+        cmd.arg("--quiet").arg("--").args(args);
+        let status = cmd.status().context("Failed to run cargo")?;
+        if !status.success() {
+            std::process::exit(status.code().unwrap_or(1));
+        }
+    }
+    Ok(())
+}
+
+/// cwd 所在的工作区成员（取最深的一个）
+fn current_member<'a>(project: &'a NuProject, cwd: &Path) -> Option<&'a NuPackage> {
+    project
+        .members
+        .iter()
+        .filter(|member| cwd.starts_with(&member.dir))
+        .max_by_key(|member| member.dir.components().count())
+}
+
+fn cargo_command(subcommand: &str, root: &Path, shadow: &Path, release: bool) -> Command {
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let mut cmd = Command::new(cargo);
+    cmd.arg(subcommand)
+        .arg("--manifest-path")
+        .arg(shadow.join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(root.join("target"));
+    if release {
+        cmd.arg("--release");
+    }
+    cmd
+}
+
+/// 运行 cargo build，诊断映射回 .nu 并按 Nu 源码重新渲染
+fn cargo_build(cwd: &Path, root: &Path, shadow: &Path, release: bool) -> Result<bool> {
+    let display_root = root.strip_prefix(cwd).unwrap_or(root);
+    let cmd = cargo_command("build", root, shadow, release);
+    let summary = run_cargo_json(cmd, shadow, display_root, |value| {
+        if value["reason"] == "compiler-message" {
+            if let Some(rendered) = value["message"]["rendered"].as_str() {
+                eprintln!("{}", rendered);
+            }
         }
-        Commands::Run { args } => {
-            println!("Running Nu project with args: {:?}", args);
-            println!("TODO: Implement project run");
+    })?;
+    Ok(summary.success)
+}
+
+/// nuc compress：Rust -> Nu 转换并输出字节/行/token 压缩统计
+fn compress(
+    input: &Path,
+    output: Option<&Path>,
+    force: bool,
+    vocab: Option<&Path>,
+    json: bool,
+) -> Result<()> {
+    let counter = match vocab {
+        Some(path) => TokenCounter::bpe(path)?,
+        None => TokenCounter::Simple,
+    };
+    let files = collect_files(input, "rs")?;
+    if files.is_empty() {
+        anyhow::bail!("No .rs files found in {}", input.display());
+    }
+
+    let mut report = CompressionReport::new(&counter);
+    for file in &files {
+        let rust_code = fs::read_to_string(file)
+            .with_context(|| format!("Failed to read input file: {}", file.display()))?;
+        let display = if input.is_dir() {
+            file.strip_prefix(input).unwrap_or(file)
+        } else {
+            file.as_path()
+        };
+        let display = display.display().to_string();
+
+        let nu_code = match Rust2NuConverter::new().convert(&rust_code) {
+            Ok(code) => code,
+            Err(e) => {
+                report.add_failure(display, &rust_code, format!("{:#}", e), &counter);
+                continue;
+            }
+        };
+
+        if let Some(output) = output {
+            let target = if input.is_dir() {
+                output.join(file.strip_prefix(input)?).with_extension("nu")
+            } else {
+                output.to_path_buf()
+            };
+            if target.exists() && !force {
+                anyhow::bail!(
+                    "Output file already exists: {} (use -f to overwrite)",
+                    target.display()
+                );
+            }
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&target, &nu_code)
+                .with_context(|| format!("Failed to write output file: {}", target.display()))?;
         }
-        Commands::Compress { input, output } => {
-            println!("Compressing {} to {:?}", input, output);
-            println!("TODO: Implement Rust to Nu compression");
-            println!("Hint: Use the rust2nu binary instead");
+        report.add(display, &rust_code, &nu_code, &counter);
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_compression_report(&report);
+    }
+    if report.failed() > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn print_compression_report(report: &CompressionReport) {
+    let width = report
+        .files
+        .iter()
+        .map(|f| f.path.chars().count())
+        .chain(std::iter::once(5))
+        .max()
+        .unwrap_or(5);
+    let cell = |before: usize, after: usize, saving: f64| {
+        format!("{:>8} → {:<8} {:>6.1}%", before, after, saving)
+    };
+
+    println!(
+        "{:<width$}  {:^27}  {:^27}  {:^27}",
+        "File",
+        "Bytes",
+        "Lines",
+        format!("Tokens ({})", report.tokenizer),
+    );
+    for file in &report.files {
+        match (&file.nu, &file.savings) {
+            (Some(nu), Some(savings)) => println!(
+                "{:<width$}  {}  {}  {}",
+                file.path,
+                cell(file.rust.bytes, nu.bytes, savings.bytes),
+                cell(file.rust.lines, nu.lines, savings.lines),
+                cell(file.rust.tokens, nu.tokens, savings.tokens),
+            ),
+            _ => println!(
+                "{:<width$}  ✗ {}",
+                file.path,
+                file.error.as_deref().unwrap_or("conversion failed")
+            ),
         }
-        Commands::Check { file } => {
-            println!("Checking Nu file: {}", file);
-            println!("TODO: Implement syntax check");
+    }
+    let (rust, nu, savings) = (&report.total_rust, &report.total_nu, &report.total_savings);
+    println!(
+        "{:<width$}  {}  {}  {}",
+        "Total",
+        cell(rust.bytes, nu.bytes, savings.bytes),
+        cell(rust.lines, nu.lines, savings.lines),
+        cell(rust.tokens, nu.tokens, savings.tokens),
+    );
+}
+
+/// nuc check：语法检查，可选翻译为 Rust 后用 syn 校验；有错误时以非零状态退出
+fn check(path: &Path, translate: bool) -> Result<()> {
+    let files = collect_files(path, "nu")?;
+    if files.is_empty() {
+        anyhow::bail!("No .nu files found in {}", path.display());
+    }
+
+    let mut error_count = 0;
+    let mut failed_files = 0;
+    for file in &files {
+        let diagnostics = check_file(file, translate)?;
+        if !diagnostics.is_empty() {
+            failed_files += 1;
+            error_count += diagnostics.len();
+            for diagnostic in diagnostics {
+                eprintln!("{}\n", diagnostic);
+            }
         }
     }
 
+    if error_count > 0 {
+        eprintln!(
+            "✗ {} error(s) in {} of {} file(s)",
+            error_count,
+            failed_files,
+            files.len()
+        );
+        std::process::exit(1);
+    }
+    println!("✓ Checked {} file(s), no errors", files.len());
     Ok(())
 }
+
+/// 收集指定扩展名的源文件（跳过 target 与隐藏目录）
+fn collect_files(path: &Path, extension: &str) -> Result<Vec<PathBuf>> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    if !path.is_dir() {
+        anyhow::bail!("Input path does not exist: {}", path.display());
+    }
+    let mut files: Vec<PathBuf> = WalkDir::new(path)
+        .into_iter()
+        .filter_entry(|e| {
+            let name = e.file_name().to_string_lossy();
+            e.depth() == 0 || !(name.starts_with('.') || name == "target")
+        })
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_file())
+        .filter(|e| e.path().extension().and_then(|s| s.to_str()) == Some(extension))
+        .map(|e| e.into_path())
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// 检查单个文件，返回渲染好的诊断信息
+fn check_file(path: &Path, translate: bool) -> Result<Vec<String>> {
+    let source = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read input file: {}", path.display()))?;
+    let display = path.display().to_string();
+
+    let (_, errors) = NuParser::with_file(&display).parse_with_errors(&source);
+    if !errors.is_empty() {
+        return Ok(errors.iter().map(|e| e.render(&source)).collect());
+    }
+    if !translate {
+        return Ok(Vec::new());
+    }
+
+    // 走与 nu2rust 相同的转换路径，借助行映射把 syn 错误定位回 .nu
+    let mut sourcemap = LazySourceMap::new(display.clone(), format!("{}.rs", display));
+    let rust_code =
+        match Nu2RustConverter::new().convert_with_sourcemap(&source, Some(&mut sourcemap)) {
+            Ok(code) => code,
+            Err(e) => {
+                return Ok(vec![format!(
+                    "error: translation failed: {:#}\n --> {}",
+                    e, display
+                )])
+            }
+        };
+    let Err(e) = syn::parse_file(&rust_code) else {
+        return Ok(Vec::new());
+    };
+
+    let (start, end) = (e.span().start(), e.span().end());
+    let rust_line = start.line;
+    let rust_len = if end.line == start.line {
+        end.column.saturating_sub(start.column)
+    } else {
+        1
+    };
+    // 优先使用列级映射；没有列信息时标出整行
+    let span = match sourcemap.find_nu_span(rust_line, start.column + 1, rust_len) {
+        Some(nu) => {
+            let text = source.lines().nth(nu.line.saturating_sub(1)).unwrap_or("");
+            let bytes = text
+                .chars()
+                .skip(nu.col - 1)
+                .take(nu.len)
+                .map(char::len_utf8)
+                .sum();
+            Span::new(nu.line, nu.col, bytes)
+        }
+        None => {
+            let nu_line = sourcemap.find_nearest_nu_line(rust_line).unwrap_or(1);
+            let text = source.lines().nth(nu_line.saturating_sub(1)).unwrap_or("");
+            let trimmed = text.trim_start();
+            let col = text[..text.len() - trimmed.len()].chars().count() + 1;
+            Span::new(nu_line, col, trimmed.trim_end().len())
+        }
+    }
+    .with_file(Some(Arc::from(display)));
+    let generated = rust_code
+        .lines()
+        .nth(rust_line.saturating_sub(1))
+        .unwrap_or("");
+    Ok(vec![format!(
+        "error: translated Rust does not parse: {}\n --> {}\n{}\n  = note: generated Rust line {}: {}",
+        e,
+        span,
+        span.snippet(&source),
+        rust_line,
+        generated.trim()
+    )])
+}
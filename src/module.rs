@@ -1,13 +1,460 @@
 // Nu Module System
-// 模块路径解析和管理
+// 模块路径解析和管理：从 crate 根文件出发沿 `D name` 加载模块树，并解析 `u` 导入
 
+use crate::ast::{
+    AttrKind, ForeignItem, NuAttr, NuExternCrate, NuFile, NuItem, NuMod, NuUse, Span, UseTree,
+    Visibility,
+};
+use crate::parser::{ParseError, Parser};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// 模块在 [`ModuleTree`] 中的索引
+pub type ModuleId = usize;
+
+/// 模块加载错误
+#[derive(Debug, Error)]
+pub enum ModuleError {
+    #[error("无法读取模块文件: {path}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error(
+        "{span}: 找不到模块 `{name}` 的文件（已尝试 {}）",
+        display_paths(candidates)
+    )]
+    FileNotFound {
+        name: String,
+        candidates: Vec<PathBuf>,
+        span: Span,
+    },
+
+    #[error("{span}: 模块文件 {path} 已被加载，忽略重复的 `D {name}`")]
+    DuplicateFile {
+        name: String,
+        path: PathBuf,
+        span: Span,
+    },
+
+    #[error("{file}: {} 个语法错误", errors.len())]
+    Parse {
+        file: PathBuf,
+        errors: Vec<ParseError>,
+    },
+}
+
+fn display_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|p| p.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// 模块中定义的条目种类
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ItemKind {
+    Fn,
+    Struct,
+    Union,
+    /// 附带变体名，用于解析 `u Shape::Circle`
+    Enum(Vec<String>),
+    Trait,
+    Const,
+    Static,
+    TypeAlias,
+    Macro,
+}
+
+/// 模块中定义的条目
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemDef {
+    pub name: String,
+    pub kind: ItemKind,
+    pub vis: Visibility,
+    pub span: Span,
+}
+
+/// 展开后的单条导入，如 `u a::{b, c as d}` 展开为两条
+#[derive(Debug, Clone, PartialEq)]
+pub struct Import {
+    /// 路径段；`::std::x` 的首段为空串
+    pub segments: Vec<String>,
+    /// 引入当前作用域的名字；glob 与 `as _` 为 None
+    pub binding: Option<String>,
+    pub is_glob: bool,
+    pub vis: Visibility,
+    pub span: Span,
+}
+
+impl Import {
+    pub fn path(&self) -> String {
+        let path = self.segments.join("::");
+        if self.is_glob {
+            format!("{}::*", path)
+        } else {
+            path
+        }
+    }
+}
+
+/// 模块树中的一个模块
+#[derive(Debug, Clone)]
+pub struct Module {
+    pub name: String,
+    /// 从 crate 开始的完整路径，如 ["crate", "a", "b"]
+    pub path: Vec<String>,
+    /// 定义该模块的文件（内联模块为其所在文件）
+    pub file: PathBuf,
+    pub inline: bool,
+    /// `D name` 声明处；crate 根为 None
+    pub decl_span: Option<Span>,
+    pub parent: Option<ModuleId>,
+    pub children: Vec<ModuleId>,
+    pub items: Vec<ItemDef>,
+    pub imports: Vec<Import>,
+    /// 子模块文件的查找目录
+    dir: PathBuf,
+}
+
+impl Module {
+    /// 如 `crate::a::b`
+    pub fn path_string(&self) -> String {
+        self.path.join("::")
+    }
+}
+
+/// 导入路径的解析结果
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+    Module(ModuleId),
+    Item {
+        module: ModuleId,
+        name: String,
+        kind: ItemKind,
+        span: Span,
+    },
+    Variant {
+        module: ModuleId,
+        enum_name: String,
+        variant: String,
+        span: Span,
+    },
+    /// 外部 crate（std、依赖等）中的路径，不再继续解析
+    External(String),
+}
+
+/// 成功解析的导入
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedImport {
+    pub module: ModuleId,
+    pub import: Import,
+    pub target: Target,
+}
+
+/// 无法解析的导入
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnresolvedImport {
+    pub module: ModuleId,
+    pub import: Import,
+    pub reason: String,
+}
+
+impl fmt::Display for UnresolvedImport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: 无法解析导入 `{}`: {}",
+            self.import.span,
+            self.import.path(),
+            self.reason
+        )
+    }
+}
+
+/// 全部导入的解析结果
+#[derive(Debug, Clone, Default)]
+pub struct ImportResolution {
+    pub resolved: Vec<ResolvedImport>,
+    pub unresolved: Vec<UnresolvedImport>,
+}
+
+/// 加载后的 crate 模块树；索引 0 为 crate 根
+#[derive(Debug)]
+pub struct ModuleTree {
+    pub modules: Vec<Module>,
+    /// 加载过程中的非致命错误（缺失的模块文件、语法错误等）
+    pub errors: Vec<ModuleError>,
+}
+
+/// 解析时跟随导入的最大深度，防止 `u` 互相引用造成死循环
+const MAX_IMPORT_DEPTH: usize = 32;
+
+impl ModuleTree {
+    pub fn root(&self) -> &Module {
+        &self.modules[0]
+    }
+
+    pub fn module(&self, id: ModuleId) -> &Module {
+        &self.modules[id]
+    }
+
+    /// 按路径查找模块，如 `crate::a::b`（`crate::` 前缀可省略）
+    pub fn find_module(&self, path: &str) -> Option<ModuleId> {
+        let mut id = 0;
+        for segment in path.split("::").filter(|s| !s.is_empty()) {
+            if id == 0 && segment == "crate" {
+                continue;
+            }
+            id = self.child(id, segment)?;
+        }
+        Some(id)
+    }
+
+    fn child(&self, module: ModuleId, name: &str) -> Option<ModuleId> {
+        self.modules[module]
+            .children
+            .iter()
+            .copied()
+            .find(|&c| self.modules[c].name == name)
+    }
+
+    /// 解析所有模块中的导入
+    pub fn resolve_imports(&self) -> ImportResolution {
+        let mut resolution = ImportResolution::default();
+        for (id, module) in self.modules.iter().enumerate() {
+            for import in &module.imports {
+                match self.resolve_import(id, import) {
+                    Ok(target) => resolution.resolved.push(ResolvedImport {
+                        module: id,
+                        import: import.clone(),
+                        target,
+                    }),
+                    Err(reason) => resolution.unresolved.push(UnresolvedImport {
+                        module: id,
+                        import: import.clone(),
+                        reason,
+                    }),
+                }
+            }
+        }
+        resolution
+    }
+
+    /// 解析单条导入；glob 导入解析到被展开的模块
+    pub fn resolve_import(&self, module: ModuleId, import: &Import) -> Result<Target, String> {
+        let target = self.resolve_path_at(module, &import.segments, 0)?;
+        if import.is_glob {
+            if let Target::Item {
+                kind: ItemKind::Enum(_),
+                ..
+            } = target
+            {
+                return Ok(target);
+            }
+            if !matches!(target, Target::Module(_) | Target::External(_)) {
+                return Err(format!(
+                    "`{}` 不是模块，不能使用 `*`",
+                    import.segments.join("::")
+                ));
+            }
+        }
+        Ok(target)
+    }
+
+    /// 在 `module` 中解析路径，如 `super::a::X`
+    pub fn resolve_path(&self, module: ModuleId, path: &str) -> Result<Target, String> {
+        let segments: Vec<String> = path.split("::").map(str::to_string).collect();
+        self.resolve_path_at(module, &segments, 0)
+    }
+
+    fn resolve_path_at(
+        &self,
+        module: ModuleId,
+        segments: &[String],
+        depth: usize,
+    ) -> Result<Target, String> {
+        if depth > MAX_IMPORT_DEPTH {
+            return Err("导入存在循环".to_string());
+        }
+        let Some(first) = segments.first() else {
+            return Ok(Target::Module(module));
+        };
+
+        let (mut target, rest) = match first.as_str() {
+            // ::std::x
+            "" => return Ok(Target::External(segments[1..].join("::"))),
+            "crate" => (Target::Module(0), &segments[1..]),
+            "self" => (Target::Module(module), &segments[1..]),
+            "super" => (Target::Module(module), segments),
+            name => match self.lookup(module, name, depth)? {
+                Some(target) => (target, &segments[1..]),
+                // 本地找不到的首段视为外部 crate
+                None => return Ok(Target::External(segments.join("::"))),
+            },
+        };
+
+        for (i, segment) in rest.iter().enumerate() {
+            target = match target {
+                Target::Module(id) => match segment.as_str() {
+                    "self" if i + 1 == rest.len() => Target::Module(id),
+                    "super" => Target::Module(
+                        self.modules[id]
+                            .parent
+                            .ok_or_else(|| "`super` 超出了 crate 根".to_string())?,
+                    ),
+                    name => self.lookup(id, name, depth)?.ok_or_else(|| {
+                        format!(
+                            "模块 `{}` 中没有 `{}`",
+                            self.modules[id].path_string(),
+                            name
+                        )
+                    })?,
+                },
+                Target::Item {
+                    module,
+                    name,
+                    kind: ItemKind::Enum(variants),
+                    span,
+                } => {
+                    if !variants.contains(segment) {
+                        return Err(format!("枚举 `{}` 中没有变体 `{}`", name, segment));
+                    }
+                    Target::Variant {
+                        module,
+                        enum_name: name,
+                        variant: segment.clone(),
+                        span,
+                    }
+                }
+                Target::External(path) => Target::External(format!("{}::{}", path, segment)),
+                Target::Item { name, .. } | Target::Variant { variant: name, .. } => {
+                    return Err(format!("`{}` 不是模块", name));
+                }
+            };
+        }
+        Ok(target)
+    }
+
+    /// 在模块作用域中查找名字：子模块、条目、导入、glob 导入
+    fn lookup(&self, module: ModuleId, name: &str, depth: usize) -> Result<Option<Target>, String> {
+        if let Some(child) = self.child(module, name) {
+            return Ok(Some(Target::Module(child)));
+        }
+        let m = &self.modules[module];
+        if let Some(item) = m.items.iter().find(|i| i.name == name) {
+            return Ok(Some(Target::Item {
+                module,
+                name: item.name.clone(),
+                kind: item.kind.clone(),
+                span: item.span.clone(),
+            }));
+        }
+        if let Some(import) = m
+            .imports
+            .iter()
+            .find(|i| i.binding.as_deref() == Some(name))
+        {
+            return self
+                .resolve_path_at(module, &import.segments, depth + 1)
+                .map(Some);
+        }
+        for import in m.imports.iter().filter(|i| i.is_glob) {
+            match self.resolve_path_at(module, &import.segments, depth + 1) {
+                Ok(Target::Module(source)) => {
+                    if let Some(target) = self.lookup(source, name, depth + 1)? {
+                        return Ok(Some(target));
+                    }
+                }
+                Ok(Target::Item {
+                    module: source,
+                    name: enum_name,
+                    kind: ItemKind::Enum(variants),
+                    span,
+                }) if variants.iter().any(|v| v == name) => {
+                    return Ok(Some(Target::Variant {
+                        module: source,
+                        enum_name,
+                        variant: name.to_string(),
+                        span,
+                    }));
+                }
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// 模块解析器：从 crate 根文件加载模块树
 pub struct ModuleResolver {
-    // TODO: 实现ModuleResolver
+    parse_errors: bool,
 }
 
 impl ModuleResolver {
     pub fn new() -> Self {
-        Self {}
+        Self { parse_errors: true }
+    }
+
+    /// 是否将语法错误记录到 [`ModuleTree::errors`]（默认开启）
+    pub fn parse_errors(mut self, enabled: bool) -> Self {
+        self.parse_errors = enabled;
+        self
+    }
+
+    /// 加载以 `root_file`（如 src/main.nu、src/lib.nu）为根的模块树
+    pub fn load(&self, root_file: &Path) -> Result<ModuleTree, ModuleError> {
+        let (ast, errors) = self.read_file(root_file)?;
+        let mut loader = Loader {
+            resolver: self,
+            modules: Vec::new(),
+            errors: Vec::new(),
+            loaded: HashSet::new(),
+        };
+        if let Ok(canonical) = root_file.canonicalize() {
+            loader.loaded.insert(canonical);
+        }
+        let dir = root_file.parent().unwrap_or(Path::new("")).to_path_buf();
+        let root = loader.push(Module {
+            name: "crate".to_string(),
+            path: vec!["crate".to_string()],
+            file: root_file.to_path_buf(),
+            inline: false,
+            decl_span: None,
+            parent: None,
+            children: Vec::new(),
+            items: Vec::new(),
+            imports: Vec::new(),
+            dir,
+        });
+        loader.record_parse_errors(root_file, errors);
+        loader.collect_items(root, &ast.items);
+        Ok(ModuleTree {
+            modules: loader.modules,
+            errors: loader.errors,
+        })
+    }
+
+    /// 加载模块树并解析全部导入
+    pub fn resolve(&self, root_file: &Path) -> Result<(ModuleTree, ImportResolution), ModuleError> {
+        let tree = self.load(root_file)?;
+        let resolution = tree.resolve_imports();
+        Ok((tree, resolution))
+    }
+
+    fn read_file(&self, path: &Path) -> Result<(NuFile, Vec<ParseError>), ModuleError> {
+        let source = fs::read_to_string(path).map_err(|source| ModuleError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let parser = Parser::with_file(path.to_string_lossy());
+        Ok(parser.parse_with_errors(&source))
     }
 }
 
@@ -16,3 +463,422 @@ impl Default for ModuleResolver {
         Self::new()
     }
 }
+
+struct Loader<'a> {
+    resolver: &'a ModuleResolver,
+    modules: Vec<Module>,
+    errors: Vec<ModuleError>,
+    /// 已加载的文件（规范化路径），防止 #[path] 造成重复加载
+    loaded: HashSet<PathBuf>,
+}
+
+impl Loader<'_> {
+    fn push(&mut self, module: Module) -> ModuleId {
+        let id = self.modules.len();
+        if let Some(parent) = module.parent {
+            self.modules[parent].children.push(id);
+        }
+        self.modules.push(module);
+        id
+    }
+
+    fn record_parse_errors(&mut self, file: &Path, errors: Vec<ParseError>) {
+        if self.resolver.parse_errors && !errors.is_empty() {
+            self.errors.push(ModuleError::Parse {
+                file: file.to_path_buf(),
+                errors,
+            });
+        }
+    }
+
+    fn collect_items(&mut self, module: ModuleId, items: &[NuItem]) {
+        for item in items {
+            match item {
+                NuItem::Mod(m) => self.load_child(module, m),
+                NuItem::Use(u) => self.collect_use(module, u),
+                NuItem::ExternCrate(c) => self.collect_extern_crate(module, c),
+                NuItem::Extern(ext) => {
+                    for foreign in &ext.items {
+                        let def = match foreign {
+                            ForeignItem::Fn(f) => def(&f.name, ItemKind::Fn, &f.vis, &f.span),
+                            ForeignItem::Static(s) => {
+                                def(&s.name, ItemKind::Static, &s.vis, &s.span)
+                            }
+                            ForeignItem::Type(t) => {
+                                def(&t.name, ItemKind::TypeAlias, &t.vis, &t.span)
+                            }
+                            ForeignItem::Macro(_) => continue,
+                        };
+                        self.modules[module].items.push(def);
+                    }
+                }
+                _ => {
+                    if let Some(def) = item_def(item) {
+                        self.modules[module].items.push(def);
+                    }
+                }
+            }
+        }
+    }
+
+    fn collect_use(&mut self, module: ModuleId, u: &NuUse) {
+        let mut imports = Vec::new();
+        flatten_use(&u.tree, &mut Vec::new(), &u.vis, &u.span, &mut imports);
+        self.modules[module].imports.extend(imports);
+    }
+
+    /// `EXT crate foo as bar;` 相当于把外部 crate `::foo` 导入为 `bar`
+    fn collect_extern_crate(&mut self, module: ModuleId, c: &NuExternCrate) {
+        let segments = if c.name == "self" {
+            vec!["crate".to_string()]
+        } else {
+            vec![String::new(), c.name.clone()]
+        };
+        self.modules[module].imports.push(Import {
+            segments,
+            binding: Some(c.rename.clone().unwrap_or_else(|| c.name.clone())),
+            is_glob: false,
+            vis: c.vis.clone(),
+            span: c.span.clone(),
+        });
+    }
+
+    /// 加载 `D name` 声明的子模块（内联或外部文件）
+    fn load_child(&mut self, parent: ModuleId, decl: &NuMod) {
+        let parent_module = &self.modules[parent];
+        let mut path = parent_module.path.clone();
+        path.push(decl.name.clone());
+        let path_attr = path_attribute(&decl.attrs);
+
+        if let Some(items) = &decl.items {
+            // 内联模块：#[path] 只影响其中子模块的查找目录
+            let dir = match &path_attr {
+                Some(p) => parent_module.dir.join(p),
+                None => parent_module.dir.join(&decl.name),
+            };
+            let child = self.push(Module {
+                name: decl.name.clone(),
+                path,
+                file: parent_module.file.clone(),
+                inline: true,
+                decl_span: Some(decl.span.clone()),
+                parent: Some(parent),
+                children: Vec::new(),
+                items: Vec::new(),
+                imports: Vec::new(),
+                dir,
+            });
+            self.collect_items(child, items);
+            return;
+        }
+
+        let (file, dir) = match &path_attr {
+            // #[path] 加载的文件按 mod.nu 规则查找其子模块
+            Some(p) => {
+                let base = if parent_module.inline {
+                    parent_module.dir.clone()
+                } else {
+                    parent_module
+                        .file
+                        .parent()
+                        .unwrap_or(Path::new(""))
+                        .to_path_buf()
+                };
+                let file = base.join(p);
+                if !file.is_file() {
+                    self.errors.push(ModuleError::FileNotFound {
+                        name: decl.name.clone(),
+                        candidates: vec![file],
+                        span: decl.span.clone(),
+                    });
+                    return;
+                }
+                let dir = file.parent().unwrap_or(Path::new("")).to_path_buf();
+                (file, dir)
+            }
+            None => {
+                let flat = parent_module.dir.join(format!("{}.nu", decl.name));
+                let nested = parent_module.dir.join(&decl.name).join("mod.nu");
+                if flat.is_file() {
+                    (flat, parent_module.dir.join(&decl.name))
+                } else if nested.is_file() {
+                    (nested, parent_module.dir.join(&decl.name))
+                } else {
+                    self.errors.push(ModuleError::FileNotFound {
+                        name: decl.name.clone(),
+                        candidates: vec![flat, nested],
+                        span: decl.span.clone(),
+                    });
+                    return;
+                }
+            }
+        };
+
+        if let Ok(canonical) = file.canonicalize() {
+            if !self.loaded.insert(canonical) {
+                self.errors.push(ModuleError::DuplicateFile {
+                    name: decl.name.clone(),
+                    path: file,
+                    span: decl.span.clone(),
+                });
+                return;
+            }
+        }
+
+        let (ast, errors) = match self.resolver.read_file(&file) {
+            Ok(ast) => ast,
+            Err(e) => {
+                self.errors.push(e);
+                return;
+            }
+        };
+        let child = self.push(Module {
+            name: decl.name.clone(),
+            path,
+            file: file.clone(),
+            inline: false,
+            decl_span: Some(decl.span.clone()),
+            parent: Some(parent),
+            children: Vec::new(),
+            items: Vec::new(),
+            imports: Vec::new(),
+            dir,
+        });
+        self.record_parse_errors(&file, errors);
+        self.collect_items(child, &ast.items);
+    }
+}
+
+fn def(name: &str, kind: ItemKind, vis: &Visibility, span: &Span) -> ItemDef {
+    ItemDef {
+        name: name.to_string(),
+        kind,
+        vis: vis.clone(),
+        span: span.clone(),
+    }
+}
+
+/// 条目定义；impl、use、mod 与普通宏调用不引入名字
+fn item_def(item: &NuItem) -> Option<ItemDef> {
+    Some(match item {
+        NuItem::Fn(f) => def(&f.name, ItemKind::Fn, &f.vis, &f.span),
+        NuItem::Struct(s) => def(&s.name, ItemKind::Struct, &s.vis, &s.span),
+        NuItem::Union(u) => def(&u.name, ItemKind::Union, &u.vis, &u.span),
+        NuItem::Enum(e) => def(
+            &e.name,
+            ItemKind::Enum(e.variants.iter().map(|v| v.name.clone()).collect()),
+            &e.vis,
+            &e.span,
+        ),
+        NuItem::Trait(t) => def(&t.name, ItemKind::Trait, &t.vis, &t.span),
+        NuItem::TraitAlias(t) => def(&t.name, ItemKind::Trait, &t.vis, &t.span),
+        NuItem::Const(c) => def(&c.name, ItemKind::Const, &c.vis, &c.span),
+        NuItem::Static(s) => def(&s.name, ItemKind::Static, &s.vis, &s.span),
+        NuItem::TypeAlias(t) => def(&t.name, ItemKind::TypeAlias, &t.vis, &t.span),
+        NuItem::Macro(m) => def(
+            m.name.as_deref()?,
+            ItemKind::Macro,
+            &Visibility::Private,
+            &m.span,
+        ),
+        NuItem::Impl(_)
+        | NuItem::Use(_)
+        | NuItem::Mod(_)
+        | NuItem::Extern(_)
+        | NuItem::ExternCrate(_) => return None,
+    })
+}
+
+/// `#[path = "..."]` 的路径
+fn path_attribute(attrs: &[NuAttr]) -> Option<String> {
+    attrs.iter().find_map(|attr| match &attr.kind {
+        AttrKind::Meta(meta) => {
+            let value = meta.strip_prefix("path")?.trim_start().strip_prefix('=')?;
+            let value = value.trim();
+            value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .map(str::to_string)
+        }
+        _ => None,
+    })
+}
+
+/// 将 use 树展开为单条导入
+fn flatten_use(
+    tree: &UseTree,
+    prefix: &mut Vec<String>,
+    vis: &Visibility,
+    span: &Span,
+    out: &mut Vec<Import>,
+) {
+    let import = |segments: Vec<String>, binding: Option<String>, is_glob: bool| Import {
+        segments,
+        binding,
+        is_glob,
+        vis: vis.clone(),
+        span: span.clone(),
+    };
+    match tree {
+        UseTree::Path { ident, tree } => {
+            prefix.push(ident.clone());
+            flatten_use(tree, prefix, vis, span, out);
+            prefix.pop();
+        }
+        // a::{self} 引入 a 本身
+        UseTree::Name(name) if name == "self" => {
+            if let Some(last) = prefix.last() {
+                out.push(import(prefix.clone(), Some(last.clone()), false));
+            }
+        }
+        UseTree::Name(name) => {
+            let mut segments = prefix.clone();
+            segments.push(name.clone());
+            out.push(import(segments, Some(name.clone()), false));
+        }
+        UseTree::Rename { ident, rename } => {
+            let mut segments = prefix.clone();
+            if ident != "self" {
+                segments.push(ident.clone());
+            }
+            let binding = (rename != "_").then(|| rename.clone());
+            out.push(import(segments, binding, false));
+        }
+        UseTree::Glob => out.push(import(prefix.clone(), None, true)),
+        UseTree::Group(trees) => {
+            for tree in trees {
+                flatten_use(tree, prefix, vis, span, out);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write_file(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_load_module_tree() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        write_file(
+            &src.join("main.nu"),
+            "D a;\nD b;\n#[path = \"other/c_impl.nu\"]\nD c;\nD inline {\n    S Local;\n    D deep;\n}\nD ghost;\nf main() {}\n",
+        );
+        write_file(&src.join("a.nu"), "D sub;\nE Shape { Circle, Square }\n");
+        write_file(&src.join("a/sub.nu"), "F f() {}\n");
+        write_file(&src.join("b/mod.nu"), "S Z;\n");
+        write_file(&src.join("other/c_impl.nu"), "C K: i32 = 1;\n");
+        write_file(&src.join("inline/deep.nu"), "F deep_fn() {}\n");
+
+        let tree = ModuleResolver::new().load(&src.join("main.nu")).unwrap();
+        let paths: Vec<String> = tree.modules.iter().map(|m| m.path_string()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "crate",
+                "crate::a",
+                "crate::a::sub",
+                "crate::b",
+                "crate::c",
+                "crate::inline",
+                "crate::inline::deep",
+            ]
+        );
+        let c = tree.find_module("crate::c").unwrap();
+        assert!(tree.module(c).file.ends_with("other/c_impl.nu"));
+        assert_eq!(tree.module(c).items[0].kind, ItemKind::Const);
+        assert!(tree.module(tree.find_module("inline").unwrap()).inline);
+
+        assert_eq!(tree.errors.len(), 1);
+        let message = tree.errors[0].to_string();
+        assert!(message.contains("`ghost`"), "{}", message);
+        assert!(message.contains("ghost/mod.nu"), "{}", message);
+    }
+
+    #[test]
+    fn test_resolve_imports() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        write_file(
+            &src.join("lib.nu"),
+            "D a;\nD b;\nu crate::a::b2::{X, Y};\nu self::b::Z;\nu a::Shape::Circle;\nu std::collections::HashMap;\nu crate::missing::Q;\nu crate::a::Nope;\nu b::ReX;\nEXT crate alloc as a2;\nUN Bits { i: i32 }\n",
+        );
+        write_file(
+            &src.join("a.nu"),
+            "DM b2 {\n    S X;\n    F Y() {}\n}\nE Shape { Circle, Square }\nD sub;\n",
+        );
+        write_file(
+            &src.join("a/sub.nu"),
+            "u super::super::b::Z as Zed;\nu super::Shape::*;\n",
+        );
+        write_file(&src.join("b/mod.nu"), "S Z;\nU crate::a::b2::X as ReX;\n");
+
+        let (tree, resolution) = ModuleResolver::new().resolve(&src.join("lib.nu")).unwrap();
+        assert!(tree.errors.is_empty(), "{:?}", tree.errors);
+
+        let unresolved: Vec<String> = resolution
+            .unresolved
+            .iter()
+            .map(|u| u.import.path())
+            .collect();
+        assert_eq!(unresolved, vec!["crate::missing::Q", "crate::a::Nope"]);
+        assert!(resolution.unresolved[1].to_string().contains("Nope"));
+
+        let target = |path: &str| {
+            resolution
+                .resolved
+                .iter()
+                .find(|r| r.import.path() == path)
+                .map(|r| r.target.clone())
+                .unwrap()
+        };
+        let b2 = tree.find_module("crate::a::b2").unwrap();
+        assert!(matches!(target("crate::a::b2::X"),
+            Target::Item { module, ref name, kind: ItemKind::Struct, .. } if module == b2 && name == "X"));
+        assert!(matches!(
+            target("crate::a::b2::Y"),
+            Target::Item {
+                kind: ItemKind::Fn,
+                ..
+            }
+        ));
+        assert!(matches!(target("a::Shape::Circle"),
+            Target::Variant { ref variant, .. } if variant == "Circle"));
+        assert_eq!(
+            target("std::collections::HashMap"),
+            Target::External("std::collections::HashMap".to_string())
+        );
+        // 通过 b 中的 `U ... as ReX` 重导出解析到原始定义
+        assert!(matches!(target("b::ReX"),
+            Target::Item { module, .. } if module == b2));
+        assert!(matches!(target("super::super::b::Z"),
+            Target::Item { ref name, .. } if name == "Z"));
+        assert!(matches!(target("super::Shape::*"), Target::Item { .. }));
+
+        // glob 导入的变体可在子模块作用域中查到
+        let sub = tree.find_module("crate::a::sub").unwrap();
+        assert!(matches!(tree.resolve_path(sub, "self::Square"),
+            Ok(Target::Variant { ref variant, .. }) if variant == "Square"));
+        assert!(tree.resolve_path(sub, "super::super::super::X").is_err());
+
+        // EXT crate 的别名指向外部 crate
+        assert_eq!(
+            tree.resolve_path(0, "a2::vec::Vec"),
+            Ok(Target::External("alloc::vec::Vec".to_string()))
+        );
+        assert!(matches!(
+            tree.resolve_path(0, "Bits"),
+            Ok(Target::Item {
+                kind: ItemKind::Union,
+                ..
+            })
+        ));
+    }
+}
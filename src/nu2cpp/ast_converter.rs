@@ -45,6 +45,7 @@ impl NuToCppAstConverter {
         let mut unit = CppTranslationUnit::new();
         unit.add_standard_includes();
 
+        let nu_code = super::unraw_for_cpp(nu_code);
         let lines: Vec<&str> = nu_code.lines().collect();
         let mut i = 0;
 
@@ -6,6 +6,8 @@
 // - cpp_codegen: AST -> C++ source code generator
 // - converter (this file): Nu AST -> C++ AST converter
 
+use crate::conversion::{placeholder_diagnostics, ConversionOutput, Diagnostic, Transpile};
+use crate::sourcemap::MappingTable;
 use anyhow::Result;
 
 // 导出 sourcemap 模块
@@ -20,6 +22,85 @@ pub use ast_converter::NuToCppAstConverter;
 pub use cpp_ast::*;
 pub use cpp_codegen::CppCodegen;
 
+/// C++ 关键字：Nu 中 `r#` 转义的同名标识符需改名
+const CPP_KEYWORDS: &[&str] = &[
+    "alignas",
+    "alignof",
+    "and",
+    "asm",
+    "auto",
+    "bool",
+    "break",
+    "case",
+    "catch",
+    "char",
+    "class",
+    "const",
+    "constexpr",
+    "continue",
+    "decltype",
+    "default",
+    "delete",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "explicit",
+    "export",
+    "extern",
+    "false",
+    "float",
+    "for",
+    "friend",
+    "goto",
+    "if",
+    "inline",
+    "int",
+    "long",
+    "mutable",
+    "namespace",
+    "new",
+    "noexcept",
+    "not",
+    "nullptr",
+    "operator",
+    "or",
+    "private",
+    "protected",
+    "public",
+    "register",
+    "return",
+    "short",
+    "signed",
+    "sizeof",
+    "static",
+    "struct",
+    "switch",
+    "template",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typedef",
+    "typeid",
+    "typename",
+    "union",
+    "unsigned",
+    "using",
+    "virtual",
+    "void",
+    "volatile",
+    "while",
+    "xor",
+];
+
+/// 去掉 `r#` 前缀，与 C++ 关键字冲突时追加 `_`
+pub(crate) fn unraw_for_cpp(nu_code: &str) -> String {
+    crate::escape::unraw_identifiers(nu_code, |name| {
+        CPP_KEYWORDS.contains(&name).then(|| format!("{}_", name))
+    })
+}
+
 pub struct Nu2CppConverter {
     // 转换上下文
     context: ConversionContext,
@@ -82,6 +163,7 @@ impl Nu2CppConverter {
              #include <optional>\n\n",
         );
 
+        let nu_code = unraw_for_cpp(nu_code);
         let lines: Vec<&str> = nu_code.lines().collect();
         let mut context = ConversionContext::default();
 
@@ -92,6 +174,9 @@ impl Nu2CppConverter {
             let line = lines[i];
             let trimmed = line.trim();
             let nu_line = i + 1;
+            let leading_whitespace: String =
+                line.chars().take_while(|c| c.is_whitespace()).collect();
+            let nu_col = leading_whitespace.chars().count() + 1;
 
             // 保留空行
             if trimmed.is_empty() {
@@ -104,7 +189,7 @@ impl Nu2CppConverter {
             // 保留注释行
             if trimmed.starts_with("//") || trimmed.starts_with("/*") || trimmed.starts_with("*") {
                 if let Some(ref mut sm) = sourcemap {
-                    sm.add_mapping(cpp_line, nu_line);
+                    sm.add_statement_mapping(cpp_line, nu_col, nu_line, nu_col);
                 }
                 output.push_str(line);
                 output.push('\n');
@@ -118,7 +203,7 @@ impl Nu2CppConverter {
             {
                 // C++没有直接对应的属性，转为注释
                 if let Some(ref mut sm) = sourcemap {
-                    sm.add_mapping(cpp_line, nu_line);
+                    sm.add_statement_mapping(cpp_line, 1, nu_line, nu_col);
                 }
                 output.push_str(&format!("// {}\n", trimmed));
                 cpp_line += 1;
@@ -126,11 +211,6 @@ impl Nu2CppConverter {
                 continue;
             }
 
-            // 记录当前行的映射
-            if let Some(ref mut sm) = sourcemap {
-                sm.add_mapping(cpp_line, nu_line);
-            }
-
             // 处理各种Nu语法
             if let Some(mut converted) = self.convert_line(line, &lines, &mut i, &mut context)? {
                 // P0修复: 在impl块内，对所有转换后的行进行Self和self替换
@@ -176,13 +256,22 @@ impl Nu2CppConverter {
                 };
 
                 if !final_converted.is_empty() {
-                    let leading_whitespace: String =
-                        line.chars().take_while(|c| c.is_whitespace()).collect();
                     let trimmed_converted = final_converted.trim_start();
+                    // 记录该语句生成的每一行（如 public: 标签加字段）的映射
+                    if let Some(ref mut sm) = sourcemap {
+                        for (k, cpp_text) in trimmed_converted.split('\n').enumerate() {
+                            let cpp_col = if k == 0 {
+                                nu_col
+                            } else {
+                                cpp_text.chars().take_while(|c| c.is_whitespace()).count() + 1
+                            };
+                            sm.add_statement_mapping(cpp_line + k, cpp_col, nu_line, nu_col);
+                        }
+                    }
                     output.push_str(&leading_whitespace);
                     output.push_str(trimmed_converted);
                     output.push('\n');
-                    cpp_line += 1;
+                    cpp_line += trimmed_converted.matches('\n').count() + 1;
                 }
             }
 
@@ -3653,8 +3742,46 @@ impl Nu2CppConverter {
     }
 }
 
+impl Transpile for Nu2CppConverter {
+    /// 生成代码中的 `/* TODO */` 占位报告为警告
+    fn transpile(&self, nu_code: &str) -> ConversionOutput {
+        let mut map = SourceMap::new(String::new(), String::new());
+        match self.convert_with_sourcemap(nu_code, Some(&mut map)) {
+            Ok(code) => {
+                let sourcemap = map.mappings();
+                let diagnostics = placeholder_diagnostics(&code, &sourcemap);
+                ConversionOutput {
+                    code,
+                    sourcemap,
+                    diagnostics,
+                }
+            }
+            Err(err) => ConversionOutput::failed(Diagnostic::error(
+                "conversion-failed",
+                format!("{:#}", err),
+            )),
+        }
+    }
+}
+
 impl Default for Nu2CppConverter {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raw_identifiers() {
+        let converter = Nu2CppConverter::new();
+        let nu_code = "F main() {\n    l r#l = 1;\n    l r#new = r#l + 2;\n}\n";
+
+        let cpp_code = converter.convert(nu_code).unwrap();
+        assert!(cpp_code.contains("const auto l = 1;"));
+        assert!(cpp_code.contains("const auto new_ = l + 2;"));
+        assert!(!cpp_code.contains("r#"));
+    }
+}
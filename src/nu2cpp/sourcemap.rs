@@ -1,6 +1,7 @@
 // C++ Source Map Support
 // 用于Nu到C++转换的源码映射
 
+use crate::sourcemap::{Mapping, MappingTable};
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::fs;
@@ -17,6 +18,16 @@ pub struct SourceMap {
 pub struct LineMapping {
     pub cpp_line: usize,
     pub nu_line: usize,
+    /// 语句在 C++ 行中的起始列（从 1 开始）
+    #[serde(default = "first_column")]
+    pub cpp_col: usize,
+    /// 语句在 Nu 行中的起始列（从 1 开始）
+    #[serde(default = "first_column")]
+    pub nu_col: usize,
+}
+
+fn first_column() -> usize {
+    1
 }
 
 impl SourceMap {
@@ -29,7 +40,23 @@ impl SourceMap {
     }
 
     pub fn add_mapping(&mut self, cpp_line: usize, nu_line: usize) {
-        self.mappings.push(LineMapping { cpp_line, nu_line });
+        self.add_statement_mapping(cpp_line, 1, nu_line, 1);
+    }
+
+    /// 记录一条语句的映射：C++ 与 Nu 两侧的行号和语句起始列
+    pub fn add_statement_mapping(
+        &mut self,
+        cpp_line: usize,
+        cpp_col: usize,
+        nu_line: usize,
+        nu_col: usize,
+    ) {
+        self.mappings.push(LineMapping {
+            cpp_line,
+            nu_line,
+            cpp_col,
+            nu_col,
+        });
     }
 
     pub fn mapping_count(&self) -> usize {
@@ -63,6 +90,28 @@ impl SourceMap {
     }
 }
 
+impl MappingTable for SourceMap {
+    fn source_file(&self) -> &str {
+        &self.source_file
+    }
+
+    fn generated_file(&self) -> &str {
+        &self.target_file
+    }
+
+    fn mappings(&self) -> Vec<Mapping> {
+        self.mappings
+            .iter()
+            .map(|m| Mapping {
+                generated_line: m.cpp_line,
+                generated_col: m.cpp_col,
+                source_line: m.nu_line,
+                source_col: m.nu_col,
+            })
+            .collect()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -78,5 +127,32 @@ mod tests {
         assert_eq!(map.mapping_count(), 3);
         assert_eq!(map.find_nu_line(5), Some(3));
         assert_eq!(map.find_cpp_line(3), Some(5));
+
+        let v3 = crate::sourcemap::SourceMapV3::from_table(&map);
+        assert_eq!(v3.mappings, "AAAA;AACA;;;AACA");
+    }
+
+    #[test]
+    fn test_statement_mappings() {
+        let nu_code = "S Point {\n    x: i32,\n}\n\nF calc(a: i32) -> i32 {\n    l x = a + 1;\n    l y = x * 2;\n    < x + y;\n}\n";
+        let mut map = SourceMap::new("test.nu".to_string(), "test.cpp".to_string());
+        let cpp = crate::nu2cpp::Nu2CppConverter::new()
+            .convert_with_sourcemap(nu_code, Some(&mut map))
+            .unwrap();
+        let cpp_lines: Vec<&str> = cpp.lines().collect();
+        let mapping_of = |needle: &str| {
+            let line = cpp_lines.iter().position(|l| l.contains(needle)).unwrap() + 1;
+            map.mappings()
+                .into_iter()
+                .find(|m| m.generated_line == line)
+                .map(|m| (m.source_line, m.source_col, m.generated_col))
+        };
+
+        // 结构体插入的 public: 标签不会让后续映射错位
+        assert_eq!(mapping_of("public:"), Some((2, 5, 5)));
+        assert_eq!(mapping_of("calc("), Some((5, 1, 1)));
+        assert_eq!(mapping_of("auto x"), Some((6, 5, 5)));
+        assert_eq!(mapping_of("auto y"), Some((7, 5, 5)));
+        assert_eq!(mapping_of("return x + y"), Some((8, 5, 5)));
     }
 }
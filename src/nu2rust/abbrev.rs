@@ -0,0 +1,641 @@
+// Nu 类型缩写展开
+// 基于词法单元识别 V/O/R/A/X/B/W 缩写（以及 I< -> impl<），
+// 跳过字符串、字符、注释与 macro_rules! 定义体，并尊重用户定义的同名类型和泛型参数
+
+use std::collections::HashSet;
+
+use super::lexer::{
+    is_type_item_keyword, next_significant, prev_significant, tokenize, Kind, LexState, Token,
+};
+use super::sourcemap::Rewrite;
+use crate::profile::TYPE_ABBREVIATIONS;
+
+/// rust2nu 旧版本按子串缩写留下的错误名称（整词修复）
+const LEGACY_RENAMES: &[(&str, &str)] = &[
+    ("MyV", "MyVec"),
+    ("MyB", "MyBox"),
+    ("VDeque", "VecDeque"),
+    ("BedError", "BoxedError"),
+];
+
+/// 可写成 `V::xxx` 的 Vec 关联函数
+const VEC_METHODS: &[&str] = &[
+    "new",
+    "with_capacity",
+    "from_raw_parts",
+    "from_raw_parts_in",
+    "new_in",
+    "with_capacity_in",
+    "try_with_capacity",
+    "try_with_capacity_in",
+];
+
+/// 可写成 `A::xxx` 的 Arc 关联函数（不含 default/clone 等通用 trait 方法）
+const ARC_METHODS: &[&str] = &[
+    "new",
+    "pin",
+    "try_pin",
+    "new_cyclic",
+    "new_uninit",
+    "new_zeroed",
+    "try_new",
+    "try_new_uninit",
+    "try_new_zeroed",
+    "new_uninit_slice",
+    "new_zeroed_slice",
+    "downgrade",
+    "weak_count",
+    "strong_count",
+    "ptr_eq",
+    "make_mut",
+    "get_mut",
+    "try_unwrap",
+    "into_inner",
+    "increment_strong_count",
+    "decrement_strong_count",
+    "as_ptr",
+    "into_raw",
+    "from_raw",
+];
+
+/// 可写成 `B::xxx` 的 Box 关联函数
+const BOX_METHODS: &[&str] = &[
+    "new",
+    "new_uninit",
+    "new_zeroed",
+    "new_in",
+    "new_uninit_in",
+    "new_zeroed_in",
+    "try_new",
+    "try_new_uninit",
+    "try_new_zeroed",
+    "try_new_in",
+    "try_new_uninit_in",
+    "try_new_zeroed_in",
+    "pin",
+    "pin_in",
+    "into_pin",
+    "leak",
+    "into_raw",
+    "from_raw",
+    "from_raw_in",
+    "downcast",
+    "downcast_unchecked",
+    "into_boxed_slice",
+    "into_inner",
+    "assume_init",
+    "write",
+];
+
+/// 声明常量/静态量的关键字；与类型声明一样，名称会遮蔽同名缩写
+const VALUE_ITEM_KEYWORDS: &[&str] = &["C", "CP", "ST", "SP", "SM", "SMP", "const", "static"];
+
+/// 函数关键字；与类型声明一样，名称后可紧跟泛型参数列表
+const FN_KEYWORDS: &[&str] = &["F", "f", "fn"];
+
+/// 是否是需要跟踪遮蔽的缩写名
+fn is_abbreviation(name: &str) -> bool {
+    name == "I" || TYPE_ABBREVIATIONS.iter().any(|(abbr, _)| *abbr == name)
+}
+
+/// `X::method` 形式允许展开的方法；None 表示任意方法都展开
+fn path_methods(abbr: &str) -> Option<&'static [&'static str]> {
+    match abbr {
+        "V" => Some(VEC_METHODS),
+        "A" => Some(ARC_METHODS),
+        "B" => Some(BOX_METHODS),
+        "W" => Some(&["new"]),
+        // Result 几乎没有关联函数，R::Output 等通常是泛型的关联类型
+        "R" => Some(&[]),
+        _ => None,
+    }
+}
+
+/// tokens[j] 与 tokens[j + 1] 是否组成 `::`
+fn is_path_sep(tokens: &[Token], j: usize) -> bool {
+    tokens[j].is_punct(':') && tokens.get(j + 1).is_some_and(|t| t.is_punct(':'))
+}
+
+/// 从 tokens[open] 处的分组起始符开始，返回匹配结束符之后的下标
+fn skip_group(tokens: &[Token], open: usize) -> usize {
+    let mut depth = 0usize;
+    for (j, token) in tokens.iter().enumerate().skip(open) {
+        if token.kind != Kind::Punct {
+            continue;
+        }
+        match token.text {
+            "{" | "(" | "[" => depth += 1,
+            "}" | ")" | "]" => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    return j + 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    tokens.len()
+}
+
+/// 缩写 tokens[i] 应展开成的名称；None 表示保持原样
+///
+/// is_generic 表示缩写名是当前作用域的泛型参数：泛型参数不能带 `<..>`，
+/// 因此 `V<T>` 仍展开，只保留 `V::xxx` 这类路径
+fn expansion(tokens: &[Token], i: usize, abbr: &str, is_generic: bool) -> Option<&'static str> {
+    let full = if abbr == "I" {
+        "impl"
+    } else {
+        TYPE_ABBREVIATIONS
+            .iter()
+            .find(|(a, _)| *a == abbr)
+            .map(|(_, full)| *full)?
+    };
+
+    // 字段/方法访问（x.V）和宏元变量（$V）不是类型
+    if let Some(p) = prev_significant(tokens, i) {
+        if tokens[p].is_punct('.') || tokens[p].is_punct('$') {
+            return None;
+        }
+    }
+    let next = next_significant(tokens, i + 1)?;
+    let token = tokens[next];
+
+    // 泛型参数：V<T>（排除 <= 与 <<）
+    if token.is_punct('<') {
+        let follow = tokens.get(next + 1);
+        if follow.is_some_and(|t| t.is_punct('=') || t.is_punct('<')) {
+            return None;
+        }
+        return Some(full);
+    }
+    if abbr == "I" || is_generic {
+        return None;
+    }
+
+    // 路径：V::<T> 总是类型；V::method 仅对该类型特有的关联函数展开
+    if is_path_sep(tokens, next) {
+        let after = next_significant(tokens, next + 2)?;
+        if tokens[after].is_punct('<') {
+            return Some(full);
+        }
+        if tokens[after].kind != Kind::Ident {
+            return None;
+        }
+        return match path_methods(abbr) {
+            None => Some(full),
+            Some(methods) if methods.contains(&tokens[after].text) => Some(full),
+            Some(_) => None,
+        };
+    }
+
+    // 模块路径后的 Result：io::R、fmt::R
+    if abbr == "R" && i >= 2 && is_path_sep(tokens, i - 2) {
+        let follow = tokens.get(i + 1);
+        if follow.is_some_and(|t| t.kind == Kind::Space) {
+            return Some(full);
+        }
+    }
+    None
+}
+
+/// 展开一段 Nu 代码中的类型缩写
+///
+/// state 为该片段开头所处的词法状态；types/generics 为当前作用域中与缩写同名的用户类型和泛型参数；
+/// 每次展开追加到 rewrites
+pub(crate) fn expand_abbreviations(
+    src: &str,
+    state: LexState,
+    types: &HashSet<String>,
+    generics: &HashSet<String>,
+    rewrites: &mut Vec<Rewrite>,
+) -> String {
+    let (tokens, _) = tokenize(src, state);
+    let mut out = String::with_capacity(src.len() + 16);
+    let mut i = 0;
+
+    while i < tokens.len() {
+        let token = tokens[i];
+        if token.kind != Kind::Ident {
+            out.push_str(token.text);
+            i += 1;
+            continue;
+        }
+
+        // macro_rules! 定义体原样保留
+        if token.text == "macro_rules" {
+            if let Some(bang) = next_significant(&tokens, i + 1) {
+                if tokens[bang].is_punct('!') {
+                    let open = (bang + 1..tokens.len()).find(|&j| {
+                        tokens[j].is_punct('{')
+                            || tokens[j].is_punct('(')
+                            || tokens[j].is_punct('[')
+                    });
+                    let end = open.map_or(tokens.len(), |open| skip_group(&tokens, open));
+                    for t in &tokens[i..end] {
+                        out.push_str(t.text);
+                    }
+                    i = end;
+                    continue;
+                }
+            }
+        }
+
+        if let Some((from, fixed)) = LEGACY_RENAMES.iter().find(|(from, _)| *from == token.text) {
+            rewrites.push(Rewrite::new(from, fixed));
+            out.push_str(fixed);
+            i += 1;
+            continue;
+        }
+
+        if !is_abbreviation(token.text) || types.contains(token.text) {
+            out.push_str(token.text);
+            i += 1;
+            continue;
+        }
+
+        // V![..] -> vec![..]
+        if token.text == "V" {
+            if let Some(bang) = next_significant(&tokens, i + 1) {
+                let bracket = next_significant(&tokens, bang + 1);
+                if tokens[bang].is_punct('!') && bracket.is_some_and(|b| tokens[b].is_punct('[')) {
+                    rewrites.push(Rewrite::new("V", "vec"));
+                    out.push_str("vec![");
+                    i = bracket.unwrap_or(bang) + 1;
+                    continue;
+                }
+            }
+        }
+
+        match expansion(&tokens, i, token.text, generics.contains(token.text)) {
+            Some(full) => {
+                rewrites.push(Rewrite::new(token.text, full));
+                out.push_str(full);
+                // `V <T>` -> `Vec<T>`
+                let next = next_significant(&tokens, i + 1).unwrap_or(i + 1);
+                i = if tokens.get(next).is_some_and(|t| t.is_punct('<')) {
+                    next
+                } else {
+                    i + 1
+                };
+            }
+            None => {
+                out.push_str(token.text);
+                i += 1;
+            }
+        }
+    }
+
+    out
+}
+
+/// 每行的缩写展开上下文
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LineScope {
+    /// 行首所处的词法状态
+    pub state: LexState,
+    /// 与缩写同名的用户类型/常量（整个文件内不展开）
+    pub types: HashSet<String>,
+    /// 当前作用域内与缩写同名的泛型参数
+    pub generics: HashSet<String>,
+    /// 行首位于 macro_rules! 定义体内
+    pub in_macro_rules: bool,
+    /// 本行以 macro_rules! 开始一个定义
+    pub macro_rules_start: bool,
+}
+
+/// 解析中的泛型作用域
+struct GenericScope {
+    names: Vec<String>,
+    start_line: usize,
+    nest: usize,
+    body_depth: Option<usize>,
+}
+
+/// 解析 `u`/`U` 导入树，tokens[start] 为关键字之后的第一个词法单元；
+/// 返回导入后可见的名称（叶子名或 `as` 别名）与 `;` 之后的下标
+fn parse_use_names(tokens: &[(usize, Token)], start: usize) -> (Vec<String>, usize) {
+    let mut names = Vec::new();
+    let mut j = start;
+    while j < tokens.len() {
+        let token = tokens[j].1;
+        if token.is_punct(';') {
+            return (names, j + 1);
+        }
+        let ends_leaf = tokens
+            .get(j + 1)
+            .is_none_or(|(_, t)| t.is_punct(',') || t.is_punct('}') || t.is_punct(';'));
+        if token.kind == Kind::Ident && token.text != "self" && ends_leaf {
+            names.push(token.text.to_string());
+        }
+        j += 1;
+    }
+    (names, j)
+}
+
+/// 解析泛型参数列表，tokens[open] 为 `<`；返回参数名与列表之后的下标
+fn parse_generic_params(tokens: &[(usize, Token)], open: usize) -> (Vec<String>, usize) {
+    let mut names = Vec::new();
+    let mut depth = 0usize;
+    let mut expect_param = false;
+    let mut j = open;
+    while j < tokens.len() {
+        let token = tokens[j].1;
+        if token.is_punct('<') {
+            depth += 1;
+            expect_param = depth == 1;
+        } else if token.is_punct('>') {
+            // Fn() -> R 中的箭头
+            if j > 0 && tokens[j - 1].1.is_punct('-') {
+                j += 1;
+                continue;
+            }
+            depth = depth.saturating_sub(1);
+            if depth == 0 {
+                return (names, j + 1);
+            }
+        } else if depth == 1 && token.is_punct(',') {
+            expect_param = true;
+        } else if expect_param && token.is(Kind::Ident, "const") {
+            // const N: usize
+        } else if expect_param {
+            if token.kind == Kind::Ident {
+                names.push(token.text.to_string());
+            }
+            expect_param = false;
+        } else if token.is_punct(';') || token.is_punct('{') {
+            break;
+        }
+        j += 1;
+    }
+    (names, j)
+}
+
+/// 扫描整个 Nu 文件，计算每一行的缩写展开上下文
+pub(crate) fn analyze(nu_code: &str) -> Vec<LineScope> {
+    let lines: Vec<&str> = nu_code.lines().collect();
+    let mut scopes: Vec<LineScope> = vec![LineScope::default(); lines.len()];
+
+    // 逐行切分，记录行首状态，只保留有效词法单元
+    let mut tokens: Vec<(usize, Token)> = Vec::new();
+    let mut state = LexState::Code;
+    for (line, text) in lines.iter().enumerate() {
+        scopes[line].state = state;
+        let (line_tokens, end) = tokenize(text, state);
+        tokens.extend(
+            line_tokens
+                .into_iter()
+                .filter(Token::is_significant)
+                .map(|t| (line, t)),
+        );
+        state = end;
+    }
+
+    let mut globals: HashSet<String> = HashSet::new();
+    let mut active: Vec<GenericScope> = Vec::new();
+    let mut spans: Vec<(Vec<String>, usize, usize)> = Vec::new();
+    let mut depth = 0usize;
+    let mut nest = 0usize;
+    let next_is = |k: usize, c: char| tokens.get(k).is_some_and(|(_, t)| t.is_punct(c));
+
+    let mut k = 0;
+    while k < tokens.len() {
+        let (line, token) = tokens[k];
+        match token.kind {
+            Kind::Ident if token.text == "macro_rules" && next_is(k + 1, '!') => {
+                let open = (k + 2..tokens.len()).find(|&j| {
+                    let t = tokens[j].1;
+                    t.is_punct('{') || t.is_punct('(') || t.is_punct('[')
+                });
+                if let Some(open) = open {
+                    let body: Vec<Token> = tokens[open..].iter().map(|(_, t)| *t).collect();
+                    let end = open + skip_group(&body, 0);
+                    let last_line = tokens.get(end - 1).map_or(line, |(l, _)| *l);
+                    for scope in scopes.iter_mut().take(last_line + 1).skip(line + 1) {
+                        scope.in_macro_rules = true;
+                    }
+                    if k == 0 || tokens[k - 1].0 != line {
+                        scopes[line].macro_rules_start = true;
+                    }
+                    k = end;
+                    continue;
+                }
+            }
+            Kind::Ident
+                if is_type_item_keyword(token.text)
+                    || VALUE_ITEM_KEYWORDS.contains(&token.text)
+                    || FN_KEYWORDS.contains(&token.text) =>
+            {
+                let is_fn = FN_KEYWORDS.contains(&token.text);
+                if let Some(&(_, name)) = tokens.get(k + 1).filter(|(_, t)| t.kind == Kind::Ident) {
+                    if !is_fn && is_abbreviation(name.text) {
+                        globals.insert(name.text.to_string());
+                    }
+                    let generic = is_fn || is_type_item_keyword(token.text);
+                    if generic && next_is(k + 2, '<') {
+                        let (names, end) = parse_generic_params(&tokens, k + 2);
+                        active.push(GenericScope {
+                            names,
+                            start_line: line,
+                            nest,
+                            body_depth: None,
+                        });
+                        k = end;
+                        continue;
+                    }
+                    k += 2;
+                    continue;
+                }
+            }
+            // 导入对整个模块生效，与声明一样遮蔽同名缩写
+            Kind::Ident
+                if matches!(token.text, "u" | "U" | "use")
+                    && tokens.get(k + 1).is_some_and(|(_, t)| {
+                        t.kind == Kind::Ident || t.is_punct(':') || t.is_punct('{')
+                    }) =>
+            {
+                let (names, end) = parse_use_names(&tokens, k + 1);
+                globals.extend(names.into_iter().filter(|n| is_abbreviation(n)));
+                k = end;
+                continue;
+            }
+            Kind::Ident if matches!(token.text, "I" | "impl") && next_is(k + 1, '<') => {
+                let (names, end) = parse_generic_params(&tokens, k + 1);
+                active.push(GenericScope {
+                    names,
+                    start_line: line,
+                    nest,
+                    body_depth: None,
+                });
+                k = end;
+                continue;
+            }
+            Kind::Punct => match token.text {
+                "{" => {
+                    depth += 1;
+                    if let Some(scope) = active.last_mut() {
+                        if scope.body_depth.is_none() && scope.nest == nest {
+                            scope.body_depth = Some(depth);
+                        }
+                    }
+                }
+                "}" => {
+                    if active
+                        .last()
+                        .is_some_and(|scope| scope.body_depth == Some(depth))
+                    {
+                        let scope = active.pop().unwrap_or_else(|| unreachable!());
+                        spans.push((scope.names, scope.start_line, line));
+                    }
+                    depth = depth.saturating_sub(1);
+                }
+                "(" | "[" => nest += 1,
+                ")" | "]" => nest = nest.saturating_sub(1),
+                // 无函数体的声明（t R<T> = ..; 或 trait 方法签名）在 `;` 处结束
+                ";" if active
+                    .last()
+                    .is_some_and(|scope| scope.body_depth.is_none() && scope.nest == nest) =>
+                {
+                    let scope = active.pop().unwrap_or_else(|| unreachable!());
+                    spans.push((scope.names, scope.start_line, line));
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+        k += 1;
+    }
+
+    let last_line = lines.len().saturating_sub(1);
+    spans.extend(
+        active
+            .into_iter()
+            .map(|scope| (scope.names, scope.start_line, last_line)),
+    );
+
+    for (names, start, end) in spans {
+        let names: Vec<String> = names.into_iter().filter(|n| is_abbreviation(n)).collect();
+        if names.is_empty() {
+            continue;
+        }
+        for scope in scopes.iter_mut().take(end + 1).skip(start) {
+            scope.generics.extend(names.iter().cloned());
+        }
+    }
+    if !globals.is_empty() {
+        for scope in &mut scopes {
+            scope.types.extend(globals.iter().cloned());
+        }
+    }
+    scopes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(src: &str) -> String {
+        expand_abbreviations(
+            src,
+            LexState::Code,
+            &HashSet::new(),
+            &HashSet::new(),
+            &mut Vec::new(),
+        )
+    }
+
+    #[test]
+    fn test_expand_type_positions() {
+        assert_eq!(
+            expand("items: V<O<R<A<X<B<W<u8>>>>>>>, Error>"),
+            "items: Vec<Option<Result<Arc<Mutex<Box<Weak<u8>>>>>>>, Error>"
+        );
+        assert_eq!(expand("V::new()"), "Vec::new()");
+        assert_eq!(
+            expand("V::<u8>::with_capacity(4)"),
+            "Vec::<u8>::with_capacity(4)"
+        );
+        assert_eq!(expand("A::clone(&x)"), "A::clone(&x)");
+        assert_eq!(expand("R::Output"), "R::Output");
+        assert_eq!(expand("O::Some(1)"), "Option::Some(1)");
+        assert_eq!(expand("x: V <T>"), "x: Vec<T>");
+        assert_eq!(expand("V![1, 2]"), "vec![1, 2]");
+        assert_eq!(expand("-> io::R<()> {"), "-> io::Result<()> {");
+        assert_eq!(expand("-> fmt::R {"), "-> fmt::Result {");
+        assert_eq!(expand("I<T> Foo for Bar<T>"), "impl<T> Foo for Bar<T>");
+        // 非类型位置保持不变
+        assert_eq!(expand("x.V < y.R"), "x.V < y.R");
+        assert_eq!(expand("YEAR < 3 && R <= 2"), "YEAR < 3 && R <= 2");
+        assert_eq!(expand("EmbedError + BedError"), "EmbedError + BoxedError");
+    }
+
+    #[test]
+    fn test_literals_and_comments_untouched() {
+        assert_eq!(
+            expand(r#"l s: V<u8> = "V<i32> and V::new()"; // O<T>"#),
+            r#"l s: Vec<u8> = "V<i32> and V::new()"; // O<T>"#
+        );
+        assert_eq!(
+            expand(r##"f(r#"B<T>"#, b"R<", 'V', '\'', 'a) /* A<T> */ X<T>"##),
+            r##"f(r#"B<T>"#, b"R<", 'V', '\'', 'a) /* A<T> */ Mutex<T>"##
+        );
+        assert_eq!(
+            expand("macro_rules! m { ($V:ty) => { V<$V> } } V<u8>"),
+            "macro_rules! m { ($V:ty) => { V<$V> } } Vec<u8>"
+        );
+        // 从跨行字符串中间开始
+        assert_eq!(
+            expand_abbreviations(
+                "V<u8>\" + V<u8>",
+                LexState::Str,
+                &HashSet::new(),
+                &HashSet::new(),
+                &mut Vec::new()
+            ),
+            "V<u8>\" + Vec<u8>"
+        );
+    }
+
+    #[test]
+    fn test_analyze_shadowing() {
+        let nu = "S R {\n    x: i32,\n}\nF first<V: Clone>(v: V<u8>) -> O<V> {\n    v\n}\nF second() -> V<u8> {\n    l s = \"\n    V<u8>\";\n}\nmacro_rules! m {\n    ($x:expr) => { V<u8> };\n}\n";
+        let scopes = analyze(nu);
+        assert!(scopes.iter().all(|s| s.types.contains("R")));
+        assert!(scopes[3].generics.contains("V"));
+        assert!(scopes[5].generics.contains("V"));
+        assert!(!scopes[6].generics.contains("V"));
+        assert_eq!(scopes[8].state, LexState::Str);
+        assert!(scopes[11].in_macro_rules);
+        assert!(!scopes[10].in_macro_rules);
+        assert!(scopes[10].macro_rules_start);
+
+        let expand_in = |src: &str, scope: &LineScope| {
+            expand_abbreviations(
+                src,
+                LexState::Code,
+                &scope.types,
+                &scope.generics,
+                &mut Vec::new(),
+            )
+        };
+        // 泛型参数 V 不带 <..>，V<u8> 仍是 Vec；V::new 可能是 trait 关联函数
+        assert_eq!(
+            expand_in(
+                "F first<V: Clone>(v: V<u8>) -> O<V> { V::new() }",
+                &scopes[3]
+            ),
+            "F first<V: Clone>(v: Vec<u8>) -> Option<V> { V::new() }"
+        );
+        assert_eq!(expand_in("-> R<u8, E>", &scopes[0]), "-> R<u8, E>");
+    }
+
+    #[test]
+    fn test_analyze_use_shadows_whole_file() {
+        let nu = "f a() -> V<i32> {\n    V::new()\n}\nu crate::types::{V, Other as O};\nU std::sync::Arc;\n";
+        let scopes = analyze(nu);
+        for scope in &scopes {
+            assert!(scope.types.contains("V"));
+            assert!(scope.types.contains("O"));
+            assert!(!scope.types.contains("A"));
+        }
+    }
+}
@@ -0,0 +1,309 @@
+// Nu 源码词法视图
+// 基于 crate::lexer 的 logos 词法分析，补回空白与注释并按类别归并，
+// 供类型缩写展开与逻辑语句切分使用；支持从跨行字符串/块注释中间开始
+
+use logos::Logos;
+
+use crate::lexer::{self as nu_lexer, LexErrorKind, SpannedToken};
+
+/// 行首的词法状态（跨行的字符串或块注释）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum LexState {
+    #[default]
+    Code,
+    Str,
+    RawStr(usize),
+    BlockComment(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Kind {
+    Ident,
+    Lifetime,
+    Literal,
+    Comment,
+    Space,
+    Punct,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Token<'a> {
+    pub kind: Kind,
+    pub text: &'a str,
+}
+
+impl Token<'_> {
+    pub fn is(&self, kind: Kind, text: &str) -> bool {
+        self.kind == kind && self.text == text
+    }
+
+    pub fn is_punct(&self, c: char) -> bool {
+        self.kind == Kind::Punct && self.text.starts_with(c)
+    }
+
+    pub fn is_significant(&self) -> bool {
+        !matches!(self.kind, Kind::Space | Kind::Comment)
+    }
+}
+
+/// 词法切分；state 为输入开头所处的状态，返回切分结果与结尾状态
+pub(super) fn tokenize(src: &str, state: LexState) -> (Vec<Token<'_>>, LexState) {
+    let mut tokens = Vec::new();
+
+    // 续接上一行未闭合的字面量或注释
+    let (resume, state) = match continue_state(src, state) {
+        Some((kind, end, next)) => {
+            tokens.push(Token {
+                kind,
+                text: &src[..end],
+            });
+            if next != LexState::Code {
+                return (tokens, next);
+            }
+            (end, next)
+        }
+        None => (0, state),
+    };
+
+    let rest = &src[resume..];
+    let (spanned, errors) = nu_lexer::tokenize_with_errors(rest);
+    let mut pieces: Vec<(usize, usize, Kind)> = Vec::new();
+    let mut state = state;
+    for SpannedToken { token, span, .. } in &spanned {
+        let text = &rest[span.clone()];
+        let kind = if token.as_ident().is_some() {
+            Kind::Ident
+        } else if matches!(token, nu_lexer::Token::Lifetime(_)) {
+            Kind::Lifetime
+        } else if token.is_literal() {
+            Kind::Literal
+        } else if matches!(
+            token,
+            nu_lexer::Token::DocComment(_) | nu_lexer::Token::InnerDocComment(_)
+        ) {
+            Kind::Comment
+        } else {
+            split_punct(text, span.start, &mut pieces);
+            continue;
+        };
+        pieces.push((span.start, span.end, kind));
+    }
+    for error in &errors {
+        let text = &rest[error.span.clone()];
+        match error.kind {
+            LexErrorKind::UnterminatedRawString => {
+                let hashes = text.trim_start_matches(['b', 'r']).len()
+                    - text
+                        .trim_start_matches(['b', 'r'])
+                        .trim_start_matches('#')
+                        .len();
+                pieces.push((error.span.start, error.span.end, Kind::Literal));
+                state = LexState::RawStr(hashes);
+            }
+            LexErrorKind::UnterminatedBlockComment => {
+                let depth = nu_lexer::scan_block_comment(&text[2..], 1)
+                    .err()
+                    .unwrap_or(1);
+                pieces.push((error.span.start, error.span.end, Kind::Comment));
+                state = LexState::BlockComment(depth);
+            }
+            LexErrorKind::UnknownChar if text.starts_with('"') || text.starts_with("b\"") => {
+                pieces.push((error.span.start, error.span.end, Kind::Literal));
+                state = LexState::Str;
+            }
+            LexErrorKind::UnknownChar => {
+                // 非 ASCII 标识符字符（如中文变量名）按标识符处理
+                for (i, c) in text.char_indices() {
+                    let at = error.span.start + i;
+                    let kind = if c.is_alphanumeric() {
+                        Kind::Ident
+                    } else if c.is_whitespace() {
+                        Kind::Space
+                    } else {
+                        Kind::Punct
+                    };
+                    pieces.push((at, at + c.len_utf8(), kind));
+                }
+            }
+        }
+    }
+    pieces.sort_unstable_by_key(|&(start, _, _)| start);
+    // 相邻的标识符片段（如 `a` + `ü`）合并为一个标识符
+    pieces.dedup_by(|next, prev| {
+        let joined = prev.1 == next.0 && prev.2 == Kind::Ident && next.2 == Kind::Ident;
+        if joined {
+            prev.1 = next.1;
+        }
+        joined
+    });
+
+    let mut copied = 0;
+    for (start, end, kind) in pieces {
+        push_trivia(&rest[copied..start], &mut tokens);
+        tokens.push(Token {
+            kind,
+            text: &rest[start..end],
+        });
+        copied = end;
+    }
+    push_trivia(&rest[copied..], &mut tokens);
+
+    (tokens, state)
+}
+
+/// 从跨行状态开始：返回续接部分的类别、结束偏移与之后的状态；Code 状态返回 None
+fn continue_state(src: &str, state: LexState) -> Option<(Kind, usize, LexState)> {
+    match state {
+        LexState::Code => None,
+        LexState::Str => {
+            // 补上开头的引号交给词法分析器处理转义
+            let quoted = format!("\"{src}");
+            let mut lexer = nu_lexer::Token::lexer(&quoted);
+            Some(match lexer.next() {
+                Some(Ok(nu_lexer::Token::StringLit(_))) => {
+                    (Kind::Literal, lexer.span().end - 1, LexState::Code)
+                }
+                _ => (Kind::Literal, src.len(), state),
+            })
+        }
+        LexState::RawStr(hashes) => {
+            let terminator = format!("\"{}", "#".repeat(hashes));
+            Some(match src.find(&terminator) {
+                Some(pos) => (Kind::Literal, pos + terminator.len(), LexState::Code),
+                None => (Kind::Literal, src.len(), state),
+            })
+        }
+        LexState::BlockComment(depth) => Some(match nu_lexer::scan_block_comment(src, depth) {
+            Ok(len) => (Kind::Comment, len + 2, LexState::Code),
+            Err(depth) => (Kind::Comment, src.len(), LexState::BlockComment(depth)),
+        }),
+    }
+}
+
+/// 符号按字符拆分（`::`、`#D` 等与逐字符扫描保持一致）：字母数字归为标识符
+fn split_punct(text: &str, offset: usize, pieces: &mut Vec<(usize, usize, Kind)>) {
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let mut end = i + c.len_utf8();
+        let kind = if c.is_alphanumeric() {
+            while let Some(&(j, c)) = chars.peek().filter(|(_, c)| c.is_alphanumeric()) {
+                end = j + c.len_utf8();
+                chars.next();
+            }
+            Kind::Ident
+        } else {
+            Kind::Punct
+        };
+        pieces.push((offset + i, offset + end, kind));
+    }
+}
+
+/// 词法分析器跳过的部分：空白与普通注释
+fn push_trivia<'a>(gap: &'a str, tokens: &mut Vec<Token<'a>>) {
+    let mut rest = gap;
+    while !rest.is_empty() {
+        let len = if rest.starts_with("//") {
+            rest.find('\n').unwrap_or(rest.len())
+        } else if let Some(body) = rest.strip_prefix("/*") {
+            nu_lexer::scan_block_comment(body, 1).map_or(rest.len(), |len| len + 4)
+        } else {
+            rest.find(|c: char| !c.is_whitespace())
+                .unwrap_or(rest.len())
+        };
+        let kind = if rest.starts_with('/') {
+            Kind::Comment
+        } else {
+            Kind::Space
+        };
+        tokens.push(Token {
+            kind,
+            text: &rest[..len],
+        });
+        rest = &rest[len..];
+    }
+}
+
+/// 下一个有效（非空白、非注释）词法单元的下标
+pub(super) fn next_significant(tokens: &[Token], from: usize) -> Option<usize> {
+    (from..tokens.len()).find(|&j| tokens[j].is_significant())
+}
+
+pub(super) fn prev_significant(tokens: &[Token], before: usize) -> Option<usize> {
+    (0..before).rev().find(|&j| tokens[j].is_significant())
+}
+
+/// 声明具名类型的关键字：Nu 的 S/s/E/e/TR/tr/UN/un/t 与对应的 Rust 关键字
+pub(super) fn is_type_item_keyword(word: &str) -> bool {
+    use nu_lexer::Token as T;
+    matches!(
+        nu_lexer::keyword(word),
+        Some(
+            T::StructPub
+                | T::StructPriv
+                | T::EnumPub
+                | T::EnumPriv
+                | T::TraitPub
+                | T::TraitPriv
+                | T::UnionPub
+                | T::UnionPriv
+                | T::Type
+        )
+    ) || matches!(word, "struct" | "enum" | "union" | "trait" | "type")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(src: &str, state: LexState) -> (Vec<(Kind, &str)>, LexState) {
+        let (tokens, end) = tokenize(src, state);
+        (tokens.iter().map(|t| (t.kind, t.text)).collect(), end)
+    }
+
+    #[test]
+    fn test_tokenize_states() {
+        let (tokens, end) = kinds("l x: V<'a> = #D(1.5) // c", LexState::Code);
+        assert_eq!(end, LexState::Code);
+        assert_eq!(
+            tokens
+                .iter()
+                .filter(|t| t.0 != Kind::Space)
+                .collect::<Vec<_>>(),
+            [
+                &(Kind::Ident, "l"),
+                &(Kind::Ident, "x"),
+                &(Kind::Punct, ":"),
+                &(Kind::Ident, "V"),
+                &(Kind::Punct, "<"),
+                &(Kind::Lifetime, "'a"),
+                &(Kind::Punct, ">"),
+                &(Kind::Punct, "="),
+                &(Kind::Punct, "#"),
+                &(Kind::Ident, "D"),
+                &(Kind::Punct, "("),
+                &(Kind::Literal, "1.5"),
+                &(Kind::Punct, ")"),
+                &(Kind::Comment, "// c"),
+            ]
+        );
+
+        // 跨行字符串、原始字符串与嵌套块注释
+        assert_eq!(kinds("l s = \"ab", LexState::Code).1, LexState::Str);
+        let (tokens, end) = kinds("c\\\" d\" + V", LexState::Str);
+        assert_eq!(tokens[0], (Kind::Literal, "c\\\" d\""));
+        assert_eq!(end, LexState::Code);
+        assert_eq!(kinds("r##\"x", LexState::Code).1, LexState::RawStr(2));
+        assert_eq!(
+            kinds("\"# \"##", LexState::RawStr(2)).0,
+            [(Kind::Literal, "\"# \"##")]
+        );
+        assert_eq!(
+            kinds("/* a /* b */", LexState::Code).1,
+            LexState::BlockComment(1)
+        );
+        let (tokens, end) = kinds("a */ */ 中文", LexState::BlockComment(2));
+        assert_eq!(tokens[0], (Kind::Comment, "a */ */"));
+        assert_eq!(tokens[2], (Kind::Ident, "中文"));
+        assert_eq!(end, LexState::Code);
+    }
+}
@@ -0,0 +1,365 @@
+// 逻辑语句切分
+// 把跨多个物理行的 Nu 语句（多行签名、wh 子句、链式调用、多行参数列表）合并为一个逻辑行，
+// 使转换结果不随源码的换行方式改变；`{` 打开的代码块内部仍逐行转换
+
+use super::lexer::{is_type_item_keyword, tokenize, Kind, LexState, Token};
+
+/// 单个逻辑语句最多合并的物理行数
+const MAX_LOGICAL_LINES: usize = 64;
+
+/// 可出现在声明关键字之前的修饰符
+const ITEM_PREFIXES: &[&str] = &["pub", "unsafe", "async", "const", "extern", "EXT", "U"];
+
+/// 逻辑语句的首行类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Head {
+    /// 条目声明头：F/S/E/TR/I/t ...
+    Item,
+    /// 以 `;` 结束的语句：l/v 变量声明、`<` 返回
+    Statement,
+    /// 其他表达式
+    Other,
+}
+
+fn significant<'a>(tokens: &[Token<'a>]) -> Vec<Token<'a>> {
+    tokens
+        .iter()
+        .copied()
+        .filter(Token::is_significant)
+        .collect()
+}
+
+fn classify(tokens: &[Token]) -> Head {
+    let mut k = 0;
+    // 跳过 pub(crate)、extern "C"、EXT "C"、~（async）等修饰
+    while let Some(t) = tokens.get(k) {
+        if t.kind == Kind::Ident && ITEM_PREFIXES.contains(&t.text) {
+            k += 1;
+            if t.text == "pub" && tokens.get(k).is_some_and(|t| t.is_punct('(')) {
+                while tokens.get(k).is_some_and(|t| !t.is_punct(')')) {
+                    k += 1;
+                }
+                k += 1;
+            } else if matches!(t.text, "extern" | "EXT")
+                && tokens.get(k).is_some_and(|t| t.kind == Kind::Literal)
+            {
+                k += 1;
+            }
+        } else if t.is_punct('~') {
+            k += 1;
+        } else {
+            break;
+        }
+    }
+
+    let Some(first) = tokens.get(k) else {
+        return Head::Other;
+    };
+    let second = tokens.get(k + 1);
+    let third = tokens.get(k + 2);
+    let (head, matched) = match (first.kind, first.text) {
+        (Kind::Ident, "I" | "impl") => (
+            Head::Item,
+            second.is_some_and(|t| t.kind == Kind::Ident || t.is_punct('<')),
+        ),
+        (Kind::Ident, "F" | "f" | "fn") => (
+            Head::Item,
+            second.is_some_and(|t| t.kind == Kind::Ident)
+                && third.is_some_and(|t| t.is_punct('(') || t.is_punct('<')),
+        ),
+        // 其余条目声明头一直合并到 `{` 或 `;`
+        (Kind::Ident, keyword) if is_type_item_keyword(keyword) => {
+            (Head::Item, second.is_some_and(|t| t.kind == Kind::Ident))
+        }
+        (Kind::Ident, "l" | "v" | "let") => (
+            Head::Statement,
+            second.is_some_and(|t| {
+                t.kind == Kind::Ident || t.is_punct('(') || t.is_punct('[') || t.is_punct('&')
+            }),
+        ),
+        (Kind::Punct, "<") => (
+            Head::Statement,
+            !second.is_some_and(|t| t.is_punct('=') || t.is_punct('<')),
+        ),
+        _ => (Head::Other, false),
+    };
+    if matched {
+        head
+    } else {
+        Head::Other
+    }
+}
+
+/// 下一行是否以续行记号开头：.method、?、&&、||、->、as、wh
+fn starts_continuation(tokens: &[Token]) -> bool {
+    let Some(first) = tokens.first() else {
+        return false;
+    };
+    let second = tokens.get(1);
+    match (first.kind, first.text) {
+        (Kind::Punct, ".") => !second.is_some_and(|t| t.is_punct('.')),
+        (Kind::Punct, "?") => true,
+        (Kind::Punct, "&") => second.is_some_and(|t| t.is_punct('&')),
+        (Kind::Punct, "|") => second.is_some_and(|t| t.is_punct('|')),
+        (Kind::Punct, "-") => second.is_some_and(|t| t.is_punct('>')),
+        (Kind::Ident, "as" | "wh" | "where") => true,
+        _ => false,
+    }
+}
+
+/// 返回从第 start 行开始的逻辑语句的结束行号（不含）
+///
+/// states 为每行行首的词法状态（见 abbrev::analyze）
+pub(super) fn statement_end(lines: &[&str], states: &[LexState], start: usize) -> usize {
+    let state_at = |i: usize| states.get(i).copied().unwrap_or_default();
+    let (first, _) = tokenize(lines[start], state_at(start));
+    let head = classify(&significant(&first));
+
+    // 未闭合的分隔符与整段的最后一个有效词法单元
+    let mut stack: Vec<char> = Vec::new();
+    let mut last: Option<Token> = None;
+    let mut end = start;
+    loop {
+        let (tokens, _) = tokenize(lines[end], state_at(end));
+        for token in significant(&tokens) {
+            if token.kind == Kind::Punct {
+                match token.text {
+                    "(" => stack.push('('),
+                    "[" => stack.push('['),
+                    "{" => stack.push('{'),
+                    ")" | "]" | "}" => {
+                        stack.pop();
+                    }
+                    _ => {}
+                }
+            }
+            last = Some(token);
+        }
+        end += 1;
+        if end >= lines.len() || end - start >= MAX_LOGICAL_LINES {
+            return end;
+        }
+
+        let next_state = state_at(end);
+        // 下一行从跨行字符串中间开始
+        if matches!(next_state, LexState::Str | LexState::RawStr(_)) {
+            continue;
+        }
+        // 未闭合的 ( [：多行参数列表、数组字面量
+        if matches!(stack.last(), Some('(') | Some('[')) {
+            continue;
+        }
+        // `{` 打开的代码块逐行转换
+        if !stack.is_empty() {
+            return end;
+        }
+        let ended = last.is_none_or(|t| t.is_punct(';') || t.is_punct('{') || t.is_punct('}'));
+        if ended {
+            return end;
+        }
+
+        let (next_tokens, _) = tokenize(lines[end], next_state);
+        let next = significant(&next_tokens);
+        let Some(next_first) = next.first() else {
+            return end;
+        };
+        if next_first.is_punct('}') || next_first.is_punct(')') || next_first.is_punct(']') {
+            return end;
+        }
+        let continues = match head {
+            // 声明头一直到 `{` 或 `;`（多行泛型参数、单独一行的 wh 子句）
+            Head::Item => true,
+            // l/v/< 语句一直到 `;`
+            Head::Statement => !last.is_some_and(|t| t.is_punct(',')),
+            Head::Other => false,
+        };
+        if !continues && !starts_continuation(&next) {
+            return end;
+        }
+    }
+}
+
+/// 拆出逻辑语句末尾的行尾注释（同一行注释前有代码），返回 (代码, 注释)
+///
+/// 注释不参与转换，输出时原样接回，避免其内容被当作 Nu 代码改写
+pub(super) fn split_trailing_comment(src: &str, state: LexState) -> (&str, Option<&str>) {
+    let (tokens, end_state) = tokenize(src, state);
+    let Some(pos) = tokens.iter().rposition(|t| t.kind != Kind::Space) else {
+        return (src, None);
+    };
+    if tokens[pos].kind != Kind::Comment || end_state != LexState::Code {
+        return (src, None);
+    }
+    let has_code = tokens[..pos]
+        .iter()
+        .rev()
+        .take_while(|t| !t.text.contains('\n'))
+        .any(|t| t.kind != Kind::Space);
+    if !has_code {
+        return (src, None);
+    }
+    let offset: usize = tokens[..pos].iter().map(|t| t.text.len()).sum();
+    (src[..offset].trim_end(), Some(tokens[pos].text))
+}
+
+/// 拆出逻辑语句开头、同一行后面还有代码的块注释（`/* a */ l x = 1;`），返回 (注释, 去掉注释的代码)
+///
+/// 代码保留原有缩进，输出时注释接回缩进之后
+pub(super) fn split_leading_comment(src: &str, state: LexState) -> Option<(&str, String)> {
+    if state != LexState::Code {
+        return None;
+    }
+    let (tokens, _) = tokenize(src, state);
+    let start = tokens.iter().position(|t| t.kind != Kind::Space)?;
+    let comment = &tokens[start];
+    if comment.kind != Kind::Comment
+        || !comment.text.starts_with("/*")
+        || comment.text.contains('\n')
+    {
+        return None;
+    }
+    let next = start
+        + 1
+        + tokens[start + 1..]
+            .iter()
+            .position(|t| t.kind != Kind::Space)?;
+    // 注释之后在同一行还要有代码
+    if tokens[next].kind == Kind::Comment
+        || tokens[start + 1..next]
+            .iter()
+            .any(|t| t.text.contains('\n'))
+    {
+        return None;
+    }
+    let offset = |end: usize| tokens[..end].iter().map(|t| t.text.len()).sum::<usize>();
+    let code = format!("{}{}", &src[..offset(start)], &src[offset(next)..]);
+    Some((comment.text, code))
+}
+
+/// 字符串字面量的占位符前缀
+pub(super) const LITERAL_PLACEHOLDER: &str = "__NU_STR_";
+
+/// 把逻辑语句中的字符串字面量替换为占位符，避免其内容（格式串、跨行文本）被当作 Nu 代码改写
+///
+/// `r#` 原始标识符（rust2nu 转义的 `r#l`、`r#V` 等）同样替换，还原时去掉不需要的 `r#`
+pub(super) fn mask_string_literals(src: &str, state: LexState) -> (String, Vec<String>) {
+    let (tokens, _) = tokenize(src, state);
+    let mut masked = String::with_capacity(src.len());
+    let mut literals = Vec::new();
+    for token in tokens {
+        if token.kind == Kind::Literal && token.text.contains('"') {
+            masked.push_str(&format!("{}{}__", LITERAL_PLACEHOLDER, literals.len()));
+            literals.push(token.text.to_string());
+        } else if token.kind == Kind::Ident && token.text.starts_with("r#") {
+            masked.push_str(&format!("{}{}__", LITERAL_PLACEHOLDER, literals.len()));
+            literals.push(crate::escape::unescape(token.text).to_string());
+        } else {
+            masked.push_str(token.text);
+        }
+    }
+    (masked, literals)
+}
+
+/// 还原 mask_string_literals 替换掉的字符串字面量
+pub(super) fn restore_string_literals(src: &str, literals: &[String]) -> String {
+    let mut result = src.to_string();
+    for (idx, literal) in literals.iter().enumerate() {
+        let placeholder = format!("{}{}__", LITERAL_PLACEHOLDER, idx);
+        result = result.replacen(&placeholder, literal, 1);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn groups(code: &str) -> Vec<(usize, usize)> {
+        let lines: Vec<&str> = code.lines().collect();
+        let states = crate::nu2rust::abbrev::analyze(code)
+            .into_iter()
+            .map(|scope| scope.state)
+            .collect::<Vec<_>>();
+        let mut result = Vec::new();
+        let mut i = 0;
+        while i < lines.len() {
+            let end = statement_end(&lines, &states, i);
+            result.push((i, end));
+            i = end;
+        }
+        result
+    }
+
+    #[test]
+    fn test_multi_line_signatures() {
+        let code = "F pick<\n    T: Clone,\n    F : Fn(i32) -> i32,\n>(x: T, f: F) -> T\nwh\n    T: Default,\n{\n    x\n}\n";
+        assert_eq!(groups(code), vec![(0, 7), (7, 8), (8, 9)]);
+
+        let code = "TR Shape {\n    f area(&self) -> f64;\n    f scale(\n        &self,\n        k: f64,\n    ) -> Self;\n}\n";
+        assert_eq!(groups(code), vec![(0, 1), (1, 2), (2, 6), (6, 7)]);
+    }
+
+    #[test]
+    fn test_multi_line_statements() {
+        let code = "    l ok = a\n        < 3;\n    l s = add(\n        l_val(),\n        a\n            + 1,\n    );\n    l w = xs\n        .iter()\n        .count();\n    < w\n}\n";
+        assert_eq!(
+            groups(code),
+            vec![(0, 2), (2, 7), (7, 10), (10, 11), (11, 12)]
+        );
+
+        // 闭包体与 match 块逐行转换
+        let code = "    l v = xs.iter().map(|x| {\n        l y = x;\n        y\n    });\n    M v {\n        _ => {}\n    }\n";
+        assert_eq!(
+            groups(code),
+            vec![(0, 1), (1, 2), (2, 3), (3, 4), (4, 5), (5, 6), (6, 7)]
+        );
+
+        // 跨行字符串与链式调用
+        let code = "    l s = \"a\nb\";\n    builder\n        .name(\"x\")\n        .build()!;\n";
+        assert_eq!(groups(code), vec![(0, 2), (2, 5)]);
+    }
+
+    #[test]
+    fn test_split_trailing_comment() {
+        assert_eq!(
+            split_trailing_comment("    l x = g()!; // V<i32> < wh", LexState::Code),
+            ("    l x = g()!;", Some("// V<i32> < wh"))
+        );
+        assert_eq!(
+            split_trailing_comment("    l s = \"a // b\"; /* c */", LexState::Code),
+            ("    l s = \"a // b\";", Some("/* c */"))
+        );
+        for src in ["    // own line", "    l x = 1;", "    l x = 1; /* open"] {
+            assert_eq!(split_trailing_comment(src, LexState::Code), (src, None));
+        }
+    }
+
+    #[test]
+    fn test_split_leading_comment() {
+        assert_eq!(
+            split_leading_comment("    /* a */ l x = 1; // b", LexState::Code),
+            Some(("/* a */", "    l x = 1; // b".to_string()))
+        );
+        for src in [
+            "    /* own line */",
+            "    /* a */ // b",
+            "    l x = 1;",
+            "    /* a\n */ l x = 1;",
+        ] {
+            assert_eq!(split_leading_comment(src, LexState::Code), None);
+        }
+    }
+
+    #[test]
+    fn test_mask_string_literals() {
+        let src =
+            "    l s = r#\"S a {\n    x: V<i32>, <1\n}\"#;\n    println!(\"{} < {}\", a, '\"');";
+        let (masked, literals) = mask_string_literals(src, LexState::Code);
+        assert_eq!(
+            masked,
+            "    l s = __NU_STR_0__;\n    println!(__NU_STR_1__, a, __NU_STR_2__);"
+        );
+        assert_eq!(literals.len(), 3);
+        assert_eq!(restore_string_literals(&masked, &literals), src);
+    }
+}
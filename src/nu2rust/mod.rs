@@ -1,16 +1,42 @@
 // Nu to Rust Converter
 // 将Nu代码转换回标准Rust代码
 
+use crate::conversion::{ConversionOutput, Diagnostic, Severity, Transpile};
+use crate::profile::CompressionProfile;
+use crate::sourcemap::MappingTable;
 use anyhow::Result;
+use std::cell::{Cell, RefCell};
 
 // 导出 sourcemap 模块
 pub mod sourcemap;
-pub use sourcemap::LazySourceMap;
+use sourcemap::Rewrite;
+pub use sourcemap::{LazySourceMap, NuSpan, Segment};
+
+mod abbrev;
+mod lexer;
+mod logical;
+mod strict;
+use abbrev::LineScope;
+use lexer::LexState;
+pub use strict::{Nu2RustOptions, StrictError, Strictness, UnrecognizedConstruct};
 
 pub struct Nu2RustConverter {
     // 转换上下文 - 预留用于未来扩展
     #[allow(dead_code)]
     context: ConversionContext,
+    // 当前行的类型缩写展开上下文（遮蔽的缩写名、行首词法状态）
+    line_scope: RefCell<LineScope>,
+    options: Nu2RustOptions,
+    // 本次转换使用的压缩配置（文件首行记录优先于选项）
+    profile: RefCell<CompressionProfile>,
+    // 当前逻辑语句中被占位符替换的字符串字面量与原始标识符
+    literals: RefCell<Vec<String>>,
+    // 当前逻辑语句中已做的 token 改写，用于记录列级源码映射
+    rewrites: RefCell<Vec<Rewrite>>,
+    // 当前转换的 Nu 行号（1-based）
+    nu_line: Cell<usize>,
+    // 上一次转换中无法识别的结构
+    unrecognized: RefCell<Vec<UnrecognizedConstruct>>,
 }
 
 #[derive(Default)]
@@ -24,11 +50,32 @@ struct ConversionContext {
 
 impl Nu2RustConverter {
     pub fn new() -> Self {
+        Self::with_options(Nu2RustOptions::default())
+    }
+
+    /// 按选项创建转换器（严格模式等）
+    pub fn with_options(options: Nu2RustOptions) -> Self {
         Self {
             context: ConversionContext::default(),
+            line_scope: RefCell::new(LineScope::default()),
+            profile: RefCell::new(options.profile.clone()),
+            literals: RefCell::new(Vec::new()),
+            rewrites: RefCell::new(Vec::new()),
+            options,
+            nu_line: Cell::new(0),
+            unrecognized: RefCell::new(Vec::new()),
         }
     }
 
+    pub fn options(&self) -> &Nu2RustOptions {
+        &self.options
+    }
+
+    /// 上一次转换中无法识别的结构（Lenient 模式下也会记录，但不报告）
+    pub fn unrecognized_constructs(&self) -> Vec<UnrecognizedConstruct> {
+        self.unrecognized.borrow().clone()
+    }
+
     /// 转换 Nu 代码为 Rust 代码
     ///
     /// # Arguments
@@ -44,6 +91,35 @@ impl Nu2RustConverter {
     /// * `nu_code` - Nu 源代码
     /// * `sourcemap` - 可选的 SourceMap，用于记录行号映射
     pub fn convert_with_sourcemap(
+        &self,
+        nu_code: &str,
+        sourcemap: Option<&mut LazySourceMap>,
+    ) -> Result<String> {
+        // 非 Lenient 模式用 syn 校验输出，需要行映射把错误定位回 .nu
+        let mut local = None;
+        let mut sourcemap = match sourcemap {
+            None if self.options.strictness != Strictness::Lenient => {
+                Some(local.insert(LazySourceMap::new(String::new(), String::new())))
+            }
+            sourcemap => sourcemap,
+        };
+        let output = self.convert_lines(nu_code, sourcemap.as_deref_mut())?;
+        if let Some(sourcemap) =
+            sourcemap.filter(|_| self.options.strictness != Strictness::Lenient)
+        {
+            self.check_syntax(nu_code, &output, sourcemap);
+        }
+        if self.options.strictness == Strictness::Strict {
+            let constructs = self.unrecognized.borrow().clone();
+            if !constructs.is_empty() {
+                return Err(StrictError { constructs }.into());
+            }
+        }
+        Ok(output)
+    }
+
+    /// 逐个逻辑语句转换；无法识别的结构记录在 self.unrecognized 中
+    fn convert_lines(
         &self,
         nu_code: &str,
         mut sourcemap: Option<&mut LazySourceMap>,
@@ -51,16 +127,40 @@ impl Nu2RustConverter {
         let mut output = String::new();
         let lines: Vec<&str> = nu_code.lines().collect();
         let mut context = ConversionContext::default();
-
-        let mut i = 0;
+        // 首行记录的压缩配置优先；该行只对 Nu 有意义，不输出到 Rust
+        let header = match lines.first() {
+            Some(first) => CompressionProfile::from_header(first)?,
+            None => None,
+        };
+        let profile = header
+            .clone()
+            .unwrap_or_else(|| self.options.profile.clone());
+        // 预扫描：每行的用户类型/泛型遮蔽与跨行字面量状态；
+        // 本配置不使用的缩写与用户类型同样对待，保持原样
+        let mut line_scopes = abbrev::analyze(nu_code);
+        let unused: Vec<String> = crate::profile::TYPE_ABBREVIATIONS
+            .iter()
+            .filter(|(abbr, _)| profile.expansion(abbr).is_none())
+            .map(|(abbr, _)| abbr.to_string())
+            .collect();
+        for scope in &mut line_scopes {
+            scope.types.extend(unused.iter().cloned());
+        }
+        let line_states: Vec<_> = line_scopes.iter().map(|scope| scope.state).collect();
+        *self.profile.borrow_mut() = profile;
+        self.unrecognized.borrow_mut().clear();
+
+        let mut i = usize::from(header.is_some());
         let mut rust_line = 1; // 跟踪当前生成的 Rust 行号（1-based）
 
         while i < lines.len() {
+            *self.line_scope.borrow_mut() = line_scopes.get(i).cloned().unwrap_or_default();
             // v1.8: 保留原始行（包含前导空格）用于输出
             // trimmed 仅用于模式检测
             let line = lines[i];
             let trimmed = line.trim();
             let nu_line = i + 1; // Nu 源代码行号（1-based）
+            self.nu_line.set(nu_line);
 
             // 保留空行和注释（不跳过）
             if trimmed.is_empty() {
@@ -76,10 +176,16 @@ impl Nu2RustConverter {
             // 解引用: "*identifier" (星号后跟标识符字符)
             let is_comment_star =
                 trimmed.starts_with("* ") || trimmed == "*" || trimmed.starts_with("*/");
-            if trimmed.starts_with("//") || trimmed.starts_with("/*") || is_comment_star {
+            let state = line_states.get(i).copied().unwrap_or_default();
+            let is_comment_line = trimmed.starts_with("//")
+                || (trimmed.starts_with("/*")
+                    && logical::split_leading_comment(line, state).is_none())
+                || is_comment_star;
+            if is_comment_line {
                 // 记录映射：注释行也映射
                 if let Some(ref mut sm) = sourcemap {
                     sm.add_mapping(rust_line, nu_line);
+                    sm.add_statement_segments(rust_line, line, nu_line, line, state, &[]);
                 }
                 output.push_str(lines[i]);
                 output.push('\n');
@@ -88,8 +194,65 @@ impl Nu2RustConverter {
                 continue;
             }
 
+            // macro_rules! 定义（匹配器与展开体）逐字节保留，不做 Nu 转换
+            if line_scopes
+                .get(i)
+                .is_some_and(|scope| scope.macro_rules_start)
+            {
+                let mut end = i + 1;
+                while line_scopes
+                    .get(end)
+                    .is_some_and(|scope| scope.in_macro_rules)
+                {
+                    end += 1;
+                }
+                if let Some(ref mut sm) = sourcemap {
+                    let text = lines[i..end].join("\n");
+                    for k in 0..end - i {
+                        sm.add_mapping(rust_line + k, nu_line + k);
+                    }
+                    let state = line_states.get(i).copied().unwrap_or_default();
+                    sm.add_statement_segments(rust_line, &text, nu_line, &text, state, &[]);
+                }
+                for text in &lines[i..end] {
+                    output.push_str(text);
+                    output.push('\n');
+                }
+                rust_line += end - i;
+                i = end;
+                continue;
+            }
+
+            // 合并跨多行的逻辑语句（多行签名、wh 子句、多行参数、链式调用）
+            let end = logical::statement_end(&lines, &line_states, i);
+            let group = &lines[i..end];
+            // 行尾注释不参与转换；字符串字面量用占位符保护，输出前还原
+            let source = group.join("\n");
+            let (leading, source) = match logical::split_leading_comment(&source, state) {
+                Some((comment, code)) => (Some(comment), code),
+                None => (None, source),
+            };
+            let (source, comment) = logical::split_trailing_comment(&source, state);
+            let with_comment = |code: String| {
+                let code = match leading {
+                    Some(leading) => {
+                        let indent = code.len() - code.trim_start().len();
+                        format!("{}{} {}", &code[..indent], leading, &code[indent..])
+                    }
+                    None => code,
+                };
+                match comment {
+                    Some(comment) => format!("{} {}", code, comment),
+                    None => code,
+                }
+            };
+            let (joined, literals) = logical::mask_string_literals(source, state);
+            self.literals.replace(literals.clone());
+            self.rewrites.borrow_mut().clear();
+            let (line, trimmed) = (joined.as_str(), joined.trim());
+
             // 处理属性标记
-            if trimmed.starts_with("#D") {
+            if trimmed.starts_with("#D") && self.profile.borrow().derive {
                 // 转换 #D(Debug) -> #[derive(Debug)]
                 let attr_content = trimmed.trim_start_matches("#D");
                 // 修复派生属性中的空格: #D (Debug) -> #[derive(Debug)]
@@ -97,13 +260,37 @@ impl Nu2RustConverter {
                     .replace(" (", "(")
                     .replace(" )", ")")
                     .replace(" ,", ",");
+                let derive = format!("#[derive{}]", fixed_content);
+                self.record("#D", "#[derive");
+                let derive = with_comment(logical::restore_string_literals(&derive, &literals));
                 // 记录映射
                 if let Some(ref mut sm) = sourcemap {
-                    sm.add_mapping(rust_line, nu_line);
+                    self.map_group(sm, rust_line, &derive, nu_line, group, state);
+                }
+                output.push_str(&derive);
+                output.push('\n');
+                rust_line += derive.lines().count().max(1);
+                i = end;
+                continue;
+            }
+
+            // #I -> #[inline]，#I(always) -> #[inline(always)]
+            if (trimmed == "#I" || trimmed.starts_with("#I(") || trimmed.starts_with("#I ("))
+                && self.profile.borrow().inline
+            {
+                let args = trimmed.trim_start_matches("#I").trim_start();
+                let indent = &line[..line.len() - line.trim_start().len()];
+                let args = args.replace(" (", "(").replace(" )", ")");
+                let inline = format!("{}#[inline{}]", indent, args);
+                self.record("#I", "#[inline");
+                let inline = with_comment(logical::restore_string_literals(&inline, &literals));
+                if let Some(ref mut sm) = sourcemap {
+                    self.map_group(sm, rust_line, &inline, nu_line, group, state);
                 }
-                output.push_str(&format!("#[derive{}]\n", fixed_content));
+                output.push_str(&inline);
+                output.push('\n');
                 rust_line += 1;
-                i += 1;
+                i = end;
                 continue;
             }
 
@@ -127,22 +314,24 @@ impl Nu2RustConverter {
                     fixed_attr = fixed_attr.replace("deny(missing_docs", "warn(missing_docs");
                 }
 
+                let fixed_attr =
+                    with_comment(logical::restore_string_literals(&fixed_attr, &literals));
                 // 记录映射
                 if let Some(ref mut sm) = sourcemap {
-                    sm.add_mapping(rust_line, nu_line);
+                    self.map_group(sm, rust_line, &fixed_attr, nu_line, group, state);
                 }
                 output.push_str(&fixed_attr);
                 output.push('\n');
-                rust_line += 1;
-                i += 1;
+                rust_line += fixed_attr.lines().count().max(1);
+                i = end;
                 continue;
             }
 
             // 智能恢复丢失的cfg属性
             // 检测panic语句：当行包含panic!("key-value support时，在前面添加#[cfg(not(feature = "kv"))]
             // 注意：Nu转换可能在!后添加空格，所以检查 panic!( 和 panic ! (
-            if (line.contains("panic!(") || line.contains("panic !"))
-                && line.contains("key-value support")
+            if (source.contains("panic!(") || source.contains("panic !"))
+                && source.contains("key-value support")
             {
                 output.push_str("#[cfg(not(feature = \"kv\"))]\n");
                 rust_line += 1;
@@ -174,12 +363,15 @@ impl Nu2RustConverter {
                         && !trimmed.starts_with("pub ")
                         && !trimmed.starts_with("S ")   // 排除 struct 定义行
                         && !trimmed.starts_with("s ")   // 排除 struct 定义行
+                        && !trimmed.starts_with("UN ")  // 排除 union 定义行
+                        && !trimmed.starts_with("un ")
                         && !is_visibility_struct        // v1.8.4: 排除受限可见性的 struct 定义行
                         && trimmed.contains(':')
                         && !trimmed.starts_with("fn ")
                         && !trimmed.starts_with('}')
                     {
                         // 在converted前添加pub
+                        self.record("", "pub");
                         converted = format!("pub {}", converted);
                     }
                 }
@@ -256,6 +448,10 @@ impl Nu2RustConverter {
                             || brace_line.starts_with("E ")  // Nu enum
                             || brace_line.starts_with("pub enum ")
                             || brace_line.starts_with("enum ")
+                            || brace_line.starts_with("UN ")  // Nu union
+                            || brace_line.starts_with("un ")
+                            || brace_line.starts_with("EXT ")  // Nu extern 块
+                            || brace_line.starts_with("unsafe EXT ")
                             // 也检查前一行是否是 trait/impl/mod/struct/enum 开始（当 { 单独一行时）
                             || (brace_line == "{" && line_idx > 0 && {
                                 let prev_line = lines[line_idx - 1].trim();
@@ -277,6 +473,8 @@ impl Nu2RustConverter {
                                     || prev_line.starts_with("E ")
                                     || prev_line.starts_with("pub enum ")
                                     || prev_line.starts_with("enum ")
+                                    || prev_line.starts_with("UN ")
+                                    || prev_line.starts_with("un ")
                                     || prev_line.contains(" impl ")
                                     || prev_line.ends_with(" {")
                             })
@@ -337,7 +535,7 @@ impl Nu2RustConverter {
                 // v1.8.12: 检查下一行是否是单独的 } (函数/块结束)
                 // 如果是，则当前行是函数/块的最后一个表达式，不需要分号
                 let next_line_is_closing_brace = {
-                    let next_idx = i + 1;
+                    let next_idx = end;
                     if next_idx < lines.len() {
                         let next_line = lines[next_idx].trim();
                         next_line == "}" || next_line == "};" || next_line.starts_with("}")
@@ -355,7 +553,7 @@ impl Nu2RustConverter {
                 // 如果当前行以 } 结尾，且下一行是 #[cfg]，则需要添加分号
                 // 因为 #[cfg] 属性后面的语句必须是完整的语句
                 let next_line_is_cfg = {
-                    let next_idx = i + 1;
+                    let next_idx = end;
                     if next_idx < lines.len() {
                         let next_line = lines[next_idx].trim();
                         next_line.starts_with("#[cfg")
@@ -380,39 +578,86 @@ impl Nu2RustConverter {
                 // 抑制未使用变量警告
                 let _ = next_line_is_closing_brace;
 
-                output.push_str(&leading_whitespace);
+                let mut emitted = leading_whitespace;
                 if needs_semicolon {
                     // 在 } 后添加分号
                     let without_brace = &trimmed_converted[..trimmed_converted.len() - 1];
-                    output.push_str(without_brace);
-                    output.push_str("};");
+                    emitted.push_str(without_brace);
+                    emitted.push_str("};");
                 } else {
-                    output.push_str(trimmed_converted);
+                    emitted.push_str(trimmed_converted);
+                }
+                let emitted = with_comment(logical::restore_string_literals(&emitted, &literals));
+                // 列级映射：逐 token 对齐原始 Nu 行与生成的 Rust 行
+                let emitted_lines = emitted.matches('\n').count() + 1;
+                if let Some(ref mut sm) = sourcemap {
+                    self.map_group(sm, rust_line, &emitted, nu_line, group, state);
                 }
+                output.push_str(&emitted);
                 output.push('\n');
-                rust_line += 1;
+                rust_line += emitted_lines;
             }
 
-            i += 1;
+            i = end;
         }
 
         // Post-processing: Remove #![doc = ...] that appears after /// doc comments
         // This fixes the issue where rust2nu creates duplicate doc comments
-        let output = self.fix_inner_doc_attribute_placement(output);
+        let (output, removed) = self.fix_inner_doc_attribute_placement(output);
+        if let Some(sm) = sourcemap {
+            for rust_line in removed.into_iter().rev() {
+                sm.remove_rust_line(rust_line);
+            }
+        }
 
         Ok(output)
     }
 
+    /// 记录一次 token 改写（Nu 片段 → Rust 片段），供列级源码映射使用；nu 为空表示插入
+    fn record(&self, nu: &str, rust: &str) {
+        self.rewrites.borrow_mut().push(Rewrite::new(nu, rust));
+    }
+
+    /// 按 rust 片段在转换前后的出现次数之差记录改写（用于整体替换的转换步骤），返回记录的次数
+    fn record_added(&self, before: &str, after: &str, nu: &str, rust: &str) -> usize {
+        let added = count_added(before, after, rust);
+        for _ in 0..added {
+            self.record(nu, rust);
+        }
+        added
+    }
+
+    /// 记录逻辑语句的映射：生成的第 k 行对应语句的第 k 个 Nu 物理行，列级片段按记录的改写对应
+    fn map_group(
+        &self,
+        sm: &mut LazySourceMap,
+        rust_line: usize,
+        emitted: &str,
+        nu_line: usize,
+        group: &[&str],
+        state: LexState,
+    ) {
+        for offset in 0..emitted.lines().count() {
+            let k = offset.min(group.len() - 1);
+            sm.add_mapping(rust_line + offset, nu_line + k);
+        }
+        let nu_text = group.join("\n");
+        let rewrites = self.rewrites.borrow();
+        sm.add_statement_segments(rust_line, emitted, nu_line, &nu_text, state, &rewrites);
+    }
+
     /// Fix inner doc attributes (#![doc = ...]) that appear after /// doc comments
     /// This is invalid in Rust - inner attributes must appear at the start of the file
     /// before any other items. When rust2nu creates both //! and #![doc = ...] for the
     /// same content, we need to remove the duplicate #![doc = ...] that appears later.
-    fn fix_inner_doc_attribute_placement(&self, output: String) -> String {
+    /// Returns the fixed code and the removed (1-based) line numbers so source maps can be shifted.
+    fn fix_inner_doc_attribute_placement(&self, output: String) -> (String, Vec<usize>) {
         let lines: Vec<&str> = output.lines().collect();
         let mut result_lines: Vec<&str> = Vec::new();
         let mut seen_non_inner_attr = false;
+        let mut removed = Vec::new();
 
-        for line in &lines {
+        for (idx, line) in lines.iter().enumerate() {
             let trimmed = line.trim();
 
             // Track if we've seen any non-inner-attribute content
@@ -424,13 +669,14 @@ impl Nu2RustConverter {
             // If we've seen non-inner-attribute content and this is a #![doc = ...] line,
             // skip it (it's a duplicate that would cause a compile error)
             if seen_non_inner_attr && trimmed.starts_with("#![doc") {
+                removed.push(idx + 1);
                 continue;
             }
 
             result_lines.push(line);
         }
 
-        result_lines.join("\n") + "\n"
+        (result_lines.join("\n") + "\n", removed)
     }
 
     fn convert_line(
@@ -444,7 +690,8 @@ impl Nu2RustConverter {
 
         // Loop: L (必须在函数定义之前检查，避免 "L {" 被误判为函数)
         // v1.7.5: 添加 L( 模式支持用于带模式匹配的 for 循环，如 L(i,(word, count)) in ...
-        if trimmed.starts_with("L ") || trimmed == "L {" || trimmed.starts_with("L(") {
+        let loops = self.profile.borrow().loops;
+        if loops && (trimmed.starts_with("L ") || trimmed == "L {" || trimmed.starts_with("L(")) {
             return Ok(Some(self.convert_loop(trimmed)?));
         }
 
@@ -469,6 +716,11 @@ impl Nu2RustConverter {
             return Ok(Some(self.convert_match(trimmed)?));
         }
 
+        // extern: EXT crate / EXT "C" { / EXT "C" F (指定 ABI 的函数)
+        if let Some(converted) = self.convert_extern(trimmed, lines, index, context)? {
+            return Ok(Some(converted));
+        }
+
         // Unsafe函数定义:
         // v1.8: "unsafe F"/"unsafe f" (新格式，unsafe 不缩写)
         // 兼容: "U F"/"U f" (旧格式)
@@ -520,7 +772,7 @@ impl Nu2RustConverter {
             }
 
             // 如果没有括号，也不是函数定义，作为表达式处理
-            return Ok(Some(self.convert_expression(trimmed)?));
+            return Ok(Some(self.convert_unclassified(line)?));
         }
 
         // 异步函数: ~F/~f
@@ -541,6 +793,14 @@ impl Nu2RustConverter {
             return Ok(Some(self.convert_enum_with_visibility(line)?));
         }
 
+        // 联合体: UN/un (UN=pub union, un=union)，以及 pub(crate) UN
+        if let Some(converted) = self.convert_union(trimmed)? {
+            if trimmed.ends_with('{') {
+                context.in_struct_block = true;
+            }
+            return Ok(Some(converted));
+        }
+
         // 结构体: S/s (S=pub struct, s=struct)
         // v1.8.2: 确保不会将 "s = expr" 误识别为 struct 定义
         // struct 定义格式必须是 "S Name" 或 "s Name"，其中 Name 是标识符
@@ -620,11 +880,13 @@ impl Nu2RustConverter {
         if trimmed.starts_with("pub(crate) DM ") {
             let content = &trimmed[14..]; // 跳过 "pub(crate) DM "
             let converted = self.convert_types_in_string(content);
+            self.record("DM", "mod");
             return Ok(Some(format!("pub(crate) mod {}", converted)));
         }
         if trimmed.starts_with("pub(super) DM ") {
             let content = &trimmed[14..]; // 跳过 "pub(super) DM "
             let converted = self.convert_types_in_string(content);
+            self.record("DM", "mod");
             return Ok(Some(format!("pub(super) mod {}", converted)));
         }
         // v1.8.4: 支持 pub(in path) DM 格式，如 "pub(in crate :: runtime) DM time_alt;"
@@ -639,6 +901,7 @@ impl Nu2RustConverter {
                     .replace(" ::", "::")
                     .replace(":: ", "::");
                 let converted = self.convert_types_in_string(content);
+                self.record("DM", "mod");
                 return Ok(Some(format!("{} mod {}", cleaned_vis, converted)));
             }
         }
@@ -691,7 +954,9 @@ impl Nu2RustConverter {
         // 但要排除以下模式：
         // 1. <Type>::method() 模式（类型转换语法）
         // 2. <T> Identifier 模式（泛型参数，如宏调用中的 <T> Serialize for Box<T>）
-        let is_return = if trimmed.starts_with("< ") || trimmed == "<" {
+        let is_return = if !self.profile.borrow().returns {
+            false
+        } else if trimmed.starts_with("< ") || trimmed == "<" {
             true
         } else if trimmed.starts_with('<')
             && trimmed.len() > 1
@@ -796,7 +1061,63 @@ impl Nu2RustConverter {
         }
 
         // 其他情况：转换类型和表达式
-        Ok(Some(self.convert_expression(trimmed)?))
+        Ok(Some(self.convert_unclassified(line)?))
+    }
+
+    /// 用 syn 校验生成的 Rust；首个语法错误所在的 Nu 行尚未记录时作为无法识别的结构
+    fn check_syntax(&self, nu_code: &str, rust_code: &str, sourcemap: &LazySourceMap) {
+        let Some((rust_line, rust_col)) = strict::syntax_error(rust_code) else {
+            return;
+        };
+        let Some((nu_line, nu_col)) = sourcemap.find_nu_position(rust_line, rust_col) else {
+            return;
+        };
+        let mut unrecognized = self.unrecognized.borrow_mut();
+        if unrecognized.iter().any(|c| c.span.line == nu_line) {
+            return;
+        }
+        let source_line = nu_code
+            .lines()
+            .nth(nu_line.saturating_sub(1))
+            .unwrap_or_default();
+        let indent = source_line.len() - source_line.trim_start().len();
+        let line_col = source_line[..indent].chars().count() + 1;
+        let word = |col: usize| -> String {
+            source_line
+                .chars()
+                .skip(col - 1)
+                .take_while(|c| !c.is_whitespace())
+                .collect()
+        };
+        let (col, construct) = match nu_col.map(|col| (col, word(col))) {
+            Some((col, construct)) if !construct.is_empty() => (col, construct),
+            _ => (line_col, word(line_col)),
+        };
+        unrecognized.push(UnrecognizedConstruct {
+            span: crate::ast::Span::new(nu_line, col, construct.len().max(1)),
+            construct,
+            source_line: source_line.to_string(),
+        });
+    }
+
+    /// 无法归类的行按表达式转换，并记录其中无法识别的 Nu 结构
+    fn convert_unclassified(&self, line: &str) -> Result<String> {
+        let scope = self.line_scope.borrow();
+        let construct = (!scope.in_macro_rules)
+            .then(|| strict::unrecognized(line, scope.state))
+            .flatten();
+        drop(scope);
+        if let Some(construct) = construct {
+            let source_line = line.lines().next().unwrap_or_default();
+            let indent = source_line.len() - source_line.trim_start().len();
+            let col = source_line[..indent].chars().count() + 1;
+            self.unrecognized.borrow_mut().push(UnrecognizedConstruct {
+                span: crate::ast::Span::new(self.nu_line.get(), col, construct.len()),
+                construct,
+                source_line: source_line.to_string(),
+            });
+        }
+        self.convert_expression(line.trim())
     }
 
     fn convert_function(&self, line: &str, context: &ConversionContext) -> Result<String> {
@@ -821,11 +1142,15 @@ impl Nu2RustConverter {
         // 处理 !self -> mut self (按值接收的可变self)
         // v1.8.3: 处理更多模式
         // v1.8.4: 添加 !self: 模式 (如 !self: Pin<&mut Self>)
+        if converted.contains("(!self") {
+            self.record("!", "mut");
+        }
         converted = converted.replace("(!self)", "(mut self)");
         converted = converted.replace("(!self,", "(mut self,");
         converted = converted.replace("(!self:", "(mut self:");
         converted = converted.replace("(!self", "(mut self");
 
+        self.record(&line[..1], &format!("{}fn", visibility));
         Ok(format!("{}fn {}", visibility, converted))
     }
 
@@ -851,11 +1176,15 @@ impl Nu2RustConverter {
         // 处理 !self -> mut self (按值接收的可变self)
         // v1.8.3: 处理更多模式
         // v1.8.4: 添加 !self: 模式 (如 !self: Pin<&mut Self>)
+        if converted.contains("(!self") {
+            self.record("!", "mut");
+        }
         converted = converted.replace("(!self)", "(mut self)");
         converted = converted.replace("(!self,", "(mut self,");
         converted = converted.replace("(!self:", "(mut self:");
         converted = converted.replace("(!self", "(mut self");
 
+        self.record(&line[..3], &format!("{}unsafe fn", visibility));
         Ok(format!("{}unsafe fn {}", visibility, converted))
     }
 
@@ -879,11 +1208,15 @@ impl Nu2RustConverter {
         let mut converted = self.convert_types_in_string(content);
         // v1.8.3: 处理更多模式
         // v1.8.4: 添加 !self: 模式 (如 !self: Pin<&mut Self>)
+        if converted.contains("(!self") {
+            self.record("!", "mut");
+        }
         converted = converted.replace("(!self)", "(mut self)");
         converted = converted.replace("(!self,", "(mut self,");
         converted = converted.replace("(!self:", "(mut self:");
         converted = converted.replace("(!self", "(mut self");
 
+        self.record(&line[..7], &format!("{}const fn", visibility));
         Ok(format!("{}const fn {}", visibility, converted))
     }
 
@@ -910,11 +1243,15 @@ impl Nu2RustConverter {
 
         let mut converted = self.convert_types_in_string(content);
         // v1.8.3: 处理更多模式
+        if converted.contains("(!self") {
+            self.record("!", "mut");
+        }
         converted = converted.replace("(!self)", "(mut self)");
         converted = converted.replace("(!self,", "(mut self,");
         converted = converted.replace("(!self:", "(mut self:");
         converted = converted.replace("(!self", "(mut self");
 
+        self.record(&line[..8], &format!("{}unsafe fn", visibility));
         Ok(format!("{}unsafe fn {}", visibility, converted))
     }
 
@@ -926,11 +1263,15 @@ impl Nu2RustConverter {
         let mut converted = self.convert_types_in_string(content);
 
         // v1.8.3: 处理 !self -> mut self
+        if converted.contains("(!self") {
+            self.record("!", "mut");
+        }
         converted = converted.replace("(!self)", "(mut self)");
         converted = converted.replace("(!self,", "(mut self,");
         converted = converted.replace("(!self:", "(mut self:");
         converted = converted.replace("(!self", "(mut self");
 
+        self.record(&line[..2], &format!("{}async fn", visibility));
         Ok(format!("{}async fn {}", visibility, converted))
     }
 
@@ -972,6 +1313,7 @@ impl Nu2RustConverter {
                                     self.convert_types_in_string(trimmed)
                                 } else if is_pub_marker {
                                     // struct 是 pub，给字段添加 pub
+                                    self.record("", "pub");
                                     format!("pub {}", self.convert_types_in_string(trimmed))
                                 } else {
                                     // struct 是私有的，不添加 pub
@@ -1006,6 +1348,7 @@ impl Nu2RustConverter {
             };
 
         let visibility = if is_pub_marker { "pub " } else { "" };
+        self.record(&line[..1], &format!("{}struct", visibility));
         Ok(format!("{}struct {}", visibility, converted))
     }
 
@@ -1054,21 +1397,114 @@ impl Nu2RustConverter {
         result
     }
 
+    /// UN Name { -> pub union Name {；不是联合体声明时返回 None
+    fn convert_union(&self, line: &str) -> Result<Option<String>> {
+        let (visibility, rest) = if let Some(rest) = line.strip_prefix("UN ") {
+            ("pub ".to_string(), rest)
+        } else if let Some(rest) = line.strip_prefix("un ") {
+            (String::new(), rest)
+        } else if line.starts_with("pub(") {
+            match line.find(") UN ").or_else(|| line.find(") un ")) {
+                Some(pos) => (format!("{} ", &line[..pos + 1]), &line[pos + 5..]),
+                None => return Ok(None),
+            }
+        } else {
+            return Ok(None);
+        };
+        // "un = 1" 之类的赋值不是声明
+        if !rest.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+            return Ok(None);
+        }
+        let converted = self.convert_types_in_string(rest);
+        self.record(
+            &line[..line.len() - rest.len()],
+            &format!("{}union", visibility),
+        );
+        Ok(Some(format!("{}union {}", visibility, converted)))
+    }
+
+    /// EXT crate foo; / EXT "C" { / EXT "C" F name(..)；不是 extern 条目时返回 None
+    fn convert_extern(
+        &self,
+        line: &str,
+        lines: &[&str],
+        index: &mut usize,
+        context: &mut ConversionContext,
+    ) -> Result<Option<String>> {
+        let Some(pos) = line
+            .match_indices("EXT")
+            .map(|(pos, _)| pos)
+            .find(|&pos| pos == 0 || line[..pos].ends_with(' '))
+        else {
+            return Ok(None);
+        };
+        // EXT 之前只能是 pub/unsafe/const 等修饰
+        let prefix = &line[..pos];
+        let is_modifier =
+            |w: &str| matches!(w, "pub" | "unsafe" | "const") || w.starts_with("pub(");
+        if !prefix.split_whitespace().all(is_modifier) {
+            return Ok(None);
+        }
+        let Some(rest) = line[pos + 3..].strip_prefix(' ') else {
+            return Ok(None);
+        };
+
+        self.record("EXT", "extern");
+        if rest.starts_with("crate ") {
+            return Ok(Some(format!("{}extern {}", prefix, rest)));
+        }
+        // ABI 字符串此时已被替换为占位符
+        let (abi, rest) = match rest.split_once(' ') {
+            Some((abi, rest)) if abi.starts_with(logical::LITERAL_PLACEHOLDER) => {
+                (format!("{} ", abi), rest)
+            }
+            _ => (String::new(), rest),
+        };
+        if rest.starts_with('{') {
+            return Ok(Some(format!("{}extern {}{}", prefix, abi, rest)));
+        }
+        let is_function = ["F ", "f ", "~F ", "~f "]
+            .iter()
+            .any(|keyword| rest.starts_with(keyword));
+        if !is_function {
+            return Ok(None);
+        }
+        let function = format!("{}{}", prefix, rest);
+        let Some(converted) = self.convert_line(&function, lines, index, context)? else {
+            return Ok(None);
+        };
+        // pub unsafe fn name -> pub unsafe extern "C" fn name
+        match converted
+            .match_indices("fn ")
+            .map(|(pos, _)| pos)
+            .find(|&pos| pos == 0 || converted[..pos].ends_with(' '))
+        {
+            Some(fn_pos) => Ok(Some(format!(
+                "{}extern {}{}",
+                &converted[..fn_pos],
+                abi,
+                &converted[fn_pos..]
+            ))),
+            None => Ok(Some(converted)),
+        }
+    }
+
     fn convert_enum(&self, line: &str, _lines: &[&str], _index: &mut usize) -> Result<String> {
         // Nu v1.5.1: 只有 E（移除了 e）
         // 可见性由标识符首字母决定（Go风格）
         let content = &line[2..]; // 跳过 "E "
         let converted = self.convert_types_in_string(content);
 
-        // 检查枚举名称的首字母是否大写来决定可见性
-        let is_pub = content
-            .trim()
+        // 检查枚举名称的首字母是否大写来决定可见性（原始标识符按原名判断）
+        let name = logical::restore_string_literals(content.trim(), &self.literals.borrow());
+        let is_pub = name
             .chars()
             .next()
             .map(|c| c.is_uppercase())
             .unwrap_or(false);
 
         let visibility = if is_pub { "pub " } else { "" };
+        self.record("E", &format!("{}enum", visibility));
         Ok(format!("{}enum {}", visibility, converted))
     }
 
@@ -1090,6 +1526,7 @@ impl Nu2RustConverter {
         let rest = &line[s_pos + 2..]; // "Name {...}"
         let converted = self.convert_types_in_string(rest);
 
+        self.record(&line[s_pos..s_pos + 1], "struct");
         let result = format!("{} struct {}", visibility, converted);
 
         Ok(result)
@@ -1110,6 +1547,7 @@ impl Nu2RustConverter {
         let rest = &line[e_pos + 2..]; // "Name {...}"
         let converted = self.convert_types_in_string(rest);
 
+        self.record(&line[e_pos..e_pos + 1], "enum");
         Ok(format!("{} enum {}", visibility, converted))
     }
 
@@ -1120,6 +1558,7 @@ impl Nu2RustConverter {
         let visibility = if is_pub { "pub " } else { "" };
         let converted = self.convert_types_in_string(content);
 
+        self.record(&line[..2], &format!("{}trait", visibility));
         Ok(format!("{}trait {}", visibility, converted))
     }
 
@@ -1136,6 +1575,7 @@ impl Nu2RustConverter {
         let visibility = if is_pub { "pub " } else { "" };
         let converted = self.convert_types_in_string(content);
 
+        self.record(&line[7..9], &format!("{}unsafe trait", visibility));
         Ok(format!("{}unsafe trait {}", visibility, converted))
     }
 
@@ -1145,6 +1585,7 @@ impl Nu2RustConverter {
         // 但我们需要在这里检查原始行是否有前导的"U "
         let content = &line[2..]; // 跳过 "I "
         let converted = self.convert_types_in_string(content);
+        self.record("I", "impl");
         Ok(format!("impl {}", converted))
     }
 
@@ -1152,6 +1593,7 @@ impl Nu2RustConverter {
         // Nu v1.6.3: D=mod (私有)
         let content = &line[2..]; // 跳过 "D "
         let converted = self.convert_types_in_string(content);
+        self.record("D", "mod");
         Ok(format!("mod {}", converted))
     }
 
@@ -1159,6 +1601,7 @@ impl Nu2RustConverter {
         // Nu v1.6.3: DM=pub mod (公有)
         let content = &line[3..]; // 跳过 "DM "
         let converted = self.convert_types_in_string(content);
+        self.record("DM", "pub mod");
         Ok(format!("pub mod {}", converted))
     }
 
@@ -1167,6 +1610,7 @@ impl Nu2RustConverter {
         // 先转换关键字，再转换类型
         let converted = self.convert_inline_keywords(content)?;
         let converted = self.convert_types_in_string(&converted);
+        self.record("l", "let");
         Ok(format!("let {}", converted))
     }
 
@@ -1176,6 +1620,7 @@ impl Nu2RustConverter {
         // 检查是否是元组解构赋值模式: v (a, b) = ... 或 v (a , b) = ...
         // 处理空格：移除content开头的空格，检查是否以 ( 开头
         let trimmed = content.trim_start();
+        self.record("v", "let mut");
         if trimmed.starts_with('(') {
             // 元组解构：v (a, b) = ... -> let mut (a, b) = ...
             // 注意：需要保留原始content，因为可能有前导空格需要处理
@@ -1191,6 +1636,7 @@ impl Nu2RustConverter {
     }
 
     fn convert_return(&self, line: &str) -> Result<String> {
+        self.record("<", "return");
         if line == "<" {
             Ok("return;".to_string())
         } else {
@@ -1221,6 +1667,7 @@ impl Nu2RustConverter {
     }
 
     fn convert_break(&self, line: &str) -> Result<String> {
+        self.record("br", "break");
         if line == "br" || line == "br;" || line.starts_with("br;") {
             Ok("break;".to_string())
         } else {
@@ -1231,6 +1678,7 @@ impl Nu2RustConverter {
     }
 
     fn convert_continue(&self, line: &str) -> Result<String> {
+        self.record("ct", "continue");
         if line == "ct" || line == "ct;" || line.starts_with("ct;") {
             Ok("continue;".to_string())
         } else {
@@ -1242,6 +1690,7 @@ impl Nu2RustConverter {
 
     fn convert_loop(&self, line: &str) -> Result<String> {
         if line == "L {" {
+            self.record("L", "loop");
             Ok("loop {".to_string())
         } else if let Some(content) = line.strip_prefix("L ") {
             // 先检查是否以 { 开头 - 这是 loop
@@ -1250,6 +1699,7 @@ impl Nu2RustConverter {
                 let body = &content[1..]; // 跳过 {
                 let converted_body = self.convert_inline_keywords(body)?;
                 let converted_body = self.convert_types_in_string(&converted_body);
+                self.record("L", "loop");
                 Ok(format!("loop {{ {}", converted_body))
             } else if content.contains(" in ")
                 || content.contains(" in(")
@@ -1297,6 +1747,7 @@ impl Nu2RustConverter {
                         if let Some(colon_pos) = content.find(": ") {
                             // 只替换第一个 ": "
                             let (before, after) = content.split_at(colon_pos);
+                            self.record(":", "in");
                             format!("{} in {}", before, &after[2..]) // 跳过 ": "
                         } else {
                             content.to_string()
@@ -1314,16 +1765,19 @@ impl Nu2RustConverter {
                     };
                     let converted = self.convert_inline_keywords(&converted_content)?;
                     let converted = self.convert_types_in_string(&converted);
+                    self.record("L", "for");
                     Ok(format!("for {}", converted))
                 } else {
                     // loop { ... 中包含 for ... }
                     let body = content;
                     let converted_body = self.convert_inline_keywords(body)?;
                     let converted_body = self.convert_types_in_string(&converted_body);
+                    self.record("L", "loop");
                     Ok(format!("loop {{ {}", converted_body))
                 }
             } else {
                 // 无限循环
+                self.record("L", "loop");
                 Ok("loop {".to_string())
             }
         } else if let Some(content) = line.strip_prefix("L(") {
@@ -1331,6 +1785,7 @@ impl Nu2RustConverter {
             // 例如: L(i,(word, count)) in freq.iter().take(top_n).enumerate()
             let converted = self.convert_inline_keywords(content)?;
             let converted = self.convert_types_in_string(&converted);
+            self.record("L", "for");
             Ok(format!("for ({}", converted))
         } else {
             Ok(line.to_string())
@@ -1365,6 +1820,7 @@ impl Nu2RustConverter {
         // 递归处理if语句内容
         let converted = self.convert_inline_keywords(&content)?;
         let converted = self.convert_types_in_string(&converted);
+        self.record("?", "if");
         Ok(format!("if {}", converted))
     }
 
@@ -1389,6 +1845,7 @@ impl Nu2RustConverter {
         // 递归处理if not语句内容
         let converted = self.convert_inline_keywords(content)?;
         let converted = self.convert_types_in_string(&converted);
+        self.record("?", "if");
         Ok(format!("if !{}", converted))
     }
 
@@ -1427,6 +1884,7 @@ impl Nu2RustConverter {
             self.convert_types_in_string(&temp)
         };
 
+        self.record("M", "match");
         Ok(format!("match {}", converted))
     }
 
@@ -1450,6 +1908,7 @@ impl Nu2RustConverter {
         content = content.replace(" ,", ",");
 
         let visibility = if is_pub { "pub " } else { "" };
+        self.record(&line[..1], &format!("{}use", visibility));
         Ok(format!("{}use {}", visibility, content))
     }
 
@@ -1457,6 +1916,10 @@ impl Nu2RustConverter {
         let content = if is_pub { &line[3..] } else { &line[2..] };
         let converted = self.convert_types_in_string(content);
         let visibility = if is_pub { "pub " } else { "" };
+        self.record(
+            &line[..line.len() - content.len()],
+            &format!("{}const", visibility),
+        );
         Ok(format!("{}const {}", visibility, converted))
     }
 
@@ -1478,6 +1941,10 @@ impl Nu2RustConverter {
         let converted = self.convert_types_in_string(content);
         let visibility = if is_pub { "pub " } else { "" };
         let mut_str = if is_mut { "mut " } else { "" };
+        self.record(
+            &line[..line.len() - content.len()],
+            &format!("{}static {}", visibility, mut_str),
+        );
 
         Ok(format!("{}static {}{}", visibility, mut_str, converted))
     }
@@ -1491,6 +1958,7 @@ impl Nu2RustConverter {
         // Nu v1.6.3: U I = unsafe impl (旧格式)
         let content = &line[4..]; // 跳过 "U I "
         let converted = self.convert_types_in_string(content);
+        self.record("U I", "unsafe impl");
         Ok(format!("unsafe impl {}", converted))
     }
 
@@ -1508,6 +1976,7 @@ impl Nu2RustConverter {
             &line[8..] // 跳过 "unsafe I" (紧跟 <)
         };
         let converted = self.convert_types_in_string(content);
+        self.record("I", "impl");
         // 对于 "unsafe I<T>"，converted 以 "<" 开头，不需要额外空格
         // 对于 "unsafe I Trait"，converted 以字母开头，需要空格
         if converted.starts_with('<') || converted.starts_with(' ') {
@@ -1524,6 +1993,7 @@ impl Nu2RustConverter {
         // "Value < 'a > =" -> "Value<'a> ="
         converted = converted.replace(" < ", "<").replace(" >", ">");
         converted = converted.replace("= & ", "= &");
+        self.record("t", "type");
         Ok(format!("type {}", converted))
     }
 
@@ -1532,6 +2002,7 @@ impl Nu2RustConverter {
         let content = &line[2..]; // 跳过 "U "
         let converted = self.convert_inline_keywords(content)?;
         let converted = self.convert_types_in_string(&converted);
+        self.record("U", "unsafe");
         Ok(format!("unsafe {}", converted))
     }
 
@@ -2543,7 +3014,9 @@ impl Nu2RustConverter {
 
             // loop: L { (必须在 for 之前检查)
             // v1.8: 添加边界检查，避免替换 MAX_OL 中的 L
-            if remaining.starts_with("L {") || remaining.starts_with("L ") {
+            if (remaining.starts_with("L {") || remaining.starts_with("L "))
+                && self.profile.borrow().loops
+            {
                 let is_start_boundary =
                     i == 0 || (!chars[i - 1].is_alphanumeric() && chars[i - 1] != '_');
 
@@ -2634,453 +3107,24 @@ impl Nu2RustConverter {
             i += 1;
         }
 
-        Ok(result)
-    }
-
-    /// v1.8.1: 带边界检查的类型替换辅助函数
-    /// 只在单字母类型缩写前后是非字母数字时才替换
-    /// 例如: "R <" -> "Result<" 但 "YEAR <" 保持不变
-    fn replace_type_with_boundary(s: &str, from: &str, to: &str) -> String {
-        let mut result = String::new();
-        let chars: Vec<char> = s.chars().collect();
-        let from_chars: Vec<char> = from.chars().collect();
-        let mut i = 0;
-
-        while i < chars.len() {
-            // 检查是否匹配 from 模式
-            let mut matches = true;
-            if i + from_chars.len() <= chars.len() {
-                for (j, fc) in from_chars.iter().enumerate() {
-                    if chars[i + j] != *fc {
-                        matches = false;
-                        break;
-                    }
-                }
-            } else {
-                matches = false;
-            }
-
-            if matches {
-                // 检查前边界: 前一个字符不能是字母或数字或下划线
-                let has_start_boundary =
-                    i == 0 || (!chars[i - 1].is_alphanumeric() && chars[i - 1] != '_');
-
-                if has_start_boundary {
-                    result.push_str(to);
-                    i += from_chars.len();
-                    continue;
-                }
-            }
-
-            result.push(chars[i]);
-            i += 1;
-        }
-
-        result
-    }
-
-    /// v1.8.16: 智能转换 I< 到 impl<
-    /// 只有当 I< 是独立的（前面不是字母数字或下划线）时才转换
-    /// 避免将 ParserI< 转换为 Parserimpl<
-    fn convert_i_to_impl_smart(s: &str) -> String {
-        let mut result = String::new();
-        let chars: Vec<char> = s.chars().collect();
-        let mut i = 0;
-
-        while i < chars.len() {
-            // 检查 " I<" 模式（空格 + I<）
-            if i + 2 < chars.len() && chars[i] == ' ' && chars[i + 1] == 'I' && chars[i + 2] == '<'
-            {
-                result.push(' ');
-                result.push_str("impl<");
-                i += 3;
-                continue;
-            }
-
-            // 检查 "I<" 模式（行首或非字母数字后）
-            if i + 1 < chars.len() && chars[i] == 'I' && chars[i + 1] == '<' {
-                // 检查前边界: 前一个字符不能是字母或数字或下划线
-                let has_start_boundary =
-                    i == 0 || (!chars[i - 1].is_alphanumeric() && chars[i - 1] != '_');
-
-                if has_start_boundary {
-                    result.push_str("impl<");
-                    i += 2;
-                    continue;
-                }
-            }
-
-            result.push(chars[i]);
-            i += 1;
-        }
-
-        result
-    }
-
-    /// v1.8.5: 智能转换 V:: 到 Vec::
-    /// 只有当 V:: 后面跟着 Vec 特有的方法时才转换
-    /// 如果后面跟着通用方法或大写字母，则保持不变（如 V::Value 是泛型参数的关联类型）
-    fn convert_v_to_vec_smart(s: &str) -> String {
-        // Vec 特有的方法列表
-        let vec_specific_methods = [
-            "new",
-            "with_capacity",
-            "from_raw_parts",
-            "from_raw_parts_in",
-            "new_in",
-            "with_capacity_in",
-            "try_with_capacity",
-            "try_with_capacity_in",
-        ];
-
-        let mut result = String::new();
-        let chars: Vec<char> = s.chars().collect();
-        let mut i = 0;
-
-        while i < chars.len() {
-            // 检查 "V :: " 模式（带空格）
-            if i + 4 < chars.len()
-                && chars[i] == 'V'
-                && chars[i + 1] == ' '
-                && chars[i + 2] == ':'
-                && chars[i + 3] == ':'
-                && chars[i + 4] == ' '
-            {
-                // 检查前边界
-                let has_start_boundary =
-                    i == 0 || (!chars[i - 1].is_alphanumeric() && chars[i - 1] != '_');
-
-                if has_start_boundary {
-                    // 提取后面的方法名
-                    let method_start = i + 5;
-                    let mut method_end = method_start;
-                    while method_end < chars.len()
-                        && (chars[method_end].is_alphanumeric() || chars[method_end] == '_')
-                    {
-                        method_end += 1;
-                    }
-                    let method_name: String = chars[method_start..method_end].iter().collect();
-
-                    if vec_specific_methods.contains(&method_name.as_str()) {
-                        result.push_str("Vec::");
-                        i += 5;
-                        continue;
-                    }
-                }
-            }
-
-            // 检查 "V::" 模式（无空格）
-            if i + 2 < chars.len() && chars[i] == 'V' && chars[i + 1] == ':' && chars[i + 2] == ':'
-            {
-                // 检查前边界
-                let has_start_boundary =
-                    i == 0 || (!chars[i - 1].is_alphanumeric() && chars[i - 1] != '_');
-
-                if has_start_boundary {
-                    // 提取后面的方法名
-                    let method_start = i + 3;
-                    let mut method_end = method_start;
-                    while method_end < chars.len()
-                        && (chars[method_end].is_alphanumeric() || chars[method_end] == '_')
-                    {
-                        method_end += 1;
-                    }
-                    let method_name: String = chars[method_start..method_end].iter().collect();
-
-                    if vec_specific_methods.contains(&method_name.as_str()) {
-                        result.push_str("Vec::");
-                        i += 3;
-                        continue;
-                    }
-                }
-            }
-
-            result.push(chars[i]);
-            i += 1;
-        }
-
-        result
-    }
-
-    /// v1.8.5: 智能转换 A:: 到 Arc::
-    /// 只有当 A:: 后面跟着 Arc 特有的方法时才转换
-    /// 如果后面跟着通用方法（如 default）或大写字母，则保持不变
-    fn convert_a_to_arc_smart(s: &str) -> String {
-        // Arc 特有的方法列表（不包括通用 trait 方法如 default, clone 等）
-        let arc_specific_methods = [
-            "new",
-            "pin",
-            "try_pin",
-            "new_cyclic",
-            "new_uninit",
-            "new_zeroed",
-            "try_new",
-            "try_new_uninit",
-            "try_new_zeroed",
-            "new_uninit_slice",
-            "new_zeroed_slice",
-            "downgrade",
-            "weak_count",
-            "strong_count",
-            "ptr_eq",
-            "make_mut",
-            "get_mut",
-            "try_unwrap",
-            "into_inner",
-            "increment_strong_count",
-            "decrement_strong_count",
-            "as_ptr",
-            "into_raw",
-            "from_raw",
-        ];
-
-        let mut result = String::new();
-        let chars: Vec<char> = s.chars().collect();
-        let mut i = 0;
-
-        while i < chars.len() {
-            // 检查 "A :: " 模式（带空格）
-            if i + 4 < chars.len()
-                && chars[i] == 'A'
-                && chars[i + 1] == ' '
-                && chars[i + 2] == ':'
-                && chars[i + 3] == ':'
-                && chars[i + 4] == ' '
-            {
-                // 检查前边界
-                let has_start_boundary =
-                    i == 0 || (!chars[i - 1].is_alphanumeric() && chars[i - 1] != '_');
-
-                if has_start_boundary {
-                    // 提取后面的方法名
-                    let method_start = i + 5;
-                    let mut method_end = method_start;
-                    while method_end < chars.len()
-                        && (chars[method_end].is_alphanumeric() || chars[method_end] == '_')
-                    {
-                        method_end += 1;
-                    }
-                    let method_name: String = chars[method_start..method_end].iter().collect();
-
-                    if arc_specific_methods.contains(&method_name.as_str()) {
-                        result.push_str("Arc::");
-                        i += 5;
-                        continue;
-                    }
-                }
-            }
-
-            // 检查 "A::" 模式（无空格）
-            if i + 2 < chars.len() && chars[i] == 'A' && chars[i + 1] == ':' && chars[i + 2] == ':'
-            {
-                // 检查前边界
-                let has_start_boundary =
-                    i == 0 || (!chars[i - 1].is_alphanumeric() && chars[i - 1] != '_');
-
-                if has_start_boundary {
-                    // 提取后面的方法名
-                    let method_start = i + 3;
-                    let mut method_end = method_start;
-                    while method_end < chars.len()
-                        && (chars[method_end].is_alphanumeric() || chars[method_end] == '_')
-                    {
-                        method_end += 1;
-                    }
-                    let method_name: String = chars[method_start..method_end].iter().collect();
-
-                    if arc_specific_methods.contains(&method_name.as_str()) {
-                        result.push_str("Arc::");
-                        i += 3;
-                        continue;
-                    }
-                }
-            }
-
-            result.push(chars[i]);
-            i += 1;
-        }
-
-        result
-    }
-
-    /// v1.8.6: 智能转换 R:: 到 Result::
-    /// 只有当 R:: 后面跟着 Result 特有的方法时才转换
-    /// 如果后面跟着通用方法或大写字母，则保持不变（如 R::Output 是泛型参数的关联类型）
-    fn convert_r_to_result_smart(s: &str) -> String {
-        // Result 特有的方法列表
-        // 注意：Result 没有太多静态方法，大多数是实例方法
-        // 这里只列出可能作为 Result:: 调用的方法
-        let result_specific_methods: [&str; 0] = []; // Result 几乎没有静态方法
-
-        let mut result = String::new();
-        let chars: Vec<char> = s.chars().collect();
-        let mut i = 0;
-
-        while i < chars.len() {
-            // 检查 "R :: " 模式（带空格）
-            if i + 4 < chars.len()
-                && chars[i] == 'R'
-                && chars[i + 1] == ' '
-                && chars[i + 2] == ':'
-                && chars[i + 3] == ':'
-                && chars[i + 4] == ' '
-            {
-                // 检查前边界
-                let has_start_boundary =
-                    i == 0 || (!chars[i - 1].is_alphanumeric() && chars[i - 1] != '_');
-
-                if has_start_boundary {
-                    // 提取后面的方法名
-                    let method_start = i + 5;
-                    let mut method_end = method_start;
-                    while method_end < chars.len()
-                        && (chars[method_end].is_alphanumeric() || chars[method_end] == '_')
-                    {
-                        method_end += 1;
-                    }
-                    let method_name: String = chars[method_start..method_end].iter().collect();
-
-                    if result_specific_methods.contains(&method_name.as_str()) {
-                        result.push_str("Result::");
-                        i += 5;
-                        continue;
-                    }
-                }
-            }
-
-            // 检查 "R::" 模式（无空格）
-            if i + 2 < chars.len() && chars[i] == 'R' && chars[i + 1] == ':' && chars[i + 2] == ':'
-            {
-                // 检查前边界
-                let has_start_boundary =
-                    i == 0 || (!chars[i - 1].is_alphanumeric() && chars[i - 1] != '_');
-
-                if has_start_boundary {
-                    // 提取后面的方法名
-                    let method_start = i + 3;
-                    let mut method_end = method_start;
-                    while method_end < chars.len()
-                        && (chars[method_end].is_alphanumeric() || chars[method_end] == '_')
-                    {
-                        method_end += 1;
-                    }
-                    let method_name: String = chars[method_start..method_end].iter().collect();
-
-                    if result_specific_methods.contains(&method_name.as_str()) {
-                        result.push_str("Result::");
-                        i += 3;
-                        continue;
-                    }
-                }
-            }
-
-            result.push(chars[i]);
-            i += 1;
+        // 记录本次转换的行内关键字改写
+        let pub_uses = self.record_added(content, &result, "U", "pub use");
+        for _ in pub_uses..count_added(content, &result, "use") {
+            self.record("u", "use");
         }
-
-        result
-    }
-
-    /// v1.8.6: 智能转换 B:: 到 Box::
-    /// 只有当 B:: 后面跟着 Box 特有的方法时才转换
-    /// 如果后面跟着通用方法（如 default）或大写字母，则保持不变
-    fn convert_b_to_box_smart(s: &str) -> String {
-        // Box 特有的方法列表（不包括通用 trait 方法如 default, clone 等）
-        let box_specific_methods = [
-            "new",
-            "new_uninit",
-            "new_zeroed",
-            "new_in",
-            "new_uninit_in",
-            "new_zeroed_in",
-            "try_new",
-            "try_new_uninit",
-            "try_new_zeroed",
-            "try_new_in",
-            "try_new_uninit_in",
-            "try_new_zeroed_in",
-            "pin",
-            "pin_in",
-            "into_pin",
-            "leak",
-            "into_raw",
-            "from_raw",
-            "from_raw_in",
-            "downcast",
-            "downcast_unchecked",
-            "into_boxed_slice",
-            "into_inner",
-            "assume_init",
-            "write",
-        ];
-
-        let mut result = String::new();
-        let chars: Vec<char> = s.chars().collect();
-        let mut i = 0;
-
-        while i < chars.len() {
-            // 检查 "B :: " 模式（带空格）
-            if i + 4 < chars.len()
-                && chars[i] == 'B'
-                && chars[i + 1] == ' '
-                && chars[i + 2] == ':'
-                && chars[i + 3] == ':'
-                && chars[i + 4] == ' '
-            {
-                // 检查前边界
-                let has_start_boundary =
-                    i == 0 || (!chars[i - 1].is_alphanumeric() && chars[i - 1] != '_');
-
-                if has_start_boundary {
-                    // 提取后面的方法名
-                    let method_start = i + 5;
-                    let mut method_end = method_start;
-                    while method_end < chars.len()
-                        && (chars[method_end].is_alphanumeric() || chars[method_end] == '_')
-                    {
-                        method_end += 1;
-                    }
-                    let method_name: String = chars[method_start..method_end].iter().collect();
-
-                    if box_specific_methods.contains(&method_name.as_str()) {
-                        result.push_str("Box::");
-                        i += 5;
-                        continue;
-                    }
-                }
-            }
-
-            // 检查 "B::" 模式（无空格）
-            if i + 2 < chars.len() && chars[i] == 'B' && chars[i + 1] == ':' && chars[i + 2] == ':'
-            {
-                // 检查前边界
-                let has_start_boundary =
-                    i == 0 || (!chars[i - 1].is_alphanumeric() && chars[i - 1] != '_');
-
-                if has_start_boundary {
-                    // 提取后面的方法名
-                    let method_start = i + 3;
-                    let mut method_end = method_start;
-                    while method_end < chars.len()
-                        && (chars[method_end].is_alphanumeric() || chars[method_end] == '_')
-                    {
-                        method_end += 1;
-                    }
-                    let method_name: String = chars[method_start..method_end].iter().collect();
-
-                    if box_specific_methods.contains(&method_name.as_str()) {
-                        result.push_str("Box::");
-                        i += 3;
-                        continue;
-                    }
-                }
-            }
-
-            result.push(chars[i]);
-            i += 1;
+        for (nu, rust) in [
+            ("br", "break"),
+            ("ct", "continue"),
+            ("?", "if"),
+            ("M", "match"),
+            ("D", "mod"),
+            ("L", "loop"),
+            ("L", "for"),
+            ("!", "?"),
+        ] {
+            self.record_added(content, &result, nu, rust);
         }
-
-        result
+        Ok(result)
     }
 
     /// 转换Nu类型回Rust类型
@@ -3088,6 +3132,23 @@ impl Nu2RustConverter {
         // 第一步：先转换 $| -> move | (在保护闭包之前)
         let mut result = s.replace("$|", "move |");
 
+        // 基于词法单元展开类型缩写：跳过字面量、注释与 macro_rules! 定义体，
+        // 不展开被用户类型或泛型参数遮蔽的缩写
+        {
+            let mut scope = self.line_scope.borrow_mut();
+            // 行首状态只适用于该行的第一个片段
+            let state = std::mem::take(&mut scope.state);
+            if !scope.in_macro_rules {
+                result = abbrev::expand_abbreviations(
+                    &result,
+                    state,
+                    &scope.types,
+                    &scope.generics,
+                    &mut self.rewrites.borrow_mut(),
+                );
+            }
+        }
+
         // 第二步：保护闭包参数和返回类型，避免单字母变量被误转换
         // 识别闭包模式: |param1, param2| 或 |params| -> RetType 或 move |...|
         let mut protected_closures = Vec::new();
@@ -3173,67 +3234,6 @@ impl Nu2RustConverter {
             result = result.replacen(closure, &format!("__CLOSURE_PARAMS_{}__", idx), 1);
         }
 
-        // v1.7: String 不再缩写为 Str，因此无需 Str -> String 转换
-        // 直接进行其他类型和关键字替换
-        // 注意：只转换明确的类型缩写（如Vec<），不转换可能是泛型参数的单字母（如 V ）
-        result = result
-            // 自定义类型名称修复（优先级最高，防止被标准库替换影响）
-            .replace("MyV ", "MyVec ")
-            .replace("MyV<", "MyVec<")
-            .replace("MyV{", "MyVec{")
-            .replace("MyV(", "MyVec(")
-            .replace("MyV::", "MyVec::")
-            .replace("MyB ", "MyBox ")
-            .replace("MyB<", "MyBox<")
-            .replace("MyB{", "MyBox{")
-            .replace("MyB(", "MyBox(")
-            .replace("MyB::", "MyBox::")
-            .replace("VecDeque<", "VecDeque<") // 保持VecDeque<不变
-            .replace("VecDeque::", "VecDeque::") // 保持VecDeque::不变
-            .replace("VDeque :: ", "VecDeque::") // VDeque :: -> VecDeque:: (带空格，优先处理)
-            .replace("VDeque::", "VecDeque::") // VDeque:: -> VecDeque:: (优先处理，避免被V::转换)
-            .replace("VDeque <", "VecDeque<") // VDeque < -> VecDeque< (带空格)
-            .replace("VDeque<", "VecDeque<") // VDeque< -> VecDeque< (rust2nu错误缩写)
-            .replace("Vec<", "Vec<"); // 保持Vec<不变，它已经是完整的
-
-        // v1.8.5: 智能处理 V:: -> Vec:: 转换
-        // 只有当 V:: 后面跟着小写字母时才转换（如 V::new, V::with_capacity）
-        // 如果后面跟着大写字母，则保持不变（如 V::Value 是泛型参数的关联类型）
-        result = Self::convert_v_to_vec_smart(&result);
-
-        // v1.8.1: 使用边界检查的替换，避免 YEAR < 被替换成 YEAResult<
-        result = Self::replace_type_with_boundary(&result, "V <", "Vec<");
-        result = Self::replace_type_with_boundary(&result, "V<", "Vec<");
-        result = Self::replace_type_with_boundary(&result, "O <", "Option<");
-        result = Self::replace_type_with_boundary(&result, "O<", "Option<");
-        result = Self::replace_type_with_boundary(&result, "O::", "Option::");
-        result = Self::replace_type_with_boundary(&result, "R <", "Result<");
-        result = Self::replace_type_with_boundary(&result, "R<", "Result<");
-        // v1.8.6: 使用智能转换 R:: -> Result:: (只在后面跟小写字母时转换)
-        result = Self::convert_r_to_result_smart(&result);
-        result = result
-            .replace("::R ", "::Result ") // ::R  -> ::Result (模块路径后的Result，带空格)
-            .replace("::R<", "::Result<") // ::R< -> ::Result< (模块路径后的Result泛型)
-            // v1.8.3: 处理 io::R< 模式（io模块的Result类型）
-            .replace("io::R<", "io::Result<")
-            .replace("BedError", "BoxedError"); // Bed -> Boxed (类型缩写，anyhow库)
-
-        // v1.8.6: 使用智能转换 B:: -> Box:: (只在后面跟小写字母时转换)
-        result = Self::convert_b_to_box_smart(&result);
-
-        // v1.8.1: A/X/B 也使用边界检查
-        result = Self::replace_type_with_boundary(&result, "A<", "Arc<");
-        // v1.8.5: 使用智能转换 A:: -> Arc:: (只在后面跟小写字母时转换)
-        result = Self::convert_a_to_arc_smart(&result);
-        result = Self::replace_type_with_boundary(&result, "X<", "Mutex<");
-        result = Self::replace_type_with_boundary(&result, "X::", "Mutex::");
-        result = Self::replace_type_with_boundary(&result, "B <", "Box<");
-        result = Self::replace_type_with_boundary(&result, "B<", "Box<");
-
-        // v1.8.16: 使用智能转换 I< -> impl< (只在单词边界处转换)
-        // 避免将 ParserI< 转换为 Parserimpl<
-        result = Self::convert_i_to_impl_smart(&result);
-
         result = result
             // v1.8.3: 先处理 .~! -> .await? (await with try operator)
             .replace(".~!", ".await?")
@@ -3242,6 +3242,7 @@ impl Nu2RustConverter {
             .replace(" wh ", " where ")
             .replace(") wh ", ") where ") // v1.8.16: tuple struct where clause
             .replace("wh ", "where ")
+            .replace("\nwh\n", "\nwhere\n") // 多行签名中单独一行的 wh
             // v1.8.24: 修复 where 子句中的格式问题
             // "where  F :" -> "where F:" (双空格和冒号前空格)
             .replace("where  ", "where ") // 修复双空格
@@ -3439,25 +3440,14 @@ impl Nu2RustConverter {
         }
         result = new_result;
 
-        // V! -> vec! (宏调用)
-        result = result.replace("V![", "vec![").replace("V! [", "vec![");
-
         // v1.8.3: 恢复闭包参数，但先转换其中的类型缩写
         // v1.8.20: 也需要转换 &! -> &mut 和 *! -> *mut
         for (idx, closure) in protected_closures.iter().enumerate() {
-            // 转换闭包中的类型缩写
-            let mut converted_closure = closure
-                .replace("io::R<", "io::Result<")
-                .replace("R<", "Result<")
-                .replace("O<", "Option<")
-                .replace("V<", "Vec<")
-                .replace("A<", "Arc<")
-                .replace("X<", "Mutex<")
-                .replace("B<", "Box<");
+            // 类型缩写已在词法展开阶段处理
             // v1.8.20: 转换 &! -> &mut 和 *! -> *mut
             // 在闭包参数中，&! 总是表示 &mut（类型位置）
             // 注意：&! 后面可能有空格，所以用 "&mut " 替换 "&! "，用 "&mut" 替换 "&!"
-            converted_closure = converted_closure.replace("&! ", "&mut ");
+            let mut converted_closure = closure.replace("&! ", "&mut ");
             converted_closure = converted_closure.replace("&!", "&mut ");
             converted_closure = converted_closure.replace("*! ", "*mut ");
             converted_closure = converted_closure.replace("*!", "*mut ");
@@ -3594,10 +3584,66 @@ impl Nu2RustConverter {
         }
         result = fixed_result;
 
+        // 记录本次转换的关键字与修饰符改写（类型缩写在展开时已记录）
+        for (nu, rust) in [
+            ("$", "move"),
+            ("~", "await"),
+            ("wh", "where"),
+            ("!", "?"),
+            ("!", "mut"),
+        ] {
+            self.record_added(s, &result, nu, rust);
+        }
         result
     }
 }
 
+/// rust 片段在 after 中比 before 多出现的次数
+fn count_added(before: &str, after: &str, rust: &str) -> usize {
+    after
+        .matches(rust)
+        .count()
+        .saturating_sub(before.matches(rust).count())
+}
+
+impl Transpile for Nu2RustConverter {
+    /// 无法识别的结构在 Strict 下为错误、其余模式下为警告
+    fn transpile(&self, nu_code: &str) -> ConversionOutput {
+        let mut sourcemap = LazySourceMap::new(String::new(), String::new());
+        let code = match self.convert_lines(nu_code, Some(&mut sourcemap)) {
+            Ok(code) => code,
+            Err(err) => {
+                return ConversionOutput::failed(Diagnostic::error(
+                    "conversion-failed",
+                    format!("{:#}", err),
+                ))
+            }
+        };
+        if self.options.strictness != Strictness::Lenient {
+            self.check_syntax(nu_code, &code, &sourcemap);
+        }
+        let severity = if self.options.strictness == Strictness::Strict {
+            Severity::Error
+        } else {
+            Severity::Warning
+        };
+        let diagnostics = self
+            .unrecognized_constructs()
+            .into_iter()
+            .map(|construct| {
+                Diagnostic::new(severity, "unrecognized-construct", construct.message())
+                    .with_span(construct.span)
+                    .with_note("该行按表达式原样透传，生成的 Rust 可能无法编译")
+            })
+            .collect();
+        ConversionOutput {
+            code,
+            sourcemap: sourcemap.mappings(),
+            diagnostics,
+        }
+    }
+}
+
 impl Default for Nu2RustConverter {
     fn default() -> Self {
         Self::new()
@@ -3641,6 +3687,33 @@ mod tests {
         assert!(rust_code.contains("to_string()"));
     }
 
+    #[test]
+    fn test_abbreviations_respect_literals_and_shadowing() {
+        let converter = Nu2RustConverter::new();
+        let nu_code = "S B {\n    x: i32,\n}\n\nF make<V: Default>() -> V<u8> {\n    l s = \"V<i32> and B::new()\";\n    l b: B = B::new();\n    l v = V::new();\n    < V![1]\n}\n\nf plain() -> O<V<u8>> {\n    < O::Some(V::new())\n}\n";
+        let rust_code = converter.convert(nu_code).unwrap();
+        assert!(rust_code.contains("pub fn make<V: Default>() -> Vec<u8> {"));
+        assert!(rust_code.contains("let s = \"V<i32> and B::new()\";"));
+        assert!(rust_code.contains("let b: B = B::new();"));
+        assert!(rust_code.contains("let v = V::new();"));
+        assert!(rust_code.contains("vec![1]"));
+        assert!(rust_code.contains("fn plain() -> Option<Vec<u8>> {"));
+        assert!(rust_code.contains("Option::Some(Vec::new())"));
+    }
+
+    #[test]
+    fn test_later_use_shadows_abbreviations() {
+        // u 对整个模块生效：前面函数中的 V<i32> 同样指导入的 V，与 AST 后端一致
+        let nu_code = "f a() -> V<i32> {\n    V::new()\n}\n\nu crate::types::V;\n";
+        let rust_code = Nu2RustConverter::new().convert(nu_code).unwrap();
+        assert!(rust_code.contains("fn a() -> V<i32> {"));
+        assert!(rust_code.contains("use crate::types::V;"));
+
+        let ast = crate::parser::Parser::new().parse(nu_code).unwrap();
+        let ast_code = crate::codegen::CodeGenerator::new().generate(&ast).unwrap();
+        assert!(ast_code.contains("fn a() -> V<i32> {"));
+    }
+
     #[test]
     fn test_convert_variables() {
         let converter = Nu2RustConverter::new();
@@ -3662,4 +3735,147 @@ mod tests {
         let rust_code = converter.convert(nu_code).unwrap();
         assert!(rust_code.contains("return x + 1"));
     }
+
+    #[test]
+    fn test_strict_validates_output_with_syn() {
+        let strict = Nu2RustConverter::with_options(Nu2RustOptions {
+            strictness: Strictness::Strict,
+            ..Default::default()
+        });
+        for (nu_code, line) in [("??? weird\n", 1), ("F run() {\n    x @@@ y\n}\n", 2)] {
+            let err = strict.convert(nu_code).unwrap_err();
+            let err = err.downcast::<StrictError>().unwrap();
+            assert_eq!(err.constructs[0].span.line, line, "{}", nu_code);
+        }
+        assert!(strict.convert("F run() {\n    l x = 1;\n}\n").is_ok());
+    }
+
+    #[test]
+    fn test_strictness() {
+        let nu_code = "F run() {\n    F helper\n    l x = 1;\n}\n";
+
+        let lenient = Nu2RustConverter::new();
+        assert!(lenient.convert(nu_code).is_ok());
+        let output = lenient.transpile(nu_code);
+        assert!(output.code.contains("let x = 1;"));
+        assert!(!output.sourcemap.is_empty());
+        assert_eq!(output.warnings().count(), 1);
+        assert_eq!(output.diagnostics[0].code, "unrecognized-construct");
+
+        let warn = Nu2RustConverter::with_options(Nu2RustOptions {
+            strictness: Strictness::Warn,
+            ..Default::default()
+        });
+        assert!(warn.convert(nu_code).unwrap().contains("let x = 1;"));
+        let constructs = warn.unrecognized_constructs();
+        assert_eq!(constructs.len(), 1);
+        assert_eq!(constructs[0].construct, "F");
+        assert_eq!((constructs[0].span.line, constructs[0].span.col), (2, 5));
+
+        let strict = Nu2RustConverter::with_options(Nu2RustOptions {
+            strictness: Strictness::Strict,
+            ..Default::default()
+        });
+        let err = strict.convert(nu_code).unwrap_err();
+        let err = err.downcast_ref::<StrictError>().unwrap();
+        assert_eq!(err.constructs, constructs);
+        assert!(strict.convert("F run() {\n    helper();\n}\n").is_ok());
+        assert!(strict.transpile(nu_code).has_errors());
+    }
+
+    #[test]
+    fn test_multi_line_statements() {
+        let converter = Nu2RustConverter::new();
+        let nu_code = "F pick<\n    T: Clone,\n    F : Fn(i32) -> i32,\n>(x: T, f: F) -> T\nwh\n    T: Default,\n{\n    l ok = f(1)\n        < 3;\n    x\n}\n";
+        let mut sm = LazySourceMap::new("a.nu".to_string(), "a.rs".to_string());
+        let rust_code = converter
+            .convert_with_sourcemap(nu_code, Some(&mut sm))
+            .unwrap();
+        assert!(rust_code.starts_with("pub fn pick<\n    T: Clone,\n    F: Fn(i32) -> i32,\n"));
+        assert!(rust_code.contains(">(x: T, f: F) -> T\nwhere\n    T: Default,\n{"));
+        assert!(rust_code.contains("let ok = f(1)\n"));
+        assert!(!rust_code.contains("return 3"));
+
+        // 逻辑语句的每个生成行仍映射回各自的 Nu 行
+        assert_eq!(sm.find_nearest_nu_line(3), Some(3));
+        assert_eq!(sm.find_nearest_nu_line(6), Some(6));
+        assert_eq!(sm.find_nearest_nu_line(9), Some(9));
+    }
+
+    #[test]
+    fn test_macro_bodies() {
+        let converter = Nu2RustConverter::new();
+        let nu_code = "macro_rules! make {\n    ($t:ty) => {\n        V::<$t>::new() // l x = O<i32>\n    };\n}\n\nF run() -> R<(), String> {\n    assert_eq!(V![V::new()].len(), f()!);\n    println!(\"{} V<i32> wh {:?}\", g()!, O::Some(1));\n    < Ok(())\n}\n";
+        let rust_code = converter.convert(nu_code).unwrap();
+        let macro_rules = &nu_code[..nu_code.find("\n\n").unwrap()];
+        assert!(rust_code.contains(macro_rules));
+        assert!(rust_code.contains("assert_eq!(vec![Vec::new()].len(), f()?);"));
+        assert!(rust_code.contains("println!(\"{} V<i32> wh {:?}\", g()?, Option::Some(1));"));
+    }
+
+    #[test]
+    fn test_sourcemap_columns() {
+        let converter = Nu2RustConverter::new();
+        let nu_code = "//! crate doc\nF load() -> R<V<i32>, String> {\n    v items = parse()!;\n    #![doc = \"late\"]\n    < Ok(items)\n}\n";
+        let mut sm = LazySourceMap::new("a.nu".to_string(), "a.rs".to_string());
+        let rust_code = converter
+            .convert_with_sourcemap(nu_code, Some(&mut sm))
+            .unwrap();
+        let rust_lines: Vec<&str> = rust_code.lines().collect();
+
+        // 定位生成代码中的 token，返回其 (行, 列, 长度)
+        let find = |needle: &str| {
+            rust_lines
+                .iter()
+                .enumerate()
+                .find_map(|(i, l)| l.find(needle).map(|c| (i + 1, c + 1, needle.len())))
+                .unwrap()
+        };
+        let span = |needle: &str| {
+            let (line, col, len) = find(needle);
+            sm.find_nu_span(line, col, len).unwrap()
+        };
+        assert_eq!(
+            span("Result"),
+            NuSpan {
+                line: 2,
+                col: 13,
+                len: 1
+            }
+        );
+        assert_eq!(
+            span("parse"),
+            NuSpan {
+                line: 3,
+                col: 15,
+                len: 5
+            }
+        );
+        assert_eq!(
+            span("?"),
+            NuSpan {
+                line: 3,
+                col: 22,
+                len: 1
+            }
+        );
+        // 删除 #![doc] 行后，后续映射随之上移
+        assert!(!rust_code.contains("late"));
+        assert_eq!(
+            span("return"),
+            NuSpan {
+                line: 5,
+                col: 5,
+                len: 1
+            }
+        );
+        assert_eq!(
+            span("Ok(items)"),
+            NuSpan {
+                line: 5,
+                col: 7,
+                len: 9
+            }
+        );
+    }
 }
@@ -1,13 +1,81 @@
 // SourceMap Generator for Nu to Rust Conversion
-// Phase 1: LazySourceMap - Line-to-line mapping only
+// Phase 1: LazySourceMap - Line-to-line mapping
+// Phase 2: 列级片段映射（转换器改写 token 时记录改写，按记录对应 Nu 与 Rust token）
 
+use super::lexer::{tokenize, Kind, LexState};
+use crate::sourcemap::{Mapping, MappingTable};
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::fs;
+use std::ops::Range;
 use std::path::Path;
 
-/// LazySourceMap 结构体 - Phase 1 实现
-/// 只存储行号映射，不包含列信息
+/// 列级映射片段：Rust 行内 `[rust_col, rust_col + rust_len)` 对应 Nu 行内 `[nu_col, nu_col + nu_len)`
+///
+/// 行列均为 1-based，列按字符计数（与 rustc 诊断一致）。序列化为六元数组以减小 .map 体积。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "[usize; 6]", into = "[usize; 6]")]
+pub struct Segment {
+    pub rust_line: usize,
+    pub rust_col: usize,
+    pub rust_len: usize,
+    pub nu_line: usize,
+    pub nu_col: usize,
+    pub nu_len: usize,
+}
+
+impl Segment {
+    /// 两侧等长（未改写或等长替换，如 `!` → `?`），片段内可按偏移精确定位
+    fn is_identity(&self) -> bool {
+        self.rust_len == self.nu_len
+    }
+
+    fn rust_end(&self) -> usize {
+        self.rust_col + self.rust_len
+    }
+
+    fn contains(&self, rust_col: usize) -> bool {
+        self.rust_col <= rust_col && rust_col < self.rust_end()
+    }
+}
+
+impl From<[usize; 6]> for Segment {
+    fn from(v: [usize; 6]) -> Self {
+        Self {
+            rust_line: v[0],
+            rust_col: v[1],
+            rust_len: v[2],
+            nu_line: v[3],
+            nu_col: v[4],
+            nu_len: v[5],
+        }
+    }
+}
+
+impl From<Segment> for [usize; 6] {
+    fn from(s: Segment) -> Self {
+        [
+            s.rust_line,
+            s.rust_col,
+            s.rust_len,
+            s.nu_line,
+            s.nu_col,
+            s.nu_len,
+        ]
+    }
+}
+
+/// Rust 位置映射回的 Nu 区间（1-based，列按字符计数）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NuSpan {
+    pub line: usize,
+    pub col: usize,
+    pub len: usize,
+}
+
+/// LazySourceMap 结构体
+/// 行号映射用于粗粒度定位；segments 提供列级精确定位（旧版 .map 文件没有该字段）
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LazySourceMap {
     /// 源文件名（Nu 文件）
@@ -19,6 +87,10 @@ pub struct LazySourceMap {
     /// 行号映射表：(rust_line, nu_line)
     /// 按 rust_line 排序，用于二分查找
     pub line_map: Vec<(usize, usize)>,
+
+    /// 列级映射片段，按 (rust_line, rust_col) 排序
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub segments: Vec<Segment>,
 }
 
 impl LazySourceMap {
@@ -28,6 +100,7 @@ impl LazySourceMap {
             nu_file,
             rust_file,
             line_map: Vec::new(),
+            segments: Vec::new(),
         }
     }
 
@@ -113,7 +186,373 @@ impl LazySourceMap {
     /// 清空所有映射
     pub fn clear(&mut self) {
         self.line_map.clear();
+        self.segments.clear();
+    }
+
+    /// 添加列级映射片段（需按 Rust 位置顺序添加）
+    pub fn add_segment(&mut self, segment: Segment) {
+        self.segments.push(segment);
+    }
+
+    /// 获取列级片段数量
+    pub fn segment_count(&self) -> usize {
+        self.segments.len()
+    }
+
+    /// 记录一条逻辑语句的列级映射
+    ///
+    /// 未改写的 token 按偏移精确对应；改写过的 token 按转换器记录的 `rewrites` 对应到原 Nu token；
+    /// 未记录的插入（如字段前补的 `pub`）归入相邻的 Nu token。state 为 Nu 片段开头的词法状态。
+    pub(crate) fn add_statement_segments(
+        &mut self,
+        rust_line: usize,
+        rust_text: &str,
+        nu_line: usize,
+        nu_text: &str,
+        state: LexState,
+        rewrites: &[Rewrite],
+    ) {
+        let rust = locate(rust_text, state);
+        let nu = locate(nu_text, state);
+        if nu.is_empty() {
+            return;
+        }
+        for (token, range) in rust.iter().zip(pair_tokens(&nu, &rust, rewrites)) {
+            let first = &nu[range.start];
+            let last = &nu[range.end - 1];
+            if range.len() == 1 && first.text == token.text {
+                // 原样保留的 token（含跨行的字符串字面量）逐行对应
+                for (k, piece) in token.text.split('\n').enumerate() {
+                    let len = piece.chars().count();
+                    let (rust_col, nu_col) = if k == 0 {
+                        (token.col, first.col)
+                    } else {
+                        (1, 1)
+                    };
+                    self.push_segment(Segment {
+                        rust_line: rust_line + token.line + k,
+                        rust_col,
+                        rust_len: len,
+                        nu_line: nu_line + first.line + k,
+                        nu_col,
+                        nu_len: len,
+                    });
+                }
+                continue;
+            }
+            let nu_len = if last.line == first.line {
+                last.col + last.first_line_len() - first.col
+            } else {
+                first.first_line_len()
+            };
+            self.push_segment(Segment {
+                rust_line: rust_line + token.line,
+                rust_col: token.col,
+                rust_len: token.first_line_len(),
+                nu_line: nu_line + first.line,
+                nu_col: first.col,
+                nu_len,
+            });
+        }
+    }
+
+    /// 追加片段，合并同偏移的等长片段或对应同一个 Nu 区间的改写片段
+    fn push_segment(&mut self, segment: Segment) {
+        if let Some(last) = self.segments.last_mut() {
+            let same_line = last.rust_line == segment.rust_line && last.nu_line == segment.nu_line;
+            let same_offset = last.is_identity()
+                && segment.is_identity()
+                && last.rust_col + segment.nu_col == last.nu_col + segment.rust_col;
+            let same_nu = !last.is_identity()
+                && !segment.is_identity()
+                && last.nu_col == segment.nu_col
+                && last.nu_len == segment.nu_len;
+            if same_line && (same_offset || same_nu) && segment.rust_col >= last.rust_col {
+                last.rust_len = segment.rust_end() - last.rust_col;
+                if same_offset {
+                    last.nu_len = last.rust_len;
+                }
+                return;
+            }
+        }
+        self.segments.push(segment);
+    }
+
+    /// 查找 Rust 区间（行、起始列、长度）对应的 Nu 区间
+    ///
+    /// 落在未改写片段内的位置按偏移精确映射；落在改写片段（如 `let` ← `l`）内时映射到整个 Nu token。
+    /// 该行没有列级片段时返回 None，调用方可回退到 [`find_nearest_nu_line`](Self::find_nearest_nu_line)。
+    pub fn find_nu_span(
+        &self,
+        rust_line: usize,
+        rust_col: usize,
+        rust_len: usize,
+    ) -> Option<NuSpan> {
+        let start = self.segments.partition_point(|s| s.rust_line < rust_line);
+        let end = self.segments.partition_point(|s| s.rust_line <= rust_line);
+        let segments = &self.segments[start..end];
+        if segments.is_empty() {
+            return None;
+        }
+
+        let (line, first_col, first_len) = map_column(segments, rust_col, false);
+        let (end_line, last_col, last_len) =
+            map_column(segments, rust_col + rust_len.max(1) - 1, true);
+        let first_end = first_col + first_len;
+        let last_end = last_col + last_len;
+        let len = if end_line == line && last_end > first_col {
+            last_end.max(first_end) - first_col
+        } else {
+            first_len
+        };
+        Some(NuSpan {
+            line,
+            col: first_col,
+            len,
+        })
+    }
+
+    /// 查找 Rust 位置对应的 Nu 位置 (nu_line, nu_col)；没有列级片段时回退到行映射，列为 None
+    pub fn find_nu_position(
+        &self,
+        rust_line: usize,
+        rust_col: usize,
+    ) -> Option<(usize, Option<usize>)> {
+        match self.find_nu_span(rust_line, rust_col, 1) {
+            Some(span) => Some((span.line, Some(span.col))),
+            None => self
+                .find_nearest_nu_line(rust_line)
+                .map(|line| (line, None)),
+        }
+    }
+
+    /// 生成的代码在后处理中删除了一行时，同步修正映射
+    pub fn remove_rust_line(&mut self, rust_line: usize) {
+        self.line_map.retain(|&(r, _)| r != rust_line);
+        for entry in &mut self.line_map {
+            if entry.0 > rust_line {
+                entry.0 -= 1;
+            }
+        }
+        self.segments.retain(|s| s.rust_line != rust_line);
+        for segment in &mut self.segments {
+            if segment.rust_line > rust_line {
+                segment.rust_line -= 1;
+            }
+        }
+    }
+}
+
+impl MappingTable for LazySourceMap {
+    fn source_file(&self) -> &str {
+        &self.nu_file
+    }
+
+    fn generated_file(&self) -> &str {
+        &self.rust_file
+    }
+
+    /// 有列级片段的行按片段起点输出，其余行映射到 Nu 行首
+    fn mappings(&self) -> Vec<Mapping> {
+        let segment_lines: HashSet<usize> = self.segments.iter().map(|s| s.rust_line).collect();
+        let lines = self
+            .line_map
+            .iter()
+            .filter(|(rust_line, _)| !segment_lines.contains(rust_line))
+            .map(|&(rust_line, nu_line)| Mapping {
+                generated_line: rust_line,
+                generated_col: 1,
+                source_line: nu_line,
+                source_col: 1,
+            });
+        let segments = self.segments.iter().map(|s| Mapping {
+            generated_line: s.rust_line,
+            generated_col: s.rust_col,
+            source_line: s.nu_line,
+            source_col: s.nu_col,
+        });
+        lines.chain(segments).collect()
+    }
+}
+
+/// 在同一 Rust 行的片段中映射一个列，返回 (nu_line, nu_col, nu_len)
+///
+/// 位于片段之间的列（空白、未对齐的字符）归入后一个片段；作为区间终点时归入前一个片段。
+fn map_column(segments: &[Segment], rust_col: usize, is_end: bool) -> (usize, usize, usize) {
+    if let Some(s) = segments.iter().find(|s| s.contains(rust_col)) {
+        if s.is_identity() {
+            let col = s.nu_col + (rust_col - s.rust_col);
+            return (s.nu_line, col, 1);
+        }
+        return (s.nu_line, s.nu_col, s.nu_len);
+    }
+    let next = segments.iter().find(|s| s.rust_col > rust_col);
+    let prev = segments.iter().rev().find(|s| s.rust_end() <= rust_col);
+    let s = match (prev, next) {
+        (Some(p), Some(n)) => {
+            if is_end {
+                p
+            } else {
+                n
+            }
+        }
+        (Some(p), None) => p,
+        (None, Some(n)) => n,
+        (None, None) => unreachable!("segments 非空"),
+    };
+    (s.nu_line, s.nu_col, s.nu_len)
+}
+
+/// 转换器改写 token 时记录的一次改写：Nu 片段 → 生成的 Rust 片段；nu 为空表示插入的代码
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Rewrite {
+    pub nu: String,
+    pub rust: String,
+}
+
+impl Rewrite {
+    pub fn new(nu: &str, rust: &str) -> Self {
+        Self {
+            nu: nu.to_string(),
+            rust: rust.to_string(),
+        }
+    }
+}
+
+/// 带位置的 token：行偏移（0-based）与列（1-based，按字符计）
+struct Located<'a> {
+    text: &'a str,
+    line: usize,
+    col: usize,
+}
+
+impl Located<'_> {
+    fn first_line_len(&self) -> usize {
+        self.text
+            .split('\n')
+            .next()
+            .unwrap_or_default()
+            .chars()
+            .count()
+    }
+}
+
+/// 用 Nu 词法视图切分并定位非空白 token（注释保留，原样对应）
+fn locate(text: &str, state: LexState) -> Vec<Located<'_>> {
+    let (tokens, _) = tokenize(text, state);
+    let (mut line, mut col) = (0, 1);
+    let mut located = Vec::new();
+    for token in tokens {
+        if token.kind != Kind::Space {
+            located.push(Located {
+                text: token.text,
+                line,
+                col,
+            });
+        }
+        for c in token.text.chars() {
+            if c == '\n' {
+                line += 1;
+                col = 1;
+            } else {
+                col += 1;
+            }
+        }
     }
+    located
+}
+
+/// 非空白 token 文本
+fn token_texts(text: &str) -> Vec<&str> {
+    locate(text, LexState::Code)
+        .into_iter()
+        .map(|token| token.text)
+        .collect()
+}
+
+/// 原样保留的 token；`r#` 转义在生成 Rust 时被去掉，同样视为未改写
+fn same_token(nu: &str, rust: &str) -> bool {
+    nu == rust || crate::escape::unescape(nu) == rust
+}
+
+/// 未记录的差异向前查找对应 token 的最大距离
+const RESYNC_WINDOW: usize = 3;
+
+/// 按转换记录的改写把 Rust token 配对到 Nu token，返回每个 Rust token 对应的 Nu token 区间
+fn pair_tokens(nu: &[Located], rust: &[Located], rewrites: &[Rewrite]) -> Vec<Range<usize>> {
+    let mut pending: Vec<(Vec<&str>, Vec<&str>)> = rewrites
+        .iter()
+        .map(|rewrite| (token_texts(&rewrite.nu), token_texts(&rewrite.rust)))
+        .filter(|(_, rust)| !rust.is_empty())
+        .collect();
+    let starts_with = |tokens: &[Located], from: usize, texts: &[&str]| {
+        tokens.len() >= from + texts.len()
+            && tokens[from..]
+                .iter()
+                .zip(texts)
+                .all(|(token, text)| token.text == *text)
+    };
+
+    let mut pairs = Vec::with_capacity(rust.len());
+    let (mut i, mut j) = (0, 0);
+    while j < rust.len() {
+        if i >= nu.len() {
+            // Nu 侧已结束：其余生成的代码归入最后一个 Nu token
+            pairs.push(nu.len() - 1..nu.len());
+            j += 1;
+            continue;
+        }
+        // 记录的改写优先（如 `#D` → `#[derive`）；插入的代码只在 token 不同时考虑
+        let same = same_token(nu[i].text, rust[j].text);
+        let rewrite = pending
+            .iter()
+            .enumerate()
+            .filter(|(_, (from, to))| {
+                (!from.is_empty() || !same) && starts_with(nu, i, from) && starts_with(rust, j, to)
+            })
+            .max_by_key(|(_, (from, to))| (!from.is_empty(), to.len()))
+            .map(|(k, _)| k);
+        match rewrite {
+            Some(k) => {
+                let (from, to) = pending.swap_remove(k);
+                // 插入的代码归入后一个 Nu token
+                let range = i..i + from.len().max(1);
+                pairs.extend(std::iter::repeat_n(range, to.len()));
+                i += from.len();
+                j += to.len();
+                continue;
+            }
+            None if same => {
+                pairs.push(i..i + 1);
+                i += 1;
+                j += 1;
+                continue;
+            }
+            None => {}
+        }
+        // 未记录的差异：Nu token 被删除、Rust token 被插入，或一对一替换
+        let deleted = (1..=RESYNC_WINDOW).find(|&d| {
+            nu.get(i + d)
+                .is_some_and(|token| same_token(token.text, rust[j].text))
+        });
+        let inserted = (1..=RESYNC_WINDOW).find(|&d| {
+            rust.get(j + d)
+                .is_some_and(|token| same_token(nu[i].text, token.text))
+        });
+        match (deleted, inserted) {
+            (Some(d), _) => i += d,
+            (None, Some(_)) => {
+                pairs.push(i..i + 1);
+                j += 1;
+            }
+            (None, None) => {
+                pairs.push(i..i + 1);
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    pairs
 }
 
 #[cfg(test)]
@@ -185,4 +624,193 @@ mod tests {
 
         assert_eq!(sm.line_map.len(), 1);
     }
+
+    #[test]
+    fn test_column_segments() {
+        let mut sm = LazySourceMap::new("test.nu".to_string(), "test.rs".to_string());
+        let nu = "    v items: V<i32> = load()!;";
+        let rust = "    let mut items: Vec<i32> = load()?;";
+        let rewrites = [
+            Rewrite::new("v", "let mut"),
+            Rewrite::new("V", "Vec"),
+            Rewrite::new("!", "?"),
+        ];
+        sm.add_statement_segments(3, rust, 2, nu, LexState::Code, &rewrites);
+
+        // `let mut` -> `v`
+        let span = |col, len| sm.find_nu_span(3, col, len).unwrap();
+        assert_eq!(
+            span(5, 3),
+            NuSpan {
+                line: 2,
+                col: 5,
+                len: 1
+            }
+        );
+        assert_eq!(
+            span(9, 3),
+            NuSpan {
+                line: 2,
+                col: 5,
+                len: 1
+            }
+        );
+        // 未改写的标识符按偏移精确映射
+        assert_eq!(
+            span(13, 5),
+            NuSpan {
+                line: 2,
+                col: 7,
+                len: 5
+            }
+        );
+        assert_eq!(
+            span(14, 1),
+            NuSpan {
+                line: 2,
+                col: 8,
+                len: 1
+            }
+        );
+        // `Vec<i32>` -> `V<i32>`
+        assert_eq!(
+            span(20, 8),
+            NuSpan {
+                line: 2,
+                col: 14,
+                len: 6
+            }
+        );
+        // `load()?` -> `load()!`
+        assert_eq!(
+            span(31, 7),
+            NuSpan {
+                line: 2,
+                col: 23,
+                len: 7
+            }
+        );
+        assert_eq!(
+            span(37, 1),
+            NuSpan {
+                line: 2,
+                col: 29,
+                len: 1
+            }
+        );
+
+        // 没有列级片段的行回退到行映射
+        sm.add_mapping(3, 2);
+        assert_eq!(sm.find_nu_span(4, 1, 1), None);
+        assert_eq!(sm.find_nu_position(4, 1), Some((2, None)));
+        assert_eq!(sm.find_nu_position(3, 13), Some((2, Some(7))));
+    }
+
+    #[test]
+    fn test_recorded_keyword_rewrites() {
+        let mut sm = LazySourceMap::new("test.nu".to_string(), "test.rs".to_string());
+        sm.add_statement_segments(
+            1,
+            "pub fn add(a: i32) -> i32 {",
+            1,
+            "F add(a: i32) -> i32 {",
+            LexState::Code,
+            &[Rewrite::new("F", "pub fn")],
+        );
+        // `pub fn` 整体对应 `F`
+        assert_eq!(
+            sm.find_nu_span(1, 1, 6),
+            Some(NuSpan {
+                line: 1,
+                col: 1,
+                len: 1
+            })
+        );
+        assert_eq!(
+            sm.find_nu_span(1, 8, 3),
+            Some(NuSpan {
+                line: 1,
+                col: 3,
+                len: 3
+            })
+        );
+        // 改写点之外的部分合并为少量片段
+        assert!(sm.segment_count() <= 2);
+
+        // 未记录的插入（字段前补的 pub）归入后一个 Nu token，跨行字符串逐行对应
+        sm.add_statement_segments(
+            2,
+            "    pub name: &'static str = \"a\n b\",",
+            2,
+            "    name: &'static str = \"a\n b\",",
+            LexState::Code,
+            &[],
+        );
+        assert_eq!(
+            sm.find_nu_span(2, 5, 3),
+            Some(NuSpan {
+                line: 2,
+                col: 5,
+                len: 4
+            })
+        );
+        assert_eq!(
+            sm.find_nu_span(3, 2, 1),
+            Some(NuSpan {
+                line: 3,
+                col: 2,
+                len: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_segments_serialization_and_line_removal() {
+        let mut sm = LazySourceMap::new("test.nu".to_string(), "test.rs".to_string());
+        sm.add_mapping(1, 1);
+        let doc = "#![doc = \"x\"]";
+        sm.add_statement_segments(1, doc, 1, doc, LexState::Code, &[]);
+        sm.add_mapping(2, 2);
+        sm.add_statement_segments(
+            2,
+            "let x = 1;",
+            2,
+            "l x = 1;",
+            LexState::Code,
+            &[Rewrite::new("l", "let")],
+        );
+
+        let json = sm.to_json().unwrap();
+        let loaded = LazySourceMap::from_json(&json).unwrap();
+        assert_eq!(loaded.segments, sm.segments);
+
+        // 旧版 .map 文件没有 segments 字段
+        let legacy =
+            LazySourceMap::from_json(r#"{"nu_file":"a.nu","rust_file":"a.rs","line_map":[[1,1]]}"#)
+                .unwrap();
+        assert!(legacy.segments.is_empty());
+
+        // 导出为 Source Map v3
+        let v3 = crate::sourcemap::SourceMapV3::from_table(&sm);
+        assert_eq!(v3.sources, vec!["test.nu"]);
+        assert_eq!(v3.file, "test.rs");
+        let decoded = v3.decode().unwrap();
+        assert_eq!(decoded.len(), sm.segment_count());
+        assert_eq!(
+            (decoded[1].generated_line, decoded[1].generated_col),
+            (2, 1)
+        );
+        assert_eq!((decoded[1].source_line, decoded[1].source_col), (2, 1));
+
+        sm.remove_rust_line(1);
+        assert_eq!(sm.line_map, vec![(1, 2)]);
+        assert_eq!(
+            sm.find_nu_span(1, 5, 1),
+            Some(NuSpan {
+                line: 2,
+                col: 3,
+                len: 1
+            })
+        );
+    }
 }
@@ -0,0 +1,190 @@
+// 严格模式
+// convert_line 无法归类的行会落入表达式转换并原样透传，生成的 Rust 要到 cargo 阶段才报错；
+// 这里识别这类行，按 Strictness 忽略、收集为警告或报错
+
+use super::lexer::{tokenize, Kind, LexState, Token};
+use crate::ast::Span;
+use crate::escape::RUST_KEYWORDS;
+use crate::profile::CompressionProfile;
+use std::fmt;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// 遇到无法识别的 Nu 结构时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Strictness {
+    /// 原样透传（默认）
+    #[default]
+    Lenient,
+    /// 收集为警告，转换继续
+    Warn,
+    /// 转换失败并列出所有无法识别的结构
+    Strict,
+}
+
+/// Nu2RustConverter 的转换选项
+#[derive(Debug, Clone, Default)]
+pub struct Nu2RustOptions {
+    pub strictness: Strictness,
+    /// 压缩配置；Nu 文件首行记录了配置时以首行为准
+    pub profile: CompressionProfile,
+}
+
+/// 一处无法识别的 Nu 结构
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnrecognizedConstruct {
+    /// 结构所在位置（行、列从 1 开始）
+    pub span: Span,
+    /// 无法归类的起始记号
+    pub construct: String,
+    /// 所在的 Nu 源码行
+    pub source_line: String,
+}
+
+impl UnrecognizedConstruct {
+    pub fn message(&self) -> String {
+        format!("无法识别的 Nu 结构 `{}`", self.construct)
+    }
+
+    /// 渲染为带源码片段的诊断信息，level 为 error 或 warning
+    pub fn render(&self, level: &str, file: &str) -> String {
+        let span = Span {
+            file: Some(Arc::from(file)),
+            ..self.span.clone()
+        };
+        format!(
+            "{}: {}\n --> {}\n{}",
+            level,
+            self.message(),
+            span,
+            span.snippet(&self.source_line_at(span.line))
+        )
+    }
+
+    /// 构造只含本行的源码，使 Span::snippet 能按行号取到它
+    fn source_line_at(&self, line: usize) -> String {
+        "\n".repeat(line.saturating_sub(1)) + &self.source_line
+    }
+}
+
+impl fmt::Display for UnrecognizedConstruct {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.span, self.message())
+    }
+}
+
+/// 严格模式下的转换错误
+#[derive(Debug, Clone, PartialEq, Error)]
+#[error("{} 处无法识别的 Nu 结构（首处 {}）", constructs.len(), constructs[0])]
+pub struct StrictError {
+    pub constructs: Vec<UnrecognizedConstruct>,
+}
+
+impl StrictError {
+    /// 逐条渲染，file 为显示用的 Nu 文件路径
+    pub fn render(&self, file: &str) -> String {
+        self.constructs
+            .iter()
+            .map(|c| c.render("error", file))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+/// 只在特定位置作关键字的 Rust 单词（不在 escape::RUST_KEYWORDS 中）
+const WEAK_KEYWORDS: &[&str] = &["auto", "default", "macro_rules", "union"];
+
+/// 行首可以直接跟标识符或字面量的 Rust 关键字
+fn is_keyword(token: &Token) -> bool {
+    token.kind == Kind::Ident
+        && (RUST_KEYWORDS.contains(&token.text) || WEAK_KEYWORDS.contains(&token.text))
+}
+
+/// Rust 与 Nu 都不使用的标点（词法错误），如 `` ` ``、`\`、`§`
+fn is_unknown_punct(token: &Token) -> bool {
+    token.kind == Kind::Punct
+        && token
+            .text
+            .chars()
+            .any(|c| !c.is_ascii_punctuation() || matches!(c, '`' | '\\'))
+}
+
+/// 判断落入表达式转换的行是否无法归类，返回起始记号
+///
+/// 含有未知标点的行无法归类；合法的 Rust 表达式语句也不会以「非关键字标识符 + 标识符/字面量」开头，
+/// 这种形式只可能是未被识别的 Nu 关键字或缩写（如没有参数列表的 `F name`）
+pub(super) fn unrecognized(line: &str, state: LexState) -> Option<String> {
+    let (tokens, _) = tokenize(line, state);
+    if let Some(token) = tokens.iter().find(|t| is_unknown_punct(t)) {
+        return Some(token.text.to_string());
+    }
+    let mut significant = tokens.iter().filter(|t| t.is_significant());
+    let first = significant.next()?;
+    let second = significant.next()?;
+    if first.kind != Kind::Ident || is_keyword(first) {
+        return None;
+    }
+    let juxtaposed = match second.kind {
+        Kind::Ident => !is_keyword(second),
+        Kind::Literal | Kind::Lifetime => true,
+        _ => false,
+    };
+    juxtaposed.then(|| first.text.to_string())
+}
+
+/// 生成的 Rust 首个语法错误的位置（行、列从 1 开始）；能解析时返回 None
+pub(super) fn syntax_error(rust_code: &str) -> Option<(usize, usize)> {
+    let error = syn::parse_file(rust_code).err()?;
+    let start = error.span().start();
+    Some((start.line, start.column + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unrecognized() {
+        assert_eq!(
+            unrecognized("    F helper", LexState::Code),
+            Some("F".to_string())
+        );
+        assert_eq!(
+            unrecognized("rt \"oops\";", LexState::Code),
+            Some("rt".to_string())
+        );
+        // 合法的表达式语句
+        for line in [
+            "x as u8",
+            "Some(x) if x > 0 => x,",
+            "println!(\"{}\", x);",
+            "self.items.push(x);",
+            "total += x;",
+            "}",
+            "let x = 1;",
+            "x",
+            "// F helper",
+        ] {
+            assert_eq!(unrecognized(line, LexState::Code), None, "{}", line);
+        }
+        assert_eq!(
+            unrecognized("l x = `y`;", LexState::Code),
+            Some("`".to_string())
+        );
+        // 从跨行字符串中间开始
+        assert_eq!(unrecognized("F helper\";", LexState::Str), None);
+    }
+
+    #[test]
+    fn test_render() {
+        let construct = UnrecognizedConstruct {
+            span: Span::new(3, 5, 1),
+            construct: "F".to_string(),
+            source_line: "    F helper".to_string(),
+        };
+        assert_eq!(
+            construct.render("error", "src/a.nu"),
+            "error: 无法识别的 Nu 结构 `F`\n --> src/a.nu:3:5\n  |\n3 |     F helper\n  |     ^"
+        );
+    }
+}
@@ -38,6 +38,10 @@ pub struct FunctionDef {
     pub params: Vec<Param>,
     pub return_type: Option<Type>,
     pub body: Box<Expr>,
+    /// 函数签名所在的 Nu 行号（从 1 开始）
+    pub line: usize,
+    /// 函数体各语句（含末尾表达式）起始的 Nu 行号，与 body 中的语句一一对应
+    pub body_lines: Vec<usize>,
     pub is_pub: bool,
     pub is_async: bool,
     pub attributes: Vec<Attribute>,
@@ -329,6 +333,8 @@ pub enum UnOp {
 #[derive(Debug, Clone, PartialEq)]
 pub struct NuFile {
     pub items: Vec<Item>,
+    /// 每个 item 起始的 Nu 行号（1-based），与 items 一一对应，用于生成 source map
+    pub item_lines: Vec<usize>,
 }
 
 // ============ Display 实现 ============
@@ -13,6 +13,8 @@ pub struct TsCodegen {
     temp_counter: usize,
     in_function: bool,                         // 跟踪是否在函数内部
     variable_counters: HashMap<String, usize>, // 跟踪变量使用次数
+    line_mappings: Vec<(usize, usize)>,        // (ts_line, nu_line)
+    stmt_marks: Vec<(usize, usize)>,           // 当前 item 内签名与语句起始的 (ts_line, nu_line)
 }
 
 impl TsCodegen {
@@ -24,9 +26,16 @@ impl TsCodegen {
             temp_counter: 0,
             in_function: false,
             variable_counters: HashMap::new(),
+            line_mappings: Vec::new(),
+            stmt_marks: Vec::new(),
         }
     }
 
+    /// generate_file 记录的行映射 (ts_line, nu_line)：函数体内的行映射到所属语句的 Nu 行，其余行映射到 item 起始的 Nu 行
+    pub fn line_mappings(&self) -> &[(usize, usize)] {
+        &self.line_mappings
+    }
+
     /// 生成整个文件
     pub fn generate_file(&mut self, file: &NuFile) -> Result<String> {
         // 生成 runtime import
@@ -34,9 +43,27 @@ impl TsCodegen {
         self.writeln("");
 
         // 生成所有项目
-        for item in &file.items {
+        let mut ts_line = self.output.matches('\n').count() + 1;
+        for (idx, item) in file.items.iter().enumerate() {
+            let start = self.output.len();
             self.emit_item(item)?;
+            let emitted = &self.output[start..];
+            let newlines = emitted.matches('\n').count();
+            // 未以换行结尾时最后一行由下面的 writeln 结束，同样属于该 item
+            let lines = newlines + usize::from(!emitted.is_empty() && !emitted.ends_with('\n'));
+            let marks = std::mem::take(&mut self.stmt_marks);
+            if let Some(&item_line) = file.item_lines.get(idx) {
+                for line in ts_line..ts_line + lines {
+                    let nu_line = marks
+                        .iter()
+                        .rev()
+                        .find(|&&(mark, _)| mark <= line)
+                        .map_or(item_line, |&(_, nu_line)| nu_line);
+                    self.line_mappings.push((line, nu_line));
+                }
+            }
             self.writeln("");
+            ts_line += newlines + 1;
         }
 
         // 修复#8: 在最终输出前清理重复的 return
@@ -121,6 +148,7 @@ impl TsCodegen {
         let asyncc = if f.is_async { "async " } else { "" };
 
         let func_name = if f.name == "new" { "_new" } else { &f.name };
+        self.mark_line(Some(&f.line));
         // 修复#5: 函数签名始终使用完整的 function 关键字
         self.write(&format!("{}{}function {}(", export, asyncc, func_name));
 
@@ -166,7 +194,7 @@ impl TsCodegen {
         self.in_function = true;
 
         // 函数体
-        self.emit_block_body(&f.body)?;
+        self.emit_block_body_mapped(&f.body, &f.body_lines)?;
 
         // 恢复函数状态
         self.in_function = was_in_function;
@@ -549,16 +577,23 @@ impl TsCodegen {
     // ============ 块体生成 ============
 
     fn emit_block_body(&mut self, expr: &Expr) -> Result<()> {
+        self.emit_block_body_mapped(expr, &[])
+    }
+
+    /// 生成块体，lines 为各语句的 Nu 行号，用于记录语句级行映射
+    fn emit_block_body_mapped(&mut self, expr: &Expr, lines: &[usize]) -> Result<()> {
         match expr {
             Expr::Block {
                 stmts,
                 trailing_expr,
             } => {
-                for stmt in stmts {
+                for (i, stmt) in stmts.iter().enumerate() {
+                    self.mark_line(lines.get(i));
                     self.emit_stmt(stmt)?;
                 }
                 // 修复问题2: 只有在函数内部的trailing_expr才加return
                 if let Some(e) = trailing_expr {
+                    self.mark_line(lines.get(stmts.len()));
                     self.write_indent();
                     if self.in_function {
                         // 函数内部：Implicit return
@@ -590,6 +625,14 @@ impl TsCodegen {
         Ok(())
     }
 
+    /// 记录接下来生成的 TS 行（签名或语句）对应的 Nu 行
+    fn mark_line(&mut self, nu_line: Option<&usize>) {
+        if let Some(&nu_line) = nu_line {
+            let ts_line = self.output.matches('\n').count() + 1;
+            self.stmt_marks.push((ts_line, nu_line));
+        }
+    }
+
     fn is_block_expr(&self, expr: &Expr) -> bool {
         matches!(
             expr,
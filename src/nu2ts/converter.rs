@@ -5,8 +5,69 @@ use super::codegen::TsCodegen;
 use super::parser::Parser;
 use super::runtime::{generate_micro_runtime, generate_runtime_import};
 use super::types::{ConversionContext, RuntimeMode, TsConfig};
+use crate::conversion::{placeholder_diagnostics, ConversionOutput, Diagnostic, Transpile};
+use crate::sourcemap::Mapping;
 use anyhow::{Context, Result};
 
+/// TypeScript 保留字：Nu 中 `r#` 转义的同名标识符需改名
+const TS_RESERVED_WORDS: &[&str] = &[
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "import",
+    "in",
+    "instanceof",
+    "new",
+    "null",
+    "return",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "implements",
+    "interface",
+    "let",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "static",
+    "yield",
+    "await",
+];
+
+/// 去掉 `r#` 前缀，与 TS 保留字冲突时追加 `_`
+fn unraw_for_ts(nu_code: &str) -> String {
+    crate::escape::unraw_identifiers(nu_code, |name| {
+        TS_RESERVED_WORDS
+            .contains(&name)
+            .then(|| format!("{}_", name))
+    })
+}
+
 pub struct Nu2TsConverter {
     config: TsConfig,
 }
@@ -32,8 +93,14 @@ impl Nu2TsConverter {
 
     /// 主转换方法：将Nu代码转换为TypeScript（使用AST架构）
     pub fn convert(&self, nu_code: &str) -> Result<String> {
+        Ok(self.convert_with_sourcemap(nu_code)?.0)
+    }
+
+    /// 转换并返回 source map 映射（item 粒度：每个生成行映射到所属 item 起始的 Nu 行）
+    pub fn convert_with_sourcemap(&self, nu_code: &str) -> Result<(String, Vec<Mapping>)> {
         // 1. 解析 Nu 代码为 AST
-        let mut parser = Parser::new(nu_code);
+        let nu_code = unraw_for_ts(nu_code);
+        let mut parser = Parser::new(&nu_code);
         let file = parser.parse_file().context("Failed to parse Nu code")?;
 
         // 2. 生成 TypeScript 代码
@@ -41,12 +108,23 @@ impl Nu2TsConverter {
         let ts_code = codegen
             .generate_file(&file)
             .context("Failed to generate TypeScript code")?;
-
-        Ok(ts_code)
+        let mappings = codegen
+            .line_mappings()
+            .iter()
+            .map(|&(ts_line, nu_line)| Mapping {
+                generated_line: ts_line,
+                generated_col: 1,
+                source_line: nu_line,
+                source_col: 1,
+            })
+            .collect();
+        Ok((ts_code, mappings))
     }
 
     /// 旧版转换方法（兼容性保留）
     pub fn convert_legacy(&self, nu_code: &str) -> Result<String> {
+        let nu_code = unraw_for_ts(nu_code);
+        let nu_code = nu_code.as_str();
         let mut output = String::new();
         let mut context = ConversionContext::default();
 
@@ -1856,6 +1934,26 @@ impl Nu2TsConverter {
     }
 }
 
+impl Transpile for Nu2TsConverter {
+    /// 生成代码中的 `/* TODO */` 占位报告为警告
+    fn transpile(&self, nu_code: &str) -> ConversionOutput {
+        match self.convert_with_sourcemap(nu_code) {
+            Ok((code, sourcemap)) => {
+                let diagnostics = placeholder_diagnostics(&code, &sourcemap);
+                ConversionOutput {
+                    code,
+                    sourcemap,
+                    diagnostics,
+                }
+            }
+            Err(err) => ConversionOutput::failed(Diagnostic::error(
+                "conversion-failed",
+                format!("{:#}", err),
+            )),
+        }
+    }
+}
+
 impl Default for Nu2TsConverter {
     fn default() -> Self {
         Self::with_default_config()
@@ -1922,4 +2020,57 @@ mod tests {
         let result2 = converter.strip_chain_methods("s.len()");
         assert_eq!(result2, "s.length");
     }
+
+    #[test]
+    fn test_convert_with_sourcemap() {
+        let converter = Nu2TsConverter::with_default_config();
+        let nu_code = "// header\n\nF add(a: i32, b: i32) -> i32 {\n    a + b\n}\n\nF one() -> i32 {\n    1\n}\n";
+
+        let (ts_code, mappings) = converter.convert_with_sourcemap(nu_code).unwrap();
+        let ts_lines: Vec<&str> = ts_code.lines().collect();
+        let line_of = |needle: &str| ts_lines.iter().position(|l| l.contains(needle)).unwrap() + 1;
+        let nu_line_for = |ts_line: usize| {
+            mappings
+                .iter()
+                .find(|m| m.generated_line == ts_line)
+                .map(|m| m.source_line)
+        };
+
+        assert_eq!(nu_line_for(line_of("function add")), Some(3));
+        assert_eq!(nu_line_for(line_of("function one")), Some(7));
+        // runtime import 不属于任何 item
+        assert_eq!(nu_line_for(1), None);
+    }
+
+    #[test]
+    fn test_sourcemap_statement_lines() {
+        let converter = Nu2TsConverter::with_default_config();
+        let nu_code = "F calc(a: i32) -> i32 {\n    l x = a + 1\n\n    l y = x * 2\n    x + y\n}\n";
+
+        let (ts_code, mappings) = converter.convert_with_sourcemap(nu_code).unwrap();
+        let ts_lines: Vec<&str> = ts_code.lines().collect();
+        let line_of = |needle: &str| ts_lines.iter().position(|l| l.contains(needle)).unwrap() + 1;
+        let nu_line_for = |ts_line: usize| {
+            mappings
+                .iter()
+                .find(|m| m.generated_line == ts_line)
+                .map(|m| m.source_line)
+        };
+
+        assert_eq!(nu_line_for(line_of("function calc")), Some(1));
+        assert_eq!(nu_line_for(line_of("let x")), Some(2));
+        assert_eq!(nu_line_for(line_of("let y")), Some(4));
+        assert_eq!(nu_line_for(line_of("return x + y")), Some(5));
+    }
+
+    #[test]
+    fn test_raw_identifiers() {
+        let converter = Nu2TsConverter::with_default_config();
+        let nu_code = "F main() {\n    l r#l = 1;\n    l r#new = r#l + 2;\n}\n";
+
+        let ts_code = converter.convert(nu_code).unwrap();
+        assert!(ts_code.contains("let l = 1;"));
+        assert!(ts_code.contains("let new_ = l + 2;"));
+        assert!(!ts_code.contains("r#"));
+    }
 }
@@ -24,6 +24,7 @@ impl Parser {
     /// 解析整个文件为 Item 列表
     pub fn parse_file(&mut self) -> Result<NuFile> {
         let mut items = vec![];
+        let mut item_lines = vec![];
 
         while self.current_line < self.lines.len() {
             let line = self.current_line().trim().to_string();
@@ -35,14 +36,16 @@ impl Parser {
             }
 
             // 解析顶级项目
+            let start_line = self.current_line + 1;
             if let Some(item) = self.parse_item()? {
                 items.push(item);
+                item_lines.push(start_line);
             }
 
             self.advance();
         }
 
-        Ok(NuFile { items })
+        Ok(NuFile { items, item_lines })
     }
 
     /// 解析 Stmt 列表（向后兼容）
@@ -161,6 +164,7 @@ impl Parser {
     }
 
     fn parse_function(&mut self) -> Result<FunctionDef> {
+        let nu_line = self.current_line + 1;
         let line = self.current_line().trim().to_string();
         let is_pub = line.starts_with("F ");
         let content = &line[2..]; // 跳过 "F " 或 "f "
@@ -177,7 +181,7 @@ impl Parser {
         self.advance();
 
         // 解析函数体
-        let body_stmts_raw = self.parse_block_body()?;
+        let (body_stmts_raw, body_lines) = self.parse_block_body_with_lines()?;
         let (body_stmts, trailing_expr) = self.extract_trailing_expr(body_stmts_raw);
 
         Ok(FunctionDef {
@@ -188,6 +192,8 @@ impl Parser {
                 stmts: body_stmts,
                 trailing_expr,
             }),
+            line: nu_line,
+            body_lines,
             is_pub,
             is_async: false,
             attributes: vec![],
@@ -453,7 +459,13 @@ impl Parser {
     // ============ 块解析 ============
 
     fn parse_block_body(&mut self) -> Result<Vec<Stmt>> {
+        Ok(self.parse_block_body_with_lines()?.0)
+    }
+
+    /// 解析块体，同时返回每条语句起始的 Nu 行号（从 1 开始）
+    fn parse_block_body_with_lines(&mut self) -> Result<(Vec<Stmt>, Vec<usize>)> {
         let mut stmts = vec![];
+        let mut lines = vec![];
         let mut brace_depth = 0;
 
         // 检查当前行是否包含 {
@@ -524,6 +536,7 @@ impl Parser {
             let start_line = self.current_line;
             if let Some(stmt) = self.parse_stmt()? {
                 stmts.push(stmt);
+                lines.push(start_line + 1);
                 // 如果 parse_stmt 没有推进行，强制推进
                 if self.current_line == start_line {
                     self.advance();
@@ -534,7 +547,7 @@ impl Parser {
             }
         }
 
-        Ok(stmts)
+        Ok((stmts, lines))
     }
 
     /// Helper to convert Vec<Stmt> to (Vec<Stmt>, Option<Box<Expr>>) for blocks
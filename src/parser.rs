@@ -1,17 +1,78 @@
 // Nu Language Parser
 // 将Token流解析为AST
+//
+// 递归下降解析器：覆盖条目、泛型、where 子句、语句、表达式、模式、类型与属性。
+// 单个条目解析失败时记录错误并跳到下一个条目继续（错误恢复），
+// 这样一个坏条目不会中断整个文件的解析。
+
+use crate::ast::*;
+use crate::lexer::{self, SpannedToken, Token};
+use std::sync::Arc;
+use thiserror::Error;
+
+/// 语法错误
+#[derive(Debug, Clone, PartialEq, Error)]
+#[error("{span}: {message}")]
+pub struct ParseError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl ParseError {
+    /// 渲染为带源码片段的诊断信息
+    pub fn render(&self, source: &str) -> String {
+        format!(
+            "error: {}\n --> {}\n{}",
+            self.message,
+            self.span,
+            self.span.snippet(source)
+        )
+    }
+}
 
 pub struct Parser {
-    // TODO: 实现Parser
+    file: Option<Arc<str>>,
 }
 
 impl Parser {
     pub fn new() -> Self {
-        Self {}
+        Self { file: None }
     }
 
-    pub fn parse(&self, _tokens: Vec<crate::lexer::Token>) -> anyhow::Result<crate::ast::NuFile> {
-        todo!("Parser implementation")
+    /// 指定源文件路径，写入所有节点与错误的 Span
+    pub fn with_file(file: impl AsRef<str>) -> Self {
+        Self {
+            file: Some(Arc::from(file.as_ref())),
+        }
+    }
+
+    /// 解析 Nu 源码；存在任何词法/语法错误时返回 Err（包含全部错误）
+    pub fn parse(&self, source: &str) -> anyhow::Result<NuFile> {
+        let (file, errors) = self.parse_with_errors(source);
+        if errors.is_empty() {
+            Ok(file)
+        } else {
+            let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+            Err(anyhow::anyhow!("解析失败:\n{}", messages.join("\n")))
+        }
+    }
+
+    /// 解析 Nu 源码并返回尽可能完整的 AST 与全部错误（错误恢复模式）
+    pub fn parse_with_errors(&self, source: &str) -> (NuFile, Vec<ParseError>) {
+        let (tokens, lex_errors) = lexer::tokenize_with_errors(source);
+        let mut errors: Vec<ParseError> = lex_errors
+            .into_iter()
+            .map(|e| ParseError {
+                message: format!("{} `{}`", e.kind, e.text),
+                span: Span::new(e.line, e.col, e.span.len()).with_file(self.file.clone()),
+            })
+            .collect();
+
+        let mut state = ParseState::new(source, tokens, self.file.clone());
+        let file = state.parse_file();
+        errors.extend(state.errors);
+        errors.sort_by_key(|e| (e.span.line, e.span.col));
+        (file, errors)
     }
 }
 
@@ -20,3 +81,3908 @@ impl Default for Parser {
         Self::new()
     }
 }
+
+type PResult<T> = Result<T, ParseError>;
+
+/// 函数限定符：const / ~ (async) / unsafe
+#[derive(Default)]
+struct FnQualifiers {
+    is_const: bool,
+    is_async: bool,
+    is_unsafe: bool,
+    /// EXT "C" f
+    abi: Option<String>,
+}
+
+/// trait / impl 块中的关联条目
+enum AssocItem {
+    Fn(NuFn),
+    Const(NuConst),
+    Type(NuTypeAlias),
+    Macro(NuMacroItem),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum PathMode {
+    /// 类型位置：`V<T>` 直接带泛型参数
+    Type,
+    /// 表达式/模式位置：泛型参数必须用 turbofish `::<T>`
+    Expr,
+}
+
+// 二元运算符优先级（数值越大结合越紧）
+const PREC_OR: u8 = 2;
+const PREC_AND: u8 = 3;
+const PREC_CMP: u8 = 4;
+const PREC_BIT_OR: u8 = 5;
+const PREC_BIT_XOR: u8 = 6;
+const PREC_BIT_AND: u8 = 7;
+const PREC_SHIFT: u8 = 8;
+const PREC_ADD: u8 = 9;
+const PREC_MUL: u8 = 10;
+const PREC_CAST: u8 = 11;
+
+struct ParseState<'a> {
+    src: &'a str,
+    file: Option<Arc<str>>,
+    tokens: Vec<SpannedToken>,
+    pos: usize,
+    errors: Vec<ParseError>,
+    /// 条件位置（if / while / M / L 迭代器）禁止结构体字面量
+    no_struct: bool,
+}
+
+impl<'a> ParseState<'a> {
+    fn new(src: &'a str, tokens: Vec<SpannedToken>, file: Option<Arc<str>>) -> Self {
+        Self {
+            src,
+            file,
+            tokens,
+            pos: 0,
+            errors: Vec::new(),
+            no_struct: false,
+        }
+    }
+
+    // ============ Token 游标 ============
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|t| &t.token)
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + n).map(|t| &t.token)
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    fn check(&self, token: &Token) -> bool {
+        self.peek() == Some(token)
+    }
+
+    fn check_nth(&self, n: usize, token: &Token) -> bool {
+        self.peek_nth(n) == Some(token)
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.check(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn bump(&mut self) -> Token {
+        let token = self.tokens[self.pos].token.clone();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, token: &Token) -> PResult<()> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.error_expected(&format!("`{}`", token.text())))
+        }
+    }
+
+    fn is_word(&self, word: &str) -> bool {
+        self.is_word_nth(0, word)
+    }
+
+    fn is_word_nth(&self, n: usize, word: &str) -> bool {
+        matches!(self.peek_nth(n), Some(Token::Ident(s)) if s == word)
+    }
+
+    fn eat_word(&mut self, word: &str) -> bool {
+        if self.is_word(word) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn is_ident_nth(&self, n: usize) -> bool {
+        self.peek_nth(n).and_then(|t| t.as_ident()).is_some()
+    }
+
+    fn expect_ident(&mut self) -> PResult<String> {
+        match self.peek().and_then(|t| t.as_ident()) {
+            Some(name) => {
+                let name = name.to_string();
+                self.pos += 1;
+                Ok(name)
+            }
+            None => Err(self.error_expected("标识符")),
+        }
+    }
+
+    /// 当前 Token 是否位于新的一行（Nu 允许用换行结束语句）
+    fn on_new_line(&self) -> bool {
+        if self.pos == 0 {
+            return true;
+        }
+        match self.tokens.get(self.pos) {
+            Some(tok) => tok.line > self.tokens[self.pos - 1].line,
+            None => true,
+        }
+    }
+
+    /// 当前 Token 是否与前一个 Token 紧贴（无空白）
+    fn is_adjacent(&self) -> bool {
+        self.is_adjacent_nth(0)
+    }
+
+    fn is_adjacent_nth(&self, n: usize) -> bool {
+        let i = self.pos + n;
+        i > 0 && i < self.tokens.len() && self.tokens[i - 1].span.end == self.tokens[i].span.start
+    }
+
+    fn span(&self, line: usize, col: usize, len: usize) -> Span {
+        Span::new(line, col, len).with_file(self.file.clone())
+    }
+
+    fn span_from(&self, start: usize) -> Span {
+        let Some(first) = self.tokens.get(start) else {
+            return self.eof_span();
+        };
+        let end = if self.pos > start {
+            self.tokens[self.pos - 1].span.end
+        } else {
+            first.span.end
+        };
+        self.span(first.line, first.col, end.saturating_sub(first.span.start))
+    }
+
+    fn eof_span(&self) -> Span {
+        match self.tokens.last() {
+            Some(last) => self.span(last.line, last.col + last.span.len(), 0),
+            None => self.span(1, 1, 0),
+        }
+    }
+
+    /// 从 start Token 到当前位置的源码文本
+    fn text_from(&self, start: usize) -> String {
+        if self.pos <= start {
+            return String::new();
+        }
+        let begin = self.tokens[start].span.start;
+        let end = self.tokens[self.pos - 1].span.end;
+        self.src[begin..end].to_string()
+    }
+
+    fn error_at_current(&self, message: String) -> ParseError {
+        let span = match self.tokens.get(self.pos) {
+            Some(tok) => self.span(tok.line, tok.col, tok.span.len()),
+            None => self.eof_span(),
+        };
+        ParseError { message, span }
+    }
+
+    fn error_expected(&self, what: &str) -> ParseError {
+        let found = match self.peek() {
+            Some(tok) => format!("`{}`", tok.text()),
+            None => "文件末尾".to_string(),
+        };
+        self.error_at_current(format!("期望{}，找到 {}", what, found))
+    }
+
+    /// 在条件位置临时禁止结构体字面量
+    fn with_no_struct<T>(&mut self, f: impl FnOnce(&mut Self) -> PResult<T>) -> PResult<T> {
+        let saved = self.no_struct;
+        self.no_struct = true;
+        let result = f(self);
+        self.no_struct = saved;
+        result
+    }
+
+    /// 在括号内恢复结构体字面量
+    fn with_struct<T>(&mut self, f: impl FnOnce(&mut Self) -> PResult<T>) -> PResult<T> {
+        let saved = self.no_struct;
+        self.no_struct = false;
+        let result = f(self);
+        self.no_struct = saved;
+        result
+    }
+
+    // ============ 文件与条目 ============
+
+    fn parse_file(&mut self) -> NuFile {
+        let attrs = self.parse_inner_attrs();
+        let items = self.parse_items(false);
+        NuFile { attrs, items }
+    }
+
+    /// 解析条目序列；in_block 为 true 时遇到 `}` 停止（不消耗）
+    fn parse_items(&mut self, in_block: bool) -> Vec<NuItem> {
+        let mut items = Vec::new();
+        loop {
+            while self.eat(&Token::Semi) {}
+            match self.peek() {
+                None => break,
+                Some(Token::RBrace) if in_block => break,
+                _ => {}
+            }
+            let start = self.pos;
+            match self.parse_item() {
+                Ok(item) => items.push(item),
+                Err(e) => {
+                    self.errors.push(e);
+                    self.recover(start);
+                }
+            }
+        }
+        items
+    }
+
+    /// 错误恢复：跳过当前条目（括号平衡），停在下一个条目开头或外层 `}` 前
+    fn recover(&mut self, start: usize) {
+        self.pos = start;
+        let mut depth = 0usize;
+        while let Some(tok) = self.peek() {
+            if self.pos > start && depth == 0 && self.on_new_line() && self.is_item_keyword() {
+                break;
+            }
+            match tok {
+                Token::LBrace | Token::LParen | Token::LBracket => depth += 1,
+                Token::RBrace | Token::RParen | Token::RBracket => {
+                    if depth == 0 {
+                        // 多余的闭括号：作为条目开头时必须消耗，否则会死循环
+                        if self.pos == start {
+                            self.pos += 1;
+                        }
+                        break;
+                    }
+                    depth -= 1;
+                    if depth == 0 && matches!(tok, Token::RBrace) {
+                        self.pos += 1;
+                        break;
+                    }
+                }
+                _ => {}
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn is_item_keyword(&self) -> bool {
+        match self.peek() {
+            Some(
+                Token::FnPub
+                | Token::FnPriv
+                | Token::StructPub
+                | Token::StructPriv
+                | Token::UnionPub
+                | Token::UnionPriv
+                | Token::EnumPub
+                | Token::EnumPriv
+                | Token::TraitPub
+                | Token::TraitPriv
+                | Token::Impl
+                | Token::Mod
+                | Token::ModPub
+                | Token::Use
+                | Token::PubUse
+                | Token::Const
+                | Token::ConstPub
+                | Token::Static
+                | Token::StaticPub
+                | Token::StaticMut
+                | Token::StaticMutPub
+                | Token::Extern
+                | Token::Derive
+                | Token::Inline
+                | Token::Pound
+                | Token::DocComment(_)
+                | Token::Async,
+            ) => true,
+            Some(Token::Ident(w)) => {
+                matches!(
+                    w.as_str(),
+                    "pub"
+                        | "unsafe"
+                        | "const"
+                        | "static"
+                        | "type"
+                        | "extern"
+                        | "async"
+                        | "macro_rules"
+                )
+            }
+            _ => false,
+        }
+    }
+
+    fn parse_item(&mut self) -> PResult<NuItem> {
+        let start = self.pos;
+        let attrs = self.parse_outer_attrs()?;
+        let vis = self.parse_visibility()?;
+        self.parse_item_after(start, attrs, vis)
+    }
+
+    /// 属性与可见性之后的条目主体
+    fn parse_item_after(
+        &mut self,
+        start: usize,
+        attrs: Vec<NuAttr>,
+        vis: Visibility,
+    ) -> PResult<NuItem> {
+        let quals = self.parse_fn_qualifiers();
+
+        match self.peek() {
+            Some(Token::FnPub | Token::FnPriv) => {
+                Ok(NuItem::Fn(self.parse_fn(start, attrs, vis, quals)?))
+            }
+            Some(Token::StructPub | Token::StructPriv) => {
+                Ok(NuItem::Struct(self.parse_struct(start, attrs, vis)?))
+            }
+            Some(Token::UnionPub | Token::UnionPriv) => {
+                Ok(NuItem::Union(self.parse_union(start, attrs, vis)?))
+            }
+            Some(Token::EnumPub | Token::EnumPriv) => {
+                Ok(NuItem::Enum(self.parse_enum(start, attrs, vis)?))
+            }
+            Some(Token::TraitPub | Token::TraitPriv) => {
+                self.parse_trait(start, attrs, vis, quals.is_unsafe)
+            }
+            Some(Token::Impl) => Ok(NuItem::Impl(self.parse_impl(
+                start,
+                attrs,
+                quals.is_unsafe,
+            )?)),
+            Some(Token::Mod | Token::ModPub) => Ok(NuItem::Mod(self.parse_mod(start, attrs, vis)?)),
+            Some(Token::Use | Token::PubUse) => Ok(NuItem::Use(self.parse_use(start, attrs, vis)?)),
+            _ if self.is_const_start() => Ok(NuItem::Const(self.parse_const(start, attrs, vis)?)),
+            _ if self.is_static_start() => {
+                Ok(NuItem::Static(self.parse_static(start, attrs, vis, true)?))
+            }
+            _ if self.is_type_alias_start() => {
+                Ok(NuItem::TypeAlias(self.parse_type_alias(start, attrs, vis)?))
+            }
+            _ if self.is_extern_crate_start() => Ok(NuItem::ExternCrate(
+                self.parse_extern_crate(start, attrs, vis)?,
+            )),
+            _ if self.is_extern_block_start() => Ok(NuItem::Extern(self.parse_extern(
+                start,
+                attrs,
+                quals.is_unsafe,
+            )?)),
+            Some(Token::Ident(_)) if self.is_macro_call_start() => {
+                Ok(NuItem::Macro(self.parse_macro_item(start, attrs)?))
+            }
+            _ => Err(self.error_expected("条目（F/f/S/E/TR/I/D/u/...）")),
+        }
+    }
+
+    fn parse_fn_qualifiers(&mut self) -> FnQualifiers {
+        let mut quals = FnQualifiers::default();
+        loop {
+            match self.peek() {
+                Some(Token::Async) => {
+                    quals.is_async = true;
+                    self.pos += 1;
+                }
+                Some(Token::Ident(w)) if w == "async" && !self.check_nth(1, &Token::LBrace) => {
+                    quals.is_async = true;
+                    self.pos += 1;
+                }
+                Some(Token::Ident(w)) if w == "unsafe" && !self.check_nth(1, &Token::LBrace) => {
+                    quals.is_unsafe = true;
+                    self.pos += 1;
+                }
+                Some(Token::Extern | Token::Ident(_)) if self.is_extern_fn_start() => {
+                    self.pos += 1;
+                    quals.abi = Some(match self.peek() {
+                        Some(Token::StringLit(abi)) => {
+                            let abi = abi.clone();
+                            self.pos += 1;
+                            abi
+                        }
+                        _ => String::new(),
+                    });
+                }
+                Some(Token::Ident(w))
+                    if w == "const"
+                        && (matches!(
+                            self.peek_nth(1),
+                            Some(Token::FnPub | Token::FnPriv | Token::Async)
+                        ) || self.is_word_nth(1, "unsafe")) =>
+                {
+                    quals.is_const = true;
+                    self.pos += 1;
+                }
+                _ => break,
+            }
+        }
+        quals
+    }
+
+    /// `path!(...)` / `macro_rules! name { ... }`
+    fn is_macro_call_start(&self) -> bool {
+        let mut i = 0;
+        if self.check(&Token::PathSep) {
+            i += 1;
+        }
+        while self.is_ident_nth(i) && self.check_nth(i + 1, &Token::PathSep) {
+            i += 2;
+        }
+        self.is_ident_nth(i) && self.check_nth(i + 1, &Token::Bang)
+    }
+
+    fn is_extern_kw(&self, n: usize) -> bool {
+        self.check_nth(n, &Token::Extern) || self.is_word_nth(n, "extern")
+    }
+
+    /// EXT crate foo;
+    fn is_extern_crate_start(&self) -> bool {
+        self.is_extern_kw(0) && self.is_word_nth(1, "crate") && self.is_ident_nth(2)
+    }
+
+    /// EXT { ... } / EXT "C" { ... }
+    fn is_extern_block_start(&self) -> bool {
+        if !self.is_extern_kw(0) {
+            return false;
+        }
+        let n = if matches!(self.peek_nth(1), Some(Token::StringLit(_))) {
+            2
+        } else {
+            1
+        };
+        self.check_nth(n, &Token::LBrace)
+    }
+
+    /// EXT f / EXT "C" F / extern "C" fn
+    fn is_extern_fn_start(&self) -> bool {
+        if !self.is_extern_kw(0) {
+            return false;
+        }
+        let n = if matches!(self.peek_nth(1), Some(Token::StringLit(_))) {
+            2
+        } else {
+            1
+        };
+        matches!(
+            self.peek_nth(n),
+            Some(Token::FnPub | Token::FnPriv | Token::Async)
+        ) || self.is_word_nth(n, "unsafe")
+            || self.is_word_nth(n, "async")
+    }
+
+    /// C NAME: T / const NAME: T
+    fn is_const_start(&self) -> bool {
+        let keyword =
+            matches!(self.peek(), Some(Token::Const | Token::ConstPub)) || self.is_word("const");
+        keyword && self.is_ident_nth(1) && self.check_nth(2, &Token::Colon)
+    }
+
+    /// ST / SP / SM / SMP / static [mut]
+    fn is_static_start(&self) -> bool {
+        match self.peek() {
+            Some(Token::Static | Token::StaticPub | Token::StaticMut | Token::StaticMutPub) => {
+                self.is_ident_nth(1) && self.check_nth(2, &Token::Colon)
+            }
+            Some(Token::Ident(w)) if w == "static" => {
+                let n = if self.is_word_nth(1, "mut") { 2 } else { 1 };
+                self.is_ident_nth(n) && self.check_nth(n + 1, &Token::Colon)
+            }
+            _ => false,
+        }
+    }
+
+    /// t Name = T / type Name<T> = U（`t += 1` 等赋值不是类型别名）
+    fn is_type_alias_start(&self) -> bool {
+        (self.check(&Token::Type) || self.is_word("type"))
+            && self.is_ident_nth(1)
+            && matches!(
+                self.peek_nth(2),
+                Some(Token::Eq | Token::LessThanOrReturn | Token::Colon | Token::Semi)
+                    | Some(Token::Where)
+            )
+    }
+
+    fn parse_visibility(&mut self) -> PResult<Visibility> {
+        if !self.eat_word("pub") {
+            return Ok(Visibility::Private);
+        }
+        if !self.check(&Token::LParen) {
+            return Ok(Visibility::Public);
+        }
+        let vis = if self.is_word_nth(1, "crate") && self.check_nth(2, &Token::RParen) {
+            Visibility::Crate
+        } else if self.is_word_nth(1, "super") && self.check_nth(2, &Token::RParen) {
+            Visibility::Super
+        } else if self.is_word_nth(1, "self") && self.check_nth(2, &Token::RParen) {
+            Visibility::SelfMod
+        } else if self.is_word_nth(1, "in") {
+            self.pos += 2;
+            let start = self.pos;
+            self.parse_path(PathMode::Expr)?;
+            let path = self.text_from(start);
+            self.expect(&Token::RParen)?;
+            return Ok(Visibility::In(path));
+        } else {
+            // `pub (A, B)` 元组字段类型，不是受限可见性
+            return Ok(Visibility::Public);
+        };
+        self.pos += 3;
+        Ok(vis)
+    }
+
+    // ============ 属性 ============
+
+    fn parse_outer_attrs(&mut self) -> PResult<Vec<NuAttr>> {
+        let mut attrs = Vec::new();
+        loop {
+            let start = self.pos;
+            let kind = match self.peek() {
+                Some(Token::DocComment(doc)) => {
+                    let doc = doc.clone();
+                    self.pos += 1;
+                    AttrKind::Doc(doc)
+                }
+                Some(Token::Inline) => {
+                    self.pos += 1;
+                    AttrKind::Inline
+                }
+                Some(Token::Derive) => {
+                    self.pos += 1;
+                    self.expect(&Token::LParen)?;
+                    let mut derives = Vec::new();
+                    while !self.eat(&Token::RParen) {
+                        let path_start = self.pos;
+                        self.parse_path(PathMode::Expr)?;
+                        derives.push(self.text_from(path_start));
+                        if !self.eat(&Token::Comma) {
+                            self.expect(&Token::RParen)?;
+                            break;
+                        }
+                    }
+                    AttrKind::Derive(derives)
+                }
+                Some(Token::Pound) if self.check_nth(1, &Token::LBracket) => {
+                    self.pos += 1;
+                    AttrKind::Meta(self.parse_attr_meta()?)
+                }
+                _ => break,
+            };
+            attrs.push(NuAttr {
+                kind,
+                is_inner: false,
+                span: self.span_from(start),
+            });
+        }
+        Ok(attrs)
+    }
+
+    /// #![...] 与 //! 文档；格式错误时记录错误并停止
+    fn parse_inner_attrs(&mut self) -> Vec<NuAttr> {
+        let mut attrs = Vec::new();
+        loop {
+            let start = self.pos;
+            let kind = match self.peek() {
+                Some(Token::InnerDocComment(doc)) => {
+                    let doc = doc.clone();
+                    self.pos += 1;
+                    AttrKind::Doc(doc)
+                }
+                Some(Token::Pound)
+                    if self.check_nth(1, &Token::Bang) && self.check_nth(2, &Token::LBracket) =>
+                {
+                    self.pos += 2;
+                    match self.parse_attr_meta() {
+                        Ok(meta) => AttrKind::Meta(meta),
+                        Err(e) => {
+                            self.errors.push(e);
+                            break;
+                        }
+                    }
+                }
+                _ => break,
+            };
+            attrs.push(NuAttr {
+                kind,
+                is_inner: true,
+                span: self.span_from(start),
+            });
+        }
+        attrs
+    }
+
+    /// `[ ... ]`，返回方括号内的原始源码
+    fn parse_attr_meta(&mut self) -> PResult<String> {
+        let open = self.pos;
+        let close = self.skip_delimited()?;
+        let begin = self.tokens[open].span.end;
+        let end = self.tokens[close].span.start;
+        Ok(self.src[begin..end].trim().to_string())
+    }
+
+    /// 跳过一个平衡的 (...) / [...] / {...}，返回闭括号的 Token 下标
+    fn skip_delimited(&mut self) -> PResult<usize> {
+        let open = self.pos;
+        if !matches!(
+            self.peek(),
+            Some(Token::LParen | Token::LBracket | Token::LBrace)
+        ) {
+            return Err(self.error_expected("`(`、`[` 或 `{`"));
+        }
+        let mut depth = 0usize;
+        while let Some(tok) = self.peek() {
+            match tok {
+                Token::LParen | Token::LBracket | Token::LBrace => depth += 1,
+                Token::RParen | Token::RBracket | Token::RBrace => {
+                    depth -= 1;
+                    if depth == 0 {
+                        let close = self.pos;
+                        self.pos += 1;
+                        return Ok(close);
+                    }
+                }
+                _ => {}
+            }
+            self.pos += 1;
+        }
+        let tok = &self.tokens[open];
+        Err(ParseError {
+            message: format!("未闭合的 `{}`", tok.token.text()),
+            span: self.span(tok.line, tok.col, 1),
+        })
+    }
+
+    // ============ 函数 ============
+
+    fn parse_fn(
+        &mut self,
+        start: usize,
+        attrs: Vec<NuAttr>,
+        vis: Visibility,
+        quals: FnQualifiers,
+    ) -> PResult<NuFn> {
+        let is_pub_kw = matches!(self.bump(), Token::FnPub);
+        let name = self.expect_ident()?;
+        let mut generics = self.parse_generic_params()?;
+        self.expect(&Token::LParen)?;
+        let (self_param, params, variadic) = self.with_struct(|p| p.parse_fn_params())?;
+        let ret = if self.eat(&Token::Arrow) {
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+        generics.where_clause = self.parse_where_clause()?;
+        let body = if self.check(&Token::LBrace) {
+            Some(self.parse_block()?)
+        } else {
+            self.eat(&Token::Semi);
+            None
+        };
+
+        let vis = keyword_vis(is_pub_kw, vis);
+        Ok(NuFn {
+            attrs,
+            name,
+            is_public: vis.is_public(),
+            vis,
+            is_async: quals.is_async,
+            is_unsafe: quals.is_unsafe,
+            is_const: quals.is_const,
+            abi: quals.abi,
+            generics,
+            self_param,
+            params,
+            variadic,
+            ret,
+            body,
+            span: self.span_from(start),
+        })
+    }
+
+    /// 参数列表（`(` 已消耗，消耗到 `)`）；末尾的 `...` 记为可变参数
+    fn parse_fn_params(&mut self) -> PResult<(Option<SelfParam>, Vec<Param>, bool)> {
+        let self_param = self.parse_self_param()?;
+        if self_param.is_some() && !self.eat(&Token::Comma) {
+            self.expect(&Token::RParen)?;
+            return Ok((self_param, Vec::new(), false));
+        }
+
+        let mut params = Vec::new();
+        while !self.eat(&Token::RParen) {
+            if self.eat(&Token::DotDotDot) {
+                self.eat(&Token::Comma);
+                self.expect(&Token::RParen)?;
+                return Ok((self_param, params, true));
+            }
+            let start = self.pos;
+            let attrs = self.parse_outer_attrs()?;
+            let pat = self.parse_pat_no_alt()?;
+            self.expect(&Token::Colon)?;
+            let ty = self.parse_type()?;
+            params.push(Param {
+                attrs,
+                pat,
+                ty,
+                span: self.span_from(start),
+            });
+            if !self.eat(&Token::Comma) {
+                self.expect(&Token::RParen)?;
+                break;
+            }
+        }
+        Ok((self_param, params, false))
+    }
+
+    /// self / !self / mut self / &self / &!self / &'a mut self / self: Type
+    fn parse_self_param(&mut self) -> PResult<Option<SelfParam>> {
+        let is_self_at =
+            |p: &Self, n: usize| p.is_word_nth(n, "self") && !p.check_nth(n + 1, &Token::PathSep);
+
+        let (skip, param) = match self.peek() {
+            Some(Token::RefMut) if is_self_at(self, 1) => (
+                2,
+                SelfParam::Ref {
+                    lifetime: None,
+                    mutable: true,
+                },
+            ),
+            Some(Token::Amp) => {
+                let mut n = 1;
+                let lifetime = match self.peek_nth(n) {
+                    Some(Token::Lifetime(l)) => {
+                        n += 1;
+                        Some(l.clone())
+                    }
+                    _ => None,
+                };
+                let mutable = self.is_word_nth(n, "mut") || self.check_nth(n, &Token::Bang);
+                if mutable {
+                    n += 1;
+                }
+                if !is_self_at(self, n) {
+                    return Ok(None);
+                }
+                (n + 1, SelfParam::Ref { lifetime, mutable })
+            }
+            Some(Token::Bang) if is_self_at(self, 1) => (2, SelfParam::Value { mutable: true }),
+            Some(Token::Ident(w)) if w == "mut" && is_self_at(self, 1) => {
+                (2, SelfParam::Value { mutable: true })
+            }
+            Some(Token::Ident(w)) if w == "self" && is_self_at(self, 0) => {
+                (1, SelfParam::Value { mutable: false })
+            }
+            _ => return Ok(None),
+        };
+        self.pos += skip;
+
+        if let SelfParam::Value { mutable } = param {
+            if self.eat(&Token::Colon) {
+                let ty = self.parse_type()?;
+                return Ok(Some(SelfParam::Typed { mutable, ty }));
+            }
+        }
+        Ok(Some(param))
+    }
+
+    // ============ 泛型 ============
+
+    fn parse_generic_params(&mut self) -> PResult<NuGenerics> {
+        let mut generics = NuGenerics::default();
+        if !self.eat(&Token::LessThanOrReturn) {
+            return Ok(generics);
+        }
+        while !self.eat_closing_angle() {
+            let start = self.pos;
+            self.parse_outer_attrs()?;
+            let param = match self.peek() {
+                Some(Token::Lifetime(name)) => {
+                    let name = name.clone();
+                    self.pos += 1;
+                    let bounds = if self.eat(&Token::Colon) {
+                        self.parse_lifetime_bounds()
+                    } else {
+                        Vec::new()
+                    };
+                    GenericParam::Lifetime {
+                        name,
+                        bounds,
+                        span: self.span_from(start),
+                    }
+                }
+                Some(Token::Ident(w)) if w == "const" && self.is_ident_nth(1) => {
+                    self.pos += 1;
+                    let name = self.expect_ident()?;
+                    self.expect(&Token::Colon)?;
+                    let ty = self.parse_type()?;
+                    let default = if self.eat(&Token::Eq) {
+                        Some(self.parse_const_arg()?)
+                    } else {
+                        None
+                    };
+                    GenericParam::Const {
+                        name,
+                        ty,
+                        default,
+                        span: self.span_from(start),
+                    }
+                }
+                _ => {
+                    let name = self.expect_ident()?;
+                    let bounds = if self.eat(&Token::Colon) {
+                        self.parse_bounds()?
+                    } else {
+                        Vec::new()
+                    };
+                    let default = if self.eat(&Token::Eq) {
+                        Some(self.parse_type()?)
+                    } else {
+                        None
+                    };
+                    GenericParam::Type {
+                        name,
+                        bounds,
+                        default,
+                        span: self.span_from(start),
+                    }
+                }
+            };
+            generics.params.push(param);
+            if !self.eat(&Token::Comma) {
+                if !self.eat_closing_angle() {
+                    return Err(self.error_expected("`>`"));
+                }
+                break;
+            }
+        }
+        Ok(generics)
+    }
+
+    /// 消耗一个 `>`；`>=`、`>>`、`>>=` 会拆出剩余部分（如 `V<i32>= V::new()`、`V<V<i32>>`）
+    fn eat_closing_angle(&mut self) -> bool {
+        let rest = match self.peek() {
+            Some(Token::GreaterThan) => {
+                self.pos += 1;
+                return true;
+            }
+            Some(Token::GreaterEq) => Token::Eq,
+            Some(Token::Shr) => Token::GreaterThan,
+            Some(Token::ShrEq) => Token::GreaterEq,
+            _ => return false,
+        };
+        let tok = &mut self.tokens[self.pos];
+        tok.token = rest;
+        tok.span.start += 1;
+        tok.col += 1;
+        true
+    }
+
+    fn parse_lifetime_bounds(&mut self) -> Vec<String> {
+        let mut bounds = Vec::new();
+        while let Some(Token::Lifetime(l)) = self.peek() {
+            bounds.push(l.clone());
+            self.pos += 1;
+            if !self.eat(&Token::Plus) {
+                break;
+            }
+        }
+        bounds
+    }
+
+    /// for<'a, 'b>
+    fn parse_for_lifetimes(&mut self) -> PResult<Vec<String>> {
+        if !(self.is_word("for") && self.check_nth(1, &Token::LessThanOrReturn)) {
+            return Ok(Vec::new());
+        }
+        self.pos += 2;
+        let mut lifetimes = Vec::new();
+        while !self.eat_closing_angle() {
+            match self.peek() {
+                Some(Token::Lifetime(l)) => {
+                    lifetimes.push(l.clone());
+                    self.pos += 1;
+                }
+                _ => return Err(self.error_expected("生命周期")),
+            }
+            if !self.eat(&Token::Comma) {
+                if !self.eat_closing_angle() {
+                    return Err(self.error_expected("`>`"));
+                }
+                break;
+            }
+        }
+        Ok(lifetimes)
+    }
+
+    /// Clone + Send + 'a + ?Sized
+    fn parse_bounds(&mut self) -> PResult<Vec<TypeBound>> {
+        let mut bounds = Vec::new();
+        loop {
+            match self.peek() {
+                Some(Token::Lifetime(l)) => {
+                    bounds.push(TypeBound::Lifetime(l.clone()));
+                    self.pos += 1;
+                }
+                Some(Token::Question) => {
+                    self.pos += 1;
+                    let path = self.parse_path(PathMode::Type)?;
+                    bounds.push(TypeBound::Trait {
+                        path,
+                        maybe: true,
+                        for_lifetimes: Vec::new(),
+                    });
+                }
+                Some(Token::LParen) => {
+                    self.pos += 1;
+                    let inner = self.parse_bounds()?;
+                    self.expect(&Token::RParen)?;
+                    bounds.extend(inner);
+                }
+                Some(Token::PathSep) => {
+                    let path = self.parse_path(PathMode::Type)?;
+                    bounds.push(TypeBound::Trait {
+                        path,
+                        maybe: false,
+                        for_lifetimes: Vec::new(),
+                    });
+                }
+                Some(tok) if tok.as_ident().is_some() => {
+                    let for_lifetimes = self.parse_for_lifetimes()?;
+                    let path = self.parse_path(PathMode::Type)?;
+                    bounds.push(TypeBound::Trait {
+                        path,
+                        maybe: false,
+                        for_lifetimes,
+                    });
+                }
+                _ => break,
+            }
+            if !self.eat(&Token::Plus) {
+                break;
+            }
+        }
+        Ok(bounds)
+    }
+
+    /// wh T: Clone, U: Debug（也接受 Rust 的 where）
+    fn parse_where_clause(&mut self) -> PResult<Vec<WherePredicate>> {
+        if !(self.eat(&Token::Where) || self.eat_word("where")) {
+            return Ok(Vec::new());
+        }
+        let mut predicates = Vec::new();
+        loop {
+            match self.peek() {
+                None | Some(Token::LBrace | Token::Semi | Token::RBrace) => break,
+                _ => {}
+            }
+            let start = self.pos;
+            let pred = if let Some(Token::Lifetime(name)) = self.peek() {
+                let name = name.clone();
+                self.pos += 1;
+                self.expect(&Token::Colon)?;
+                WherePredicate::Lifetime {
+                    name,
+                    bounds: self.parse_lifetime_bounds(),
+                    span: self.span_from(start),
+                }
+            } else {
+                let for_lifetimes = self.parse_for_lifetimes()?;
+                let ty = self.parse_type()?;
+                self.expect(&Token::Colon)?;
+                let bounds = self.parse_bounds()?;
+                WherePredicate::Type {
+                    ty,
+                    bounds,
+                    for_lifetimes,
+                    span: self.span_from(start),
+                }
+            };
+            predicates.push(pred);
+            if !self.eat(&Token::Comma) {
+                break;
+            }
+        }
+        Ok(predicates)
+    }
+
+    // ============ 路径 ============
+
+    fn parse_path(&mut self, mode: PathMode) -> PResult<NuPath> {
+        let start = self.pos;
+        let leading_colon = self.eat(&Token::PathSep);
+        let mut segments = Vec::new();
+        loop {
+            let ident = self.expect_ident()?;
+            let mut args = None;
+            if mode == PathMode::Type {
+                if self.check(&Token::LessThanOrReturn) {
+                    args = Some(self.parse_generic_args(false)?);
+                } else if self.check(&Token::PathSep) && self.check_nth(1, &Token::LessThanOrReturn)
+                {
+                    self.pos += 1;
+                    args = Some(self.parse_generic_args(true)?);
+                } else if self.check(&Token::LParen) && is_fn_trait(&ident) {
+                    args = Some(self.parse_parenthesized_args()?);
+                }
+            } else if self.check(&Token::PathSep) && self.check_nth(1, &Token::LessThanOrReturn) {
+                self.pos += 1;
+                args = Some(self.parse_generic_args(true)?);
+            }
+            segments.push(PathSegment { ident, args });
+
+            if self.check(&Token::PathSep) && self.is_ident_nth(1) {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        Ok(NuPath {
+            leading_colon,
+            segments,
+            span: self.span_from(start),
+        })
+    }
+
+    /// <T, 'a, Item = U>（当前位于 `<`）
+    fn parse_generic_args(&mut self, turbofish: bool) -> PResult<GenericArgs> {
+        self.expect(&Token::LessThanOrReturn)?;
+        let mut args = Vec::new();
+        while !self.eat_closing_angle() {
+            let arg = match self.peek() {
+                Some(Token::Lifetime(l)) => {
+                    let l = l.clone();
+                    self.pos += 1;
+                    GenericArg::Lifetime(l)
+                }
+                Some(Token::LBrace | Token::Minus) => GenericArg::Const(self.parse_const_arg()?),
+                Some(tok) if tok.is_literal() => GenericArg::Const(self.parse_const_arg()?),
+                Some(Token::Ident(w)) if w == "true" || w == "false" => {
+                    GenericArg::Const(self.parse_const_arg()?)
+                }
+                _ if self.is_ident_nth(0) && self.check_nth(1, &Token::Eq) => {
+                    let name = self.expect_ident()?;
+                    self.pos += 1;
+                    GenericArg::Binding {
+                        name,
+                        ty: self.parse_type()?,
+                    }
+                }
+                _ if self.is_ident_nth(0) && self.check_nth(1, &Token::Colon) => {
+                    let name = self.expect_ident()?;
+                    self.pos += 1;
+                    GenericArg::Constraint {
+                        name,
+                        bounds: self.parse_bounds()?,
+                    }
+                }
+                _ => GenericArg::Type(self.parse_type()?),
+            };
+            args.push(arg);
+            if !self.eat(&Token::Comma) {
+                if !self.eat_closing_angle() {
+                    return Err(self.error_expected("`>`"));
+                }
+                break;
+            }
+        }
+        Ok(GenericArgs::AngleBracketed { args, turbofish })
+    }
+
+    /// Fn(A, B) -> C
+    fn parse_parenthesized_args(&mut self) -> PResult<GenericArgs> {
+        self.expect(&Token::LParen)?;
+        let mut inputs = Vec::new();
+        while !self.eat(&Token::RParen) {
+            inputs.push(self.parse_type()?);
+            if !self.eat(&Token::Comma) {
+                self.expect(&Token::RParen)?;
+                break;
+            }
+        }
+        let output = if self.eat(&Token::Arrow) {
+            Some(Box::new(self.parse_type_no_bounds()?))
+        } else {
+            None
+        };
+        Ok(GenericArgs::Parenthesized { inputs, output })
+    }
+
+    /// 常量泛型参数：字面量、-1 或 { expr }
+    fn parse_const_arg(&mut self) -> PResult<NuExpr> {
+        if self.check(&Token::LBrace) {
+            let start = self.pos;
+            let block = self.parse_block()?;
+            return Ok(self.mk_expr(start, ExprKind::Block { label: None, block }));
+        }
+        self.parse_unary()
+    }
+
+    // ============ 类型 ============
+
+    fn parse_type(&mut self) -> PResult<NuType> {
+        let start = self.pos;
+        let kind = match self.peek() {
+            Some(Token::Amp) => {
+                self.pos += 1;
+                let lifetime = match self.peek() {
+                    Some(Token::Lifetime(l)) => {
+                        let l = l.clone();
+                        self.pos += 1;
+                        Some(l)
+                    }
+                    _ => None,
+                };
+                let mutable = self.eat_word("mut") || self.eat(&Token::Bang);
+                TypeKind::Ref {
+                    lifetime,
+                    mutable,
+                    ty: Box::new(self.parse_type_no_bounds()?),
+                }
+            }
+            Some(Token::AndAnd) => {
+                self.pos += 1;
+                let mutable = self.eat_word("mut") || self.eat(&Token::Bang);
+                let inner_start = self.pos;
+                let inner = TypeKind::Ref {
+                    lifetime: None,
+                    mutable,
+                    ty: Box::new(self.parse_type_no_bounds()?),
+                };
+                TypeKind::Ref {
+                    lifetime: None,
+                    mutable: false,
+                    ty: Box::new(NuType {
+                        kind: inner,
+                        span: self.span_from(inner_start),
+                    }),
+                }
+            }
+            Some(Token::RefMut) => {
+                self.pos += 1;
+                TypeKind::Ref {
+                    lifetime: None,
+                    mutable: true,
+                    ty: Box::new(self.parse_type_no_bounds()?),
+                }
+            }
+            Some(Token::Star) => {
+                self.pos += 1;
+                let mutable = if self.eat_word("mut") {
+                    true
+                } else if self.eat_word("const") {
+                    false
+                } else {
+                    return Err(self.error_expected("`const` 或 `mut`"));
+                };
+                TypeKind::Ptr {
+                    mutable,
+                    ty: Box::new(self.parse_type_no_bounds()?),
+                }
+            }
+            Some(Token::PtrMut) => {
+                self.pos += 1;
+                TypeKind::Ptr {
+                    mutable: true,
+                    ty: Box::new(self.parse_type_no_bounds()?),
+                }
+            }
+            Some(Token::LParen) => {
+                self.pos += 1;
+                let mut elems = Vec::new();
+                let mut trailing_comma = false;
+                while !self.eat(&Token::RParen) {
+                    elems.push(self.parse_type()?);
+                    trailing_comma = self.eat(&Token::Comma);
+                    if !trailing_comma {
+                        self.expect(&Token::RParen)?;
+                        break;
+                    }
+                }
+                if elems.len() == 1 && !trailing_comma {
+                    // 括号类型 (T)
+                    return Ok(elems.pop().unwrap());
+                }
+                TypeKind::Tuple(elems)
+            }
+            Some(Token::LBracket) => {
+                self.pos += 1;
+                let ty = Box::new(self.parse_type()?);
+                let kind = if self.eat(&Token::Semi) {
+                    let len = Box::new(self.with_struct(|p| p.parse_expr())?);
+                    TypeKind::Array { ty, len }
+                } else {
+                    TypeKind::Slice(ty)
+                };
+                self.expect(&Token::RBracket)?;
+                kind
+            }
+            Some(Token::Bang) => {
+                self.pos += 1;
+                TypeKind::Never
+            }
+            Some(Token::LessThanOrReturn) => self.parse_qself_type()?,
+            Some(Token::Ident(w)) if w == "_" => {
+                self.pos += 1;
+                TypeKind::Infer
+            }
+            Some(Token::Ident(w)) if w == "impl" => {
+                self.pos += 1;
+                TypeKind::ImplTrait(self.parse_bounds()?)
+            }
+            Some(Token::Ident(w)) if w == "dyn" => {
+                self.pos += 1;
+                TypeKind::DynTrait(self.parse_bounds()?)
+            }
+            Some(Token::Ident(w))
+                if w == "fn"
+                    || (w == "unsafe" || w == "extern")
+                        && matches!(
+                            self.peek_nth(1),
+                            Some(Token::Ident(_) | Token::StringLit(_))
+                        ) =>
+            {
+                self.parse_fn_type()?
+            }
+            Some(Token::Ident(w)) if w == "for" => {
+                self.parse_for_lifetimes()?;
+                return self.parse_type();
+            }
+            Some(tok) if tok.as_ident().is_some() || *tok == Token::PathSep => {
+                if self.is_macro_call_start() {
+                    let path = self.parse_path(PathMode::Expr)?;
+                    TypeKind::Macro(self.parse_macro_call(start, path)?)
+                } else {
+                    TypeKind::Path(self.parse_path(PathMode::Type)?)
+                }
+            }
+            _ => return Err(self.error_expected("类型")),
+        };
+        Ok(NuType {
+            kind,
+            span: self.span_from(start),
+        })
+    }
+
+    /// 引用/指针目标与 Fn 返回类型中不允许裸 `+`，与 parse_type 相同即可
+    fn parse_type_no_bounds(&mut self) -> PResult<NuType> {
+        self.parse_type()
+    }
+
+    /// <T as Trait>::Assoc
+    fn parse_qself_type(&mut self) -> PResult<TypeKind> {
+        self.expect(&Token::LessThanOrReturn)?;
+        let ty = Box::new(self.parse_type()?);
+        let trait_path = if self.eat(&Token::As) || self.eat_word("as") {
+            Some(self.parse_path(PathMode::Type)?)
+        } else {
+            None
+        };
+        if !self.eat_closing_angle() {
+            return Err(self.error_expected("`>`"));
+        }
+        let mut rest = Vec::new();
+        while self.eat(&Token::PathSep) {
+            let ident = self.expect_ident()?;
+            let args = if self.check(&Token::LessThanOrReturn) {
+                Some(self.parse_generic_args(false)?)
+            } else {
+                None
+            };
+            rest.push(PathSegment { ident, args });
+        }
+        Ok(TypeKind::QSelf {
+            ty,
+            trait_path,
+            rest,
+        })
+    }
+
+    /// unsafe extern "C" fn(A, B) -> C
+    fn parse_fn_type(&mut self) -> PResult<TypeKind> {
+        let is_unsafe = self.eat_word("unsafe");
+        let abi = if self.eat_word("extern") {
+            match self.peek() {
+                Some(Token::StringLit(s)) => {
+                    let s = s.clone();
+                    self.pos += 1;
+                    Some(s)
+                }
+                _ => Some(String::new()),
+            }
+        } else {
+            None
+        };
+        if !self.eat_word("fn") {
+            return Err(self.error_expected("`fn`"));
+        }
+        self.expect(&Token::LParen)?;
+        let mut inputs = Vec::new();
+        while !self.eat(&Token::RParen) {
+            // 允许命名参数：fn(x: i32)
+            if self.is_ident_nth(0) && self.check_nth(1, &Token::Colon) {
+                self.pos += 2;
+            }
+            inputs.push(self.parse_type()?);
+            if !self.eat(&Token::Comma) {
+                self.expect(&Token::RParen)?;
+                break;
+            }
+        }
+        let output = if self.eat(&Token::Arrow) {
+            Some(Box::new(self.parse_type_no_bounds()?))
+        } else {
+            None
+        };
+        Ok(TypeKind::Fn {
+            is_unsafe,
+            abi,
+            inputs,
+            output,
+        })
+    }
+
+    // ============ 结构体 / 枚举 ============
+
+    fn parse_struct(
+        &mut self,
+        start: usize,
+        attrs: Vec<NuAttr>,
+        vis: Visibility,
+    ) -> PResult<NuStruct> {
+        let is_pub_kw = matches!(self.bump(), Token::StructPub);
+        let name = self.expect_ident()?;
+        let mut generics = self.parse_generic_params()?;
+        generics.where_clause = self.parse_where_clause()?;
+
+        let fields = if self.check(&Token::LBrace) {
+            Fields::Named(self.parse_named_fields()?)
+        } else if self.check(&Token::LParen) {
+            let fields = Fields::Tuple(self.parse_tuple_fields()?);
+            // 元组结构体的 where 子句在字段之后
+            let mut trailing = self.parse_where_clause()?;
+            generics.where_clause.append(&mut trailing);
+            self.eat(&Token::Semi);
+            fields
+        } else {
+            self.eat(&Token::Semi);
+            Fields::Unit
+        };
+
+        let vis = keyword_vis(is_pub_kw, vis);
+        Ok(NuStruct {
+            attrs,
+            name,
+            is_public: vis.is_public(),
+            vis,
+            generics,
+            fields,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_union(
+        &mut self,
+        start: usize,
+        attrs: Vec<NuAttr>,
+        vis: Visibility,
+    ) -> PResult<NuUnion> {
+        let is_pub_kw = matches!(self.bump(), Token::UnionPub);
+        let name = self.expect_ident()?;
+        let mut generics = self.parse_generic_params()?;
+        generics.where_clause = self.parse_where_clause()?;
+        let fields = self.parse_named_fields()?;
+
+        let vis = keyword_vis(is_pub_kw, vis);
+        Ok(NuUnion {
+            attrs,
+            name,
+            is_public: vis.is_public(),
+            vis,
+            generics,
+            fields,
+            span: self.span_from(start),
+        })
+    }
+
+    /// 字段之间可以用逗号或换行分隔
+    fn expect_list_separator(&mut self, close: &Token) -> PResult<()> {
+        if self.eat(&Token::Comma) || self.check(close) || self.on_new_line() {
+            Ok(())
+        } else {
+            Err(self.error_expected(&format!("`,` 或 `{}`", close.text())))
+        }
+    }
+
+    fn parse_named_fields(&mut self) -> PResult<Vec<Field>> {
+        self.expect(&Token::LBrace)?;
+        let mut fields = Vec::new();
+        while !self.eat(&Token::RBrace) {
+            if self.at_end() {
+                return Err(self.error_expected("`}`"));
+            }
+            let start = self.pos;
+            let attrs = self.parse_outer_attrs()?;
+            let vis = self.parse_visibility()?;
+            let name = self.expect_ident()?;
+            self.expect(&Token::Colon)?;
+            let ty = self.parse_type()?;
+            fields.push(Field {
+                attrs,
+                vis,
+                name: Some(name),
+                ty,
+                span: self.span_from(start),
+            });
+            self.expect_list_separator(&Token::RBrace)?;
+        }
+        Ok(fields)
+    }
+
+    fn parse_tuple_fields(&mut self) -> PResult<Vec<Field>> {
+        self.expect(&Token::LParen)?;
+        let mut fields = Vec::new();
+        while !self.eat(&Token::RParen) {
+            let start = self.pos;
+            let attrs = self.parse_outer_attrs()?;
+            let vis = self.parse_visibility()?;
+            let ty = self.parse_type()?;
+            fields.push(Field {
+                attrs,
+                vis,
+                name: None,
+                ty,
+                span: self.span_from(start),
+            });
+            if !self.eat(&Token::Comma) {
+                self.expect(&Token::RParen)?;
+                break;
+            }
+        }
+        Ok(fields)
+    }
+
+    fn parse_enum(&mut self, start: usize, attrs: Vec<NuAttr>, vis: Visibility) -> PResult<NuEnum> {
+        self.pos += 1;
+        let name = self.expect_ident()?;
+        let mut generics = self.parse_generic_params()?;
+        generics.where_clause = self.parse_where_clause()?;
+
+        self.expect(&Token::LBrace)?;
+        let mut variants = Vec::new();
+        while !self.eat(&Token::RBrace) {
+            if self.at_end() {
+                return Err(self.error_expected("`}`"));
+            }
+            let vstart = self.pos;
+            let vattrs = self.parse_outer_attrs()?;
+            let vname = self.expect_ident()?;
+            let fields = if self.check(&Token::LParen) {
+                Fields::Tuple(self.parse_tuple_fields()?)
+            } else if self.check(&Token::LBrace) {
+                Fields::Named(self.parse_named_fields()?)
+            } else {
+                Fields::Unit
+            };
+            let discriminant = if self.eat(&Token::Eq) {
+                Some(self.parse_expr()?)
+            } else {
+                None
+            };
+            variants.push(Variant {
+                attrs: vattrs,
+                name: vname,
+                fields,
+                discriminant,
+                span: self.span_from(vstart),
+            });
+            self.expect_list_separator(&Token::RBrace)?;
+        }
+
+        // 可见性由标识符首字母决定（Go风格）
+        let vis = if vis == Visibility::Private && starts_uppercase(&name) {
+            Visibility::Public
+        } else {
+            vis
+        };
+        Ok(NuEnum {
+            attrs,
+            name,
+            is_public: vis.is_public(),
+            vis,
+            generics,
+            variants,
+            span: self.span_from(start),
+        })
+    }
+
+    // ============ trait / impl ============
+
+    fn parse_trait(
+        &mut self,
+        start: usize,
+        attrs: Vec<NuAttr>,
+        vis: Visibility,
+        is_unsafe: bool,
+    ) -> PResult<NuItem> {
+        let is_pub_kw = matches!(self.bump(), Token::TraitPub);
+        let name = self.expect_ident()?;
+        let mut generics = self.parse_generic_params()?;
+        let vis = keyword_vis(is_pub_kw, vis);
+
+        // TR Alias = A + B [wh ...];
+        if self.eat(&Token::Eq) {
+            let bounds = self.parse_bounds()?;
+            generics.where_clause = self.parse_where_clause()?;
+            self.expect(&Token::Semi)?;
+            return Ok(NuItem::TraitAlias(NuTraitAlias {
+                attrs,
+                name,
+                is_public: vis.is_public(),
+                vis,
+                generics,
+                bounds,
+                span: self.span_from(start),
+            }));
+        }
+
+        let supertraits = if self.eat(&Token::Colon) {
+            self.parse_bounds()?
+        } else {
+            Vec::new()
+        };
+        generics.where_clause = self.parse_where_clause()?;
+
+        let items = self
+            .parse_assoc_items("trait 条目")?
+            .into_iter()
+            .map(|item| match item {
+                AssocItem::Fn(f) => TraitItem::Fn(f),
+                AssocItem::Const(c) => TraitItem::Const(c),
+                AssocItem::Type(t) => TraitItem::Type(t),
+                AssocItem::Macro(m) => TraitItem::Macro(m),
+            })
+            .collect();
+
+        Ok(NuItem::Trait(NuTrait {
+            attrs,
+            name,
+            is_public: vis.is_public(),
+            vis,
+            is_unsafe,
+            generics,
+            supertraits,
+            items,
+            span: self.span_from(start),
+        }))
+    }
+
+    fn parse_impl(&mut self, start: usize, attrs: Vec<NuAttr>, is_unsafe: bool) -> PResult<NuImpl> {
+        self.expect(&Token::Impl)?;
+        let mut generics = self.parse_generic_params()?;
+        let negative = self.eat(&Token::Bang);
+
+        let first_start = self.pos;
+        let first = self.parse_type()?;
+        let (trait_ref, self_ty, ty_start) = if self.eat_word("for") {
+            let trait_ref = match first.kind {
+                TypeKind::Path(path) => path,
+                _ => {
+                    return Err(ParseError {
+                        message: "impl 的 trait 必须是路径".to_string(),
+                        span: first.span,
+                    })
+                }
+            };
+            let ty_start = self.pos;
+            (Some(trait_ref), self.parse_type()?, ty_start)
+        } else {
+            (None, first, first_start)
+        };
+        let type_name = self.text_from(ty_start);
+        generics.where_clause = self.parse_where_clause()?;
+
+        let items = self
+            .parse_assoc_items("impl 条目")?
+            .into_iter()
+            .map(|item| match item {
+                AssocItem::Fn(f) => ImplItem::Fn(f),
+                AssocItem::Const(c) => ImplItem::Const(c),
+                AssocItem::Type(t) => ImplItem::Type(t),
+                AssocItem::Macro(m) => ImplItem::Macro(m),
+            })
+            .collect();
+
+        Ok(NuImpl {
+            attrs,
+            type_name,
+            is_unsafe,
+            generics,
+            trait_ref,
+            negative,
+            self_ty,
+            items,
+            span: self.span_from(start),
+        })
+    }
+
+    /// `{ ... }` 中的关联函数、常量、类型与宏
+    fn parse_assoc_items(&mut self, what: &str) -> PResult<Vec<AssocItem>> {
+        self.expect(&Token::LBrace)?;
+        let mut items = Vec::new();
+        while !self.eat(&Token::RBrace) {
+            if self.eat(&Token::Semi) {
+                continue;
+            }
+            if self.at_end() {
+                return Err(self.error_expected("`}`"));
+            }
+            let start = self.pos;
+            let attrs = self.parse_outer_attrs()?;
+            let vis = self.parse_visibility()?;
+            let quals = self.parse_fn_qualifiers();
+            let item = match self.peek() {
+                Some(Token::FnPub | Token::FnPriv) => {
+                    AssocItem::Fn(self.parse_fn(start, attrs, vis, quals)?)
+                }
+                _ if self.is_const_start() => {
+                    AssocItem::Const(self.parse_const(start, attrs, vis)?)
+                }
+                _ if self.is_type_alias_start() => {
+                    AssocItem::Type(self.parse_type_alias(start, attrs, vis)?)
+                }
+                Some(Token::Ident(_)) if self.is_macro_call_start() => {
+                    AssocItem::Macro(self.parse_macro_item(start, attrs)?)
+                }
+                _ => return Err(self.error_expected(what)),
+            };
+            items.push(item);
+        }
+        Ok(items)
+    }
+
+    // ============ const / static / type / extern ============
+
+    fn parse_const(
+        &mut self,
+        start: usize,
+        attrs: Vec<NuAttr>,
+        vis: Visibility,
+    ) -> PResult<NuConst> {
+        let is_pub_kw = matches!(self.bump(), Token::ConstPub);
+        let name = self.expect_ident()?;
+        self.expect(&Token::Colon)?;
+        let ty = self.parse_type()?;
+        let value = if self.eat(&Token::Eq) {
+            Some(self.parse_expr()?)
+        } else {
+            None
+        };
+        self.expect_stmt_end()?;
+
+        let vis = keyword_vis(is_pub_kw, vis);
+        Ok(NuConst {
+            attrs,
+            name,
+            is_public: vis.is_public(),
+            vis,
+            ty,
+            value,
+            span: self.span_from(start),
+        })
+    }
+
+    /// require_value 为 false 时允许 EXT 块中的 `ST NAME: T;` 声明
+    fn parse_static(
+        &mut self,
+        start: usize,
+        attrs: Vec<NuAttr>,
+        vis: Visibility,
+        require_value: bool,
+    ) -> PResult<NuStatic> {
+        let (is_pub_kw, mutable) = match self.bump() {
+            Token::StaticPub => (true, false),
+            Token::StaticMut => (false, true),
+            Token::StaticMutPub => (true, true),
+            Token::Static => (false, false),
+            _ => (false, self.eat_word("mut")),
+        };
+        let name = self.expect_ident()?;
+        self.expect(&Token::Colon)?;
+        let ty = self.parse_type()?;
+        let value = if self.eat(&Token::Eq) {
+            Some(self.parse_expr()?)
+        } else if require_value {
+            return Err(self.error_expected("`=`"));
+        } else {
+            None
+        };
+        self.expect_stmt_end()?;
+
+        let vis = keyword_vis(is_pub_kw, vis);
+        Ok(NuStatic {
+            attrs,
+            name,
+            is_public: vis.is_public(),
+            vis,
+            mutable,
+            ty,
+            value,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_type_alias(
+        &mut self,
+        start: usize,
+        attrs: Vec<NuAttr>,
+        vis: Visibility,
+    ) -> PResult<NuTypeAlias> {
+        self.pos += 1;
+        let name = self.expect_ident()?;
+        let mut generics = self.parse_generic_params()?;
+        let bounds = if self.eat(&Token::Colon) {
+            self.parse_bounds()?
+        } else {
+            Vec::new()
+        };
+        generics.where_clause = self.parse_where_clause()?;
+        let ty = if self.eat(&Token::Eq) {
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+        let mut trailing = self.parse_where_clause()?;
+        generics.where_clause.append(&mut trailing);
+        self.expect_stmt_end()?;
+
+        Ok(NuTypeAlias {
+            attrs,
+            name,
+            is_public: vis.is_public(),
+            vis,
+            generics,
+            bounds,
+            ty,
+            span: self.span_from(start),
+        })
+    }
+
+    /// EXT crate foo [as bar];
+    fn parse_extern_crate(
+        &mut self,
+        start: usize,
+        attrs: Vec<NuAttr>,
+        vis: Visibility,
+    ) -> PResult<NuExternCrate> {
+        self.pos += 2;
+        let name = self.expect_ident()?;
+        let rename = if self.eat(&Token::As) || self.eat_word("as") {
+            Some(self.expect_ident()?)
+        } else {
+            None
+        };
+        self.expect(&Token::Semi)?;
+        Ok(NuExternCrate {
+            attrs,
+            vis,
+            name,
+            rename,
+            span: self.span_from(start),
+        })
+    }
+
+    /// EXT "C" { f strlen(s: *const u8) -> usize; ST errno: i32; }
+    fn parse_extern(
+        &mut self,
+        start: usize,
+        mut attrs: Vec<NuAttr>,
+        is_unsafe: bool,
+    ) -> PResult<NuExtern> {
+        self.pos += 1;
+        let abi = match self.peek() {
+            Some(Token::StringLit(abi)) => {
+                let abi = abi.clone();
+                self.pos += 1;
+                Some(abi)
+            }
+            _ => None,
+        };
+        self.expect(&Token::LBrace)?;
+        attrs.extend(self.parse_inner_attrs());
+
+        let mut items = Vec::new();
+        while !self.eat(&Token::RBrace) {
+            if self.eat(&Token::Semi) {
+                continue;
+            }
+            if self.at_end() {
+                return Err(self.error_expected("`}`"));
+            }
+            let istart = self.pos;
+            let iattrs = self.parse_outer_attrs()?;
+            let ivis = self.parse_visibility()?;
+            let quals = self.parse_fn_qualifiers();
+            let item = match self.peek() {
+                Some(Token::FnPub | Token::FnPriv) => {
+                    ForeignItem::Fn(self.parse_fn(istart, iattrs, ivis, quals)?)
+                }
+                _ if self.is_static_start() => {
+                    ForeignItem::Static(self.parse_static(istart, iattrs, ivis, false)?)
+                }
+                _ if self.is_type_alias_start() => {
+                    ForeignItem::Type(self.parse_type_alias(istart, iattrs, ivis)?)
+                }
+                Some(Token::Ident(_)) if self.is_macro_call_start() => {
+                    ForeignItem::Macro(self.parse_macro_item(istart, iattrs)?)
+                }
+                _ => return Err(self.error_expected("EXT 块条目")),
+            };
+            items.push(item);
+        }
+
+        Ok(NuExtern {
+            attrs,
+            is_unsafe,
+            abi,
+            items,
+            span: self.span_from(start),
+        })
+    }
+
+    // ============ mod / use / 宏条目 ============
+
+    fn parse_mod(
+        &mut self,
+        start: usize,
+        mut attrs: Vec<NuAttr>,
+        vis: Visibility,
+    ) -> PResult<NuMod> {
+        let is_pub_kw = matches!(self.bump(), Token::ModPub);
+        let name = self.expect_ident()?;
+        let items = if self.eat(&Token::LBrace) {
+            attrs.extend(self.parse_inner_attrs());
+            let items = self.parse_items(true);
+            self.expect(&Token::RBrace)?;
+            Some(items)
+        } else {
+            self.eat(&Token::Semi);
+            None
+        };
+
+        let vis = keyword_vis(is_pub_kw, vis);
+        Ok(NuMod {
+            attrs,
+            name,
+            is_public: vis.is_public(),
+            vis,
+            items,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_use(&mut self, start: usize, attrs: Vec<NuAttr>, vis: Visibility) -> PResult<NuUse> {
+        let is_pub_kw = matches!(self.bump(), Token::PubUse);
+        let tree = self.parse_use_tree()?;
+        self.eat(&Token::Semi);
+
+        let vis = keyword_vis(is_pub_kw, vis);
+        Ok(NuUse {
+            attrs,
+            path: tree.to_string(),
+            is_public: vis.is_public(),
+            vis,
+            tree,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_use_tree(&mut self) -> PResult<UseTree> {
+        if self.eat(&Token::Star) {
+            return Ok(UseTree::Glob);
+        }
+        if self.eat(&Token::LBrace) {
+            let mut trees = Vec::new();
+            while !self.eat(&Token::RBrace) {
+                trees.push(self.parse_use_tree()?);
+                if !self.eat(&Token::Comma) {
+                    self.expect(&Token::RBrace)?;
+                    break;
+                }
+            }
+            return Ok(UseTree::Group(trees));
+        }
+        if self.eat(&Token::PathSep) {
+            // ::std::x
+            return Ok(UseTree::Path {
+                ident: String::new(),
+                tree: Box::new(self.parse_use_tree()?),
+            });
+        }
+
+        let ident = self.expect_ident()?;
+        if self.eat(&Token::PathSep) {
+            return Ok(UseTree::Path {
+                ident,
+                tree: Box::new(self.parse_use_tree()?),
+            });
+        }
+        if self.eat(&Token::As) || self.eat_word("as") {
+            return Ok(UseTree::Rename {
+                ident,
+                rename: self.expect_ident()?,
+            });
+        }
+        Ok(UseTree::Name(ident))
+    }
+
+    fn parse_macro_item(&mut self, start: usize, attrs: Vec<NuAttr>) -> PResult<NuMacroItem> {
+        let path_start = self.pos;
+        let path = self.parse_path(PathMode::Expr)?;
+        let is_macro_rules = path.as_ident() == Some("macro_rules");
+        let (mac, name) = if is_macro_rules {
+            self.expect(&Token::Bang)?;
+            let name = self.expect_ident()?;
+            let mac = self.parse_macro_body(path_start, path)?;
+            (mac, Some(name))
+        } else {
+            self.expect(&Token::Bang)?;
+            (self.parse_macro_body(path_start, path)?, None)
+        };
+        self.eat(&Token::Semi);
+        Ok(NuMacroItem {
+            attrs,
+            mac,
+            name,
+            span: self.span_from(start),
+        })
+    }
+
+    /// path 已解析，当前位于 `!`
+    fn parse_macro_call(&mut self, start: usize, path: NuPath) -> PResult<NuMacroCall> {
+        self.expect(&Token::Bang)?;
+        self.parse_macro_body(start, path)
+    }
+
+    /// 当前位于宏分隔符，宏体按原始源码保存
+    fn parse_macro_body(&mut self, start: usize, path: NuPath) -> PResult<NuMacroCall> {
+        let delimiter = match self.peek() {
+            Some(Token::LParen) => MacroDelimiter::Paren,
+            Some(Token::LBracket) => MacroDelimiter::Bracket,
+            Some(Token::LBrace) => MacroDelimiter::Brace,
+            _ => return Err(self.error_expected("宏分隔符")),
+        };
+        let open = self.pos;
+        let close = self.skip_delimited()?;
+        let tokens =
+            self.src[self.tokens[open].span.end..self.tokens[close].span.start].to_string();
+        Ok(NuMacroCall {
+            path,
+            delimiter,
+            tokens,
+            span: self.span_from(start),
+        })
+    }
+
+    // ============ 语句 ============
+
+    fn parse_block(&mut self) -> PResult<NuBlock> {
+        let start = self.pos;
+        self.expect(&Token::LBrace)?;
+        let stmts = self.with_struct(|p| {
+            p.parse_inner_attrs();
+            let mut stmts = Vec::new();
+            while !p.eat(&Token::RBrace) {
+                if p.at_end() {
+                    let open = &p.tokens[start];
+                    return Err(ParseError {
+                        message: "未闭合的 `{`".to_string(),
+                        span: p.span(open.line, open.col, 1),
+                    });
+                }
+                stmts.push(p.parse_stmt()?);
+            }
+            Ok(stmts)
+        })?;
+        Ok(NuBlock {
+            stmts,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_stmt(&mut self) -> PResult<NuStmt> {
+        let start = self.pos;
+        if self.eat(&Token::Semi) {
+            return Ok(NuStmt {
+                kind: StmtKind::Empty,
+                span: self.span_from(start),
+            });
+        }
+
+        let attrs = self.parse_outer_attrs()?;
+
+        if self.is_let_start() {
+            let local = self.parse_let(attrs)?;
+            self.expect_stmt_end()?;
+            return Ok(NuStmt {
+                kind: StmtKind::Let(local),
+                span: self.span_from(start),
+            });
+        }
+
+        if self.is_stmt_item_start() {
+            let vis = self.parse_visibility()?;
+            let item = self.parse_item_after(start, attrs, vis)?;
+            return Ok(NuStmt {
+                kind: StmtKind::Item(Box::new(item)),
+                span: self.span_from(start),
+            });
+        }
+
+        let block_like = self.is_block_like_start();
+        let mut expr = self.parse_expr()?;
+        expr.attrs.splice(0..0, attrs);
+
+        let has_semi = self.eat(&Token::Semi);
+        if !has_semi && !block_like {
+            self.expect_stmt_end()?;
+        }
+        Ok(NuStmt {
+            kind: StmtKind::Expr { expr, has_semi },
+            span: self.span_from(start),
+        })
+    }
+
+    /// 语句结束：`;`、`}`、换行或文件末尾
+    fn expect_stmt_end(&mut self) -> PResult<()> {
+        if self.eat(&Token::Semi) || self.check(&Token::RBrace) || self.on_new_line() {
+            Ok(())
+        } else {
+            Err(self.error_expected("`;` 或换行"))
+        }
+    }
+
+    /// l/v 后跟模式时为 let；`v.push(x)`、`l(x)`、`v = 1` 则是普通表达式
+    fn is_let_start(&self) -> bool {
+        match self.peek() {
+            Some(Token::Ident(w)) if w == "let" => true,
+            Some(Token::Let | Token::LetMut) => match self.peek_nth(1) {
+                Some(Token::LParen | Token::LBracket) => !self.is_adjacent_nth(1),
+                Some(Token::Amp | Token::AndAnd | Token::RefMut) => true,
+                Some(tok) => tok.as_ident().is_some(),
+                None => false,
+            },
+            _ => false,
+        }
+    }
+
+    fn parse_let(&mut self, attrs: Vec<NuAttr>) -> PResult<NuLocal> {
+        let is_mut = match self.bump() {
+            Token::LetMut => true,
+            _ => self.eat_word("mut"),
+        };
+        let pat = self.parse_pat_no_alt()?;
+        let ty = if self.eat(&Token::Colon) {
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+        let init = if self.eat(&Token::Eq) {
+            Some(self.parse_expr()?)
+        } else {
+            None
+        };
+        let else_block = if init.is_some() && self.eat_word("else") {
+            Some(self.parse_block()?)
+        } else {
+            None
+        };
+        Ok(NuLocal {
+            attrs,
+            is_mut,
+            pat,
+            ty,
+            init,
+            else_block,
+        })
+    }
+
+    /// 语句位置的条目：关键字后必须跟名字（`s.len()`、`f(x)` 仍是表达式）
+    fn is_stmt_item_start(&self) -> bool {
+        if self.is_const_start()
+            || self.is_static_start()
+            || self.is_type_alias_start()
+            || self.is_extern_block_start()
+        {
+            return true;
+        }
+        let mut n = 0;
+        if self.is_word("pub") {
+            n = 1;
+            if self.check_nth(1, &Token::LParen) {
+                n = 4;
+            }
+        }
+        loop {
+            match self.peek_nth(n) {
+                Some(Token::Async) => n += 1,
+                Some(Token::Ident(w))
+                    if (w == "unsafe" || w == "const" || w == "async")
+                        && !self.check_nth(n + 1, &Token::LBrace)
+                        && !self.check_nth(n + 1, &Token::Pipe)
+                        && !self.check_nth(n + 1, &Token::OrOr) =>
+                {
+                    n += 1
+                }
+                _ => break,
+            }
+        }
+        match self.peek_nth(n) {
+            Some(Token::FnPub | Token::FnPriv) => {
+                self.is_ident_nth(n + 1)
+                    && matches!(
+                        self.peek_nth(n + 2),
+                        Some(Token::LParen | Token::LessThanOrReturn)
+                    )
+            }
+            Some(
+                Token::StructPub
+                | Token::StructPriv
+                | Token::UnionPub
+                | Token::UnionPriv
+                | Token::EnumPub
+                | Token::EnumPriv
+                | Token::TraitPub
+                | Token::TraitPriv
+                | Token::Mod
+                | Token::ModPub,
+            ) => self.is_ident_nth(n + 1),
+            Some(Token::Impl) => {
+                matches!(
+                    self.peek_nth(n + 1),
+                    Some(Token::LessThanOrReturn | Token::Bang | Token::Amp)
+                ) || self.is_ident_nth(n + 1)
+            }
+            Some(Token::Use | Token::PubUse) => {
+                self.is_ident_nth(n + 1)
+                    || matches!(self.peek_nth(n + 1), Some(Token::LBrace | Token::Star))
+            }
+            Some(Token::Ident(w)) if w == "macro_rules" => self.check_nth(n + 1, &Token::Bang),
+            _ => false,
+        }
+    }
+
+    // ============ 表达式 ============
+
+    fn mk_expr(&self, start: usize, kind: ExprKind) -> NuExpr {
+        NuExpr {
+            attrs: Vec::new(),
+            kind,
+            span: self.span_from(start),
+        }
+    }
+
+    fn parse_expr(&mut self) -> PResult<NuExpr> {
+        self.parse_assign()
+    }
+
+    fn parse_assign(&mut self) -> PResult<NuExpr> {
+        let start = self.pos;
+        let lhs = self.parse_range()?;
+        let op = match self.peek() {
+            Some(Token::Eq) => {
+                self.pos += 1;
+                let rhs = self.parse_assign()?;
+                return Ok(self.mk_expr(
+                    start,
+                    ExprKind::Assign {
+                        lhs: Box::new(lhs),
+                        rhs: Box::new(rhs),
+                    },
+                ));
+            }
+            Some(Token::PlusEq) => BinOp::Add,
+            Some(Token::MinusEq) => BinOp::Sub,
+            Some(Token::StarEq) => BinOp::Mul,
+            Some(Token::SlashEq) => BinOp::Div,
+            Some(Token::PercentEq) => BinOp::Rem,
+            Some(Token::CaretEq) => BinOp::BitXor,
+            Some(Token::AndEq) => BinOp::BitAnd,
+            Some(Token::OrEq) => BinOp::BitOr,
+            Some(Token::ShlEq) => BinOp::Shl,
+            Some(Token::ShrEq) => BinOp::Shr,
+            _ => return Ok(lhs),
+        };
+        self.pos += 1;
+        let rhs = self.parse_assign()?;
+        Ok(self.mk_expr(
+            start,
+            ExprKind::AssignOp {
+                op,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            },
+        ))
+    }
+
+    fn parse_range(&mut self) -> PResult<NuExpr> {
+        let start = self.pos;
+        let lhs = if matches!(self.peek(), Some(Token::DotDot | Token::DotDotEq)) {
+            None
+        } else {
+            Some(self.parse_binary(PREC_OR)?)
+        };
+        let inclusive = match self.peek() {
+            Some(Token::DotDot) if !(lhs.is_some() && self.on_new_line()) => false,
+            Some(Token::DotDotEq) => true,
+            _ => return Ok(lhs.expect("range 起点存在")),
+        };
+        self.pos += 1;
+        let end = if self.can_start_expr() {
+            Some(Box::new(self.parse_binary(PREC_OR)?))
+        } else {
+            None
+        };
+        Ok(self.mk_expr(
+            start,
+            ExprKind::Range {
+                start: lhs.map(Box::new),
+                end,
+                inclusive,
+            },
+        ))
+    }
+
+    /// 开区间 `a..` 后是否还有终点表达式
+    fn can_start_expr(&self) -> bool {
+        if self.on_new_line() {
+            return false;
+        }
+        match self.peek() {
+            None => false,
+            Some(
+                Token::RParen
+                | Token::RBracket
+                | Token::RBrace
+                | Token::Comma
+                | Token::Semi
+                | Token::FatArrow
+                | Token::Eq
+                | Token::Colon,
+            ) => false,
+            Some(Token::LBrace) => !self.no_struct,
+            _ => true,
+        }
+    }
+
+    /// (运算符, 优先级, 占用 Token 数)
+    fn peek_binop(&self) -> Option<(BinOp, u8, usize)> {
+        let op = match self.peek()? {
+            Token::OrOr => (BinOp::Or, PREC_OR, 1),
+            Token::AndAnd => (BinOp::And, PREC_AND, 1),
+            Token::EqEq => (BinOp::Eq, PREC_CMP, 1),
+            Token::NotEq => (BinOp::Ne, PREC_CMP, 1),
+            Token::LessThanOrReturn => (BinOp::Lt, PREC_CMP, 1),
+            Token::LessEq => (BinOp::Le, PREC_CMP, 1),
+            Token::GreaterEq => (BinOp::Ge, PREC_CMP, 1),
+            Token::GreaterThan => (BinOp::Gt, PREC_CMP, 1),
+            Token::Shr => (BinOp::Shr, PREC_SHIFT, 1),
+            Token::Pipe => (BinOp::BitOr, PREC_BIT_OR, 1),
+            Token::Caret => (BinOp::BitXor, PREC_BIT_XOR, 1),
+            Token::Amp => (BinOp::BitAnd, PREC_BIT_AND, 1),
+            // 二元位置的 << 按左移处理（与 Rust 源码保持一致）
+            Token::Channel => (BinOp::Shl, PREC_SHIFT, 1),
+            Token::Plus => (BinOp::Add, PREC_ADD, 1),
+            Token::Minus => (BinOp::Sub, PREC_ADD, 1),
+            Token::Star => (BinOp::Mul, PREC_MUL, 1),
+            Token::Slash => (BinOp::Div, PREC_MUL, 1),
+            Token::Percent => (BinOp::Rem, PREC_MUL, 1),
+            _ => return None,
+        };
+        Some(op)
+    }
+
+    fn parse_binary(&mut self, min_prec: u8) -> PResult<NuExpr> {
+        let start = self.pos;
+        let mut lhs = self.parse_unary()?;
+        loop {
+            // 换行后的 `<` / `*` / `&` / `a` 开始新语句（return / 解引用 / 引用 / 变量）
+            if self.on_new_line()
+                && matches!(
+                    self.peek(),
+                    Some(Token::LessThanOrReturn | Token::Star | Token::Amp | Token::As)
+                )
+            {
+                break;
+            }
+
+            if (self.check(&Token::As) || self.is_word("as")) && PREC_CAST >= min_prec {
+                self.pos += 1;
+                let ty = self.parse_type_no_bounds()?;
+                lhs = self.mk_expr(
+                    start,
+                    ExprKind::Cast {
+                        expr: Box::new(lhs),
+                        ty,
+                    },
+                );
+                continue;
+            }
+
+            let Some((op, prec, width)) = self.peek_binop() else {
+                break;
+            };
+            if prec < min_prec {
+                break;
+            }
+            self.pos += width;
+            let rhs = self.parse_binary(prec + 1)?;
+            lhs = self.mk_expr(
+                start,
+                ExprKind::Binary {
+                    op,
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(rhs),
+                },
+            );
+        }
+        Ok(lhs)
+    }
+
+    fn parse_unary(&mut self) -> PResult<NuExpr> {
+        let start = self.pos;
+        let kind = match self.peek() {
+            Some(Token::Minus) => {
+                self.pos += 1;
+                ExprKind::Unary {
+                    op: UnOp::Neg,
+                    expr: Box::new(self.parse_unary()?),
+                }
+            }
+            Some(Token::Bang) => {
+                self.pos += 1;
+                ExprKind::Unary {
+                    op: UnOp::Not,
+                    expr: Box::new(self.parse_unary()?),
+                }
+            }
+            Some(Token::Star) => {
+                self.pos += 1;
+                ExprKind::Unary {
+                    op: UnOp::Deref,
+                    expr: Box::new(self.parse_unary()?),
+                }
+            }
+            Some(Token::Amp) => {
+                self.pos += 1;
+                let mutable = self.eat_word("mut");
+                ExprKind::Ref {
+                    mutable,
+                    expr: Box::new(self.parse_unary()?),
+                }
+            }
+            Some(Token::RefMut) => {
+                self.pos += 1;
+                ExprKind::Ref {
+                    mutable: true,
+                    expr: Box::new(self.parse_unary()?),
+                }
+            }
+            Some(Token::AndAnd) => {
+                self.pos += 1;
+                let mutable = self.eat_word("mut");
+                let inner_start = self.pos;
+                let inner = ExprKind::Ref {
+                    mutable,
+                    expr: Box::new(self.parse_unary()?),
+                };
+                ExprKind::Ref {
+                    mutable: false,
+                    expr: Box::new(self.mk_expr(inner_start, inner)),
+                }
+            }
+            Some(Token::Spawn) => {
+                self.pos += 1;
+                ExprKind::Spawn(Box::new(self.parse_unary()?))
+            }
+            Some(Token::Thread) => {
+                self.pos += 1;
+                ExprKind::Thread(Box::new(self.parse_unary()?))
+            }
+            Some(Token::Channel) => {
+                self.pos += 1;
+                ExprKind::Recv(Box::new(self.parse_unary()?))
+            }
+            _ => {
+                let primary = self.parse_primary()?;
+                return self.parse_postfix(start, primary);
+            }
+        };
+        Ok(self.mk_expr(start, kind))
+    }
+
+    fn parse_postfix(&mut self, start: usize, mut expr: NuExpr) -> PResult<NuExpr> {
+        loop {
+            let kind = match self.peek() {
+                Some(Token::Try) => {
+                    self.pos += 1;
+                    ExprKind::Try(Box::new(expr))
+                }
+                Some(Token::Question) if self.is_adjacent() => {
+                    self.pos += 1;
+                    ExprKind::Try(Box::new(expr))
+                }
+                Some(Token::Await) => {
+                    self.pos += 1;
+                    ExprKind::Await(Box::new(expr))
+                }
+                Some(Token::Dot) => {
+                    self.pos += 1;
+                    match self.peek().cloned() {
+                        Some(Token::Ident(w)) if w == "await" => {
+                            self.pos += 1;
+                            ExprKind::Await(Box::new(expr))
+                        }
+                        Some(Token::IntLit(index)) => {
+                            self.pos += 1;
+                            ExprKind::Field {
+                                expr: Box::new(expr),
+                                name: index,
+                            }
+                        }
+                        Some(Token::FloatLit(index)) => {
+                            // x.0.1 被词法分析为 x . 0.1
+                            self.pos += 1;
+                            let mut parts = index.splitn(2, '.');
+                            let first = parts.next().unwrap_or_default().to_string();
+                            let second = parts.next().unwrap_or_default().to_string();
+                            let inner = self.mk_expr(
+                                start,
+                                ExprKind::Field {
+                                    expr: Box::new(expr),
+                                    name: first,
+                                },
+                            );
+                            ExprKind::Field {
+                                expr: Box::new(inner),
+                                name: second,
+                            }
+                        }
+                        _ => {
+                            let method = self.expect_ident()?;
+                            let turbofish = if self.check(&Token::PathSep)
+                                && self.check_nth(1, &Token::LessThanOrReturn)
+                            {
+                                self.pos += 1;
+                                Some(self.parse_generic_args(true)?)
+                            } else {
+                                None
+                            };
+                            if self.check(&Token::LParen) {
+                                let args = self.parse_call_args()?;
+                                ExprKind::MethodCall {
+                                    receiver: Box::new(expr),
+                                    method,
+                                    turbofish,
+                                    args,
+                                }
+                            } else {
+                                ExprKind::Field {
+                                    expr: Box::new(expr),
+                                    name: method,
+                                }
+                            }
+                        }
+                    }
+                }
+                Some(Token::LParen) if !self.on_new_line() => ExprKind::Call {
+                    func: Box::new(expr),
+                    args: self.parse_call_args()?,
+                },
+                Some(Token::LBracket) if !self.on_new_line() => {
+                    self.pos += 1;
+                    let index = self.with_struct(|p| p.parse_expr())?;
+                    self.expect(&Token::RBracket)?;
+                    ExprKind::Index {
+                        expr: Box::new(expr),
+                        index: Box::new(index),
+                    }
+                }
+                _ => return Ok(expr),
+            };
+            expr = self.mk_expr(start, kind);
+        }
+    }
+
+    fn parse_call_args(&mut self) -> PResult<Vec<NuExpr>> {
+        self.expect(&Token::LParen)?;
+        self.with_struct(|p| {
+            let mut args = Vec::new();
+            while !p.eat(&Token::RParen) {
+                args.push(p.parse_expr()?);
+                if !p.eat(&Token::Comma) {
+                    p.expect(&Token::RParen)?;
+                    break;
+                }
+            }
+            Ok(args)
+        })
+    }
+
+    /// 语句位置的块状表达式（其后不需要 `;`）
+    fn is_block_like_start(&self) -> bool {
+        let mut n = 0;
+        if matches!(self.peek(), Some(Token::Lifetime(_))) && self.check_nth(1, &Token::Colon) {
+            n = 2;
+        }
+        match self.peek_nth(n) {
+            Some(Token::LBrace) => true,
+            Some(Token::Match | Token::Loop) => self.keyword_starts_expr(n),
+            Some(Token::Async) => self.check_nth(n + 1, &Token::LBrace),
+            Some(Token::Ident(w)) => match w.as_str() {
+                "if" | "while" | "match" | "loop" | "for" => true,
+                "unsafe" => self.check_nth(n + 1, &Token::LBrace),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// M / L 之后的 Token 是否表明它们是关键字而不是变量名
+    fn keyword_starts_expr(&self, n: usize) -> bool {
+        if self.tokens.get(self.pos + n + 1).map(|t| t.line)
+            != self.tokens.get(self.pos + n).map(|t| t.line)
+        {
+            return false;
+        }
+        !matches!(
+            self.peek_nth(n + 1),
+            None | Some(
+                Token::PathSep
+                    | Token::Dot
+                    | Token::Comma
+                    | Token::Semi
+                    | Token::RParen
+                    | Token::RBracket
+                    | Token::RBrace
+                    | Token::Eq
+                    | Token::EqEq
+                    | Token::NotEq
+                    | Token::FatArrow
+                    | Token::Try
+                    | Token::Question
+                    | Token::Await
+                    | Token::Colon
+                    | Token::Plus
+                    | Token::Slash
+                    | Token::Percent,
+            )
+        )
+    }
+
+    fn parse_primary(&mut self) -> PResult<NuExpr> {
+        let start = self.pos;
+        let Some(tok) = self.peek().cloned() else {
+            return Err(self.error_expected("表达式"));
+        };
+
+        let kind = match tok {
+            Token::IntLit(s) => {
+                self.pos += 1;
+                ExprKind::Lit(Lit::Int(s))
+            }
+            Token::FloatLit(s) => {
+                self.pos += 1;
+                ExprKind::Lit(Lit::Float(s))
+            }
+            Token::StringLit(s) | Token::RawStringLit(s) => {
+                self.pos += 1;
+                ExprKind::Lit(Lit::Str(s))
+            }
+            Token::ByteStringLit(s) => {
+                self.pos += 1;
+                ExprKind::Lit(Lit::ByteStr(s))
+            }
+            Token::CharLit(s) => {
+                self.pos += 1;
+                ExprKind::Lit(Lit::Char(s))
+            }
+            Token::ByteLit(s) => {
+                self.pos += 1;
+                ExprKind::Lit(Lit::Byte(s))
+            }
+            Token::Lifetime(label) if self.check_nth(1, &Token::Colon) => {
+                self.pos += 2;
+                return self.parse_labeled(start, label);
+            }
+            Token::LParen => self.parse_paren_or_tuple()?,
+            Token::LBracket => self.parse_array()?,
+            Token::LBrace => ExprKind::Block {
+                label: None,
+                block: self.parse_block()?,
+            },
+            Token::Pipe | Token::OrOr => return self.parse_closure(start, false, false),
+            Token::Move => {
+                self.pos += 1;
+                if self.check(&Token::LBrace) {
+                    return Err(self.error_expected("闭包参数 `|`"));
+                }
+                return self.parse_closure(start, true, false);
+            }
+            Token::Async => {
+                self.pos += 1;
+                let is_move = self.eat(&Token::Move) || self.eat_word("move");
+                if matches!(self.peek(), Some(Token::Pipe | Token::OrOr)) {
+                    return self.parse_closure(start, is_move, true);
+                }
+                ExprKind::Async {
+                    is_move,
+                    block: self.parse_block()?,
+                }
+            }
+            Token::LessThanOrReturn => {
+                if self.looks_like_qpath() {
+                    let ty_start = self.pos;
+                    let kind = self.parse_qself_type()?;
+                    ExprKind::QPath(NuType {
+                        kind,
+                        span: self.span_from(ty_start),
+                    })
+                } else {
+                    self.pos += 1;
+                    ExprKind::Return(self.parse_opt_operand()?)
+                }
+            }
+            Token::Match if self.keyword_starts_expr(0) => {
+                self.pos += 1;
+                self.parse_match()?
+            }
+            Token::Loop if self.keyword_starts_expr(0) => {
+                self.pos += 1;
+                return self.parse_loop(start, None);
+            }
+            Token::Break => {
+                self.pos += 1;
+                self.parse_break()?
+            }
+            Token::Continue => {
+                self.pos += 1;
+                ExprKind::Continue {
+                    label: self.parse_opt_label(),
+                }
+            }
+            // 旧语法：? cond { } = if
+            Token::Question => {
+                self.pos += 1;
+                self.parse_if()?
+            }
+            Token::Ident(w) => match w.as_str() {
+                "true" | "false" => {
+                    self.pos += 1;
+                    ExprKind::Lit(Lit::Bool(w == "true"))
+                }
+                "if" => {
+                    self.pos += 1;
+                    self.parse_if()?
+                }
+                "while" => return self.parse_labeled_body(start, None),
+                "match" => {
+                    self.pos += 1;
+                    self.parse_match()?
+                }
+                "loop" | "for" => return self.parse_labeled_body(start, None),
+                "return" => {
+                    self.pos += 1;
+                    ExprKind::Return(self.parse_opt_operand()?)
+                }
+                "break" => {
+                    self.pos += 1;
+                    self.parse_break()?
+                }
+                "continue" => {
+                    self.pos += 1;
+                    ExprKind::Continue {
+                        label: self.parse_opt_label(),
+                    }
+                }
+                "unsafe" if self.check_nth(1, &Token::LBrace) => {
+                    self.pos += 1;
+                    ExprKind::Unsafe(self.parse_block()?)
+                }
+                "async"
+                    if matches!(self.peek_nth(1), Some(Token::LBrace))
+                        || self.is_word_nth(1, "move") =>
+                {
+                    self.pos += 1;
+                    let is_move = self.eat_word("move");
+                    ExprKind::Async {
+                        is_move,
+                        block: self.parse_block()?,
+                    }
+                }
+                "move" if matches!(self.peek_nth(1), Some(Token::Pipe | Token::OrOr)) => {
+                    self.pos += 1;
+                    return self.parse_closure(start, true, false);
+                }
+                "let" => {
+                    self.pos += 1;
+                    self.parse_let_expr()?
+                }
+                _ => return self.parse_path_expr(start),
+            },
+            Token::Let if self.no_struct => {
+                // if l Some(x) = y
+                self.pos += 1;
+                self.parse_let_expr()?
+            }
+            Token::PathSep => return self.parse_path_expr(start),
+            ref t if t.as_ident().is_some() => return self.parse_path_expr(start),
+            _ => return Err(self.error_expected("表达式")),
+        };
+        Ok(self.mk_expr(start, kind))
+    }
+
+    /// `<` 在表达式开头：若匹配的 `>` 之后紧跟 `::`，则为限定路径 `<T as Trait>::f`，否则为 return
+    fn looks_like_qpath(&self) -> bool {
+        let start_line = self.tokens[self.pos].line;
+        let mut depth = 0usize;
+        let mut i = self.pos;
+        while let Some(tok) = self.tokens.get(i) {
+            if tok.line != start_line {
+                return false;
+            }
+            match tok.token {
+                Token::LessThanOrReturn => depth += 1,
+                Token::GreaterThan | Token::Shr => {
+                    // `>>` 同时闭合两层
+                    let closing = if tok.token == Token::Shr { 2 } else { 1 };
+                    depth = depth.saturating_sub(closing);
+                    if depth == 0 {
+                        return matches!(
+                            self.tokens.get(i + 1).map(|t| &t.token),
+                            Some(Token::PathSep)
+                        );
+                    }
+                }
+                Token::Semi | Token::LBrace | Token::RBrace | Token::Eq => return false,
+                _ => {}
+            }
+            i += 1;
+        }
+        false
+    }
+
+    /// return / break 的可选操作数
+    fn parse_opt_operand(&mut self) -> PResult<Option<Box<NuExpr>>> {
+        if self.on_new_line()
+            || matches!(
+                self.peek(),
+                None | Some(
+                    Token::Semi | Token::RBrace | Token::RParen | Token::RBracket | Token::Comma
+                )
+            )
+        {
+            return Ok(None);
+        }
+        Ok(Some(Box::new(self.parse_expr()?)))
+    }
+
+    fn parse_opt_label(&mut self) -> Option<String> {
+        match self.peek() {
+            Some(Token::Lifetime(l)) if !self.on_new_line() => {
+                let l = l.clone();
+                self.pos += 1;
+                Some(l)
+            }
+            _ => None,
+        }
+    }
+
+    fn parse_break(&mut self) -> PResult<ExprKind> {
+        let label = self.parse_opt_label();
+        let expr = self.parse_opt_operand()?;
+        Ok(ExprKind::Break { label, expr })
+    }
+
+    /// `'a:` 之后的 loop / for / while / 块
+    fn parse_labeled(&mut self, start: usize, label: String) -> PResult<NuExpr> {
+        if self.check(&Token::LBrace) {
+            let block = self.parse_block()?;
+            return Ok(self.mk_expr(
+                start,
+                ExprKind::Block {
+                    label: Some(label),
+                    block,
+                },
+            ));
+        }
+        self.parse_labeled_body(start, Some(label))
+    }
+
+    /// L / loop / for / while
+    fn parse_labeled_body(&mut self, start: usize, label: Option<String>) -> PResult<NuExpr> {
+        if self.eat_word("while") {
+            let cond = self.with_no_struct(|p| p.parse_expr())?;
+            let body = self.parse_block()?;
+            return Ok(self.mk_expr(
+                start,
+                ExprKind::While {
+                    label,
+                    cond: Box::new(cond),
+                    body,
+                },
+            ));
+        }
+        if self.eat_word("loop") {
+            let body = self.parse_block()?;
+            return Ok(self.mk_expr(start, ExprKind::Loop { label, body }));
+        }
+        if self.eat_word("for") {
+            let pat = self.parse_pat()?;
+            if !self.eat_word("in") {
+                return Err(self.error_expected("`in`"));
+            }
+            return self.finish_for(start, label, pat);
+        }
+        self.expect(&Token::Loop)?;
+        self.parse_loop(start, label)
+    }
+
+    /// `L` 已消耗：L { } 或 L pat: iter { } / L pat in iter { }
+    fn parse_loop(&mut self, start: usize, label: Option<String>) -> PResult<NuExpr> {
+        if self.check(&Token::LBrace) {
+            let body = self.parse_block()?;
+            return Ok(self.mk_expr(start, ExprKind::Loop { label, body }));
+        }
+        let pat = self.parse_pat()?;
+        if !(self.eat(&Token::Colon) || self.eat_word("in")) {
+            return Err(self.error_expected("`:` 或 `in`"));
+        }
+        self.finish_for(start, label, pat)
+    }
+
+    fn finish_for(&mut self, start: usize, label: Option<String>, pat: NuPat) -> PResult<NuExpr> {
+        let iter = self.with_no_struct(|p| p.parse_expr())?;
+        let body = self.parse_block()?;
+        Ok(self.mk_expr(
+            start,
+            ExprKind::ForLoop {
+                label,
+                pat,
+                iter: Box::new(iter),
+                body,
+            },
+        ))
+    }
+
+    /// `if` 已消耗
+    fn parse_if(&mut self) -> PResult<ExprKind> {
+        let cond = self.with_no_struct(|p| p.parse_expr())?;
+        let then_branch = self.parse_block()?;
+        let else_branch = if self.eat_word("else") {
+            let start = self.pos;
+            if self.eat_word("if") {
+                let kind = self.parse_if()?;
+                Some(Box::new(self.mk_expr(start, kind)))
+            } else {
+                let block = self.parse_block()?;
+                Some(Box::new(
+                    self.mk_expr(start, ExprKind::Block { label: None, block }),
+                ))
+            }
+        } else {
+            None
+        };
+        Ok(ExprKind::If {
+            cond: Box::new(cond),
+            then_branch,
+            else_branch,
+        })
+    }
+
+    /// if let / while let 中的 `let pat = expr`
+    fn parse_let_expr(&mut self) -> PResult<ExprKind> {
+        let pat = self.parse_pat()?;
+        self.expect(&Token::Eq)?;
+        // let 的右侧不吞掉 && / ||（let 链）
+        let expr = self.parse_binary(PREC_CMP)?;
+        Ok(ExprKind::Let {
+            pat,
+            expr: Box::new(expr),
+        })
+    }
+
+    /// `M` 已消耗
+    fn parse_match(&mut self) -> PResult<ExprKind> {
+        let scrutinee = self.with_no_struct(|p| p.parse_expr())?;
+        self.expect(&Token::LBrace)?;
+        let arms = self.with_struct(|p| {
+            let mut arms = Vec::new();
+            while !p.eat(&Token::RBrace) {
+                if p.at_end() {
+                    return Err(p.error_expected("`}`"));
+                }
+                let start = p.pos;
+                let attrs = p.parse_outer_attrs()?;
+                let pat = p.parse_pat()?;
+                let guard = if p.eat_word("if") {
+                    Some(p.parse_expr()?)
+                } else {
+                    None
+                };
+                // `=>` 或 README 风格的 `:`
+                if !(p.eat(&Token::FatArrow) || p.eat(&Token::Colon)) {
+                    return Err(p.error_expected("`=>`"));
+                }
+                let body_block_like = p.is_block_like_start();
+                let body = p.parse_expr()?;
+                if !p.eat(&Token::Comma)
+                    && !body_block_like
+                    && !p.check(&Token::RBrace)
+                    && !p.on_new_line()
+                {
+                    return Err(p.error_expected("`,`"));
+                }
+                arms.push(MatchArm {
+                    attrs,
+                    pat,
+                    guard,
+                    body,
+                    span: p.span_from(start),
+                });
+            }
+            Ok(arms)
+        })?;
+        Ok(ExprKind::Match {
+            expr: Box::new(scrutinee),
+            arms,
+        })
+    }
+
+    fn parse_closure(&mut self, start: usize, is_move: bool, is_async: bool) -> PResult<NuExpr> {
+        if self.eat_word("move") {
+            return self.parse_closure(start, true, is_async);
+        }
+        let mut params = Vec::new();
+        if !self.eat(&Token::OrOr) {
+            self.expect(&Token::Pipe)?;
+            while !self.eat(&Token::Pipe) {
+                let pstart = self.pos;
+                let pat = self.parse_pat_no_alt()?;
+                let ty = if self.eat(&Token::Colon) {
+                    Some(self.parse_type()?)
+                } else {
+                    None
+                };
+                params.push(ClosureParam {
+                    pat,
+                    ty,
+                    span: self.span_from(pstart),
+                });
+                if !self.eat(&Token::Comma) {
+                    self.expect(&Token::Pipe)?;
+                    break;
+                }
+            }
+        }
+        let ret = if self.eat(&Token::Arrow) {
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+        let body = if ret.is_some() {
+            let body_start = self.pos;
+            let block = self.parse_block()?;
+            self.mk_expr(body_start, ExprKind::Block { label: None, block })
+        } else {
+            self.parse_expr()?
+        };
+        Ok(self.mk_expr(
+            start,
+            ExprKind::Closure {
+                is_move,
+                is_async,
+                params,
+                ret,
+                body: Box::new(body),
+            },
+        ))
+    }
+
+    /// `(` 开头：单元、括号表达式或元组
+    fn parse_paren_or_tuple(&mut self) -> PResult<ExprKind> {
+        self.expect(&Token::LParen)?;
+        self.with_struct(|p| {
+            let mut elems = Vec::new();
+            let mut trailing_comma = false;
+            while !p.eat(&Token::RParen) {
+                elems.push(p.parse_expr()?);
+                trailing_comma = p.eat(&Token::Comma);
+                if !trailing_comma {
+                    p.expect(&Token::RParen)?;
+                    break;
+                }
+            }
+            if elems.len() == 1 && !trailing_comma {
+                Ok(ExprKind::Paren(Box::new(elems.pop().unwrap())))
+            } else {
+                Ok(ExprKind::Tuple(elems))
+            }
+        })
+    }
+
+    /// [a, b] / [x; n]
+    fn parse_array(&mut self) -> PResult<ExprKind> {
+        self.expect(&Token::LBracket)?;
+        self.with_struct(|p| {
+            if p.eat(&Token::RBracket) {
+                return Ok(ExprKind::Array(Vec::new()));
+            }
+            let first = p.parse_expr()?;
+            if p.eat(&Token::Semi) {
+                let len = p.parse_expr()?;
+                p.expect(&Token::RBracket)?;
+                return Ok(ExprKind::Repeat {
+                    expr: Box::new(first),
+                    len: Box::new(len),
+                });
+            }
+            let mut elems = vec![first];
+            while p.eat(&Token::Comma) {
+                if p.check(&Token::RBracket) {
+                    break;
+                }
+                elems.push(p.parse_expr()?);
+            }
+            p.expect(&Token::RBracket)?;
+            Ok(ExprKind::Array(elems))
+        })
+    }
+
+    /// 路径表达式、宏调用或结构体字面量
+    fn parse_path_expr(&mut self, start: usize) -> PResult<NuExpr> {
+        let path = self.parse_path(PathMode::Expr)?;
+        // 紧贴的 `!` 已被归为 Try，这里的 Bang 允许有空白（`matches ! (x, ..)`）
+        if self.check(&Token::Bang)
+            && matches!(
+                self.peek_nth(1),
+                Some(Token::LParen | Token::LBracket | Token::LBrace)
+            )
+        {
+            let mac = self.parse_macro_call(start, path)?;
+            return Ok(self.mk_expr(start, ExprKind::Macro(mac)));
+        }
+        if !self.no_struct && self.check(&Token::LBrace) && self.looks_like_struct_lit() {
+            return self.parse_struct_lit(start, path);
+        }
+        Ok(self.mk_expr(start, ExprKind::Path(path)))
+    }
+
+    /// `{` 之后是否像结构体字面量：`}`、`ident:`、`ident,`、`ident }`、`..`
+    fn looks_like_struct_lit(&self) -> bool {
+        if self.on_new_line() {
+            return false;
+        }
+        match self.peek_nth(1) {
+            Some(Token::RBrace | Token::DotDot) => true,
+            Some(Token::IntLit(_)) => self.check_nth(2, &Token::Colon),
+            Some(tok) if tok.as_ident().is_some() => matches!(
+                self.peek_nth(2),
+                Some(Token::Colon | Token::Comma | Token::RBrace)
+            ),
+            _ => false,
+        }
+    }
+
+    fn parse_struct_lit(&mut self, start: usize, path: NuPath) -> PResult<NuExpr> {
+        self.expect(&Token::LBrace)?;
+        let (fields, base) = self.with_struct(|p| {
+            let mut fields = Vec::new();
+            let mut base = None;
+            while !p.eat(&Token::RBrace) {
+                if p.eat(&Token::DotDot) {
+                    base = Some(Box::new(p.parse_expr()?));
+                    p.eat(&Token::Comma);
+                    p.expect(&Token::RBrace)?;
+                    break;
+                }
+                let fstart = p.pos;
+                let name = match p.peek().cloned() {
+                    Some(Token::IntLit(n)) => {
+                        p.pos += 1;
+                        n
+                    }
+                    _ => p.expect_ident()?,
+                };
+                let expr = if p.eat(&Token::Colon) {
+                    Some(p.parse_expr()?)
+                } else {
+                    None
+                };
+                fields.push(FieldInit {
+                    name,
+                    expr,
+                    span: p.span_from(fstart),
+                });
+                p.expect_list_separator(&Token::RBrace)?;
+            }
+            Ok((fields, base))
+        })?;
+        Ok(self.mk_expr(start, ExprKind::Struct { path, fields, base }))
+    }
+
+    // ============ 模式 ============
+
+    fn parse_pat(&mut self) -> PResult<NuPat> {
+        let start = self.pos;
+        self.eat(&Token::Pipe);
+        let first = self.parse_pat_no_alt()?;
+        if !self.check(&Token::Pipe) {
+            return Ok(first);
+        }
+        let mut alts = vec![first];
+        while self.eat(&Token::Pipe) {
+            alts.push(self.parse_pat_no_alt()?);
+        }
+        Ok(NuPat {
+            kind: PatKind::Or(alts),
+            span: self.span_from(start),
+        })
+    }
+
+    fn mk_pat(&self, start: usize, kind: PatKind) -> NuPat {
+        NuPat {
+            kind,
+            span: self.span_from(start),
+        }
+    }
+
+    fn parse_pat_no_alt(&mut self) -> PResult<NuPat> {
+        let start = self.pos;
+        let kind = match self.peek().cloned() {
+            Some(Token::Ident(w)) if w == "_" => {
+                self.pos += 1;
+                PatKind::Wild
+            }
+            Some(Token::DotDot) => {
+                self.pos += 1;
+                PatKind::Rest
+            }
+            Some(Token::DotDotEq) => {
+                self.pos += 1;
+                let end = self.parse_pat_range_end()?;
+                PatKind::Range {
+                    start: None,
+                    end: Some(Box::new(end)),
+                    inclusive: true,
+                }
+            }
+            Some(Token::Amp) => {
+                self.pos += 1;
+                let mutable = self.eat_word("mut");
+                PatKind::Ref {
+                    mutable,
+                    pat: Box::new(self.parse_pat_no_alt()?),
+                }
+            }
+            Some(Token::RefMut) => {
+                self.pos += 1;
+                PatKind::Ref {
+                    mutable: true,
+                    pat: Box::new(self.parse_pat_no_alt()?),
+                }
+            }
+            Some(Token::AndAnd) => {
+                self.pos += 1;
+                let inner_start = self.pos;
+                let inner = self.parse_pat_no_alt()?;
+                let inner = self.mk_pat(
+                    inner_start,
+                    PatKind::Ref {
+                        mutable: false,
+                        pat: Box::new(inner),
+                    },
+                );
+                PatKind::Ref {
+                    mutable: false,
+                    pat: Box::new(inner),
+                }
+            }
+            Some(Token::LParen) => {
+                self.pos += 1;
+                let (elems, trailing_comma) = self.parse_pat_list(&Token::RParen)?;
+                if elems.len() == 1 && !trailing_comma {
+                    let mut inner = elems.into_iter().next().unwrap();
+                    inner.span = self.span_from(start);
+                    return Ok(inner);
+                }
+                PatKind::Tuple(elems)
+            }
+            Some(Token::LBracket) => {
+                self.pos += 1;
+                PatKind::Slice(self.parse_pat_list(&Token::RBracket)?.0)
+            }
+            Some(Token::Ident(w)) if w == "ref" || w == "mut" => {
+                let by_ref = self.eat_word("ref");
+                let mutable = self.eat_word("mut");
+                let name = self.expect_ident()?;
+                let sub = self.parse_pat_binding_sub()?;
+                PatKind::Ident {
+                    by_ref,
+                    mutable,
+                    name,
+                    sub,
+                }
+            }
+            Some(Token::Minus) => self.parse_pat_lit(start)?,
+            Some(Token::Ident(w)) if w == "true" || w == "false" => self.parse_pat_lit(start)?,
+            Some(tok) if tok.is_literal() => self.parse_pat_lit(start)?,
+            Some(tok) if tok.as_ident().is_some() || tok == Token::PathSep => {
+                let path = self.parse_path(PathMode::Expr)?;
+                self.parse_pat_after_path(start, path)?
+            }
+            _ => return Err(self.error_expected("模式")),
+        };
+        Ok(self.mk_pat(start, kind))
+    }
+
+    /// 模式列表（开括号已消耗），返回 (元素, 是否有尾逗号)
+    fn parse_pat_list(&mut self, close: &Token) -> PResult<(Vec<NuPat>, bool)> {
+        let mut elems = Vec::new();
+        let mut trailing_comma = false;
+        while !self.eat(close) {
+            elems.push(self.parse_pat()?);
+            trailing_comma = self.eat(&Token::Comma);
+            if !trailing_comma {
+                self.expect(close)?;
+                break;
+            }
+        }
+        Ok((elems, trailing_comma))
+    }
+
+    /// x @ Some(_)
+    fn parse_pat_binding_sub(&mut self) -> PResult<Option<Box<NuPat>>> {
+        if self.eat(&Token::Spawn) {
+            Ok(Some(Box::new(self.parse_pat_no_alt()?)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn parse_pat_lit(&mut self, start: usize) -> PResult<PatKind> {
+        let lit = self.parse_unary()?;
+        self.parse_pat_range_rest(start, lit)
+    }
+
+    /// 字面量或常量路径之后可能跟 `..=` / `..`
+    fn parse_pat_range_rest(&mut self, start: usize, lo: NuExpr) -> PResult<PatKind> {
+        let inclusive = match self.peek() {
+            Some(Token::DotDotEq | Token::DotDotDot) => true,
+            Some(Token::DotDot) => false,
+            _ => {
+                return Ok(match lo.kind {
+                    ExprKind::Path(path) => PatKind::Path(path),
+                    _ => PatKind::Lit(Box::new(lo)),
+                })
+            }
+        };
+        self.pos += 1;
+        let has_end = !matches!(
+            self.peek(),
+            None | Some(
+                Token::RParen
+                    | Token::RBracket
+                    | Token::Comma
+                    | Token::FatArrow
+                    | Token::Pipe
+                    | Token::Colon
+            )
+        ) && !self.is_word("if");
+        let end = if has_end {
+            Some(Box::new(self.parse_pat_range_end()?))
+        } else {
+            None
+        };
+        Ok(PatKind::Range {
+            start: Some(Box::new(lo)),
+            end,
+            inclusive,
+        })
+    }
+
+    fn parse_pat_range_end(&mut self) -> PResult<NuExpr> {
+        let start = self.pos;
+        if matches!(self.peek(), Some(tok) if tok.is_literal() || *tok == Token::Minus) {
+            return self.parse_unary();
+        }
+        let path = self.parse_path(PathMode::Expr)?;
+        Ok(self.mk_expr(start, ExprKind::Path(path)))
+    }
+
+    fn parse_pat_after_path(&mut self, start: usize, path: NuPath) -> PResult<PatKind> {
+        match self.peek() {
+            Some(Token::Bang) if self.is_adjacent() => {
+                Ok(PatKind::Macro(self.parse_macro_call(start, path)?))
+            }
+            Some(Token::LParen) => {
+                self.pos += 1;
+                let (elems, _) = self.parse_pat_list(&Token::RParen)?;
+                Ok(PatKind::TupleStruct { path, elems })
+            }
+            Some(Token::LBrace) if !self.no_struct => self.parse_struct_pat(path),
+            Some(Token::DotDot | Token::DotDotEq | Token::DotDotDot) => {
+                let lo = self.mk_expr(start, ExprKind::Path(path));
+                self.parse_pat_range_rest(start, lo)
+            }
+            _ => match path.as_ident() {
+                Some(name) => {
+                    let name = name.to_string();
+                    Ok(PatKind::Ident {
+                        by_ref: false,
+                        mutable: false,
+                        name,
+                        sub: self.parse_pat_binding_sub()?,
+                    })
+                }
+                None => Ok(PatKind::Path(path)),
+            },
+        }
+    }
+
+    fn parse_struct_pat(&mut self, path: NuPath) -> PResult<PatKind> {
+        self.expect(&Token::LBrace)?;
+        let mut fields = Vec::new();
+        let mut rest = false;
+        while !self.eat(&Token::RBrace) {
+            if self.eat(&Token::DotDot) {
+                rest = true;
+                self.expect(&Token::RBrace)?;
+                break;
+            }
+            let fstart = self.pos;
+            self.parse_outer_attrs()?;
+            let field = if self.is_word("ref") || self.is_word("mut") {
+                // 简写绑定：ref x / mut x
+                let pat = self.parse_pat_no_alt()?;
+                let name = match &pat.kind {
+                    PatKind::Ident { name, .. } => name.clone(),
+                    _ => return Err(self.error_expected("字段名")),
+                };
+                FieldPat {
+                    name,
+                    pat: Some(pat),
+                    span: self.span_from(fstart),
+                }
+            } else {
+                let name = match self.peek().cloned() {
+                    Some(Token::IntLit(n)) => {
+                        self.pos += 1;
+                        n
+                    }
+                    _ => self.expect_ident()?,
+                };
+                let pat = if self.eat(&Token::Colon) {
+                    Some(self.parse_pat()?)
+                } else {
+                    None
+                };
+                FieldPat {
+                    name,
+                    pat,
+                    span: self.span_from(fstart),
+                }
+            };
+            fields.push(field);
+            self.expect_list_separator(&Token::RBrace)?;
+        }
+        Ok(PatKind::Struct { path, fields, rest })
+    }
+}
+
+/// 大写关键字（F/S/TR/DM/U/CP/...）表示 pub；显式可见性优先
+fn keyword_vis(is_pub_kw: bool, vis: Visibility) -> Visibility {
+    if is_pub_kw && vis == Visibility::Private {
+        Visibility::Public
+    } else {
+        vis
+    }
+}
+
+/// Fn / FnMut / FnOnce 的括号语法糖
+fn is_fn_trait(ident: &str) -> bool {
+    matches!(ident, "Fn" | "FnMut" | "FnOnce")
+}
+
+fn starts_uppercase(name: &str) -> bool {
+    name.chars().next().is_some_and(|c| c.is_uppercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(src: &str) -> NuFile {
+        Parser::new().parse(src).unwrap()
+    }
+
+    fn fn_body(file: &NuFile) -> &NuBlock {
+        match &file.items[0] {
+            NuItem::Fn(f) => f.body.as_ref().unwrap(),
+            _ => panic!("expected fn"),
+        }
+    }
+
+    #[test]
+    fn test_parse_function_signature() {
+        let file = parse(
+            "F run<T: Clone + 'a>(&!self, input: &str, xs: V<T>) -> R<V<i32>, String> wh T: Debug {\n}",
+        );
+        let NuItem::Fn(f) = &file.items[0] else {
+            panic!("expected fn");
+        };
+        assert_eq!(f.name, "run");
+        assert!(f.is_public);
+        assert_eq!(
+            f.self_param,
+            Some(SelfParam::Ref {
+                lifetime: None,
+                mutable: true
+            })
+        );
+        assert_eq!(f.params.len(), 2);
+        assert_eq!(f.generics.params.len(), 1);
+        assert_eq!(f.generics.where_clause.len(), 1);
+        let ret = f.ret.as_ref().unwrap();
+        let TypeKind::Path(path) = &ret.kind else {
+            panic!("expected path type");
+        };
+        assert_eq!(path.segments[0].ident, "R");
+        assert_eq!(f.span.line, 1);
+        assert_eq!(f.span.col, 1);
+    }
+
+    #[test]
+    fn test_parse_items() {
+        let file = parse(
+            r#"
+u std::collections::{HashMap, HashSet as Set}
+D data_processor
+
+#D(Debug, Clone)
+S Processor {
+    id: u64,
+    cache: HashMap<String, i32>
+}
+
+s Pair(pub i32, String);
+
+E Shape {
+    Circle(f64),
+    Rect { w: f64, h: f64 },
+    Empty
+}
+
+TR Area: Clone {
+    f area(&self) -> f64;
+    f name(&self) -> String {
+        "shape".to_string()
+    }
+}
+
+I<T> Area for Wrapper<T> wh T: Clone {
+    f area(&self) -> f64 { 0.0 }
+}
+
+DM tests {
+    u super::*
+}
+"#,
+        );
+        assert_eq!(file.items.len(), 8);
+        let NuItem::Use(u) = &file.items[0] else {
+            panic!()
+        };
+        assert_eq!(u.path, "std::collections::{HashMap, HashSet as Set}");
+        assert_eq!(
+            u.tree.flatten(),
+            vec!["std::collections::HashMap", "std::collections::HashSet"]
+        );
+        let NuItem::Mod(m) = &file.items[1] else {
+            panic!()
+        };
+        assert!(m.items.is_none());
+        let NuItem::Struct(s) = &file.items[2] else {
+            panic!()
+        };
+        assert!(s.is_public);
+        assert_eq!(s.fields.len(), 2);
+        assert_eq!(
+            s.attrs[0].kind,
+            AttrKind::Derive(vec!["Debug".to_string(), "Clone".to_string()])
+        );
+        let NuItem::Struct(pair) = &file.items[3] else {
+            panic!()
+        };
+        assert!(!pair.is_public);
+        assert!(matches!(pair.fields, Fields::Tuple(ref f) if f.len() == 2));
+        let NuItem::Enum(e) = &file.items[4] else {
+            panic!()
+        };
+        assert_eq!(e.variants.len(), 3);
+        assert!(matches!(e.variants[1].fields, Fields::Named(_)));
+        let NuItem::Trait(t) = &file.items[5] else {
+            panic!()
+        };
+        assert_eq!(t.items.len(), 2);
+        assert_eq!(t.supertraits.len(), 1);
+        let NuItem::Impl(i) = &file.items[6] else {
+            panic!()
+        };
+        assert_eq!(i.type_name, "Wrapper<T>");
+        assert!(i.trait_ref.is_some());
+        let NuItem::Mod(tests) = &file.items[7] else {
+            panic!()
+        };
+        assert!(tests.is_public);
+        assert_eq!(tests.items.as_ref().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_parse_statements() {
+        let file = parse(
+            r#"
+f main() {
+    v results: V<i32> = V::new()
+    l parse = |s: &str| -> R<i32, String> {
+        l val = s.parse::<i32>().map_err(|_| format!("Bad num: {}", s))!;
+        < Ok(val)
+    };
+    L part: input.split(',') {
+        if part.len() < 2 { ct }
+        results.push(part.len())
+    }
+    v.push(1);
+    s.len()
+}
+"#,
+        );
+        let body = fn_body(&file);
+        assert_eq!(body.stmts.len(), 5);
+        let StmtKind::Let(local) = &body.stmts[0].kind else {
+            panic!()
+        };
+        assert!(local.is_mut);
+        assert!(local.ty.is_some());
+        let StmtKind::Let(closure) = &body.stmts[1].kind else {
+            panic!()
+        };
+        let ExprKind::Closure {
+            ret,
+            body: closure_body,
+            ..
+        } = &closure.init.as_ref().unwrap().kind
+        else {
+            panic!()
+        };
+        assert!(ret.is_some());
+        let ExprKind::Block { block, .. } = &closure_body.kind else {
+            panic!()
+        };
+        let StmtKind::Let(val) = &block.stmts[0].kind else {
+            panic!()
+        };
+        assert!(matches!(val.init.as_ref().unwrap().kind, ExprKind::Try(_)));
+        let StmtKind::Expr { expr, .. } = &block.stmts[1].kind else {
+            panic!()
+        };
+        assert!(matches!(expr.kind, ExprKind::Return(Some(_))));
+        let StmtKind::Expr { expr, .. } = &body.stmts[2].kind else {
+            panic!()
+        };
+        assert!(matches!(expr.kind, ExprKind::ForLoop { .. }));
+        // v / s 作为变量名
+        let StmtKind::Expr { expr, has_semi } = &body.stmts[3].kind else {
+            panic!()
+        };
+        assert!(*has_semi);
+        assert!(matches!(expr.kind, ExprKind::MethodCall { .. }));
+        let StmtKind::Expr { has_semi, .. } = &body.stmts[4].kind else {
+            panic!()
+        };
+        assert!(!*has_semi);
+    }
+
+    #[test]
+    fn test_parse_match_and_return() {
+        let file = parse(
+            r#"
+f check(n: i32) -> O<i32> {
+    M n {
+        0 => None,
+        1 | 2 => Some(1),
+        x if x < 0 => { < None }
+        Ok(v): Some(v),
+        _ => Some(n)
+    }
+}
+"#,
+        );
+        let body = fn_body(&file);
+        let StmtKind::Expr { expr, .. } = &body.stmts[0].kind else {
+            panic!()
+        };
+        let ExprKind::Match { arms, .. } = &expr.kind else {
+            panic!()
+        };
+        assert_eq!(arms.len(), 5);
+        assert!(matches!(arms[1].pat.kind, PatKind::Or(_)));
+        assert!(arms[2].guard.is_some());
+        assert!(matches!(arms[3].pat.kind, PatKind::TupleStruct { .. }));
+    }
+
+    #[test]
+    fn test_return_vs_less_than() {
+        let file = parse("f a(x: i32) -> bool {\n    l y = x < 3\n    < y\n}");
+        let body = fn_body(&file);
+        let StmtKind::Let(local) = &body.stmts[0].kind else {
+            panic!()
+        };
+        assert!(matches!(
+            local.init.as_ref().unwrap().kind,
+            ExprKind::Binary { op: BinOp::Lt, .. }
+        ));
+        let StmtKind::Expr { expr, .. } = &body.stmts[1].kind else {
+            panic!()
+        };
+        assert!(matches!(expr.kind, ExprKind::Return(Some(_))));
+    }
+
+    #[test]
+    fn test_shift_and_nested_generics() {
+        let file = parse("f a(x: V<V<u8>>) -> O<V<i32>>{\n    v y = x.len() >> 1;\n    y >>= 2;\n    l z: V<V<i32>>= V::new();\n    <V<i32>>::new()\n}");
+        let body = fn_body(&file);
+        let StmtKind::Let(local) = &body.stmts[0].kind else {
+            panic!()
+        };
+        assert!(matches!(
+            local.init.as_ref().unwrap().kind,
+            ExprKind::Binary { op: BinOp::Shr, .. }
+        ));
+        let StmtKind::Expr { expr, .. } = &body.stmts[1].kind else {
+            panic!()
+        };
+        assert!(matches!(
+            expr.kind,
+            ExprKind::AssignOp { op: BinOp::Shr, .. }
+        ));
+        assert!(matches!(body.stmts[2].kind, StmtKind::Let(_)));
+    }
+
+    #[test]
+    fn test_async_and_concurrency() {
+        let file = parse(
+            "~F fetch(url: &str) -> R<String, E> {\n    l r = get(url).~!;\n    l h = @@ $|| work();\n    Ok(r)\n}",
+        );
+        let NuItem::Fn(f) = &file.items[0] else {
+            panic!()
+        };
+        assert!(f.is_async);
+        let body = f.body.as_ref().unwrap();
+        let StmtKind::Let(local) = &body.stmts[0].kind else {
+            panic!()
+        };
+        let ExprKind::Try(inner) = &local.init.as_ref().unwrap().kind else {
+            panic!()
+        };
+        assert!(matches!(inner.kind, ExprKind::Await(_)));
+        let StmtKind::Let(local) = &body.stmts[1].kind else {
+            panic!()
+        };
+        let ExprKind::Thread(inner) = &local.init.as_ref().unwrap().kind else {
+            panic!()
+        };
+        assert!(matches!(
+            inner.kind,
+            ExprKind::Closure { is_move: true, .. }
+        ));
+    }
+
+    #[test]
+    fn test_macros_kept_verbatim() {
+        let file = parse(
+            "macro_rules! hello { ($x:expr) => { println!(\"{}\", $x) }; }\nf main() { println!(\"{} {}\", a, b); }",
+        );
+        let NuItem::Macro(m) = &file.items[0] else {
+            panic!()
+        };
+        assert_eq!(m.name.as_deref(), Some("hello"));
+        assert_eq!(m.mac.tokens, " ($x:expr) => { println!(\"{}\", $x) }; ");
+        let body = fn_body(&NuFile {
+            attrs: Vec::new(),
+            items: file.items[1..].to_vec(),
+        })
+        .clone();
+        let StmtKind::Expr { expr, .. } = &body.stmts[0].kind else {
+            panic!()
+        };
+        let ExprKind::Macro(mac) = &expr.kind else {
+            panic!()
+        };
+        assert_eq!(mac.tokens, "\"{} {}\", a, b");
+    }
+
+    #[test]
+    fn test_bang_after_keyword_and_spaced_macro() {
+        let file = parse("f g() { if!done { h(); } l ok = matches ! (x, 1); }");
+        let body = fn_body(&file);
+        let StmtKind::Expr { expr, .. } = &body.stmts[0].kind else {
+            panic!()
+        };
+        let ExprKind::If { cond, .. } = &expr.kind else {
+            panic!("expected if")
+        };
+        assert!(matches!(cond.kind, ExprKind::Unary { op: UnOp::Not, .. }));
+        let StmtKind::Let(local) = &body.stmts[1].kind else {
+            panic!()
+        };
+        assert!(matches!(
+            local.init.as_ref().map(|e| &e.kind),
+            Some(ExprKind::Macro(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_const_static_type_extern() {
+        let file = parse(
+            r#"
+CP MAX: usize = 100;
+C SECRET: &str = "x"
+SP NAME: &str = "nu";
+SM COUNTER: u32 = 0;
+t Res<T> = R<T, String>;
+
+E Code {
+    Ok = 0,
+    Err = -1
+}
+
+S Point {
+    pub(crate) x: i32,
+    y: i32
+}
+
+TR Shape {
+    t Out: Clone;
+    C SIDES: usize;
+    f area(&self) -> f64;
+}
+
+I Shape for Point {
+    t Out = i32;
+    C SIDES: usize = 4;
+    f area(&self) -> f64 { 0.0 }
+}
+
+EXT "C" {
+    f abs(x: i32) -> i32;
+    ST errno: i32;
+}
+
+EXT "C" f callback(x: i32) -> i32 { x }
+"#,
+        );
+        assert_eq!(file.items.len(), 11);
+        let NuItem::Const(max) = &file.items[0] else {
+            panic!()
+        };
+        assert!(max.is_public);
+        assert!(max.value.is_some());
+        let NuItem::Const(secret) = &file.items[1] else {
+            panic!()
+        };
+        assert!(!secret.is_public);
+        let NuItem::Static(name) = &file.items[2] else {
+            panic!()
+        };
+        assert!(name.is_public && !name.mutable);
+        let NuItem::Static(counter) = &file.items[3] else {
+            panic!()
+        };
+        assert!(!counter.is_public && counter.mutable);
+        let NuItem::TypeAlias(res) = &file.items[4] else {
+            panic!()
+        };
+        assert_eq!(res.generics.params.len(), 1);
+        assert!(res.ty.is_some());
+        let NuItem::Enum(code) = &file.items[5] else {
+            panic!()
+        };
+        assert!(code.variants.iter().all(|v| v.discriminant.is_some()));
+        let NuItem::Struct(point) = &file.items[6] else {
+            panic!()
+        };
+        let Fields::Named(fields) = &point.fields else {
+            panic!()
+        };
+        assert_eq!(fields[0].vis, Visibility::Crate);
+        assert_eq!(fields[1].vis, Visibility::Private);
+        let NuItem::Trait(shape) = &file.items[7] else {
+            panic!()
+        };
+        assert!(
+            matches!(&shape.items[0], TraitItem::Type(t) if t.ty.is_none() && t.bounds.len() == 1)
+        );
+        assert!(matches!(&shape.items[1], TraitItem::Const(c) if c.value.is_none()));
+        let NuItem::Impl(imp) = &file.items[8] else {
+            panic!()
+        };
+        assert!(matches!(&imp.items[0], ImplItem::Type(t) if t.ty.is_some()));
+        assert!(matches!(&imp.items[1], ImplItem::Const(c) if c.value.is_some()));
+        let NuItem::Extern(ext) = &file.items[9] else {
+            panic!()
+        };
+        assert_eq!(ext.abi.as_deref(), Some("\"C\""));
+        assert!(matches!(&ext.items[0], ForeignItem::Fn(f) if f.body.is_none()));
+        assert!(matches!(&ext.items[1], ForeignItem::Static(st) if st.value.is_none()));
+        let NuItem::Fn(callback) = &file.items[10] else {
+            panic!()
+        };
+        assert_eq!(callback.abi.as_deref(), Some("\"C\""));
+    }
+
+    #[test]
+    fn test_spans_carry_file() {
+        let parser = Parser::with_file("src/main.nu");
+        let file = parser
+            .parse("f main() {\n    l t = 1\n    t += 2\n}")
+            .unwrap();
+        let span = file.items[0].span();
+        assert_eq!(span.file.as_deref(), Some("src/main.nu"));
+        assert_eq!(span.to_string(), "src/main.nu:1:1");
+
+        let (_, errors) = parser.parse_with_errors("f main() {\n    l x = (1\n}");
+        assert!(errors[0].to_string().starts_with("src/main.nu:"));
+    }
+
+    #[test]
+    fn test_error_recovery() {
+        let (file, errors) = Parser::new().parse_with_errors(
+            "F good() {}\n\nS Broken {\n    x: ,\n}\n\nF also_good() -> i32 { 1 }\n",
+        );
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].span.line, 4);
+        assert_eq!(file.items.len(), 2);
+        assert!(matches!(&file.items[1], NuItem::Fn(f) if f.name == "also_good"));
+        assert!(Parser::new().parse("F x() { ` }").is_err());
+    }
+
+    #[test]
+    fn test_render_error_snippet() {
+        let source = "F main() {\n    l x = ;\n}";
+        let (_, errors) = Parser::with_file("main.nu").parse_with_errors(source);
+        let rendered = errors[0].render(source);
+        assert!(rendered.starts_with("error: "));
+        assert!(rendered.contains(" --> main.nu:2:11\n"));
+        assert!(rendered.ends_with("  |\n2 |     l x = ;\n  |           ^"));
+    }
+}
@@ -0,0 +1,284 @@
+// 压缩配置
+// rust2nu 与 nu2rust 共用：哪些标准库类型缩写为单字母、哪些属性/语句压缩为 Nu 简写。
+// 非默认配置记录在 Nu 文件首行（`// nu:compress ...`），反向转换按同一配置还原
+
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// 类型缩写 -> 标准库类型
+pub const TYPE_ABBREVIATIONS: &[(&str, &str)] = &[
+    ("V", "Vec"),
+    ("O", "Option"),
+    ("R", "Result"),
+    ("A", "Arc"),
+    ("X", "Mutex"),
+    ("B", "Box"),
+    ("W", "Weak"),
+];
+
+/// 记录压缩配置的 Nu 文件首行前缀
+pub const HEADER_PREFIX: &str = "// nu:compress";
+
+/// 压缩配置解析错误
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ProfileError {
+    #[error("未知的压缩配置: {0}（可用: minimal、default、max）")]
+    UnknownProfile(String),
+
+    #[error("未知的压缩选项: {0}")]
+    UnknownKey(String),
+
+    #[error("压缩选项 {key} 的值无效: {value}（应为 true 或 false）")]
+    InvalidValue { key: String, value: String },
+
+    #[error("不支持缩写的类型: {0}")]
+    UnknownType(String),
+}
+
+/// 压缩配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompressionProfile {
+    /// 缩写为单字母的标准库类型（完整名称，按 TYPE_ABBREVIATIONS 顺序）
+    pub types: Vec<String>,
+    /// `#[derive(..)]` -> `#D(..)`
+    pub derive: bool,
+    /// `#[inline]` -> `#I`
+    pub inline: bool,
+    /// 语句级 `return` -> `<`
+    pub returns: bool,
+    /// `for` / `loop` -> `L`
+    pub loops: bool,
+}
+
+impl Default for CompressionProfile {
+    fn default() -> Self {
+        Self {
+            types: ["Vec", "Option", "Result", "Arc", "Mutex", "Box"]
+                .map(String::from)
+                .to_vec(),
+            derive: true,
+            inline: false,
+            returns: true,
+            loops: true,
+        }
+    }
+}
+
+impl CompressionProfile {
+    /// 只保留关键字压缩（F/l/v 等），类型、属性、return 与循环保持 Rust 写法
+    pub fn minimal() -> Self {
+        Self {
+            types: Vec::new(),
+            derive: false,
+            inline: false,
+            returns: false,
+            loops: false,
+        }
+    }
+
+    /// 全部压缩，包括 Weak -> W 与 `#[inline]` -> `#I`
+    pub fn max() -> Self {
+        Self {
+            types: TYPE_ABBREVIATIONS
+                .iter()
+                .map(|(_, full)| full.to_string())
+                .collect(),
+            derive: true,
+            inline: true,
+            returns: true,
+            loops: true,
+        }
+    }
+
+    /// 按名称取预置配置
+    pub fn named(name: &str) -> Option<Self> {
+        match name {
+            "minimal" => Some(Self::minimal()),
+            "default" => Some(Self::default()),
+            "max" => Some(Self::max()),
+            _ => None,
+        }
+    }
+
+    /// 与预置配置相同时返回其名称
+    pub fn name(&self) -> Option<&'static str> {
+        ["minimal", "default", "max"]
+            .into_iter()
+            .find(|name| Self::named(name).as_ref() == Some(self))
+    }
+
+    /// 标准库类型 full 的缩写；本配置不缩写该类型时返回 None
+    pub fn abbreviation(&self, full: &str) -> Option<&'static str> {
+        if !self.types.iter().any(|t| t == full) {
+            return None;
+        }
+        TYPE_ABBREVIATIONS
+            .iter()
+            .find(|(_, f)| *f == full)
+            .map(|(abbr, _)| *abbr)
+    }
+
+    /// 缩写 abbr 的完整类型名；本配置不使用该缩写时返回 None
+    pub fn expansion(&self, abbr: &str) -> Option<&'static str> {
+        let (_, full) = TYPE_ABBREVIATIONS.iter().find(|(a, _)| *a == abbr)?;
+        self.types.iter().any(|t| t == full).then_some(*full)
+    }
+
+    /// 写在 Nu 文件首行的配置记录；默认配置不需要记录
+    pub fn header(&self) -> Option<String> {
+        (*self != Self::default()).then(|| format!("{} {}", HEADER_PREFIX, self))
+    }
+
+    /// 解析 Nu 文件首行的配置记录；line 不是配置记录时返回 Ok(None)
+    pub fn from_header(line: &str) -> Result<Option<Self>, ProfileError> {
+        let Some(spec) = line.trim().strip_prefix(HEADER_PREFIX) else {
+            return Ok(None);
+        };
+        if !spec.is_empty() && !spec.starts_with(char::is_whitespace) {
+            return Ok(None);
+        }
+        spec.parse().map(Some)
+    }
+
+    fn set_types(&mut self, value: &str) -> Result<(), ProfileError> {
+        let mut types = Vec::new();
+        for name in value.split(',').map(str::trim) {
+            if name.is_empty() || name == "none" {
+                continue;
+            }
+            if name == "all" {
+                types.extend(TYPE_ABBREVIATIONS.iter().map(|(_, full)| *full));
+                continue;
+            }
+            // 同时接受完整名称和缩写：Vec 或 V
+            let full = TYPE_ABBREVIATIONS
+                .iter()
+                .find(|(abbr, full)| *full == name || *abbr == name)
+                .map(|(_, full)| *full)
+                .ok_or_else(|| ProfileError::UnknownType(name.to_string()))?;
+            types.push(full);
+        }
+        self.types = TYPE_ABBREVIATIONS
+            .iter()
+            .map(|(_, full)| *full)
+            .filter(|full| types.contains(full))
+            .map(String::from)
+            .collect();
+        Ok(())
+    }
+}
+
+fn parse_bool(key: &str, value: &str) -> Result<bool, ProfileError> {
+    match value {
+        "true" | "on" | "yes" => Ok(true),
+        "false" | "off" | "no" => Ok(false),
+        _ => Err(ProfileError::InvalidValue {
+            key: key.to_string(),
+            value: value.to_string(),
+        }),
+    }
+}
+
+/// 预置名称，或 `key=value` 列表（可跟在预置名称之后覆盖其中的选项）：
+/// `minimal`、`types=Vec,Option derive=true`、`default returns=false`
+impl FromStr for CompressionProfile {
+    type Err = ProfileError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut profile = Self::default();
+        for part in s.split_whitespace() {
+            let Some((key, value)) = part.split_once('=') else {
+                profile = Self::named(part)
+                    .ok_or_else(|| ProfileError::UnknownProfile(part.to_string()))?;
+                continue;
+            };
+            match key {
+                "types" => profile.set_types(value)?,
+                "derive" => profile.derive = parse_bool(key, value)?,
+                "inline" => profile.inline = parse_bool(key, value)?,
+                "returns" => profile.returns = parse_bool(key, value)?,
+                "loops" => profile.loops = parse_bool(key, value)?,
+                _ => return Err(ProfileError::UnknownKey(key.to_string())),
+            }
+        }
+        Ok(profile)
+    }
+}
+
+impl fmt::Display for CompressionProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(name) = self.name() {
+            return f.write_str(name);
+        }
+        let types = if self.types.is_empty() {
+            "none".to_string()
+        } else {
+            self.types.join(",")
+        };
+        write!(
+            f,
+            "types={} derive={} inline={} returns={} loops={}",
+            types, self.derive, self.inline, self.returns, self.loops
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_profiles() {
+        assert_eq!(
+            "minimal".parse::<CompressionProfile>(),
+            Ok(CompressionProfile::minimal())
+        );
+        assert_eq!(
+            "".parse::<CompressionProfile>(),
+            Ok(CompressionProfile::default())
+        );
+
+        let profile: CompressionProfile = "minimal types=Option,V derive=on".parse().unwrap();
+        assert_eq!(profile.types, vec!["Vec", "Option"]);
+        assert!(profile.derive && !profile.returns);
+        assert_eq!(profile.abbreviation("Vec"), Some("V"));
+        assert_eq!(profile.abbreviation("Result"), None);
+        assert_eq!(profile.expansion("O"), Some("Option"));
+        assert_eq!(profile.expansion("R"), None);
+
+        // Display 与 FromStr 往返一致
+        assert_eq!(
+            profile.to_string().parse::<CompressionProfile>(),
+            Ok(profile)
+        );
+        assert_eq!(CompressionProfile::max().to_string(), "max");
+
+        assert_eq!(
+            "tiny".parse::<CompressionProfile>(),
+            Err(ProfileError::UnknownProfile("tiny".into()))
+        );
+        assert_eq!(
+            "types=Vec,HashMap".parse::<CompressionProfile>(),
+            Err(ProfileError::UnknownType("HashMap".into()))
+        );
+        assert!("loops=maybe".parse::<CompressionProfile>().is_err());
+        assert!("while=true".parse::<CompressionProfile>().is_err());
+    }
+
+    #[test]
+    fn test_header() {
+        assert_eq!(CompressionProfile::default().header(), None);
+        let header = CompressionProfile::minimal().header().unwrap();
+        assert_eq!(header, "// nu:compress minimal");
+        assert_eq!(
+            CompressionProfile::from_header(&header),
+            Ok(Some(CompressionProfile::minimal()))
+        );
+        assert_eq!(
+            CompressionProfile::from_header("// nu:compressed"),
+            Ok(None)
+        );
+        assert_eq!(CompressionProfile::from_header("F main() {"), Ok(None));
+    }
+}
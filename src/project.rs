@@ -1,14 +1,290 @@
 // Nu Project Management
-// Nu.toml解析和项目构建
+// Nu.toml解析和项目构建：所有工具读取 Nu.toml 的统一入口
 
-pub struct ProjectManager {
-    // TODO: 实现ProjectManager
+pub use crate::diagnostics::DiagnosticRemapper;
+use crate::workspace::{
+    convert_key_cargo_to_nu, convert_section_cargo_to_nu, BinaryTarget, Cargo2NuConverter,
+    CargoParser, ConfigFileHandler, ConvertOptions, ConvertReport, DependencySpec, FileOutcome,
+    LibTarget, Nu2CargoConverter, NuProjectConverter, NuWorkspaceAnalyzer, WorkspaceConfig,
+    WorkspacePackage, WorkspaceType,
+};
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 影子 Cargo 项目相对项目根目录的位置
+pub const SHADOW_DIR: &str = "target/nu-gen";
+
+/// cargo new 生成的 .gitignore
+const CARGO_GITIGNORE: &str = "/target\n";
+
+const MAIN_TEMPLATE: &str = r#"f main() {
+    println!("Hello, world!");
+}
+"#;
+
+const LIB_TEMPLATE: &str = r#"F add(left: u64, right: u64) -> u64 {
+    left + right
+}
+
+#[cfg(test)]
+D tests {
+    u super::*;
+
+    #[test]
+    f it_works() {
+        l result = add(2, 2);
+        assert_eq!(result, 4);
+    }
+}
+"#;
+
+/// nuc init 创建的 crate 类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProjectKind {
+    #[default]
+    Bin,
+    Lib,
+    /// 同时包含 src/lib.nu 与 src/main.nu
+    LibAndBin,
+}
+
+impl ProjectKind {
+    /// 按 `--lib` / `--bin` 选择：都未指定时与 cargo new 一样创建二进制包
+    pub fn from_flags(lib: bool, bin: bool) -> Self {
+        match (lib, bin) {
+            (true, true) => ProjectKind::LibAndBin,
+            (true, false) => ProjectKind::Lib,
+            (false, _) => ProjectKind::Bin,
+        }
+    }
+
+    /// 用于提示信息的名称
+    pub fn description(self) -> &'static str {
+        match self {
+            ProjectKind::Bin => "binary",
+            ProjectKind::Lib => "library",
+            ProjectKind::LibAndBin => "library and binary",
+        }
+    }
+}
+
+/// nuc init 选项
+#[derive(Debug, Clone, Default)]
+pub struct InitOptions {
+    pub kind: ProjectKind,
+    /// 创建 [W] 工作区根目录，成员位于 crates/<name>
+    pub workspace: bool,
 }
 
+/// 加载后的 Nu 项目：单包、混合工作区或虚拟工作区
+#[derive(Debug, Clone)]
+pub struct NuProject {
+    pub root: PathBuf,
+    pub workspace_type: WorkspaceType,
+    /// [W] 配置；非工作区项目为 None
+    pub workspace: Option<WorkspaceConfig>,
+    /// 根目录自身的包（单包项目或混合工作区）
+    pub package: Option<NuPackage>,
+    /// 工作区成员，按 [W] m 展开后的顺序
+    pub members: Vec<NuPackage>,
+}
+
+impl NuProject {
+    /// 根包与全部成员
+    pub fn packages(&self) -> impl Iterator<Item = &NuPackage> {
+        self.package.iter().chain(self.members.iter())
+    }
+
+    pub fn find_package(&self, name: &str) -> Option<&NuPackage> {
+        self.packages().find(|p| p.name == name)
+    }
+}
+
+/// Nu.toml 描述的一个包
+///
+/// 键名按 nu2cargo 的映射还原，`w = true` 的字段与依赖已从 [W.P]/[W.D] 展开；
+/// 目标路径均为相对包目录的 .nu 源文件
+#[derive(Debug, Clone, Default)]
+pub struct NuPackage {
+    pub dir: PathBuf,
+    pub name: String,
+    /// version、edition、description 等其余 [P] 字段
+    pub metadata: WorkspacePackage,
+    pub lib: Option<LibTarget>,
+    pub bins: Vec<BinaryTarget>,
+    pub examples: Vec<BinaryTarget>,
+    pub tests: Vec<BinaryTarget>,
+    pub benches: Vec<BinaryTarget>,
+    pub features: HashMap<String, Vec<String>>,
+    pub dependencies: HashMap<String, DependencySpec>,
+    pub dev_dependencies: HashMap<String, DependencySpec>,
+    pub build_dependencies: HashMap<String, DependencySpec>,
+}
+
+impl NuPackage {
+    pub fn version(&self) -> Option<&str> {
+        self.metadata.version.as_deref()
+    }
+
+    pub fn edition(&self) -> Option<&str> {
+        self.metadata.edition.as_deref()
+    }
+
+    /// 只有 lib 目标、没有 bin 目标
+    pub fn is_lib_only(&self) -> bool {
+        self.lib.is_some() && self.bins.is_empty()
+    }
+}
+
+/// Nu 项目管理：创建、加载 Nu.toml 与生成影子 Cargo 项目
+pub struct ProjectManager {}
+
 impl ProjectManager {
     pub fn new() -> Self {
         Self {}
     }
+
+    /// 加载 root 处的 Nu 项目；root 是工作区成员时从外层工作区继承字段
+    pub fn load(&self, root: &Path) -> Result<NuProject> {
+        let manifest = Manifest::read(root)?;
+        let workspace_type = manifest.workspace_type();
+
+        if !workspace_type.is_workspace() {
+            let outer = enclosing_workspace(root)?;
+            return Ok(NuProject {
+                root: root.to_path_buf(),
+                workspace_type,
+                workspace: None,
+                package: Some(load_package(&manifest, outer.as_ref())?),
+                members: Vec::new(),
+            });
+        }
+
+        let config = CargoParser::parse(&manifest.cargo)
+            .with_context(|| format!("无效的 Nu.toml: {}", manifest.path().display()))?;
+        let package = if workspace_type == WorkspaceType::Mixed {
+            Some(load_package(&manifest, Some(&manifest))?)
+        } else {
+            None
+        };
+        let members = NuWorkspaceAnalyzer::from_dir(root)?
+            .expand_members()?
+            .iter()
+            .map(|member| {
+                let member_manifest = Manifest::read(&root.join(member))?;
+                load_package(&member_manifest, Some(&manifest))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(NuProject {
+            root: root.to_path_buf(),
+            workspace_type,
+            workspace: Some(config),
+            package,
+            members,
+        })
+    }
+
+    /// 在 root 下创建 Nu 项目骨架，返回创建的文件列表
+    pub fn init(&self, root: &Path, name: &str, options: &InitOptions) -> Result<Vec<PathBuf>> {
+        validate_crate_name(name)?;
+        if root.exists() && fs::read_dir(root)?.next().is_some() {
+            anyhow::bail!("目标目录已存在且非空: {}", root.display());
+        }
+
+        let mut created = Vec::new();
+        let crate_dir = if options.workspace {
+            let cargo_toml = format!(
+                "[workspace]\nmembers = [\"crates/{}\"]\nresolver = \"2\"\n",
+                name
+            );
+            write_file(
+                &root.join("Nu.toml"),
+                &cargo_to_nu(&cargo_toml),
+                &mut created,
+            )?;
+            root.join("crates").join(name)
+        } else {
+            root.to_path_buf()
+        };
+
+        let cargo_toml = format!(
+            "[package]\nname = \"{}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[dependencies]\n",
+            name
+        );
+        write_file(
+            &crate_dir.join("Nu.toml"),
+            &cargo_to_nu(&cargo_toml),
+            &mut created,
+        )?;
+
+        let sources: &[(&str, &str)] = match options.kind {
+            ProjectKind::Bin => &[("main.nu", MAIN_TEMPLATE)],
+            ProjectKind::Lib => &[("lib.nu", LIB_TEMPLATE)],
+            ProjectKind::LibAndBin => &[("lib.nu", LIB_TEMPLATE), ("main.nu", MAIN_TEMPLATE)],
+        };
+        for (source, template) in sources {
+            write_file(&crate_dir.join("src").join(source), template, &mut created)?;
+        }
+
+        // 与 cargo2nu 转换 cargo new 项目的结果保持一致
+        let gitignore = ConfigFileHandler::gitignore_cargo_to_nu(CARGO_GITIGNORE);
+        write_file(&root.join(".gitignore"), &gitignore, &mut created)?;
+
+        Ok(created)
+    }
+
+    /// 从 start 向上查找包含 Nu.toml 的项目根目录；位于 [W] 工作区内时返回工作区根目录
+    pub fn find_root(start: &Path) -> Option<PathBuf> {
+        let nearest = start
+            .ancestors()
+            .find(|dir| dir.join("Nu.toml").is_file())?;
+        let workspace = nearest.ancestors().skip(1).find(|dir| {
+            fs::read_to_string(dir.join("Nu.toml"))
+                .map(|content| WorkspaceType::from_nu_toml(&content).is_workspace())
+                .unwrap_or(false)
+        });
+        Some(workspace.unwrap_or(nearest).to_path_buf())
+    }
+
+    /// 将 Nu 项目增量转换到 target/nu-gen 影子 Cargo 项目，返回影子目录与转换报告
+    pub fn generate_shadow(&self, root: &Path) -> Result<(PathBuf, ConvertReport)> {
+        let shadow = root.join(SHADOW_DIR);
+        let options = ConvertOptions {
+            incremental: true,
+            sourcemaps: true,
+            ..Default::default()
+        };
+        let report = NuProjectConverter::new(options)
+            .convert(root, &shadow)
+            .with_context(|| format!("无法生成影子项目: {}", shadow.display()))?;
+
+        let failures: Vec<String> = report
+            .files
+            .iter()
+            .filter_map(|f| match &f.outcome {
+                FileOutcome::Failed(e) => Some(format!("{}: {}", f.source.display(), e)),
+                _ => None,
+            })
+            .collect();
+        if !failures.is_empty() {
+            anyhow::bail!("转换失败:\n{}", failures.join("\n"));
+        }
+
+        // 影子项目位于外层目录之下，声明为独立 workspace 以免被上层 Cargo.toml 收编
+        let cargo_toml = shadow.join("Cargo.toml");
+        let content = fs::read_to_string(&cargo_toml)
+            .with_context(|| format!("缺少 Cargo.toml: {}", cargo_toml.display()))?;
+        if !content.contains("[workspace]") {
+            fs::write(
+                &cargo_toml,
+                format!("{}\n[workspace]\n", content.trim_end()),
+            )?;
+        }
+        Ok((shadow, report))
+    }
 }
 
 impl Default for ProjectManager {
@@ -16,3 +292,585 @@ impl Default for ProjectManager {
         Self::new()
     }
 }
+
+fn cargo_to_nu(cargo_toml: &str) -> String {
+    Cargo2NuConverter::new().convert(cargo_toml)
+}
+
+/// 一个 Nu.toml；按 nu2cargo 的映射转换为 Cargo 键名后解析，保证与生成的 Cargo.toml 一致
+struct Manifest {
+    dir: PathBuf,
+    cargo: String,
+    value: toml::Value,
+}
+
+impl Manifest {
+    fn read(dir: &Path) -> Result<Self> {
+        let path = dir.join("Nu.toml");
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("无法读取 Nu.toml: {}", path.display()))?;
+        let cargo = Nu2CargoConverter::new().convert(&content);
+        let value = cargo
+            .parse::<toml::Value>()
+            .with_context(|| format!("无效的 Nu.toml: {}", path.display()))?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            cargo,
+            value,
+        })
+    }
+
+    fn path(&self) -> PathBuf {
+        self.dir.join("Nu.toml")
+    }
+
+    fn workspace_type(&self) -> WorkspaceType {
+        WorkspaceType::from_cargo_toml(&self.cargo)
+    }
+
+    fn table(&self, key: &str) -> Option<&toml::Table> {
+        self.value.get(key).and_then(toml::Value::as_table)
+    }
+
+    /// [W] 下的子表，如 package、dependencies
+    fn workspace_table(&self, key: &str) -> Option<&toml::Table> {
+        self.table("workspace")?.get(key)?.as_table()
+    }
+}
+
+/// dir 外层的 [W] 工作区
+fn enclosing_workspace(dir: &Path) -> Result<Option<Manifest>> {
+    let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+    let outer = dir.ancestors().skip(1).find(|d| {
+        fs::read_to_string(d.join("Nu.toml"))
+            .map(|content| WorkspaceType::from_nu_toml(&content).is_workspace())
+            .unwrap_or(false)
+    });
+    outer.map(Manifest::read).transpose()
+}
+
+fn load_package(manifest: &Manifest, workspace: Option<&Manifest>) -> Result<NuPackage> {
+    let path = manifest.path();
+    let package = manifest
+        .table("package")
+        .ok_or_else(|| anyhow::anyhow!("{} 缺少 [P] 节", path.display()))?;
+
+    // [P] 中 `key.w = true` 的字段取自 [W.P]
+    let inherited = workspace.and_then(|w| w.workspace_table("package"));
+    let mut resolved = toml::Table::new();
+    for (key, value) in package {
+        let value = if is_inherited(value) {
+            inherited.and_then(|p| p.get(key)).cloned().ok_or_else(|| {
+                anyhow::anyhow!(
+                    "{}: [P] 的 `{}` 声明从工作区继承，但 [W.P] 中没有该字段",
+                    path.display(),
+                    convert_key_cargo_to_nu(key)
+                )
+            })?
+        } else {
+            value.clone()
+        };
+        resolved.insert(key.clone(), value);
+    }
+
+    let name = resolved
+        .get("name")
+        .and_then(toml::Value::as_str)
+        .ok_or_else(|| anyhow::anyhow!("{}: [P] 缺少 id", path.display()))?
+        .to_string();
+    let metadata = CargoParser::parse_workspace_package(&toml::Value::Table(resolved.clone()))?;
+
+    let workspace_deps = workspace.and_then(|w| w.workspace_table("dependencies"));
+    let dependencies = |key: &str| {
+        parse_dependencies(manifest.value.get(key), workspace_deps)
+            .with_context(|| format!("{}: 无效的依赖表", path.display()))
+    };
+
+    let features = match manifest.value.get("features") {
+        Some(toml::Value::Table(table)) => table
+            .iter()
+            .map(|(name, list)| Ok((name.clone(), CargoParser::parse_string_array(list)?)))
+            .collect::<Result<HashMap<_, _>>>()?,
+        _ => HashMap::new(),
+    };
+
+    let targets = |kind: TargetKind| binary_targets(manifest, &resolved, &name, kind);
+    Ok(NuPackage {
+        dir: manifest.dir.clone(),
+        lib: lib_target(manifest),
+        bins: targets(TargetKind::Bin)?,
+        examples: targets(TargetKind::Example)?,
+        tests: targets(TargetKind::Test)?,
+        benches: targets(TargetKind::Bench)?,
+        features,
+        dependencies: dependencies("dependencies")?,
+        dev_dependencies: dependencies("dev-dependencies")?,
+        build_dependencies: dependencies("build-dependencies")?,
+        name,
+        metadata,
+    })
+}
+
+/// `{ workspace = true }`（Nu.toml 中为 `w = true`）
+fn is_inherited(value: &toml::Value) -> bool {
+    value
+        .get("workspace")
+        .and_then(toml::Value::as_bool)
+        .unwrap_or(false)
+}
+
+/// 解析依赖表，`w = true` 的依赖与 [W.D] 中的同名依赖合并（features 取并集）
+fn parse_dependencies(
+    deps: Option<&toml::Value>,
+    workspace_deps: Option<&toml::Table>,
+) -> Result<HashMap<String, DependencySpec>> {
+    let Some(table) = deps.and_then(toml::Value::as_table) else {
+        return Ok(HashMap::new());
+    };
+
+    let mut resolved = toml::Table::new();
+    for (name, spec) in table {
+        if !is_inherited(spec) {
+            resolved.insert(name.clone(), spec.clone());
+            continue;
+        }
+        let base = workspace_deps.and_then(|d| d.get(name)).ok_or_else(|| {
+            anyhow::anyhow!("依赖 `{}` 声明 w = true，但 [W.D] 中没有该依赖", name)
+        })?;
+        let mut merged = match base {
+            toml::Value::String(version) => {
+                let mut table = toml::Table::new();
+                table.insert("version".to_string(), version.clone().into());
+                table
+            }
+            toml::Value::Table(table) => table.clone(),
+            _ => anyhow::bail!("[W.D] 中的依赖 `{}` 格式无效", name),
+        };
+        for (key, value) in spec.as_table().into_iter().flatten() {
+            match (key.as_str(), merged.get_mut(key), value) {
+                ("features", Some(toml::Value::Array(features)), toml::Value::Array(extra)) => {
+                    features.extend(extra.iter().cloned())
+                }
+                _ => {
+                    merged.insert(key.clone(), value.clone());
+                }
+            }
+        }
+        resolved.insert(name.clone(), toml::Value::Table(merged));
+    }
+    Ok(CargoParser::parse_dependencies(&toml::Value::Table(
+        resolved,
+    ))?)
+}
+
+/// Nu.toml 中的目标路径可能沿用 Cargo 的 .rs 写法，统一为 .nu 源文件
+fn nu_source_path(path: &str) -> String {
+    match path.strip_suffix(".rs") {
+        Some(stem) => format!("{}.nu", stem),
+        None => path.to_string(),
+    }
+}
+
+fn lib_target(manifest: &Manifest) -> Option<LibTarget> {
+    const DEFAULT_PATH: &str = "src/lib.nu";
+    let Some(table) = manifest.table("lib") else {
+        return manifest
+            .dir
+            .join(DEFAULT_PATH)
+            .is_file()
+            .then(|| LibTarget {
+                path: Some(DEFAULT_PATH.to_string()),
+                ..Default::default()
+            });
+    };
+    let str_value = |key: &str| table.get(key).and_then(toml::Value::as_str);
+    Some(LibTarget {
+        name: str_value("name").map(str::to_string),
+        path: Some(nu_source_path(str_value("path").unwrap_or(DEFAULT_PATH))),
+        proc_macro: table
+            .get("proc-macro")
+            .and_then(toml::Value::as_bool)
+            .unwrap_or(false),
+        crate_type: table
+            .get("crate-type")
+            .and_then(|v| CargoParser::parse_string_array(v).ok())
+            .unwrap_or_default(),
+    })
+}
+
+/// bin / example / test / bench 目标
+#[derive(Debug, Clone, Copy)]
+enum TargetKind {
+    Bin,
+    Example,
+    Test,
+    Bench,
+}
+
+impl TargetKind {
+    /// Cargo.toml 中的数组表名
+    fn section(self) -> &'static str {
+        match self {
+            TargetKind::Bin => "bin",
+            TargetKind::Example => "example",
+            TargetKind::Test => "test",
+            TargetKind::Bench => "bench",
+        }
+    }
+
+    /// 关闭自动发现的 [P] 键
+    fn auto_key(self) -> &'static str {
+        match self {
+            TargetKind::Bin => "autobins",
+            TargetKind::Example => "autoexamples",
+            TargetKind::Test => "autotests",
+            TargetKind::Bench => "autobenches",
+        }
+    }
+
+    /// 自动发现目标的目录
+    fn dir(self) -> &'static str {
+        match self {
+            TargetKind::Bin => "src/bin",
+            TargetKind::Example => "examples",
+            TargetKind::Test => "tests",
+            TargetKind::Bench => "benches",
+        }
+    }
+}
+
+/// 显式声明的目标加上按 Cargo 规则自动发现的目标（同名或同路径时以显式声明为准）
+fn binary_targets(
+    manifest: &Manifest,
+    package: &toml::Table,
+    package_name: &str,
+    kind: TargetKind,
+) -> Result<Vec<BinaryTarget>> {
+    let mut targets = Vec::new();
+    for entry in manifest
+        .value
+        .get(kind.section())
+        .and_then(toml::Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        let name = entry
+            .get("name")
+            .and_then(toml::Value::as_str)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "{}: {} 缺少 id",
+                    manifest.path().display(),
+                    convert_section_cargo_to_nu(&format!("[[{}]]", kind.section()))
+                )
+            })?;
+        let path = match entry.get("path").and_then(toml::Value::as_str) {
+            Some(path) => nu_source_path(path),
+            None if matches!(kind, TargetKind::Bin) && name == package_name => {
+                "src/main.nu".to_string()
+            }
+            None => format!("{}/{}.nu", kind.dir(), name),
+        };
+        targets.push(BinaryTarget {
+            name: name.to_string(),
+            path: Some(path),
+            required_features: entry
+                .get("required-features")
+                .and_then(|v| CargoParser::parse_string_array(v).ok())
+                .unwrap_or_default(),
+        });
+    }
+
+    let auto = package
+        .get(kind.auto_key())
+        .and_then(toml::Value::as_bool)
+        .unwrap_or(true);
+    if auto {
+        let mut discovered = Vec::new();
+        if matches!(kind, TargetKind::Bin) && manifest.dir.join("src/main.nu").is_file() {
+            discovered.push((package_name.to_string(), "src/main.nu".to_string()));
+        }
+        discovered.extend(discover_targets(&manifest.dir, kind.dir()));
+        for (name, path) in discovered {
+            let declared = targets
+                .iter()
+                .any(|t| t.name == name || t.path.as_deref() == Some(path.as_str()));
+            if !declared {
+                targets.push(BinaryTarget {
+                    name,
+                    path: Some(path),
+                    required_features: Vec::new(),
+                });
+            }
+        }
+    }
+    Ok(targets)
+}
+
+/// dir 下的 `name.nu` 与 `name/main.nu`，按名字排序
+fn discover_targets(package_dir: &Path, dir: &str) -> Vec<(String, String)> {
+    let Ok(entries) = fs::read_dir(package_dir.join(dir)) else {
+        return Vec::new();
+    };
+    let mut found: Vec<(String, String)> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            let name = path.file_stem()?.to_str()?.to_string();
+            if path.is_dir() {
+                path.join("main.nu")
+                    .is_file()
+                    .then(|| (name.clone(), format!("{}/{}/main.nu", dir, name)))
+            } else if path.extension().and_then(|e| e.to_str()) == Some("nu") {
+                Some((name.clone(), format!("{}/{}.nu", dir, name)))
+            } else {
+                None
+            }
+        })
+        .collect();
+    found.sort();
+    found
+}
+
+fn write_file(path: &Path, content: &str, created: &mut Vec<PathBuf>) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("无法创建目录: {}", parent.display()))?;
+    }
+    fs::write(path, content).with_context(|| format!("无法写入文件: {}", path.display()))?;
+    created.push(path.to_path_buf());
+    Ok(())
+}
+
+/// crate 名只允许字母、数字、`_`、`-`，且不能以数字开头
+fn validate_crate_name(name: &str) -> Result<()> {
+    let valid = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if !valid {
+        anyhow::bail!("无效的项目名: `{}`", name);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_init_bin_project() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("hello");
+        let created = ProjectManager::new()
+            .init(&root, "hello", &InitOptions::default())
+            .unwrap();
+        assert_eq!(created.len(), 3);
+
+        let nu_toml = fs::read_to_string(root.join("Nu.toml")).unwrap();
+        assert!(nu_toml.contains("[P]\nid = \"hello\"\nv = \"0.1.0\"\ned = \"2021\""));
+        assert!(nu_toml.contains("[D]"));
+        let cargo_toml = Nu2CargoConverter::new().convert(&nu_toml);
+        assert!(cargo_toml.contains("[package]\nname = \"hello\""));
+
+        let main = fs::read_to_string(root.join("src/main.nu")).unwrap();
+        Parser::new().parse(&main).unwrap();
+        assert_eq!(
+            fs::read_to_string(root.join(".gitignore")).unwrap(),
+            "/target\n"
+        );
+    }
+
+    #[test]
+    fn test_init_lib_workspace() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("tools");
+        let options = InitOptions {
+            kind: ProjectKind::Lib,
+            workspace: true,
+        };
+        ProjectManager::new()
+            .init(&root, "tools", &options)
+            .unwrap();
+
+        let root_toml = fs::read_to_string(root.join("Nu.toml")).unwrap();
+        assert!(root_toml.contains("[W]\nm = [\"crates/tools\"]\nr = \"2\""));
+        let lib = fs::read_to_string(root.join("crates/tools/src/lib.nu")).unwrap();
+        Parser::new().parse(&lib).unwrap();
+        assert!(root.join("crates/tools/Nu.toml").exists());
+    }
+
+    #[test]
+    fn test_init_lib_and_bin() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("both");
+        let options = InitOptions {
+            kind: ProjectKind::from_flags(true, true),
+            workspace: false,
+        };
+        let created = ProjectManager::new().init(&root, "both", &options).unwrap();
+        assert_eq!(created.len(), 4);
+        assert!(root.join("src/lib.nu").exists());
+        assert!(root.join("src/main.nu").exists());
+        assert_eq!(ProjectKind::from_flags(false, false), ProjectKind::Bin);
+        assert_eq!(ProjectKind::from_flags(false, true), ProjectKind::Bin);
+    }
+
+    #[test]
+    fn test_shadow_build_and_diagnostic_remap() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("ws");
+        let manager = ProjectManager::new();
+        let options = InitOptions {
+            workspace: true,
+            ..Default::default()
+        };
+        manager.init(&root, "ws", &options).unwrap();
+        let member = root.join("crates/ws");
+        assert_eq!(
+            ProjectManager::find_root(&member.join("src")),
+            Some(root.clone())
+        );
+
+        let (shadow, report) = manager.generate_shadow(&root).unwrap();
+        assert_eq!(shadow, root.join(SHADOW_DIR));
+        assert_eq!(report.files_failed, 0);
+        assert!(shadow.join("crates/ws/src/main.rs").exists());
+        assert!(shadow.join("crates/ws/src/main.rs.map").exists());
+
+        let mut remapper = DiagnosticRemapper::new(&shadow, "");
+        let location = remapper
+            .remap_span("crates/ws/src/main.rs", (2, 5), (2, 13))
+            .unwrap();
+        assert_eq!(location.file, Path::new("crates/ws/src/main.nu"));
+        assert_eq!((location.line_start, location.column_start), (2, 5));
+        let absolute = shadow.join("crates/ws/src/main.rs").display().to_string();
+        let location = remapper.remap_span(&absolute, (1, 1), (1, 2)).unwrap();
+        assert_eq!(location.file, Path::new("crates/ws/src/main.nu"));
+        assert_eq!(location.line_start, 1);
+        // 找不到映射时不改写
+        assert!(remapper
+            .remap_span("src/other.rs", (1, 1), (1, 2))
+            .is_none());
+    }
+
+    #[test]
+    fn test_init_rejects_bad_name_and_non_empty_dir() {
+        let dir = tempdir().unwrap();
+        let manager = ProjectManager::new();
+        let options = InitOptions::default();
+        assert!(manager
+            .init(&dir.path().join("x"), "1abc", &options)
+            .is_err());
+
+        fs::write(dir.path().join("existing.txt"), "").unwrap();
+        assert!(manager.init(dir.path(), "demo", &options).is_err());
+    }
+
+    fn write_nu(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_load_workspace_with_inheritance() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        write_nu(
+            &root.join("Nu.toml"),
+            "[W]\nm = [\"crates/*\"]\nr = \"2\"\n\n[W.P]\nv = \"1.2.0\"\ned = \"2021\"\n\n[W.D]\nserde = { v = \"1.0\", features = [\"derive\"] }\n",
+        );
+        write_nu(
+            &root.join("crates/core/Nu.toml"),
+            "[P]\nid = \"core\"\nv.w = true\ned.w = true\n\n[L]\nct = [\"cdylib\", \"rlib\"]\n\n[D]\nserde = { w = true, features = [\"rc\"], opt = true }\n\n[FE]\ndefault = [\"serde\"]\n",
+        );
+        write_nu(
+            &root.join("crates/core/src/lib.nu"),
+            "F one() -> i32 { 1 }\n",
+        );
+        write_nu(
+            &root.join("crates/app/Nu.toml"),
+            "[P]\nid = \"app\"\nv = \"0.3.0\"\n\n[D]\ncore = { path = \"../core\" }\n",
+        );
+        write_nu(&root.join("crates/app/src/main.nu"), "f main() {}\n");
+
+        let project = ProjectManager::new().load(root).unwrap();
+        assert_eq!(project.workspace_type, WorkspaceType::Virtual);
+        assert!(project.package.is_none());
+        let names: Vec<&str> = project.packages().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["app", "core"]);
+
+        let core = project.find_package("core").unwrap();
+        assert_eq!(core.version(), Some("1.2.0"));
+        assert_eq!(core.edition(), Some("2021"));
+        assert!(core.is_lib_only());
+        let lib = core.lib.as_ref().unwrap();
+        assert_eq!(lib.crate_type, vec!["cdylib", "rlib"]);
+        assert_eq!(lib.path.as_deref(), Some("src/lib.nu"));
+        let serde = &core.dependencies["serde"];
+        assert_eq!(serde.version.as_deref(), Some("1.0"));
+        assert_eq!(serde.features, vec!["derive", "rc"]);
+        assert!(serde.optional && serde.workspace);
+        assert_eq!(core.features["default"], vec!["serde"]);
+
+        let app = project.find_package("app").unwrap();
+        assert_eq!(app.version(), Some("0.3.0"));
+        assert_eq!(app.bins[0].name, "app");
+        assert_eq!(app.dependencies["core"].path.as_deref(), Some("../core"));
+
+        // 直接加载成员时仍从外层工作区继承
+        let member = ProjectManager::new()
+            .load(&root.join("crates/core"))
+            .unwrap();
+        assert_eq!(member.package.unwrap().version(), Some("1.2.0"));
+    }
+
+    #[test]
+    fn test_load_targets_and_inheritance_errors() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        write_nu(
+            &root.join("Nu.toml"),
+            "[P]\nid = \"tool\"\nv = \"0.1.0\"\nautobenches = false\n\n[[B]]\nid = \"extra\"\npath = \"src/extra.rs\"\nrf = [\"cli\"]\n",
+        );
+        write_nu(&root.join("src/main.nu"), "f main() {}\n");
+        write_nu(&root.join("src/bin/helper.nu"), "f main() {}\n");
+        write_nu(&root.join("examples/demo/main.nu"), "f main() {}\n");
+        write_nu(&root.join("tests/it.nu"), "");
+        write_nu(&root.join("benches/speed.nu"), "");
+
+        let project = ProjectManager::new().load(root).unwrap();
+        let package = project.package.unwrap();
+        assert!(package.lib.is_none());
+        let bins: Vec<(&str, &str)> = package
+            .bins
+            .iter()
+            .map(|b| (b.name.as_str(), b.path.as_deref().unwrap()))
+            .collect();
+        assert_eq!(
+            bins,
+            vec![
+                ("extra", "src/extra.nu"),
+                ("tool", "src/main.nu"),
+                ("helper", "src/bin/helper.nu"),
+            ]
+        );
+        assert_eq!(package.bins[0].required_features, vec!["cli"]);
+        assert_eq!(
+            package.examples[0].path.as_deref(),
+            Some("examples/demo/main.nu")
+        );
+        assert_eq!(package.tests[0].name, "it");
+        assert!(package.benches.is_empty());
+
+        // 非工作区中声明继承
+        write_nu(&root.join("Nu.toml"), "[P]\nid = \"tool\"\nv.w = true\n");
+        let err = ProjectManager::new().load(root).unwrap_err().to_string();
+        assert!(err.contains("`v`"), "{}", err);
+    }
+}
@@ -0,0 +1,328 @@
+// 源码注释与空行
+// syn 会丢弃普通注释和空行；这里按行号记录它们，转换时在相邻的语法节点之间按原位置插回。
+// 文档注释（`///`、`//!`、`/** */`、`/*! */`）由 syn 解析为 doc 属性，不在此记录
+
+use proc_macro2::LineColumn;
+use std::collections::VecDeque;
+
+/// 注释或空行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum Trivia {
+    /// 连续 n 个空行
+    Blank(usize),
+    /// 注释原文；own_line 表示同一行注释之前没有代码
+    Comment { text: String, own_line: bool },
+}
+
+/// 位于源码第 line 行（从 1 开始）、第 column 列（从 0 开始，按字符计）的注释或空行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Entry {
+    pub line: usize,
+    pub column: usize,
+    pub trivia: Trivia,
+}
+
+/// 按行号排序、尚未输出的注释和空行
+#[derive(Debug, Clone, Default)]
+pub(super) struct SourceComments {
+    entries: VecDeque<Entry>,
+}
+
+impl SourceComments {
+    /// 扫描源码中的普通注释和空行，跳过字符串、字符字面量中的内容
+    pub(super) fn scan(source: &str) -> Self {
+        let chars: Vec<char> = source.chars().collect();
+        let mut entries: Vec<Entry> = Vec::new();
+        let mut line = 1;
+        // 当前行首字符的位置
+        let mut line_start = 0;
+        // 当前行注释之前是否有代码、当前行是否非空
+        let mut has_code = false;
+        let mut has_content = false;
+        let mut i = 0;
+
+        while i < chars.len() {
+            let c = chars[i];
+            if c == '\n' {
+                if !has_content {
+                    push_blank(&mut entries, line);
+                }
+                line += 1;
+                line_start = i + 1;
+                has_code = false;
+                has_content = false;
+                i += 1;
+                continue;
+            }
+            if c.is_whitespace() {
+                i += 1;
+                continue;
+            }
+
+            let start_line = line;
+            let column = i - line_start;
+            let own_line = !has_code;
+            has_content = true;
+            if c == '/' && chars.get(i + 1) == Some(&'/') {
+                let end = chars[i..]
+                    .iter()
+                    .position(|&c| c == '\n')
+                    .map_or(chars.len(), |p| i + p);
+                let text: String = chars[i..end].iter().collect();
+                let text = text.trim_end();
+                if !is_doc_comment(text) {
+                    entries.push(Entry {
+                        line: start_line,
+                        column,
+                        trivia: Trivia::Comment {
+                            text: text.to_string(),
+                            own_line,
+                        },
+                    });
+                }
+                i = end;
+            } else if c == '/' && chars.get(i + 1) == Some(&'*') {
+                let end = skip_block_comment(&chars, i, &mut line);
+                let text: String = chars[i..end].iter().collect();
+                if !is_doc_comment(&text) {
+                    entries.push(Entry {
+                        line: start_line,
+                        column,
+                        trivia: Trivia::Comment { text, own_line },
+                    });
+                }
+                i = end;
+            } else if c == '"' {
+                i = skip_string(&chars, i + 1, &mut line);
+                has_code = true;
+            } else if c == '\'' {
+                i = skip_char_or_lifetime(&chars, i);
+                has_code = true;
+            } else if c.is_alphabetic() || c == '_' {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                if matches!(word.as_str(), "r" | "br" | "cr") {
+                    if let Some(end) = skip_raw_string(&chars, i, &mut line) {
+                        i = end;
+                    }
+                }
+                has_code = true;
+            } else {
+                i += 1;
+                has_code = true;
+            }
+        }
+
+        Self {
+            entries: entries.into(),
+        }
+    }
+
+    /// 取出第 line 行之前的全部条目
+    pub(super) fn take_before(&mut self, line: usize) -> Vec<Entry> {
+        let count = self
+            .entries
+            .iter()
+            .take_while(|entry| entry.line < line)
+            .count();
+        self.entries.drain(..count).collect()
+    }
+
+    /// 取出第 line 行中位于 column 列之前的注释（如 `/* a */ let x = 1;` 中的 `/* a */`）
+    pub(super) fn take_leading(&mut self, line: usize, column: usize) -> Vec<String> {
+        let count = self
+            .entries
+            .iter()
+            .take_while(|entry| entry.line == line && entry.column < column)
+            .count();
+        self.entries
+            .drain(..count)
+            .filter_map(|entry| match entry.trivia {
+                Trivia::Comment { text, .. } => Some(text),
+                Trivia::Blank(_) => None,
+            })
+            .collect()
+    }
+
+    /// start 与 end 之间（如某个表达式的源码范围内）是否还有未输出的注释
+    pub(super) fn has_comment_in(&self, start: LineColumn, end: LineColumn) -> bool {
+        self.entries.iter().any(|entry| {
+            let pos = (entry.line, entry.column);
+            matches!(entry.trivia, Trivia::Comment { .. })
+                && (start.line, start.column) <= pos
+                && pos < (end.line, end.column)
+        })
+    }
+
+    /// 丢弃 start 与 end 之间的条目（该范围的源码已原样输出）
+    pub(super) fn discard_in(&mut self, start: LineColumn, end: LineColumn) {
+        self.entries.retain(|entry| {
+            let pos = (entry.line, entry.column);
+            pos < (start.line, start.column) || (end.line, end.column) <= pos
+        });
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// 记录空行，与紧邻的上一段空行合并
+fn push_blank(entries: &mut Vec<Entry>, line: usize) {
+    if let Some(Entry {
+        line: start,
+        trivia: Trivia::Blank(count),
+        ..
+    }) = entries.last_mut()
+    {
+        if *start + *count == line {
+            *count += 1;
+            return;
+        }
+    }
+    entries.push(Entry {
+        line,
+        column: 0,
+        trivia: Trivia::Blank(1),
+    });
+}
+
+/// 文档注释：`///`（不含 `////`）、`//!`、`/**`（不含 `/***` 和 `/**/`）、`/*!`
+fn is_doc_comment(text: &str) -> bool {
+    (text.starts_with("///") && !text.starts_with("////"))
+        || text.starts_with("//!")
+        || (text.starts_with("/**") && !text.starts_with("/***") && text != "/**/")
+        || text.starts_with("/*!")
+}
+
+/// 跳过从 start 开始的（可嵌套）块注释，返回结束位置
+fn skip_block_comment(chars: &[char], start: usize, line: &mut usize) -> usize {
+    let mut depth = 0;
+    let mut i = start;
+    while i < chars.len() {
+        if chars[i] == '/' && chars.get(i + 1) == Some(&'*') {
+            depth += 1;
+            i += 2;
+        } else if chars[i] == '*' && chars.get(i + 1) == Some(&'/') {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                return i;
+            }
+        } else {
+            if chars[i] == '\n' {
+                *line += 1;
+            }
+            i += 1;
+        }
+    }
+    chars.len()
+}
+
+/// 跳过普通字符串（start 为开引号之后的位置），返回结束位置
+fn skip_string(chars: &[char], start: usize, line: &mut usize) -> usize {
+    let mut i = start;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => {
+                if chars.get(i + 1) == Some(&'\n') {
+                    *line += 1;
+                }
+                i += 2;
+            }
+            '"' => return i + 1,
+            c => {
+                if c == '\n' {
+                    *line += 1;
+                }
+                i += 1;
+            }
+        }
+    }
+    chars.len()
+}
+
+/// 跳过 r"..."、r#"..."#（start 为前缀之后的位置）；不是原始字符串时返回 None
+fn skip_raw_string(chars: &[char], start: usize, line: &mut usize) -> Option<usize> {
+    let hashes = chars[start..].iter().take_while(|&&c| c == '#').count();
+    let mut i = start + hashes;
+    if chars.get(i) != Some(&'"') {
+        return None;
+    }
+    i += 1;
+    while i < chars.len() {
+        let closing = &chars[i + 1..(i + 1 + hashes).min(chars.len())];
+        if chars[i] == '"' && closing.len() == hashes && closing.iter().all(|&c| c == '#') {
+            return Some(i + 1 + hashes);
+        }
+        if chars[i] == '\n' {
+            *line += 1;
+        }
+        i += 1;
+    }
+    Some(chars.len())
+}
+
+/// 跳过字符字面量或生命周期（start 为单引号位置）
+fn skip_char_or_lifetime(chars: &[char], start: usize) -> usize {
+    if chars.get(start + 1) == Some(&'\\') {
+        let mut i = start + 3;
+        while i < chars.len() && chars[i] != '\'' && chars[i] != '\n' {
+            i += 1;
+        }
+        return i + 1;
+    }
+    if chars.get(start + 2) == Some(&'\'') {
+        return start + 3;
+    }
+    start + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comment(line: usize, column: usize, text: &str, own_line: bool) -> Entry {
+        Entry {
+            line,
+            column,
+            trivia: Trivia::Comment {
+                text: text.to_string(),
+                own_line,
+            },
+        }
+    }
+
+    #[test]
+    fn test_scan() {
+        let source = "//! crate doc\n// header\n\nfn a<'a>(x: &'a str) -> char { // trailing\n    let s = \"// not a comment\n\";\n    let r = r#\"/* nor this */\"#;\n\n\n    /* block\n       comment */ '\"'\n}\n/// doc\nfn b() {}\n";
+        let mut comments = SourceComments::scan(source);
+        assert_eq!(
+            comments.take_before(5),
+            vec![
+                comment(2, 0, "// header", true),
+                Entry {
+                    line: 3,
+                    column: 0,
+                    trivia: Trivia::Blank(1)
+                },
+                comment(4, 31, "// trailing", false),
+            ]
+        );
+        assert_eq!(
+            comments.take_before(usize::MAX),
+            vec![
+                Entry {
+                    line: 8,
+                    column: 0,
+                    trivia: Trivia::Blank(2)
+                },
+                comment(10, 4, "/* block\n       comment */", true),
+            ]
+        );
+        assert!(comments.is_empty());
+    }
+}
@@ -1,19 +1,89 @@
 // Rust to Nu Converter
 // 将标准Rust代码压缩为Nu高密度语法
 
+mod comments;
+mod preserve;
+pub mod stats;
+pub use stats::{CompressionReport, TokenCounter};
+pub mod verify;
+pub use verify::{verify_round_trip, RoundTripFailure, RoundTripReport};
+
+use comments::{SourceComments, Trivia};
+
+use crate::ast::Span;
+use crate::conversion::{ConversionOutput, Diagnostic, Transpile};
+use crate::escape;
+use crate::profile::CompressionProfile;
 use anyhow::{Context, Result};
 use quote::ToTokens;
 use std::collections::HashSet;
 use syn::{
-    spanned::Spanned, visit::Visit, Attribute, Block, Expr, File, FnArg, Item, ItemEnum, ItemFn,
-    ItemImpl, ItemStruct, ItemTrait, ReturnType, Signature, Stmt, Type, Visibility,
+    punctuated::Punctuated, spanned::Spanned, visit::Visit, Attribute, Block, Expr, File, FnArg,
+    Item, ItemEnum, ItemFn, ItemImpl, ItemStruct, ItemTrait, ReturnType, Signature, Stmt, Token,
+    Type, Visibility,
 };
 
+/// 参数为逗号分隔表达式的常用宏：参数按普通表达式完整转换（与 nu2rust 一致）
+const EXPRESSION_MACROS: &[&str] = &[
+    "print",
+    "println",
+    "eprint",
+    "eprintln",
+    "format",
+    "write",
+    "writeln",
+    "panic",
+    "vec",
+    "assert",
+    "assert_eq",
+    "assert_ne",
+    "debug_assert",
+    "debug_assert_eq",
+    "debug_assert_ne",
+];
+
 /// v1.8.3: 检查字符是否是标识符的一部分
 fn is_ident_char(c: char) -> bool {
     c.is_alphanumeric() || c == '_'
 }
 
+/// 用占位符保护字符串字面量，避免其中的内容被空格清理或类型缩写改写
+fn protect_string_literals(s: &str) -> (String, Vec<String>) {
+    let mut literals: Vec<String> = Vec::new();
+    let mut result = String::new();
+    let chars: Vec<char> = s.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] == '"' {
+            // 找到字符串开始
+            let start = i;
+            i += 1;
+            while i < chars.len() && !(chars[i] == '"' && chars[i - 1] != '\\') {
+                i += 1;
+            }
+            if i < chars.len() {
+                i += 1; // 包含结束的引号
+            }
+            // 保存整个字符串字面量
+            result.push_str(&format!("__STRING_LITERAL_{}__", literals.len()));
+            literals.push(chars[start..i].iter().collect());
+        } else {
+            result.push(chars[i]);
+            i += 1;
+        }
+    }
+    (result, literals)
+}
+
+/// 恢复被 protect_string_literals 保护的字符串字面量
+fn restore_string_literals(s: &str, literals: &[String]) -> String {
+    let mut result = s.to_string();
+    for (idx, literal) in literals.iter().enumerate() {
+        result = result.replace(&format!("__STRING_LITERAL_{}__", idx), literal);
+    }
+    result
+}
+
 /// v1.8.3: 智能替换类型名称，只替换独立的类型名（不是其他标识符的一部分）
 /// 例如：替换 "Result" 但不替换 "BarrierWaitResult" 中的 "Result"
 /// v1.8.8: 不替换枚举变体名（如 Self::Vec(...)、Some(...)、None 等）
@@ -80,6 +150,15 @@ fn replace_standalone_type(s: &str, from: &str, to: &str) -> String {
     result
 }
 
+/// Rust2NuConverter 的转换选项
+#[derive(Debug, Clone, Default)]
+pub struct Rust2NuOptions {
+    /// 保留原始格式：按 span 原样复制未改动的源码，只改写关键字、类型缩写、`?` 和 `mut`
+    pub preserve_syntax: bool,
+    /// 压缩配置；非默认配置记录在输出的首行
+    pub profile: CompressionProfile,
+}
+
 pub struct Rust2NuConverter {
     output: String,
     indent_level: usize,
@@ -88,6 +167,11 @@ pub struct Rust2NuConverter {
     generic_scope_stack: Vec<HashSet<String>>,
     // v1.8: 保存原始源代码，用于提取宏的原始格式
     source_code: String,
+    // 转换中产生的诊断（原样保留的宏、不支持的条目）
+    diagnostics: Vec<Diagnostic>,
+    // 尚未输出的源码注释和空行
+    comments: SourceComments,
+    options: Rust2NuOptions,
 }
 
 impl Rust2NuConverter {
@@ -97,123 +181,217 @@ impl Rust2NuConverter {
             indent_level: 0,
             generic_scope_stack: Vec::new(),
             source_code: String::new(),
+            diagnostics: Vec::new(),
+            comments: SourceComments::default(),
+            options: Rust2NuOptions::default(),
+        }
+    }
+
+    /// 按选项创建转换器（保留原始格式等）
+    pub fn with_options(options: Rust2NuOptions) -> Self {
+        Self {
+            options,
+            ..Self::new()
         }
     }
 
+    pub fn options(&self) -> &Rust2NuOptions {
+        &self.options
+    }
+
     pub fn new_with_source(source: &str) -> Self {
         Self {
             output: String::new(),
             indent_level: 0,
             generic_scope_stack: Vec::new(),
             source_code: source.to_string(),
+            diagnostics: Vec::new(),
+            comments: SourceComments::scan(source),
+            options: Rust2NuOptions::default(),
         }
     }
 
     pub fn convert(&self, rust_code: &str) -> Result<String> {
-        // 策略：混合处理 - 保留注释行，转换代码行
-        // 1. 先提取所有注释和它们的位置
-        let lines: Vec<&str> = rust_code.lines().collect();
-        let mut line_types = Vec::new(); // true = comment line, false = code line
-        let mut in_block_comment = false;
-        let mut in_inner_doc = false; // 跟踪是否在 /*! ... */ 块中
-
-        for line in &lines {
-            let trimmed = line.trim();
-
-            // 检测 /*! 开始的inner doc注释块（syn会将其转换为#![doc]属性）
-            if trimmed.starts_with("/*!") {
-                in_inner_doc = true;
-                in_block_comment = true;
-                line_types.push(false); // 标记为非注释，让syn处理
-                continue;
-            }
-
-            // 如果在inner doc块中，检测结束
-            if in_inner_doc {
-                if trimmed.contains("*/") {
-                    in_inner_doc = false;
-                    in_block_comment = false;
-                }
-                line_types.push(false); // 标记为非注释，让syn处理
-                continue;
-            }
-
-            // 检测普通块注释
-            if trimmed.starts_with("/*") && !trimmed.starts_with("/*!") {
-                in_block_comment = true;
-            }
-            if in_block_comment && trimmed.contains("*/") {
-                in_block_comment = false;
-                line_types.push(true); // 普通块注释保留
-                continue;
-            }
+        Ok(self.convert_with_diagnostics(rust_code)?.0)
+    }
 
-            // 判断是否为纯注释行或空行
-            // 注意：属性（#[...] 和 #![...]）不算注释，会被syn处理并在converted_code中输出
-            let is_comment_or_empty = trimmed.is_empty()
-                || trimmed.starts_with("//")
-                || in_block_comment
-                || (trimmed.starts_with("*") && !trimmed.starts_with("*/"));
-            line_types.push(is_comment_or_empty);
+    /// 转换并返回诊断：原样保留的宏、按 Rust 语法透传的不支持条目
+    ///
+    /// 普通注释和空行按源码位置插回转换结果，文档注释保持 `///`、`//!` 原文
+    pub fn convert_with_diagnostics(&self, rust_code: &str) -> Result<(String, Vec<Diagnostic>)> {
+        let header = self.options.profile.header();
+        let with_header = |nu_code: String| match &header {
+            Some(header) => format!("{}\n{}", header, nu_code),
+            None => nu_code,
+        };
+        // 与 Nu 关键字或缩写同名的标识符先转义为 `r#` 原始标识符，nu2rust 按原名还原
+        let rust_code = &escape::escape_identifiers(rust_code, &self.options.profile)
+            .context("Failed to parse Rust code")?;
+        if self.options.preserve_syntax {
+            let nu_code = preserve::convert(rust_code, &self.options.profile)?;
+            return Ok((with_header(nu_code), Vec::new()));
         }
 
-        // 2. 解析并转换代码（syn会忽略注释）
+        // syn 会忽略注释，注释由转换器根据源码位置补回
         let syntax_tree = syn::parse_file(rust_code).context("Failed to parse Rust code")?;
 
         // v1.8: 使用包含源代码的转换器，以便提取宏的原始格式
         let mut converter = Self::new_with_source(rust_code);
+        converter.options = self.options.clone();
         converter.visit_file(&syntax_tree);
-        let converted_code = converter.output;
+        Ok((with_header(converter.output), converter.diagnostics))
+    }
 
-        // 3. 合并：在转换后的代码中插入注释
-        // 策略：保留文件开头的纯注释行，然后输出转换后的代码
-        let mut output = String::new();
-        let mut found_non_comment = false;
+    /// syn 位置转为源码位置（列从 1 开始；跨行时标到首行行尾）
+    fn source_span(&self, span: proc_macro2::Span) -> Span {
+        let (start, end) = (span.start(), span.end());
+        let len = if end.line == start.line {
+            end.column.saturating_sub(start.column)
+        } else {
+            self.source_code
+                .lines()
+                .nth(start.line.saturating_sub(1))
+                .map_or(0, |line| line.chars().count().saturating_sub(start.column))
+        };
+        Span::new(start.line, start.column + 1, len)
+    }
+
+    fn indent(&self) -> String {
+        "    ".repeat(self.indent_level)
+    }
 
-        for (i, line) in lines.iter().enumerate() {
-            if line_types[i] {
-                // 注释或空行
-                if !found_non_comment {
-                    // 文件开头的纯注释，直接保留
-                    output.push_str(line);
-                    output.push('\n');
+    fn writeln(&mut self, text: &str) {
+        self.output.push_str(&self.indent());
+        self.output.push_str(text);
+        self.output.push('\n');
+    }
+
+    fn write(&mut self, text: &str) {
+        self.output.push_str(text);
+    }
+
+    /// 输出源码第 line 行之前尚未输出的注释和空行
+    fn flush_comments(&mut self, line: usize) {
+        for entry in self.comments.take_before(line) {
+            match entry.trivia {
+                Trivia::Blank(count) => self.ensure_blank_lines(count),
+                Trivia::Comment { text, .. } => self.write_comment(&text),
+            }
+        }
+    }
+
+    /// 语法节点在源码第 line 行结束后调用：该行的行尾注释接在输出的最后一行后面，
+    /// 节点内部未能就位的注释（如单行输出的嵌套代码块中的注释）依次放在节点之后
+    fn flush_trailing_comments(&mut self, line: usize) {
+        let entries = self.comments.take_before(line + 1);
+        let mut interior = Vec::new();
+        for entry in entries {
+            match entry.trivia {
+                Trivia::Comment {
+                    text,
+                    own_line: false,
+                } if entry.line == line && self.output.ends_with('\n') => {
+                    self.output.pop();
+                    self.output.push(' ');
+                    self.output.push_str(&text);
+                    self.output.push('\n');
                 }
-            } else {
-                // 遇到第一行非注释代码（可能是属性、use、fn等）
-                if !found_non_comment {
-                    found_non_comment = true;
-                    // 追加完整的转换后代码（包含属性、use、fn等）
-                    output.push_str(&converted_code);
+                Trivia::Comment { text, .. } => {
+                    self.diagnostics.push(
+                        Diagnostic::note(
+                            "relocated-comment",
+                            "注释无法留在原位，已移到所在节点之后",
+                        )
+                        .with_span(Span::new(
+                            entry.line,
+                            entry.column + 1,
+                            text.lines().next().map_or(0, |l| l.chars().count()),
+                        )),
+                    );
+                    interior.push(text)
                 }
-                break;
+                // 节点内部的空行随输出格式重排
+                Trivia::Blank(_) => {}
             }
         }
-
-        // 如果全是注释但转换后有内容，直接返回转换内容
-        if !found_non_comment && !converted_code.is_empty() {
-            return Ok(converted_code);
+        for text in interior {
+            self.write_comment(&text);
         }
+    }
 
-        // 如果全是注释且转换后也是空的，返回注释
-        if !found_non_comment {
-            return Ok(output);
+    /// 以当前缩进输出一条注释；跨行块注释的后续行保持原样
+    fn write_comment(&mut self, text: &str) {
+        let mut lines = text.lines();
+        if let Some(first) = lines.next() {
+            self.writeln(first);
         }
+        for line in lines {
+            self.output.push_str(line);
+            self.output.push('\n');
+        }
+    }
 
-        Ok(output)
+    /// 保证输出末尾至少有 count 个空行
+    fn ensure_blank_lines(&mut self, count: usize) {
+        if !self.output.is_empty() && !self.output.ends_with('\n') {
+            return;
+        }
+        let content = self.output.trim_end();
+        let trailing = self.output[content.len()..].matches('\n').count();
+        let existing = if content.is_empty() {
+            trailing
+        } else {
+            trailing.saturating_sub(1)
+        };
+        for _ in existing..count {
+            self.output.push('\n');
+        }
     }
 
-    fn indent(&self) -> String {
-        "    ".repeat(self.indent_level)
+    /// 条目之间的分隔：有源码时空行完全来自源码，否则每个条目后空一行
+    fn item_separator(&mut self) {
+        if self.source_code.is_empty() {
+            self.output.push('\n');
+        }
     }
 
-    fn writeln(&mut self, text: &str) {
-        self.output.push_str(&self.indent());
-        self.output.push_str(text);
-        self.output.push('\n');
+    /// 以 `///`、`//!`、`/** */` 书写的文档注释，返回源码原文
+    fn doc_comment(&self, attr: &Attribute) -> Option<String> {
+        if !attr.path().is_ident("doc") {
+            return None;
+        }
+        let span = attr.span();
+        let (start, end) = (span.start(), span.end());
+        let lines: Vec<&str> = self.source_code.lines().collect();
+        if start.line == 0 || end.line > lines.len() || end.line < start.line {
+            return None;
+        }
+        let text = if start.line == end.line {
+            lines[start.line - 1]
+                .chars()
+                .skip(start.column)
+                .take(end.column.saturating_sub(start.column))
+                .collect::<String>()
+        } else {
+            let mut text: String = lines[start.line - 1].chars().skip(start.column).collect();
+            for line in &lines[start.line..end.line - 1] {
+                text.push('\n');
+                text.push_str(line);
+            }
+            text.push('\n');
+            text.extend(lines[end.line - 1].chars().take(end.column));
+            text
+        };
+        (text.starts_with("//") || text.starts_with("/*")).then_some(text)
     }
 
-    fn write(&mut self, text: &str) {
-        self.output.push_str(text);
+    /// 输出属性中的文档注释
+    fn write_doc_comments(&mut self, attrs: &[Attribute]) {
+        for attr in attrs {
+            if let Some(doc) = self.doc_comment(attr) {
+                self.write_comment(&doc);
+            }
+        }
     }
 
     /// 判断是否是pub
@@ -267,6 +445,11 @@ impl Rust2NuConverter {
             result.push_str("const ");
         }
 
+        // extern "C" fn -> EXT "C" F
+        if let Some(abi) = &sig.abi {
+            result.push_str(&self.convert_abi(abi));
+        }
+
         // async函数用 ~ 前缀
         if sig.asyncness.is_some() {
             result.push('~');
@@ -346,6 +529,17 @@ impl Rust2NuConverter {
                 }
             }
         }
+        // extern 块中的可变参数：fn printf(fmt: *const c_char, ...)
+        if let Some(variadic) = &sig.variadic {
+            if !first {
+                result.push_str(", ");
+            }
+            if let Some((pat, _)) = &variadic.pat {
+                result.push_str(&pat.to_token_stream().to_string());
+                result.push_str(": ");
+            }
+            result.push_str("...");
+        }
         result.push(')');
 
         // 返回类型
@@ -369,6 +563,14 @@ impl Rust2NuConverter {
         result
     }
 
+    /// `extern "C" ` -> `EXT "C" `（省略 ABI 时为 `EXT `）
+    fn convert_abi(&self, abi: &syn::Abi) -> String {
+        match &abi.name {
+            Some(name) => format!("EXT {} ", name.to_token_stream()),
+            None => "EXT ".to_string(),
+        }
+    }
+
     /// v1.6.5: 转换泛型参数（完整保留生命周期）
     fn convert_generics(&self, generics: &syn::Generics) -> String {
         if generics.params.is_empty() {
@@ -561,16 +763,8 @@ impl Rust2NuConverter {
                 result.push_str(&seg_name);
             } else {
                 // 应用类型缩写
-                let abbreviated = match seg_name.as_str() {
-                    "Vec" => "V",
-                    "Option" => "O",
-                    "Result" => "R",
-                    "Arc" => "A",
-                    "Mutex" => "X",
-                    "Box" => "B",
-                    _ => &seg_name,
-                };
-                result.push_str(abbreviated);
+                let profile = &self.options.profile;
+                result.push_str(profile.abbreviation(&seg_name).unwrap_or(&seg_name));
             }
 
             // 处理泛型参数
@@ -642,13 +836,13 @@ impl Rust2NuConverter {
         }
 
         // 应用类型缩写
-        type_str
-            .replace("Vec<", "V<")
-            .replace("Option<", "O<")
-            .replace("Result<", "R<")
-            .replace("Arc<", "A<")
-            .replace("Mutex<", "X<")
-            .replace("Box<", "B<")
+        let mut result = type_str;
+        for full in &self.options.profile.types {
+            if let Some(abbr) = self.options.profile.abbreviation(full) {
+                result = result.replace(&format!("{}<", full), &format!("{}<", abbr));
+            }
+        }
+        result
             .replace("&mut", "&!")
             .replace("*mut", "*mut") // 保持裸指针的mut关键字
             .replace("*const", "*const") // 保持裸指针的const关键字
@@ -764,20 +958,24 @@ impl Rust2NuConverter {
                     return;
                 }
 
+                if self.convert_commented_expr(expr) {
+                    if semi.is_some() {
+                        self.write(";");
+                    }
+                    self.write("\n");
+                    return;
+                }
+
                 // 原有的return和macro处理...(v1.8: 添加attrs支持)
                 if let Expr::Return(ret) = expr {
                     // v1.8.9: 属性已经在 Stmt::Expr 开头输出，这里不再重复输出
                     self.write(&self.indent());
-                    self.write("< ");
-                    if let Some(val) = &ret.expr {
-                        self.write(&self.convert_expr(val));
-                    }
+                    let value = ret.expr.as_ref().map(|val| self.convert_expr(val));
+                    self.write(&self.return_stmt(value, semi.is_some()));
                     self.write("\n");
-                } else if let Expr::Macro(_mac) = expr {
+                } else if let Expr::Macro(mac) = expr {
                     self.write(&self.indent());
-                    let macro_str = self
-                        .clean_token_spaces(&expr.to_token_stream().to_string())
-                        .replace("vec!", "V!"); // vec! -> V!
+                    let macro_str = self.convert_macro(&mac.mac);
                     self.write(&macro_str);
                     if semi.is_some() {
                         self.write(";");
@@ -809,12 +1007,8 @@ impl Rust2NuConverter {
                         self.writeln(&cleaned_attr);
                     }
                 }
-                // v1.6: 宏语句，vec!转换为V!，其他保留（println!, assert!, etc.）
-                // 使用 clean_token_spaces 移除 to_token_stream() 插入的空格
                 self.write(&self.indent());
-                let macro_str = self
-                    .clean_token_spaces(&mac.mac.to_token_stream().to_string())
-                    .replace("vec!", "V!"); // vec! -> V!
+                let macro_str = self.convert_macro(&mac.mac);
                 self.write(&macro_str);
                 if mac.semi_token.is_some() {
                     self.write(";");
@@ -1064,7 +1258,7 @@ impl Rust2NuConverter {
                             }
                         }
                         Stmt::Expr(Expr::Continue(_), _) => String::from("ct"),
-                        Stmt::Expr(Expr::Return(ret), _) => {
+                        Stmt::Expr(Expr::Return(ret), semi) => {
                             // v1.8: 处理return语句的 #[cfg] 等属性
                             let mut attr_prefix = String::new();
                             for attr in &ret.attrs {
@@ -1072,11 +1266,9 @@ impl Rust2NuConverter {
                                 attr_prefix.push('\n');
                                 attr_prefix.push_str("        ");
                             }
-                            if let Some(val) = &ret.expr {
-                                format!("{}< {}", attr_prefix, self.convert_expr(val))
-                            } else {
-                                format!("{}<", attr_prefix)
-                            }
+                            let value = ret.expr.as_ref().map(|val| self.convert_expr(val));
+                            let stmt = self.return_stmt(value, semi.is_some());
+                            format!("{}{}", attr_prefix, stmt)
                         }
                         _ => self.clean_token_spaces(&stmt.to_token_stream().to_string()),
                     };
@@ -1096,7 +1288,12 @@ impl Rust2NuConverter {
                 };
                 let pat = self.clean_token_spaces(&for_loop.pat.to_token_stream().to_string());
                 let iter = self.convert_expr(&for_loop.expr);
-                let mut result = format!("{}L {} in {} {{ ", label_prefix, pat, iter);
+                let keyword = if self.options.profile.loops {
+                    "L"
+                } else {
+                    "for"
+                };
+                let mut result = format!("{}{} {} in {} {{ ", label_prefix, keyword, pat, iter);
                 // 递归转换循环体中的语句
                 for stmt in &for_loop.body.stmts {
                     match stmt {
@@ -1126,9 +1323,9 @@ impl Rust2NuConverter {
                             }
                         }
                         _ => {
-                            let stmt_str = self
-                                .clean_token_spaces(&stmt.to_token_stream().to_string())
-                                .replace("vec!", "V!");
+                            let stmt_str = self.vec_macro(
+                                &self.clean_token_spaces(&stmt.to_token_stream().to_string()),
+                            );
                             result.push_str(&stmt_str);
                             result.push(' ');
                         }
@@ -1164,7 +1361,12 @@ impl Rust2NuConverter {
                 } else {
                     String::new()
                 };
-                let mut result = format!("{}L {{ ", label);
+                let keyword = if self.options.profile.loops {
+                    "L"
+                } else {
+                    "loop"
+                };
+                let mut result = format!("{}{} {{ ", label, keyword);
                 // 递归转换循环体中的语句
                 for stmt in &loop_expr.body.stmts {
                     match stmt {
@@ -1194,9 +1396,9 @@ impl Rust2NuConverter {
                             }
                         }
                         _ => {
-                            let stmt_str = self
-                                .clean_token_spaces(&stmt.to_token_stream().to_string())
-                                .replace("vec!", "V!");
+                            let stmt_str = self.vec_macro(
+                                &self.clean_token_spaces(&stmt.to_token_stream().to_string()),
+                            );
                             result.push_str(&stmt_str);
                             result.push(' ');
                         }
@@ -1282,11 +1484,11 @@ impl Rust2NuConverter {
                 result.push('}');
                 self.convert_type_in_string(&result)
             }
+            Expr::Macro(mac) => self.convert_macro(&mac.mac),
             _ => {
                 // 默认：保持原样但替换类型和vec!宏
-                let expr_str = self
-                    .clean_token_spaces(&expr.to_token_stream().to_string())
-                    .replace("vec!", "V!");
+                let expr_str =
+                    self.vec_macro(&self.clean_token_spaces(&expr.to_token_stream().to_string()));
 
                 // v1.8.2 Hotfix: 即使在 fallback 路径中，也要尝试将 ? 转换为 !
                 // 必须小心处理 ?Sized 和格式化字符串中的 {:?} 和 {:#?}
@@ -1310,39 +1512,53 @@ impl Rust2NuConverter {
         }
     }
 
+    /// 转换宏调用
+    ///
+    /// EXPRESSION_MACROS 中的宏逐个转换参数表达式（vec! -> V!）；
+    /// 其他宏及无法按逗号分隔表达式解析的参数（如 `vec![0; n]`）只清理空格，内部不做 Nu 转换
+    fn convert_macro(&self, mac: &syn::Macro) -> String {
+        let name = mac
+            .path
+            .segments
+            .last()
+            .map(|seg| seg.ident.to_string())
+            .unwrap_or_default();
+        let args = if EXPRESSION_MACROS.contains(&name.as_str()) {
+            mac.parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated)
+                .ok()
+        } else {
+            None
+        };
+        let Some(args) = args else {
+            return self.vec_macro(&self.clean_token_spaces(&mac.to_token_stream().to_string()));
+        };
+
+        let path = if name == "vec"
+            && mac.path.segments.len() == 1
+            && self.options.profile.abbreviation("Vec").is_some()
+        {
+            "V".to_string()
+        } else {
+            self.clean_token_spaces(&mac.path.to_token_stream().to_string())
+        };
+        let (open, close) = match mac.delimiter {
+            syn::MacroDelimiter::Paren(_) => ("(", ")"),
+            syn::MacroDelimiter::Bracket(_) => ("[", "]"),
+            syn::MacroDelimiter::Brace(_) => ("{", "}"),
+        };
+        let args = args
+            .iter()
+            .map(|arg| self.convert_expr(arg))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("{}!{}{}{}", path, open, args, close)
+    }
+
     /// 清理 to_token_stream() 产生的多余空格
     /// 例如: "V < i32 >" -> "V<i32>", "vec ! []" -> "vec![]", "x . method()" -> "x.method()"
     fn clean_token_spaces(&self, s: &str) -> String {
         // v1.8.2: 先保护字符串字面量，避免其中的空格被错误删除
-        let mut protected_strings: Vec<String> = Vec::new();
-        let mut result = s.to_string();
-
-        // 提取并保护所有字符串字面量
-        let chars: Vec<char> = result.chars().collect();
-        let mut i = 0;
-        let mut protected_result = String::new();
-        while i < chars.len() {
-            if chars[i] == '"' {
-                // 找到字符串开始
-                let start = i;
-                i += 1;
-                while i < chars.len() && !(chars[i] == '"' && chars[i - 1] != '\\') {
-                    i += 1;
-                }
-                if i < chars.len() {
-                    i += 1; // 包含结束的引号
-                }
-                // 保存整个字符串字面量
-                let string_literal: String = chars[start..i].iter().collect();
-                let placeholder = format!("__STRING_LITERAL_{}__", protected_strings.len());
-                protected_strings.push(string_literal);
-                protected_result.push_str(&placeholder);
-            } else {
-                protected_result.push(chars[i]);
-                i += 1;
-            }
-        }
-        result = protected_result;
+        let (mut result, protected_strings) = protect_string_literals(s);
 
         // 移除 < > 周围的空格（用于泛型如 Vec< i32 > -> Vec<i32>）
         // v1.8: 智能处理 - 只在同一行有成对 <> 时才清理空格（泛型上下文）
@@ -1414,12 +1630,31 @@ impl Rust2NuConverter {
         result = result.replace("* ", "*"); // 解引用符后不需要空格
 
         // v1.8.2: 恢复被保护的字符串字面量
-        for (idx, string_literal) in protected_strings.iter().enumerate() {
-            let placeholder = format!("__STRING_LITERAL_{}__", idx);
-            result = result.replace(&placeholder, string_literal);
+        restore_string_literals(&result, &protected_strings)
+    }
+
+    /// 语句级 return：压缩为 `< x`，否则保持 `return x`，分号按源码保留（nu2rust 只为 `<` 补分号）
+    fn return_stmt(&self, value: Option<String>, semi: bool) -> String {
+        let stmt = match (self.options.profile.returns, value) {
+            (true, Some(value)) => return format!("< {}", value),
+            (true, None) => return "<".to_string(),
+            (false, Some(value)) => format!("return {}", value),
+            (false, None) => "return".to_string(),
+        };
+        if semi {
+            stmt + ";"
+        } else {
+            stmt
         }
+    }
 
-        result
+    /// vec! -> V!（仅当配置缩写 Vec 时）
+    fn vec_macro(&self, code: &str) -> String {
+        if self.options.profile.abbreviation("Vec").is_some() {
+            code.replace("vec!", "V!")
+        } else {
+            code.to_string()
+        }
     }
 
     fn convert_type_in_string(&self, s: &str) -> String {
@@ -1428,6 +1663,8 @@ impl Rust2NuConverter {
 
         // 🔑 首先清理 to_token_stream() 产生的多余空格
         let s = self.clean_token_spaces(s);
+        // 字符串字面量中的 Vec<、vec! 等保持原样（nu2rust 同样不会展开字面量中的缩写）
+        let (s, protected_strings) = protect_string_literals(&s);
 
         // 先检查是否包含单字母泛型参数（如 <M>、<T>、where M:）
         // 这些情况下不进行类型名称的替换
@@ -1490,7 +1727,9 @@ impl Rust2NuConverter {
                 .replace("Mutex :: ", "__MUTEX_PATH_SP__")
                 .replace("Mutex::", "__MUTEX_PATH__")
                 .replace("Box :: ", "__BOX_PATH_SP__")
-                .replace("Box::", "__BOX_PATH__");
+                .replace("Box::", "__BOX_PATH__")
+                .replace("Weak :: ", "__WEAK_PATH_SP__")
+                .replace("Weak::", "__WEAK_PATH__");
 
             // v1.8: 先保护完整的标识符（如 Boxed, VecDeque）以防止被错误替换
             // 这些标识符包含 Vec/Option/Result/Box/Arc/Mutex 作为子串
@@ -1521,18 +1760,15 @@ impl Rust2NuConverter {
 
             // v1.8.3: 使用智能替换，只替换独立的类型名称
             // 这样 "BarrierWaitResult" 不会被替换成 "BarrierWaitR"
-            result = replace_standalone_type(&result, "Vec", "V");
-            result = replace_standalone_type(&result, "Option", "O");
-            result = replace_standalone_type(&result, "Result", "R");
-            result = replace_standalone_type(&result, "Arc", "A");
-            result = replace_standalone_type(&result, "Mutex", "X");
-            result = replace_standalone_type(&result, "Box", "B");
+            for full in &self.options.profile.types {
+                if let Some(abbr) = self.options.profile.abbreviation(full) {
+                    result = replace_standalone_type(&result, full, abbr);
+                }
+            }
 
             // 这些替换不需要边界检查
-            result = result
-                .replace("& mut", "&!")
-                .replace("&mut", "&!")
-                .replace("vec!", "V!"); // vec! -> V!
+            result = result.replace("& mut", "&!").replace("&mut", "&!");
+            result = self.vec_macro(&result); // vec! -> V!
 
             // 恢复被保护的标识符
             result = result
@@ -1573,7 +1809,9 @@ impl Rust2NuConverter {
                 .replace("__MUTEX_PATH_SP__", "Mutex::")
                 .replace("__MUTEX_PATH__", "Mutex::")
                 .replace("__BOX_PATH_SP__", "Box::")
-                .replace("__BOX_PATH__", "Box::");
+                .replace("__BOX_PATH__", "Box::")
+                .replace("__WEAK_PATH_SP__", "Weak::")
+                .replace("__WEAK_PATH__", "Weak::");
         }
 
         // 恢复 turbofish（保持原样，不进行类型替换）
@@ -1581,20 +1819,179 @@ impl Rust2NuConverter {
             result = result.replace(&format!("__TURBOFISH_PLACEHOLDER_{}__", idx), part);
         }
 
-        result
+        restore_string_literals(&result, &protected_strings)
     }
 
     /// 转换函数体
     fn convert_block(&mut self, block: &Block) {
         self.writeln(" {");
         self.indent_level += 1;
+        self.convert_block_stmts(block);
+        self.indent_level -= 1;
+        self.writeln("}");
+    }
 
+    /// 逐条转换块内语句，并在语句之间插回注释和空行
+    fn convert_block_stmts(&mut self, block: &Block) {
         for stmt in &block.stmts {
+            let start = stmt.span().start();
+            self.flush_comments(start.line);
+            // 同一行语句之前的注释（`/* a */ let x = 1;`）留在语句开头
+            let leading = self.comments.take_leading(start.line, start.column);
+            let mark = self.output.len();
+            let (comments, diagnostics) = (self.comments.clone(), self.diagnostics.len());
             self.convert_stmt(stmt);
+            // 单行输出的闭包、结构体字面量、宏参数中的注释无法就位：整条语句按原始格式输出
+            let end = stmt.span().end();
+            if self.comments.has_comment_in(start, end) {
+                self.output.truncate(mark);
+                self.diagnostics.truncate(diagnostics);
+                self.comments = comments;
+                self.convert_stmt_verbatim(stmt);
+            }
+            if !leading.is_empty() {
+                let indent = self.output[mark..].len() - self.output[mark..].trim_start().len();
+                let prefix: String = leading.iter().map(|text| format!("{} ", text)).collect();
+                self.output.insert_str(mark + indent, &prefix);
+            }
+            self.flush_trailing_comments(stmt.span().end().line);
+        }
+        self.flush_comments(block.brace_token.span.close().start().line);
+    }
+
+    /// 按原始格式输出一条语句（只改写关键字、类型缩写等记号），其中的注释和换行保持原位
+    fn convert_stmt_verbatim(&mut self, stmt: &Stmt) {
+        let span = stmt.span();
+        let text = preserve::convert_stmt(
+            &self.source_code,
+            &self.options.profile,
+            &self.generic_scope_stack,
+            stmt,
+        );
+        self.comments.discard_in(span.start(), span.end());
+        // 后续行按语句首行在源码中的缩进对齐到当前缩进
+        let base: String = self
+            .source_code
+            .lines()
+            .nth(span.start().line - 1)
+            .unwrap_or_default()
+            .chars()
+            .take_while(|c| c.is_whitespace())
+            .collect();
+        let indent = self.indent();
+        let mut lines = text.lines();
+        if let Some(first) = lines.next() {
+            self.output.push_str(&indent);
+            self.output.push_str(first);
+        }
+        for line in lines {
+            self.output.push('\n');
+            match line.strip_prefix(base.as_str()) {
+                Some(rest) if !line.trim().is_empty() => {
+                    self.output.push_str(&indent);
+                    self.output.push_str(rest);
+                }
+                _ => self.output.push_str(line),
+            }
         }
+        self.output.push('\n');
+    }
 
+    /// 语句位置的 if / match 内部有注释时逐条展开输出，注释留在所在的代码块或分支中；
+    /// 没有注释时返回 false，按单行表达式转换
+    fn convert_commented_expr(&mut self, expr: &Expr) -> bool {
+        let span = expr.span();
+        if !self.comments.has_comment_in(span.start(), span.end()) {
+            return false;
+        }
+        match expr {
+            Expr::If(if_expr) => {
+                self.write(&self.indent());
+                self.convert_if_stmt(if_expr);
+            }
+            Expr::Match(match_expr) => {
+                self.write(&self.indent());
+                self.convert_match_stmt(match_expr);
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// 展开输出 if 语句（不含结尾换行）
+    fn convert_if_stmt(&mut self, if_expr: &syn::ExprIf) {
+        let cond = self.convert_expr(&if_expr.cond);
+        self.write(&self.convert_type_in_string(&format!("if {} {{", cond)));
+        self.write("\n");
+        self.indent_level += 1;
+        self.convert_block_stmts(&if_expr.then_branch);
         self.indent_level -= 1;
-        self.writeln("}");
+        self.write(&self.indent());
+        self.write("}");
+
+        if let Some((_, else_branch)) = &if_expr.else_branch {
+            self.write(" else ");
+            match else_branch.as_ref() {
+                Expr::If(nested) => self.convert_if_stmt(nested),
+                Expr::Block(block_expr) => {
+                    self.write("{\n");
+                    self.indent_level += 1;
+                    self.convert_block_stmts(&block_expr.block);
+                    self.indent_level -= 1;
+                    self.write(&self.indent());
+                    self.write("}");
+                }
+                other => {
+                    let converted = self.convert_expr(other);
+                    self.write(&converted);
+                }
+            }
+        }
+    }
+
+    /// 展开输出 match 语句（不含结尾换行），分支注释按分支位置插回
+    fn convert_match_stmt(&mut self, match_expr: &syn::ExprMatch) {
+        let scrutinee = self.convert_expr(&match_expr.expr);
+        self.write(&self.convert_type_in_string(&format!("M {} {{", scrutinee)));
+        self.write("\n");
+        self.indent_level += 1;
+
+        for arm in &match_expr.arms {
+            self.flush_comments(arm.span().start().line);
+            for attr in &arm.attrs {
+                let cleaned_attr = self.convert_attribute(attr);
+                if cleaned_attr.starts_with("#[cfg") {
+                    self.writeln(&cleaned_attr);
+                }
+            }
+            let mut head = self.clean_token_spaces(&arm.pat.to_token_stream().to_string());
+            if let Some((_, guard)) = &arm.guard {
+                head.push_str(" if ");
+                head.push_str(&self.convert_expr(guard));
+            }
+            let comma = if arm.comma.is_some() { "," } else { "" };
+            match arm.body.as_ref() {
+                Expr::Block(block_expr)
+                    if block_expr.attrs.is_empty() && block_expr.label.is_none() =>
+                {
+                    self.writeln(&self.convert_type_in_string(&format!("{} => {{", head)));
+                    self.indent_level += 1;
+                    self.convert_block_stmts(&block_expr.block);
+                    self.indent_level -= 1;
+                    self.writeln(&format!("}}{}", comma));
+                }
+                body => {
+                    let line = format!("{} => {}{}", head, self.convert_expr(body), comma);
+                    self.writeln(&self.convert_type_in_string(&line));
+                }
+            }
+            self.flush_trailing_comments(arm.span().end().line);
+        }
+        self.flush_comments(match_expr.brace_token.span.close().start().line);
+
+        self.indent_level -= 1;
+        self.write(&self.indent());
+        self.write("}");
     }
 
     /// 递归检测表达式中是否包含嵌套的unsafe块
@@ -1727,12 +2124,68 @@ impl Rust2NuConverter {
         }
     }
 
+    /// 取出 span 覆盖的完整源码行（保留原始换行与缩进），其中的注释随原文输出；
+    /// 没有源码时返回 None
+    fn take_source_lines(&mut self, span: proc_macro2::Span) -> Option<String> {
+        let (start, end) = (span.start(), span.end());
+        let lines: Vec<&str> = self.source_code.lines().collect();
+        if self.source_code.is_empty() || start.line == 0 || end.line > lines.len() {
+            return None;
+        }
+        let text = lines[start.line - 1..end.line].join("\n");
+        self.comments.take_before(end.line + 1);
+        Some(text)
+    }
+
+    /// 命名字段块 ` { name: Type, ... }`（struct 与 union 共用）
+    fn write_named_fields(&mut self, fields: &syn::FieldsNamed) {
+        self.writeln(" {");
+        self.indent_level += 1;
+        for field in &fields.named {
+            self.flush_comments(field.span().start().line);
+            self.write_doc_comments(&field.attrs);
+            // 输出字段的 #[cfg] 属性
+            for attr in &field.attrs {
+                let attr_str = attr.to_token_stream().to_string();
+                // to_token_stream()会在#、[、(、)周围插入空格，需要移除
+                let cleaned_attr = attr_str
+                    .replace("# [", "#[")
+                    .replace(" [", "[")
+                    .replace(" ]", "]")
+                    .replace(" (", "(")
+                    .replace(" )", ")")
+                    .replace(" ,", ",");
+                if cleaned_attr.starts_with("#[cfg") {
+                    self.write(&self.indent());
+                    self.writeln(&cleaned_attr);
+                }
+            }
+
+            self.write(&self.indent());
+            if let Some(ident) = &field.ident {
+                self.write(&ident.to_string());
+                self.write(": ");
+                self.write(&self.convert_type(&field.ty));
+                self.writeln(",");
+            }
+            self.flush_trailing_comments(field.span().end().line);
+        }
+        self.flush_comments(fields.brace_token.span.close().start().line);
+        self.indent_level -= 1;
+        self.writeln("}");
+    }
+
     fn convert_attribute(&self, attr: &Attribute) -> String {
+        if let Some(doc) = self.doc_comment(attr) {
+            return doc;
+        }
         let path = attr.path().to_token_stream().to_string();
         let tokens = attr.meta.to_token_stream().to_string();
 
-        if path == "derive" {
+        if path == "derive" && self.options.profile.derive {
             format!("#D{}", tokens.trim_start_matches("derive"))
+        } else if path == "inline" && self.options.profile.inline {
+            format!("#I{}", tokens.trim_start_matches("inline").replace(' ', ""))
         } else {
             // 保持其他属性的完整格式，并清理多余空格
             let cleaned_tokens = tokens
@@ -1748,6 +2201,11 @@ impl<'ast> Visit<'ast> for Rust2NuConverter {
     fn visit_file(&mut self, node: &'ast File) {
         // Nu v1.6.3: 优先输出文件级属性 #![...]
         for attr in &node.attrs {
+            self.flush_comments(attr.span().start().line);
+            if let Some(doc) = self.doc_comment(attr) {
+                self.write_comment(&doc);
+                continue;
+            }
             let attr_str = attr.to_token_stream().to_string();
             // to_token_stream()会在#!、[、]周围插入空格，需要移除
             let cleaned_attr = attr_str
@@ -1763,9 +2221,12 @@ impl<'ast> Visit<'ast> for Rust2NuConverter {
         }
 
         for item in &node.items {
+            self.flush_comments(item.span().start().line);
             self.visit_item(item);
-            self.output.push('\n');
+            self.flush_trailing_comments(item.span().end().line);
+            self.item_separator();
         }
+        self.flush_comments(usize::MAX);
     }
 
     fn visit_item(&mut self, node: &'ast Item) {
@@ -1776,26 +2237,30 @@ impl<'ast> Visit<'ast> for Rust2NuConverter {
             Item::Trait(t) => self.visit_item_trait(t),
             Item::Impl(i) => self.visit_item_impl(i),
             Item::Macro(m) => {
+                let name = m
+                    .mac
+                    .path
+                    .segments
+                    .last()
+                    .map(|seg| seg.ident.to_string())
+                    .unwrap_or_default();
+                let diagnostic = match (&m.ident, name.as_str()) {
+                    (Some(ident), "macro_rules") => Diagnostic::note(
+                        "macro-passthrough",
+                        format!("macro_rules! {} 的定义原样保留", ident),
+                    ),
+                    _ => Diagnostic::warning(
+                        "macro-passthrough",
+                        format!("宏 `{}!` 原样保留，未转换为 Nu 语法", name),
+                    ),
+                };
+                self.diagnostics
+                    .push(diagnostic.with_span(self.source_span(m.mac.path.span())));
+
                 // v1.8: 使用span提取原始宏文本，保留1:1换行格式
-                // 如果有source_code，尝试从中提取原始文本
-                if !self.source_code.is_empty() {
-                    let span = m.span();
-                    let start = span.start();
-                    let end = span.end();
-
-                    // 按行分割源代码
-                    let lines: Vec<&str> = self.source_code.lines().collect();
-
-                    // 提取从start到end的所有行（行号从1开始，转为0索引）
-                    if start.line > 0 && end.line <= lines.len() {
-                        let start_line = start.line - 1;
-                        let end_line = end.line; // end.line是包含的，不需要-1
-
-                        // 提取原始宏文本
-                        let original_macro: String = lines[start_line..end_line].join("\n");
-                        self.writeln(&original_macro);
-                        return;
-                    }
+                if let Some(original_macro) = self.take_source_lines(m.span()) {
+                    self.writeln(&original_macro);
+                    return;
                 }
 
                 // 回退方案：使用to_token_stream()并清理空格
@@ -1816,6 +2281,7 @@ impl<'ast> Visit<'ast> for Rust2NuConverter {
                 self.writeln(&cleaned_macro);
             }
             Item::Mod(m) => {
+                self.write_doc_comments(&m.attrs);
                 // v1.8: 保留 #[cfg] 和 #[macro_use] 属性
                 for attr in &m.attrs {
                     let attr_str = attr.to_token_stream().to_string();
@@ -1861,9 +2327,12 @@ impl<'ast> Visit<'ast> for Rust2NuConverter {
                     self.writeln(" {");
                     self.indent_level += 1;
                     for item in items {
+                        self.flush_comments(item.span().start().line);
                         self.visit_item(item);
-                        self.output.push('\n');
+                        self.flush_trailing_comments(item.span().end().line);
+                        self.item_separator();
                     }
+                    self.flush_comments(m.span().end().line);
                     self.indent_level -= 1;
                     self.writeln("}");
                 } else {
@@ -1874,6 +2343,10 @@ impl<'ast> Visit<'ast> for Rust2NuConverter {
             Item::Use(u) => {
                 // v1.8: 先单独输出属性（每个属性一行），避免合并到 use 语句行
                 for attr in &u.attrs {
+                    if let Some(doc) = self.doc_comment(attr) {
+                        self.write_comment(&doc);
+                        continue;
+                    }
                     let attr_str = attr.to_token_stream().to_string();
                     let cleaned_attr = attr_str
                         .replace("# [", "#[")
@@ -1904,6 +2377,7 @@ impl<'ast> Visit<'ast> for Rust2NuConverter {
                 self.writeln(&format!("{}{};", vis_prefix, cleaned_tree));
             }
             Item::Const(c) => {
+                self.write_doc_comments(&c.attrs);
                 // v1.8: 保留 #[cfg] 属性
                 for attr in &c.attrs {
                     let attr_str = attr.to_token_stream().to_string();
@@ -1932,6 +2406,7 @@ impl<'ast> Visit<'ast> for Rust2NuConverter {
                 self.writeln(";");
             }
             Item::Static(s) => {
+                self.write_doc_comments(&s.attrs);
                 // Nu v1.6.3: SM = static mut, ST = static
                 let is_pub = self.is_public(&s.vis);
                 let keyword = if matches!(s.mutability, syn::StaticMutability::Mut(_)) {
@@ -1956,6 +2431,21 @@ impl<'ast> Visit<'ast> for Rust2NuConverter {
                 self.write(&s.expr.to_token_stream().to_string());
                 self.writeln(";");
             }
+            Item::Union(u) => self.visit_item_union(u),
+            Item::ForeignMod(f) => self.visit_item_foreign_mod(f),
+            Item::ExternCrate(e) => self.visit_item_extern_crate(e),
+            Item::TraitAlias(t) => self.visit_item_trait_alias(t),
+            Item::Verbatim(tokens) => {
+                // syn 无法解析的条目（如不稳定语法）：按源码原样保留
+                self.diagnostics.push(
+                    Diagnostic::note("verbatim-item", "无法解析的条目按 Rust 语法原样保留")
+                        .with_span(self.source_span(node.span())),
+                );
+                match self.take_source_lines(node.span()) {
+                    Some(text) => self.writeln(&text),
+                    None => self.writeln(&self.clean_token_spaces(&tokens.to_string())),
+                }
+            }
             _ => {
                 // 其他项保持原样
                 self.writeln(&node.to_token_stream().to_string());
@@ -2036,38 +2526,7 @@ impl<'ast> Visit<'ast> for Rust2NuConverter {
 
         // 字段
         match &node.fields {
-            syn::Fields::Named(fields) => {
-                self.writeln(" {");
-                self.indent_level += 1;
-                for field in &fields.named {
-                    // 输出字段的 #[cfg] 属性
-                    for attr in &field.attrs {
-                        let attr_str = attr.to_token_stream().to_string();
-                        // to_token_stream()会在#、[、(、)周围插入空格，需要移除
-                        let cleaned_attr = attr_str
-                            .replace("# [", "#[")
-                            .replace(" [", "[")
-                            .replace(" ]", "]")
-                            .replace(" (", "(")
-                            .replace(" )", ")")
-                            .replace(" ,", ",");
-                        if cleaned_attr.starts_with("#[cfg") {
-                            self.write(&self.indent());
-                            self.writeln(&cleaned_attr);
-                        }
-                    }
-
-                    self.write(&self.indent());
-                    if let Some(ident) = &field.ident {
-                        self.write(&ident.to_string());
-                        self.write(": ");
-                        self.write(&self.convert_type(&field.ty));
-                        self.writeln(",");
-                    }
-                }
-                self.indent_level -= 1;
-                self.writeln("}");
-            }
+            syn::Fields::Named(fields) => self.write_named_fields(fields),
             syn::Fields::Unnamed(fields) => {
                 // Tuple struct: pub struct ParseLevelError(());
                 // v1.8.7: 对于元组结构体，where 子句应该在字段之后
@@ -2173,6 +2632,158 @@ impl<'ast> Visit<'ast> for Rust2NuConverter {
         self.pop_generic_scope();
     }
 
+    fn visit_item_union(&mut self, node: &'ast syn::ItemUnion) {
+        self.push_generic_scope(&node.generics);
+        for attr in &node.attrs {
+            self.writeln(&self.convert_attribute(attr));
+        }
+
+        // union 与 struct 规则相同：UN=pub union, un=union，受限可见性显式保留
+        self.write(&self.indent());
+        if let syn::Visibility::Restricted(vis_restricted) = &node.vis {
+            let vis_str = vis_restricted.to_token_stream().to_string();
+            self.write(&self.clean_token_spaces(&vis_str));
+            self.write(" ");
+        }
+        self.write(if self.is_public(&node.vis) {
+            "UN"
+        } else {
+            "un"
+        });
+        self.write(" ");
+        self.write(&node.ident.to_string());
+        if !node.generics.params.is_empty() {
+            self.write(&self.convert_generics(&node.generics));
+        }
+        if let Some(where_clause) = &node.generics.where_clause {
+            self.write(" wh ");
+            self.write(
+                where_clause
+                    .to_token_stream()
+                    .to_string()
+                    .replace("where", "")
+                    .trim(),
+            );
+        }
+        self.write_named_fields(&node.fields);
+        self.pop_generic_scope();
+    }
+
+    fn visit_item_foreign_mod(&mut self, node: &'ast syn::ItemForeignMod) {
+        for attr in &node.attrs {
+            self.writeln(&self.convert_attribute(attr));
+        }
+
+        // extern "C" { ... } -> EXT "C" { ... }
+        let unsafe_prefix = if node.unsafety.is_some() {
+            "unsafe "
+        } else {
+            ""
+        };
+        self.writeln(&format!(
+            "{}{}{{",
+            unsafe_prefix,
+            self.convert_abi(&node.abi)
+        ));
+        self.indent_level += 1;
+
+        for item in &node.items {
+            self.flush_comments(item.span().start().line);
+            let (attrs, line) = match item {
+                syn::ForeignItem::Fn(func) => (
+                    &func.attrs,
+                    format!("{};", self.convert_fn_signature(&func.sig, &func.vis)),
+                ),
+                syn::ForeignItem::Static(stat) => {
+                    let is_pub = self.is_public(&stat.vis);
+                    let keyword = match (&stat.mutability, is_pub) {
+                        (syn::StaticMutability::Mut(_), true) => "SMP",
+                        (syn::StaticMutability::Mut(_), false) => "SM",
+                        (_, true) => "SP",
+                        (_, false) => "ST",
+                    };
+                    let ty = self.convert_type(&stat.ty);
+                    (&stat.attrs, format!("{} {}: {};", keyword, stat.ident, ty))
+                }
+                // extern type、宏调用等：与条目级 type 别名一样保持 Rust 语法
+                other => {
+                    let text = self.clean_token_spaces(&other.to_token_stream().to_string());
+                    self.writeln(&text);
+                    self.flush_trailing_comments(item.span().end().line);
+                    continue;
+                }
+            };
+            for attr in attrs {
+                self.writeln(&self.convert_attribute(attr));
+            }
+            self.writeln(&line);
+            self.flush_trailing_comments(item.span().end().line);
+        }
+        self.flush_comments(node.brace_token.span.close().start().line);
+
+        self.indent_level -= 1;
+        self.writeln("}");
+    }
+
+    fn visit_item_extern_crate(&mut self, node: &'ast syn::ItemExternCrate) {
+        for attr in &node.attrs {
+            self.writeln(&self.convert_attribute(attr));
+        }
+
+        // extern crate foo as bar; -> EXT crate foo as bar;
+        let vis_prefix = match &node.vis {
+            syn::Visibility::Inherited => String::new(),
+            vis => format!(
+                "{} ",
+                self.clean_token_spaces(&vis.to_token_stream().to_string())
+            ),
+        };
+        let rename = match &node.rename {
+            Some((_, rename)) => format!(" as {}", rename),
+            None => String::new(),
+        };
+        self.writeln(&format!(
+            "{}EXT crate {}{};",
+            vis_prefix, node.ident, rename
+        ));
+    }
+
+    fn visit_item_trait_alias(&mut self, node: &'ast syn::ItemTraitAlias) {
+        for attr in &node.attrs {
+            self.writeln(&self.convert_attribute(attr));
+        }
+
+        // trait Alias = A + B; -> TR Alias = A + B;
+        self.push_generic_scope(&node.generics);
+        let mut line = format!(
+            "{} {}",
+            if self.is_public(&node.vis) {
+                "TR"
+            } else {
+                "tr"
+            },
+            node.ident
+        );
+        if !node.generics.params.is_empty() {
+            line.push_str(&self.convert_generics(&node.generics));
+        }
+        line.push_str(" = ");
+        line.push_str(&self.convert_type_param_bounds(&node.bounds));
+        if let Some(where_clause) = &node.generics.where_clause {
+            line.push_str(" wh ");
+            line.push_str(
+                where_clause
+                    .to_token_stream()
+                    .to_string()
+                    .replace("where", "")
+                    .trim(),
+            );
+        }
+        line.push(';');
+        self.writeln(&line);
+        self.pop_generic_scope();
+    }
+
     fn visit_item_enum(&mut self, node: &'ast ItemEnum) {
         // 处理属性
         for attr in &node.attrs {
@@ -2221,8 +2832,13 @@ impl<'ast> Visit<'ast> for Rust2NuConverter {
         self.indent_level += 1;
 
         for variant in &node.variants {
+            self.flush_comments(variant.span().start().line);
             // v1.8.5: 保留 enum variant 上的 #[cfg] 和其他属性
             for attr in &variant.attrs {
+                if let Some(doc) = self.doc_comment(attr) {
+                    self.write_comment(&doc);
+                    continue;
+                }
                 let attr_str = attr.to_token_stream().to_string();
                 let cleaned_attr = attr_str
                     .replace("# [", "#[")
@@ -2267,13 +2883,16 @@ impl<'ast> Visit<'ast> for Rust2NuConverter {
             }
 
             self.writeln(",");
+            self.flush_trailing_comments(variant.span().end().line);
         }
+        self.flush_comments(node.brace_token.span.close().start().line);
 
         self.indent_level -= 1;
         self.writeln("}");
     }
 
     fn visit_item_trait(&mut self, node: &'ast ItemTrait) {
+        self.write_doc_comments(&node.attrs);
         // v1.8: 保留 #[cfg] 属性
         for attr in &node.attrs {
             let attr_str = attr.to_token_stream().to_string();
@@ -2341,6 +2960,7 @@ impl<'ast> Visit<'ast> for Rust2NuConverter {
         self.indent_level += 1;
 
         for item in &node.items {
+            self.flush_comments(item.span().start().line);
             match item {
                 syn::TraitItem::Fn(method) => {
                     // 处理方法的属性（如 #[allow(dead_code)]）
@@ -2358,7 +2978,7 @@ impl<'ast> Visit<'ast> for Rust2NuConverter {
                     if let Some(block) = &method.default {
                         // 有默认实现：输出函数体
                         self.convert_block(block);
-                        self.output.push('\n');
+                        self.item_separator();
                     } else {
                         // 无实现：只输出签名+分号
                         self.writeln(";");
@@ -2416,7 +3036,9 @@ impl<'ast> Visit<'ast> for Rust2NuConverter {
                     // 忽略其他trait item类型
                 }
             }
+            self.flush_trailing_comments(item.span().end().line);
         }
+        self.flush_comments(node.brace_token.span.close().start().line);
 
         self.indent_level -= 1;
         self.writeln("}");
@@ -2426,6 +3048,7 @@ impl<'ast> Visit<'ast> for Rust2NuConverter {
         // 进入泛型作用域，记录impl的泛型参数
         self.push_generic_scope(&node.generics);
 
+        self.write_doc_comments(&node.attrs);
         // Nu v1.6.3: 保留 #[cfg] 属性
         for attr in &node.attrs {
             let attr_str = attr.to_token_stream().to_string();
@@ -2481,9 +3104,11 @@ impl<'ast> Visit<'ast> for Rust2NuConverter {
         self.indent_level += 1;
 
         for item in &node.items {
+            self.flush_comments(item.span().start().line);
             match item {
                 syn::ImplItem::Fn(method) => {
-                    // 输出方法的 #[cfg] 属性
+                    self.write_doc_comments(&method.attrs);
+                    // 输出方法的 #[cfg] 与 #[inline] 属性
                     for attr in &method.attrs {
                         let attr_str = attr.to_token_stream().to_string();
                         // to_token_stream()会在#、[、(、)周围插入空格，需要移除
@@ -2497,6 +3122,8 @@ impl<'ast> Visit<'ast> for Rust2NuConverter {
                         if cleaned_attr.starts_with("#[cfg") {
                             self.write(&self.indent());
                             self.writeln(&cleaned_attr);
+                        } else if attr.path().is_ident("inline") {
+                            self.writeln(&self.convert_attribute(attr));
                         }
                     }
 
@@ -2504,7 +3131,7 @@ impl<'ast> Visit<'ast> for Rust2NuConverter {
                     self.write(&self.indent());
                     self.write(&sig_str);
                     self.convert_block(&method.block);
-                    self.output.push('\n');
+                    self.item_separator();
                 }
                 syn::ImplItem::Type(type_item) => {
                     // v1.8: 处理关联类型的属性（如 #[cfg]）
@@ -2565,7 +3192,9 @@ impl<'ast> Visit<'ast> for Rust2NuConverter {
                     // 其他类型的impl item暂时保持原样
                 }
             }
+            self.flush_trailing_comments(item.span().end().line);
         }
+        self.flush_comments(node.brace_token.span.close().start().line);
 
         self.indent_level -= 1;
         self.writeln("}");
@@ -2575,6 +3204,26 @@ impl<'ast> Visit<'ast> for Rust2NuConverter {
     }
 }
 
+impl Transpile for Rust2NuConverter {
+    fn transpile(&self, rust_code: &str) -> ConversionOutput {
+        match self.convert_with_diagnostics(rust_code) {
+            Ok((code, diagnostics)) => ConversionOutput {
+                code,
+                sourcemap: Vec::new(),
+                diagnostics,
+            },
+            Err(err) => {
+                let mut diagnostic = Diagnostic::error("parse-error", format!("{:#}", err));
+                if let Some(syn_err) = err.downcast_ref::<syn::Error>() {
+                    let start = syn_err.span().start();
+                    diagnostic = diagnostic.with_span(Span::new(start.line, start.column + 1, 1));
+                }
+                ConversionOutput::failed(diagnostic)
+            }
+        }
+    }
+}
+
 impl Default for Rust2NuConverter {
     fn default() -> Self {
         Self::new()
@@ -0,0 +1,542 @@
+// 保留原始格式的 Rust → Nu 转换
+// 不从 syn 重新打印：按 proc_macro2 的 span 位置原样复制未改动的源码，
+// 只改写需要变化的记号（关键字、类型缩写、`?` → `!`、`mut` → `!`），与原文的差异最小
+
+use super::EXPRESSION_MACROS;
+use crate::profile::CompressionProfile;
+use anyhow::{Context, Result};
+use proc_macro2::{LineColumn, Span};
+use std::collections::HashSet;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+use syn::{Expr, Generics, Signature, Stmt, Token, Visibility};
+
+/// 把源码区间 [start, end)（字节偏移）替换为 text
+#[derive(Debug)]
+struct Edit {
+    start: usize,
+    end: usize,
+    text: String,
+}
+
+/// 按原始格式转换整个文件
+pub(super) fn convert(source: &str, profile: &CompressionProfile) -> Result<String> {
+    let file = syn::parse_file(source).context("Failed to parse Rust code")?;
+    let mut rewriter = Rewriter::new(source, profile);
+    rewriter.visit_file(&file);
+    Ok(rewriter.apply())
+}
+
+/// 按原始格式转换单条语句（用于默认模式下内部含注释、无法展开输出的语句）；
+/// generics 为语句所在作用域的泛型参数，返回语句源码范围内的转换结果
+pub(super) fn convert_stmt(
+    source: &str,
+    profile: &CompressionProfile,
+    generics: &[HashSet<String>],
+    stmt: &Stmt,
+) -> String {
+    let mut rewriter = Rewriter::new(source, profile);
+    rewriter.generic_scopes = generics.to_vec();
+    rewriter.visit_stmt(stmt);
+    let span = stmt.span();
+    let (start, end) = (rewriter.offset(span.start()), rewriter.offset(span.end()));
+    rewriter.apply_range(start, end)
+}
+
+struct Rewriter<'s> {
+    source: &'s str,
+    /// 每行行首的字节偏移
+    line_starts: Vec<usize>,
+    edits: Vec<Edit>,
+    /// 泛型参数作用域：泛型参数名不做类型缩写
+    generic_scopes: Vec<HashSet<String>>,
+    profile: &'s CompressionProfile,
+}
+
+impl<'s> Rewriter<'s> {
+    fn new(source: &'s str, profile: &'s CompressionProfile) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self {
+            source,
+            line_starts,
+            edits: Vec::new(),
+            generic_scopes: Vec::new(),
+            profile,
+        }
+    }
+
+    /// 按起始位置应用全部改写；与前一处重叠的改写被忽略
+    fn apply(self) -> String {
+        let end = self.source.len();
+        self.apply_range(0, end)
+    }
+
+    /// 只输出源码区间 [start, end) 并应用其中的改写
+    fn apply_range(mut self, start: usize, end: usize) -> String {
+        self.edits.sort_by_key(|edit| (edit.start, edit.end));
+        let mut output = String::with_capacity(end - start);
+        let mut copied = start;
+        for edit in &self.edits {
+            if edit.start < copied || edit.end > end {
+                continue;
+            }
+            output.push_str(&self.source[copied..edit.start]);
+            output.push_str(&edit.text);
+            copied = edit.end;
+        }
+        output.push_str(&self.source[copied..end]);
+        output
+    }
+
+    /// 行列位置（行从 1 开始，列按字符计）转为字节偏移
+    fn offset(&self, pos: LineColumn) -> usize {
+        let Some(&start) = self.line_starts.get(pos.line.wrapping_sub(1)) else {
+            return self.source.len();
+        };
+        self.source[start..]
+            .char_indices()
+            .nth(pos.column)
+            .map_or(self.source.len(), |(i, _)| start + i)
+    }
+
+    /// 跳过 offset 之后同一行内的空白
+    fn skip_spaces(&self, offset: usize) -> usize {
+        let rest = &self.source[offset..];
+        offset + (rest.len() - rest.trim_start_matches([' ', '\t']).len())
+    }
+
+    fn push(&mut self, start: usize, end: usize, text: &str) {
+        self.edits.push(Edit {
+            start,
+            end,
+            text: text.to_string(),
+        });
+    }
+
+    /// 替换一个记号
+    fn replace(&mut self, span: Span, text: &str) {
+        let (start, end) = (self.offset(span.start()), self.offset(span.end()));
+        self.push(start, end, text);
+    }
+
+    /// 替换一个记号及其后的空白（`async fn` → `~F`、`move |x|` → `$|x|`）
+    fn replace_glued(&mut self, span: Span, text: &str) {
+        let start = self.offset(span.start());
+        let end = self.skip_spaces(self.offset(span.end()));
+        self.push(start, end, text);
+    }
+
+    /// 把 from 开始到 to 结束的一段（含中间空白）替换为 text
+    fn replace_range(&mut self, from: Span, to: Span, text: &str) {
+        let (start, end) = (self.offset(from.start()), self.offset(to.end()));
+        self.push(start, end, text);
+    }
+
+    fn is_generic_param(&self, name: &str) -> bool {
+        self.generic_scopes.iter().any(|scope| scope.contains(name))
+    }
+
+    fn push_generics(&mut self, generics: &Generics) {
+        let scope = generics
+            .type_params()
+            .map(|param| param.ident.to_string())
+            .collect();
+        self.generic_scopes.push(scope);
+    }
+
+    /// pub 与受限可见性都视为公开（与 Rust2NuConverter::is_public 一致）
+    fn is_public(vis: &Visibility) -> bool {
+        !matches!(vis, Visibility::Inherited)
+    }
+
+    /// 删除 `pub`，受限可见性 keep_restricted 时保留
+    fn strip_visibility(&mut self, vis: &Visibility, keep_restricted: bool) {
+        match vis {
+            Visibility::Public(_) => self.replace_glued(vis.span(), ""),
+            Visibility::Restricted(_) if !keep_restricted => self.replace_glued(vis.span(), ""),
+            _ => {}
+        }
+    }
+
+    /// fn 签名：pub fn → F、fn → f、async fn → ~F、extern "C" fn → EXT "C" F
+    fn rewrite_fn(&mut self, vis: &Visibility, sig: &Signature) {
+        let public = Self::is_public(vis);
+        self.strip_visibility(vis, false);
+        if let Some(abi) = &sig.abi {
+            self.replace(abi.extern_token.span, "EXT");
+        }
+        let keyword = if public { "F" } else { "f" };
+        match &sig.asyncness {
+            Some(asyncness) => {
+                self.replace_glued(asyncness.span, "");
+                self.replace(sig.fn_token.span, &format!("~{}", keyword));
+            }
+            None => self.replace(sig.fn_token.span, keyword),
+        }
+    }
+
+    /// 数据项关键字：pub 删除，受限可见性按 keep_restricted 处理
+    fn rewrite_keyword(
+        &mut self,
+        vis: &Visibility,
+        keyword: Span,
+        keep_restricted: bool,
+        public: &str,
+        private: &str,
+    ) {
+        self.strip_visibility(vis, keep_restricted);
+        let text = if Self::is_public(vis) {
+            public
+        } else {
+            private
+        };
+        self.replace(keyword, text);
+    }
+
+    /// static → ST/SP，static mut → SM/SMP
+    fn rewrite_static(
+        &mut self,
+        vis: &Visibility,
+        static_token: Span,
+        mutability: &syn::StaticMutability,
+    ) {
+        let public = Self::is_public(vis);
+        self.strip_visibility(vis, false);
+        match mutability {
+            syn::StaticMutability::Mut(mut_token) => {
+                let keyword = if public { "SMP" } else { "SM" };
+                self.replace_range(static_token, mut_token.span, keyword);
+            }
+            _ => {
+                let keyword = if public { "SP" } else { "ST" };
+                self.replace(static_token, keyword);
+            }
+        }
+    }
+
+    /// 记号是否是所在行的第一个非空白记号
+    fn starts_line(&self, span: Span) -> bool {
+        let start = self.offset(span.start());
+        let line_start = self.source[..start].rfind('\n').map_or(0, |i| i + 1);
+        self.source[line_start..start].trim().is_empty()
+    }
+}
+
+impl<'ast> Visit<'ast> for Rewriter<'_> {
+    fn visit_item_fn(&mut self, node: &'ast syn::ItemFn) {
+        self.rewrite_fn(&node.vis, &node.sig);
+        self.push_generics(&node.sig.generics);
+        visit::visit_item_fn(self, node);
+        self.generic_scopes.pop();
+    }
+
+    fn visit_impl_item_fn(&mut self, node: &'ast syn::ImplItemFn) {
+        self.rewrite_fn(&node.vis, &node.sig);
+        self.push_generics(&node.sig.generics);
+        visit::visit_impl_item_fn(self, node);
+        self.generic_scopes.pop();
+    }
+
+    fn visit_trait_item_fn(&mut self, node: &'ast syn::TraitItemFn) {
+        self.rewrite_fn(&Visibility::Inherited, &node.sig);
+        self.push_generics(&node.sig.generics);
+        visit::visit_trait_item_fn(self, node);
+        self.generic_scopes.pop();
+    }
+
+    fn visit_item_struct(&mut self, node: &'ast syn::ItemStruct) {
+        self.rewrite_keyword(&node.vis, node.struct_token.span, true, "S", "s");
+        self.push_generics(&node.generics);
+        visit::visit_item_struct(self, node);
+        self.generic_scopes.pop();
+    }
+
+    fn visit_item_union(&mut self, node: &'ast syn::ItemUnion) {
+        self.rewrite_keyword(&node.vis, node.union_token.span, true, "UN", "un");
+        self.push_generics(&node.generics);
+        visit::visit_item_union(self, node);
+        self.generic_scopes.pop();
+    }
+
+    fn visit_item_foreign_mod(&mut self, node: &'ast syn::ItemForeignMod) {
+        self.replace(node.abi.extern_token.span, "EXT");
+        visit::visit_item_foreign_mod(self, node);
+    }
+
+    fn visit_foreign_item_fn(&mut self, node: &'ast syn::ForeignItemFn) {
+        self.rewrite_fn(&node.vis, &node.sig);
+        self.push_generics(&node.sig.generics);
+        visit::visit_foreign_item_fn(self, node);
+        self.generic_scopes.pop();
+    }
+
+    fn visit_foreign_item_static(&mut self, node: &'ast syn::ForeignItemStatic) {
+        self.rewrite_static(&node.vis, node.static_token.span, &node.mutability);
+        visit::visit_foreign_item_static(self, node);
+    }
+
+    fn visit_item_extern_crate(&mut self, node: &'ast syn::ItemExternCrate) {
+        self.replace(node.extern_token.span, "EXT");
+        visit::visit_item_extern_crate(self, node);
+    }
+
+    fn visit_item_trait_alias(&mut self, node: &'ast syn::ItemTraitAlias) {
+        self.rewrite_keyword(&node.vis, node.trait_token.span, false, "TR", "tr");
+        self.push_generics(&node.generics);
+        visit::visit_item_trait_alias(self, node);
+        self.generic_scopes.pop();
+    }
+
+    fn visit_item_enum(&mut self, node: &'ast syn::ItemEnum) {
+        self.rewrite_keyword(&node.vis, node.enum_token.span, true, "E", "E");
+        self.push_generics(&node.generics);
+        visit::visit_item_enum(self, node);
+        self.generic_scopes.pop();
+    }
+
+    fn visit_item_trait(&mut self, node: &'ast syn::ItemTrait) {
+        self.rewrite_keyword(&node.vis, node.trait_token.span, false, "TR", "tr");
+        self.push_generics(&node.generics);
+        visit::visit_item_trait(self, node);
+        self.generic_scopes.pop();
+    }
+
+    fn visit_item_impl(&mut self, node: &'ast syn::ItemImpl) {
+        self.replace(node.impl_token.span, "I");
+        self.push_generics(&node.generics);
+        visit::visit_item_impl(self, node);
+        self.generic_scopes.pop();
+    }
+
+    fn visit_item_type(&mut self, node: &'ast syn::ItemType) {
+        self.push_generics(&node.generics);
+        visit::visit_item_type(self, node);
+        self.generic_scopes.pop();
+    }
+
+    fn visit_item_use(&mut self, node: &'ast syn::ItemUse) {
+        self.rewrite_keyword(&node.vis, node.use_token.span, true, "U", "u");
+        visit::visit_item_use(self, node);
+    }
+
+    fn visit_item_mod(&mut self, node: &'ast syn::ItemMod) {
+        self.rewrite_keyword(&node.vis, node.mod_token.span, true, "DM", "D");
+        visit::visit_item_mod(self, node);
+    }
+
+    fn visit_item_const(&mut self, node: &'ast syn::ItemConst) {
+        self.rewrite_keyword(&node.vis, node.const_token.span, false, "CP", "C");
+        visit::visit_item_const(self, node);
+    }
+
+    fn visit_impl_item_const(&mut self, node: &'ast syn::ImplItemConst) {
+        self.rewrite_keyword(&node.vis, node.const_token.span, false, "CP", "C");
+        visit::visit_impl_item_const(self, node);
+    }
+
+    fn visit_trait_item_const(&mut self, node: &'ast syn::TraitItemConst) {
+        self.replace(node.const_token.span, "C");
+        visit::visit_trait_item_const(self, node);
+    }
+
+    fn visit_impl_item_type(&mut self, node: &'ast syn::ImplItemType) {
+        self.replace(node.type_token.span, "t");
+        visit::visit_impl_item_type(self, node);
+    }
+
+    fn visit_trait_item_type(&mut self, node: &'ast syn::TraitItemType) {
+        self.replace(node.type_token.span, "t");
+        visit::visit_trait_item_type(self, node);
+    }
+
+    fn visit_item_static(&mut self, node: &'ast syn::ItemStatic) {
+        self.rewrite_static(&node.vis, node.static_token.span, &node.mutability);
+        visit::visit_item_static(self, node);
+    }
+
+    fn visit_attribute(&mut self, node: &'ast syn::Attribute) {
+        // #[derive(Debug)] → #D(Debug)、#[inline] → #I、#[inline(always)] → #I(always)
+        if node.style == syn::AttrStyle::Outer {
+            let path = node.path();
+            if (path.is_ident("derive") && self.profile.derive)
+                || (path.is_ident("inline") && self.profile.inline)
+            {
+                let short = if path.is_ident("derive") { "#D" } else { "#I" };
+                self.replace_range(node.pound_token.span, path.span(), short);
+                self.replace(node.bracket_token.span.close(), "");
+                return;
+            }
+        }
+        visit::visit_attribute(self, node);
+    }
+
+    fn visit_where_clause(&mut self, node: &'ast syn::WhereClause) {
+        self.replace(node.where_token.span, "wh");
+        visit::visit_where_clause(self, node);
+    }
+
+    fn visit_receiver(&mut self, node: &'ast syn::Receiver) {
+        // &mut self → &!self、mut self → !self
+        if let Some(mutability) = &node.mutability {
+            self.replace_glued(mutability.span, "!");
+        }
+        if node.colon_token.is_some() {
+            self.visit_type(&node.ty);
+        }
+    }
+
+    fn visit_type_reference(&mut self, node: &'ast syn::TypeReference) {
+        if let Some(mutability) = &node.mutability {
+            self.replace_glued(mutability.span, "!");
+        }
+        visit::visit_type_reference(self, node);
+    }
+
+    fn visit_type_path(&mut self, node: &'ast syn::TypePath) {
+        if node.qself.is_none() {
+            for segment in &node.path.segments {
+                let name = segment.ident.to_string();
+                let Some(abbreviated) = self.profile.abbreviation(&name) else {
+                    continue;
+                };
+                if !self.is_generic_param(&name) {
+                    self.replace(segment.ident.span(), abbreviated);
+                }
+            }
+        }
+        visit::visit_type_path(self, node);
+    }
+
+    fn visit_local(&mut self, node: &'ast syn::Local) {
+        // let mut x → v x、let mut x: T → v x: T、let → l
+        let pat = match &node.pat {
+            syn::Pat::Type(typed) => typed.pat.as_ref(),
+            pat => pat,
+        };
+        match pat {
+            syn::Pat::Ident(pat) if pat.by_ref.is_none() && pat.mutability.is_some() => {
+                let start = self.offset(node.let_token.span.start());
+                let end = self.skip_spaces(self.offset(pat.mutability.unwrap().span.end()));
+                self.push(start, end, "v ");
+            }
+            _ => self.replace(node.let_token.span, "l"),
+        }
+        visit::visit_local(self, node);
+    }
+
+    fn visit_stmt(&mut self, node: &'ast Stmt) {
+        // 独占一行的 return 语句 → <
+        if let Stmt::Expr(Expr::Return(ret), _) = node {
+            if self.profile.returns && self.starts_line(ret.return_token.span) {
+                self.replace(ret.return_token.span, "<");
+            }
+        }
+        visit::visit_stmt(self, node);
+    }
+
+    fn visit_expr_match(&mut self, node: &'ast syn::ExprMatch) {
+        self.replace(node.match_token.span, "M");
+        visit::visit_expr_match(self, node);
+    }
+
+    fn visit_expr_for_loop(&mut self, node: &'ast syn::ExprForLoop) {
+        if self.profile.loops {
+            self.replace(node.for_token.span, "L");
+        }
+        visit::visit_expr_for_loop(self, node);
+    }
+
+    fn visit_expr_loop(&mut self, node: &'ast syn::ExprLoop) {
+        if self.profile.loops {
+            self.replace(node.loop_token.span, "L");
+        }
+        visit::visit_expr_loop(self, node);
+    }
+
+    fn visit_expr_break(&mut self, node: &'ast syn::ExprBreak) {
+        self.replace(node.break_token.span, "br");
+        visit::visit_expr_break(self, node);
+    }
+
+    fn visit_expr_continue(&mut self, node: &'ast syn::ExprContinue) {
+        self.replace(node.continue_token.span, "ct");
+        visit::visit_expr_continue(self, node);
+    }
+
+    fn visit_expr_try(&mut self, node: &'ast syn::ExprTry) {
+        self.replace(node.question_token.span, "!");
+        visit::visit_expr_try(self, node);
+    }
+
+    fn visit_expr_await(&mut self, node: &'ast syn::ExprAwait) {
+        self.replace(node.await_token.span, "~");
+        visit::visit_expr_await(self, node);
+    }
+
+    fn visit_expr_closure(&mut self, node: &'ast syn::ExprClosure) {
+        if let Some(capture) = &node.capture {
+            self.replace_glued(capture.span, "$");
+        }
+        visit::visit_expr_closure(self, node);
+    }
+
+    fn visit_macro(&mut self, node: &'ast syn::Macro) {
+        // 与 Rust2NuConverter::convert_macro 的策略一致：常用表达式宏的参数完整转换，其他宏原样保留
+        let Some(last) = node.path.segments.last() else {
+            return;
+        };
+        let name = last.ident.to_string();
+        if name == "vec"
+            && node.path.segments.len() == 1
+            && self.profile.abbreviation("Vec").is_some()
+        {
+            self.replace(last.ident.span(), "V");
+        }
+        if EXPRESSION_MACROS.contains(&name.as_str()) {
+            if let Ok(args) = node.parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated)
+            {
+                for arg in &args {
+                    self.visit_expr(arg);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert() {
+        let source = "/// Doc.\n#[derive(Debug, Clone)]\npub struct Buf<'a, T> {\n    pub data: &'a mut Vec<T>, // keep\n    len: usize,\n}\n\nimpl<'a, T: Clone> Buf<'a, T>\nwhere\n    T: Default,\n{\n    pub async fn push(&mut self, x: T) -> Result<usize, String> {\n        let mut n = 1_000u64;\n        let raw = r\"fn let mut\";\n        let f = move |y: T| vec![y];\n        self.data.push(x);\n        wait().await?;\n        for _ in 0..3 {\n            if n > 0 { break; }\n        }\n        return Ok(self.data.len());\n    }\n}\n";
+        let nu = convert(source, &CompressionProfile::default()).unwrap();
+        assert_eq!(
+            nu,
+            "/// Doc.\n#D(Debug, Clone)\nS Buf<'a, T> {\n    pub data: &'a !V<T>, // keep\n    len: usize,\n}\n\nI<'a, T: Clone> Buf<'a, T>\nwh\n    T: Default,\n{\n    ~F push(&!self, x: T) -> R<usize, String> {\n        v n = 1_000u64;\n        l raw = r\"fn let mut\";\n        l f = $|y: T| V![y];\n        self.data.push(x);\n        wait().~!;\n        L _ in 0..3 {\n            if n > 0 { br; }\n        }\n        < Ok(self.data.len());\n    }\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_typed_mut_binding() {
+        let source = "fn f() {\n    let mut n: u32 = 0;\n    let (a, b): (u8, u8) = (1, 2);\n    let ref mut r: u8 = n;\n}\n";
+        let nu = convert(source, &CompressionProfile::default()).unwrap();
+        assert_eq!(
+            nu,
+            "f f() {\n    v n: u32 = 0;\n    l (a, b): (u8, u8) = (1, 2);\n    l ref mut r: u8 = n;\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_generic_params_and_macros() {
+        let source = "fn wrap<Box: Clone>(b: Box) -> Option<Box> {\n    assert_eq!(parse()?, Vec::<u8>::new());\n    println!(\"{:?}\", check()?);\n    Some(b)\n}\n";
+        let nu = convert(source, &CompressionProfile::default()).unwrap();
+        assert_eq!(
+            nu,
+            "f wrap<Box: Clone>(b: Box) -> O<Box> {\n    assert_eq!(parse()!, Vec::<u8>::new());\n    println!(\"{:?}\", check()!);\n    Some(b)\n}\n"
+        );
+    }
+}
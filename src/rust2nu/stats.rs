@@ -0,0 +1,276 @@
+// Compression Statistics
+// 统计 Rust -> Nu 压缩前后的字节数、行数与近似 token 数
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+/// 一段源码的度量
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct TextMetrics {
+    pub bytes: usize,
+    pub lines: usize,
+    pub tokens: usize,
+}
+
+impl TextMetrics {
+    pub fn measure(text: &str, counter: &TokenCounter) -> Self {
+        Self {
+            bytes: text.len(),
+            lines: text.lines().count(),
+            tokens: counter.count(text),
+        }
+    }
+
+    fn add(&mut self, other: &TextMetrics) {
+        self.bytes += other.bytes;
+        self.lines += other.lines;
+        self.tokens += other.tokens;
+    }
+}
+
+/// 压缩率（百分比，正数表示变小）
+fn saving(before: usize, after: usize) -> f64 {
+    if before == 0 {
+        return 0.0;
+    }
+    (1.0 - after as f64 / before as f64) * 100.0
+}
+
+/// 各项度量的压缩率
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Savings {
+    pub bytes: f64,
+    pub lines: f64,
+    pub tokens: f64,
+}
+
+impl Savings {
+    pub fn between(rust: &TextMetrics, nu: &TextMetrics) -> Self {
+        Self {
+            bytes: saving(rust.bytes, nu.bytes),
+            lines: saving(rust.lines, nu.lines),
+            tokens: saving(rust.tokens, nu.tokens),
+        }
+    }
+}
+
+/// 单个文件的统计；转换失败时 nu 为 None
+#[derive(Debug, Clone, Serialize)]
+pub struct FileStats {
+    pub path: String,
+    pub rust: TextMetrics,
+    pub nu: Option<TextMetrics>,
+    pub savings: Option<Savings>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 全部文件的统计报告
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CompressionReport {
+    /// token 计数方式：`simple` 或 `bpe:<词表路径>`
+    pub tokenizer: String,
+    pub files: Vec<FileStats>,
+    /// 仅统计转换成功的文件
+    pub total_rust: TextMetrics,
+    pub total_nu: TextMetrics,
+    pub total_savings: Savings,
+}
+
+impl CompressionReport {
+    pub fn new(counter: &TokenCounter) -> Self {
+        Self {
+            tokenizer: counter.name(),
+            ..Default::default()
+        }
+    }
+
+    /// 记录转换成功的文件
+    pub fn add(&mut self, path: impl Into<String>, rust: &str, nu: &str, counter: &TokenCounter) {
+        let rust = TextMetrics::measure(rust, counter);
+        let nu = TextMetrics::measure(nu, counter);
+        self.total_rust.add(&rust);
+        self.total_nu.add(&nu);
+        self.total_savings = Savings::between(&self.total_rust, &self.total_nu);
+        self.files.push(FileStats {
+            path: path.into(),
+            rust,
+            nu: Some(nu),
+            savings: Some(Savings::between(&rust, &nu)),
+            error: None,
+        });
+    }
+
+    /// 记录转换失败的文件
+    pub fn add_failure(
+        &mut self,
+        path: impl Into<String>,
+        rust: &str,
+        error: impl Into<String>,
+        counter: &TokenCounter,
+    ) {
+        self.files.push(FileStats {
+            path: path.into(),
+            rust: TextMetrics::measure(rust, counter),
+            nu: None,
+            savings: None,
+            error: Some(error.into()),
+        });
+    }
+
+    pub fn failed(&self) -> usize {
+        self.files.iter().filter(|f| f.error.is_some()).count()
+    }
+}
+
+/// 近似 token 计数器
+pub enum TokenCounter {
+    /// 按空白切分，标识符/数字连续段计 1，每个标点计 1
+    Simple,
+    /// 按 BPE 词表贪心最长匹配
+    Bpe { name: String, vocab: BpeVocab },
+}
+
+impl TokenCounter {
+    /// 从词表文件创建 BPE 计数器
+    pub fn bpe(path: &Path) -> Result<Self> {
+        Ok(TokenCounter::Bpe {
+            name: path.display().to_string(),
+            vocab: BpeVocab::load(path)?,
+        })
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            TokenCounter::Simple => "simple".to_string(),
+            TokenCounter::Bpe { name, .. } => format!("bpe:{}", name),
+        }
+    }
+
+    pub fn count(&self, text: &str) -> usize {
+        match self {
+            TokenCounter::Simple => count_simple_tokens(text),
+            TokenCounter::Bpe { vocab, .. } => vocab.count(text),
+        }
+    }
+}
+
+fn count_simple_tokens(text: &str) -> usize {
+    let mut count = 0;
+    for word in text.split_whitespace() {
+        let mut in_ident = false;
+        for c in word.chars() {
+            if c.is_alphanumeric() || c == '_' {
+                if !in_ident {
+                    count += 1;
+                    in_ident = true;
+                }
+            } else {
+                count += 1;
+                in_ident = false;
+            }
+        }
+    }
+    count
+}
+
+/// BPE 词表（只用于估算 token 数，不还原合并规则）
+pub struct BpeVocab {
+    tokens: HashSet<String>,
+    /// 最长 token 的字符数
+    max_chars: usize,
+}
+
+impl BpeVocab {
+    pub fn from_tokens<I, S>(tokens: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let tokens: HashSet<String> = tokens
+            .into_iter()
+            .map(Into::into)
+            .filter(|t| !t.is_empty())
+            .collect();
+        let max_chars = tokens.iter().map(|t| t.chars().count()).max().unwrap_or(1);
+        Self { tokens, max_chars }
+    }
+
+    /// 加载词表：JSON 对象（如 GPT-2 的 vocab.json，`Ġ`/`Ċ` 还原为空格/换行），
+    /// 否则按每行一个 token 读取
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("无法读取词表: {}", path.display()))?;
+        if content.trim_start().starts_with('{') {
+            let map: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&content)
+                .with_context(|| format!("无效的 JSON 词表: {}", path.display()))?;
+            Ok(Self::from_tokens(
+                map.keys().map(|k| k.replace('Ġ', " ").replace('Ċ', "\n")),
+            ))
+        } else {
+            Ok(Self::from_tokens(content.lines()))
+        }
+    }
+
+    /// 贪心最长匹配；词表中没有的字符单独计 1
+    pub fn count(&self, text: &str) -> usize {
+        let chars: Vec<char> = text.chars().collect();
+        let mut count = 0;
+        let mut i = 0;
+        while i < chars.len() {
+            let longest = (2..=self.max_chars.min(chars.len() - i))
+                .rev()
+                .find(|&len| {
+                    let candidate: String = chars[i..i + len].iter().collect();
+                    self.tokens.contains(&candidate)
+                })
+                .unwrap_or(1);
+            i += longest;
+            count += 1;
+        }
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simple_token_count() {
+        let counter = TokenCounter::Simple;
+        // pub fn main ( ) - > i32 { 0 }
+        assert_eq!(counter.count("pub fn main() -> i32 { 0 }"), 11);
+        assert_eq!(counter.count("   \n"), 0);
+    }
+
+    #[test]
+    fn test_bpe_greedy_count() {
+        let vocab = BpeVocab::from_tokens(["fn", " main", "()", " {", " }"]);
+        assert_eq!(vocab.count("fn main() { }"), 5);
+        // 未登录字符按单字符计数
+        assert_eq!(vocab.count("fn x"), 3);
+    }
+
+    #[test]
+    fn test_report_totals() {
+        let counter = TokenCounter::Simple;
+        let mut report = CompressionReport::new(&counter);
+        report.add("a.rs", "pub fn a() {}\n", "F a() {}\n", &counter);
+        report.add_failure("b.rs", "fn (", "parse error", &counter);
+
+        assert_eq!(report.files.len(), 2);
+        assert_eq!(report.failed(), 1);
+        assert_eq!(report.total_rust.bytes, 14);
+        assert_eq!(report.total_nu.bytes, 9);
+        assert!((report.total_savings.bytes - 35.714).abs() < 0.01);
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["tokenizer"], "simple");
+        assert_eq!(json["files"][1]["error"], "parse error");
+        assert!(json["files"][0].get("error").is_none());
+    }
+}
@@ -0,0 +1,583 @@
+// 往返验证
+// Rust → Nu → Rust 后用 syn 解析原始代码与往返结果，去掉位置信息、文档属性和格式差异（分支逗号、尾随逗号）后逐条比较 token，
+// 找出第一处不一致的条目或语句
+
+use super::{Rust2NuConverter, Rust2NuOptions};
+use crate::ast::Span;
+use crate::nu2rust::Nu2RustConverter;
+use anyhow::{Context, Result};
+use proc_macro2::{Delimiter, Group, TokenStream, TokenTree};
+use quote::ToTokens;
+use std::fmt;
+use syn::ext::IdentExt;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::visit_mut::{self, VisitMut};
+use syn::{Block, ImplItem, Item};
+
+/// 往返不等价的原因
+#[derive(Debug, Clone, PartialEq)]
+pub enum RoundTripFailure {
+    /// 往返得到的 Rust 代码无法解析
+    Unparsable {
+        message: String,
+        /// 在往返结果中的位置
+        span: Span,
+    },
+    /// 第一处不一致的条目或语句
+    Mismatch {
+        /// 所在位置，如 `impl Point › fn new › 第 2 条语句`
+        path: String,
+        /// 在原始代码中的位置
+        span: Option<Span>,
+        original: String,
+        round_tripped: String,
+    },
+}
+
+impl fmt::Display for RoundTripFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RoundTripFailure::Unparsable { message, span } => {
+                write!(f, "往返结果无法解析（{}）：{}", span, message)
+            }
+            RoundTripFailure::Mismatch { path, .. } => write!(f, "往返后不一致：{}", path),
+        }
+    }
+}
+
+/// 一个文件的往返验证结果
+#[derive(Debug, Clone)]
+pub struct RoundTripReport {
+    pub nu_code: String,
+    pub rust_code: String,
+    /// None 表示等价
+    pub failure: Option<RoundTripFailure>,
+}
+
+impl RoundTripReport {
+    pub fn is_equivalent(&self) -> bool {
+        self.failure.is_none()
+    }
+
+    /// 渲染失败原因及上下文，original 为原始 Rust 源码，file 为显示用路径
+    pub fn render(&self, original: &str, file: &str) -> String {
+        let Some(failure) = &self.failure else {
+            return String::new();
+        };
+        let mut out = format!("error: {}", failure);
+        match failure {
+            RoundTripFailure::Unparsable { span, .. } => {
+                out.push_str(&format!("\n --> <往返结果>:{}\n", span));
+                out.push_str(&span.snippet(&self.rust_code));
+            }
+            RoundTripFailure::Mismatch {
+                span,
+                original: original_tokens,
+                round_tripped,
+                ..
+            } => {
+                if let Some(span) = span {
+                    out.push_str(&format!("\n --> {}:{}\n", file, span));
+                    out.push_str(&span.snippet(original));
+                }
+                out.push_str(&format!("\n  = 原始：{}", original_tokens));
+                out.push_str(&format!("\n  = 往返：{}", round_tripped));
+            }
+        }
+        out
+    }
+}
+
+/// 把 Rust 代码转换为 Nu 再转换回 Rust，比较两者是否等价
+///
+/// 原始代码无法解析或任一方向转换失败时返回 Err；不等价时结果中带有 failure
+pub fn verify_round_trip(rust_code: &str, options: Rust2NuOptions) -> Result<RoundTripReport> {
+    let mut original = syn::parse_file(rust_code).context("Failed to parse Rust code")?;
+    let nu_code = Rust2NuConverter::with_options(options).convert(rust_code)?;
+    let back = Nu2RustConverter::new()
+        .convert(&nu_code)
+        .context("Failed to convert Nu back to Rust")?;
+
+    let failure = match syn::parse_file(&back) {
+        Err(err) => {
+            let start = err.span().start();
+            Some(RoundTripFailure::Unparsable {
+                message: err.to_string(),
+                span: Span::new(start.line, start.column + 1, 1),
+            })
+        }
+        Ok(mut round_tripped) => {
+            Normalizer.visit_file_mut(&mut original);
+            Normalizer.visit_file_mut(&mut round_tripped);
+            compare_items(&[], &original.items, &round_tripped.items)
+        }
+    };
+    Ok(RoundTripReport {
+        nu_code,
+        rust_code: back,
+        failure,
+    })
+}
+
+/// 语法层面的规范化：字段初始化简写 `Point { x: x }` → `Point { x }`，
+/// 原始标识符去掉 `r#`（rust2nu 转义的 `r#l` 往返后为 `l`），
+/// 去掉 match 分支后的逗号和列表末尾的逗号等只影响格式的标点
+struct Normalizer;
+
+/// 去掉列表末尾的分隔符
+fn strip_trailing<T, P: Default>(list: &mut Punctuated<T, P>) {
+    if list.trailing_punct() {
+        *list = std::mem::take(list).into_iter().collect();
+    }
+}
+
+impl VisitMut for Normalizer {
+    fn visit_arm_mut(&mut self, node: &mut syn::Arm) {
+        visit_mut::visit_arm_mut(self, node);
+        node.comma = None;
+    }
+
+    fn visit_expr_struct_mut(&mut self, node: &mut syn::ExprStruct) {
+        visit_mut::visit_expr_struct_mut(self, node);
+        if node.rest.is_none() {
+            strip_trailing(&mut node.fields);
+        }
+    }
+
+    fn visit_expr_call_mut(&mut self, node: &mut syn::ExprCall) {
+        visit_mut::visit_expr_call_mut(self, node);
+        strip_trailing(&mut node.args);
+    }
+
+    fn visit_expr_method_call_mut(&mut self, node: &mut syn::ExprMethodCall) {
+        visit_mut::visit_expr_method_call_mut(self, node);
+        strip_trailing(&mut node.args);
+    }
+
+    fn visit_expr_array_mut(&mut self, node: &mut syn::ExprArray) {
+        visit_mut::visit_expr_array_mut(self, node);
+        strip_trailing(&mut node.elems);
+    }
+
+    fn visit_pat_struct_mut(&mut self, node: &mut syn::PatStruct) {
+        visit_mut::visit_pat_struct_mut(self, node);
+        if node.rest.is_none() {
+            strip_trailing(&mut node.fields);
+        }
+    }
+
+    fn visit_pat_tuple_struct_mut(&mut self, node: &mut syn::PatTupleStruct) {
+        visit_mut::visit_pat_tuple_struct_mut(self, node);
+        strip_trailing(&mut node.elems);
+    }
+
+    fn visit_fields_named_mut(&mut self, node: &mut syn::FieldsNamed) {
+        visit_mut::visit_fields_named_mut(self, node);
+        strip_trailing(&mut node.named);
+    }
+
+    fn visit_item_enum_mut(&mut self, node: &mut syn::ItemEnum) {
+        visit_mut::visit_item_enum_mut(self, node);
+        strip_trailing(&mut node.variants);
+    }
+
+    fn visit_signature_mut(&mut self, node: &mut syn::Signature) {
+        visit_mut::visit_signature_mut(self, node);
+        strip_trailing(&mut node.inputs);
+    }
+
+    fn visit_generics_mut(&mut self, node: &mut syn::Generics) {
+        visit_mut::visit_generics_mut(self, node);
+        strip_trailing(&mut node.params);
+        if let Some(where_clause) = &mut node.where_clause {
+            strip_trailing(&mut where_clause.predicates);
+        }
+    }
+
+    fn visit_ident_mut(&mut self, ident: &mut proc_macro2::Ident) {
+        *ident = ident.unraw();
+    }
+
+    fn visit_macro_mut(&mut self, node: &mut syn::Macro) {
+        visit_mut::visit_macro_mut(self, node);
+        node.tokens = unraw_tokens(std::mem::take(&mut node.tokens));
+    }
+
+    fn visit_field_value_mut(&mut self, node: &mut syn::FieldValue) {
+        visit_mut::visit_field_value_mut(self, node);
+        if let (syn::Member::Named(member), syn::Expr::Path(path)) = (&node.member, &node.expr) {
+            if path.qself.is_none() && path.attrs.is_empty() && path.path.is_ident(member) {
+                node.colon_token = None;
+            }
+        }
+    }
+
+    fn visit_field_pat_mut(&mut self, node: &mut syn::FieldPat) {
+        visit_mut::visit_field_pat_mut(self, node);
+        if let (syn::Member::Named(member), syn::Pat::Ident(pat)) = (&node.member, &*node.pat) {
+            if pat.by_ref.is_none()
+                && pat.mutability.is_none()
+                && pat.subpat.is_none()
+                && pat.ident == *member
+            {
+                node.colon_token = None;
+            }
+        }
+    }
+}
+
+/// 宏参数中的原始标识符去掉 `r#`
+fn unraw_tokens(tokens: TokenStream) -> TokenStream {
+    tokens
+        .into_iter()
+        .map(|token| match token {
+            TokenTree::Ident(ident) => TokenTree::Ident(ident.unraw()),
+            TokenTree::Group(group) => {
+                let mut unrawed = Group::new(group.delimiter(), unraw_tokens(group.stream()));
+                unrawed.set_span(group.span());
+                TokenTree::Group(unrawed)
+            }
+            token => token,
+        })
+        .collect()
+}
+
+/// token 层面的规范化：去掉 `#[doc = ...]` 属性和分隔符前的尾随逗号
+fn normalize(tokens: TokenStream) -> String {
+    normalize_stream(tokens).to_string()
+}
+
+fn normalize_stream(tokens: TokenStream) -> TokenStream {
+    let tokens: Vec<TokenTree> = tokens.into_iter().collect();
+    let mut result = Vec::with_capacity(tokens.len());
+    let mut i = 0;
+    while i < tokens.len() {
+        if let Some(len) = doc_attribute_len(&tokens[i..]) {
+            i += len;
+            continue;
+        }
+        match &tokens[i] {
+            TokenTree::Punct(punct) if punct.as_char() == ',' && i + 1 == tokens.len() => {}
+            TokenTree::Group(group) => result.push(TokenTree::Group(Group::new(
+                group.delimiter(),
+                normalize_stream(group.stream()),
+            ))),
+            token => result.push(token.clone()),
+        }
+        i += 1;
+    }
+    result.into_iter().collect()
+}
+
+/// `#[doc = ...]` 或 `#![doc = ...]` 占用的 token 数
+fn doc_attribute_len(tokens: &[TokenTree]) -> Option<usize> {
+    let TokenTree::Punct(pound) = tokens.first()? else {
+        return None;
+    };
+    if pound.as_char() != '#' {
+        return None;
+    }
+    let inner = matches!(tokens.get(1), Some(TokenTree::Punct(p)) if p.as_char() == '!');
+    let index = if inner { 2 } else { 1 };
+    let TokenTree::Group(group) = tokens.get(index)? else {
+        return None;
+    };
+    let mut body = group.stream().into_iter();
+    let is_doc = group.delimiter() == Delimiter::Bracket
+        && matches!(body.next(), Some(TokenTree::Ident(ident)) if ident == "doc")
+        && matches!(body.next(), Some(TokenTree::Punct(p)) if p.as_char() == '=');
+    is_doc.then_some(index + 1)
+}
+
+/// 节点在源码中的起始位置，跳过前面的属性和文档注释
+fn source_span(node: &(impl ToTokens + Spanned)) -> Span {
+    let tokens: Vec<TokenTree> = node.to_token_stream().into_iter().collect();
+    let mut i = 0;
+    while let Some(TokenTree::Punct(pound)) = tokens.get(i) {
+        if pound.as_char() != '#' {
+            break;
+        }
+        let bang = matches!(tokens.get(i + 1), Some(TokenTree::Punct(p)) if p.as_char() == '!');
+        let index = if bang { i + 2 } else { i + 1 };
+        match tokens.get(index) {
+            Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Bracket => {
+                i = index + 1
+            }
+            _ => break,
+        }
+    }
+    let start = tokens
+        .get(i)
+        .map_or_else(|| node.span(), TokenTree::span)
+        .start();
+    Span::new(start.line, start.column + 1, 1)
+}
+
+fn join_path(path: &[String], last: &str) -> String {
+    path.iter()
+        .map(String::as_str)
+        .chain(std::iter::once(last))
+        .collect::<Vec<_>>()
+        .join(" › ")
+}
+
+fn mismatch(
+    path: &[String],
+    label: &str,
+    original: Option<&dyn ToTokensSpanned>,
+    round_tripped: Option<&dyn ToTokensSpanned>,
+) -> RoundTripFailure {
+    let render = |node: Option<&dyn ToTokensSpanned>| {
+        node.map_or_else(|| "<缺失>".to_string(), |node| normalize(node.tokens()))
+    };
+    let (original_tokens, round_tripped_tokens) =
+        excerpt(&render(original), &render(round_tripped));
+    RoundTripFailure::Mismatch {
+        path: join_path(path, label),
+        span: original.map(|node| node.start()),
+        original: original_tokens,
+        round_tripped: round_tripped_tokens,
+    }
+}
+
+/// 展示时保留的上下文字符数
+const EXCERPT_CONTEXT: usize = 40;
+/// 展示的最大字符数
+const EXCERPT_LEN: usize = 160;
+
+/// 截取两段 token 文本从第一处差异附近开始的片段
+fn excerpt(a: &str, b: &str) -> (String, String) {
+    let common = a.chars().zip(b.chars()).take_while(|(x, y)| x == y).count();
+    let skip = common.saturating_sub(EXCERPT_CONTEXT);
+    let cut = |text: &str| {
+        let mut result: String = text.chars().skip(skip).take(EXCERPT_LEN).collect();
+        if skip > 0 {
+            result.insert(0, '…');
+        }
+        if text.chars().count() > skip + EXCERPT_LEN {
+            result.push('…');
+        }
+        result
+    };
+    (cut(a), cut(b))
+}
+
+/// 可比较、可定位的语法节点
+trait ToTokensSpanned {
+    fn tokens(&self) -> TokenStream;
+    fn start(&self) -> Span;
+}
+
+impl<T: ToTokens + Spanned> ToTokensSpanned for T {
+    fn tokens(&self) -> TokenStream {
+        self.to_token_stream()
+    }
+
+    fn start(&self) -> Span {
+        source_span(self)
+    }
+}
+
+fn same(a: &impl ToTokens, b: &impl ToTokens) -> bool {
+    normalize(a.to_token_stream()) == normalize(b.to_token_stream())
+}
+
+/// 条目的显示名，如 `fn main`、`impl Point`
+fn item_label(item: &Item) -> String {
+    let named = |kind: &str, ident: &syn::Ident| format!("{} {}", kind, ident);
+    match item {
+        Item::Fn(f) => named("fn", &f.sig.ident),
+        Item::Struct(s) => named("struct", &s.ident),
+        Item::Enum(e) => named("enum", &e.ident),
+        Item::Trait(t) => named("trait", &t.ident),
+        Item::Union(u) => named("union", &u.ident),
+        Item::Mod(m) => named("mod", &m.ident),
+        Item::Const(c) => named("const", &c.ident),
+        Item::Static(s) => named("static", &s.ident),
+        Item::Type(t) => named("type", &t.ident),
+        Item::Impl(i) => format!("impl {}", normalize(i.self_ty.to_token_stream())),
+        Item::Use(_) => "use".to_string(),
+        Item::Macro(m) => format!("{}!", normalize(m.mac.path.to_token_stream())),
+        _ => "条目".to_string(),
+    }
+}
+
+fn compare_items(path: &[String], a: &[Item], b: &[Item]) -> Option<RoundTripFailure> {
+    for idx in 0..a.len().max(b.len()) {
+        let (x, y) = (a.get(idx), b.get(idx));
+        let (Some(x), Some(y)) = (x, y) else {
+            let label = x.or(y).map(item_label).unwrap_or_default();
+            return Some(mismatch(
+                path,
+                &label,
+                x.map(|x| x as &dyn ToTokensSpanned),
+                y.map(|y| y as &dyn ToTokensSpanned),
+            ));
+        };
+        if same(x, y) {
+            continue;
+        }
+        let label = item_label(x);
+        let inner = [path, std::slice::from_ref(&label)].concat();
+        let found = match (x, y) {
+            (Item::Fn(f), Item::Fn(g))
+                if same(&f.sig, &g.sig) && same_attrs(&f.attrs, &g.attrs) =>
+            {
+                compare_blocks(&inner, &f.block, &g.block)
+            }
+            (Item::Impl(i), Item::Impl(j)) if same(&impl_header(i), &impl_header(j)) => {
+                compare_impl_items(&inner, &i.items, &j.items)
+            }
+            (Item::Mod(m), Item::Mod(n)) if m.ident == n.ident => match (&m.content, &n.content) {
+                (Some((_, xs)), Some((_, ys))) if same_attrs(&m.attrs, &n.attrs) => {
+                    compare_items(&inner, xs, ys)
+                }
+                _ => None,
+            },
+            _ => None,
+        };
+        return Some(found.unwrap_or_else(|| mismatch(path, &label, Some(x), Some(y))));
+    }
+    None
+}
+
+fn same_attrs(a: &[syn::Attribute], b: &[syn::Attribute]) -> bool {
+    let tokens = |attrs: &[syn::Attribute]| {
+        normalize(attrs.iter().map(|attr| attr.to_token_stream()).collect())
+    };
+    tokens(a) == tokens(b)
+}
+
+/// impl 块去掉条目后的头部
+fn impl_header(item: &syn::ItemImpl) -> syn::ItemImpl {
+    syn::ItemImpl {
+        items: Vec::new(),
+        ..item.clone()
+    }
+}
+
+fn compare_impl_items(path: &[String], a: &[ImplItem], b: &[ImplItem]) -> Option<RoundTripFailure> {
+    let label = |item: &ImplItem| match item {
+        ImplItem::Fn(f) => format!("fn {}", f.sig.ident),
+        ImplItem::Const(c) => format!("const {}", c.ident),
+        ImplItem::Type(t) => format!("type {}", t.ident),
+        _ => "条目".to_string(),
+    };
+    for idx in 0..a.len().max(b.len()) {
+        let (x, y) = (a.get(idx), b.get(idx));
+        let (Some(x), Some(y)) = (x, y) else {
+            let name = x.or(y).map(label).unwrap_or_default();
+            return Some(mismatch(
+                path,
+                &name,
+                x.map(|x| x as &dyn ToTokensSpanned),
+                y.map(|y| y as &dyn ToTokensSpanned),
+            ));
+        };
+        if same(x, y) {
+            continue;
+        }
+        let name = label(x);
+        if let (ImplItem::Fn(f), ImplItem::Fn(g)) = (x, y) {
+            if same(&f.sig, &g.sig) && same(&f.vis, &g.vis) && same_attrs(&f.attrs, &g.attrs) {
+                let inner = [path, std::slice::from_ref(&name)].concat();
+                if let Some(found) = compare_blocks(&inner, &f.block, &g.block) {
+                    return Some(found);
+                }
+            }
+        }
+        return Some(mismatch(path, &name, Some(x), Some(y)));
+    }
+    None
+}
+
+fn compare_blocks(path: &[String], a: &Block, b: &Block) -> Option<RoundTripFailure> {
+    for idx in 0..a.stmts.len().max(b.stmts.len()) {
+        let (x, y) = (a.stmts.get(idx), b.stmts.get(idx));
+        if let (Some(x), Some(y)) = (x, y) {
+            if same(x, y) {
+                continue;
+            }
+        }
+        return Some(mismatch(
+            path,
+            &format!("第 {} 条语句", idx + 1),
+            x.map(|x| x as &dyn ToTokensSpanned),
+            y.map(|y| y as &dyn ToTokensSpanned),
+        ));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_round_trip() {
+        let source = "/// Doc.\npub struct Point {\n    pub x: i32,\n    pub y: i32,\n}\n\nimpl Point {\n    pub fn new(x: i32, y: i32) -> Self {\n        // comment\n        let p = Point { x, y };\n        p\n    }\n}\n";
+        for preserve_syntax in [false, true] {
+            let report = verify_round_trip(
+                source,
+                Rust2NuOptions {
+                    preserve_syntax,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            assert!(report.is_equivalent(), "{:?}", report.failure);
+        }
+    }
+
+    #[test]
+    fn test_raw_identifiers_and_union_label() {
+        let source = "fn f(r#l: i32) -> i32 {\n    println!(\"{}\", r#l);\n    r#l\n}\n";
+        let report = verify_round_trip(source, Rust2NuOptions::default()).unwrap();
+        assert!(report.is_equivalent(), "{:?}", report.failure);
+
+        let item: Item = syn::parse_str("union Value { i: i32, f: f32 }").unwrap();
+        assert_eq!(item_label(&item), "union Value");
+    }
+
+    #[test]
+    fn test_formatting_only_differences() {
+        // rustfmt 风格：块分支之间没有逗号，结构体字面量、参数列表带尾随逗号
+        let source = "fn f(x: u8) -> u8 {\n    match x {\n        1 => {}\n        _ => {}\n    }\n    let p = Point {\n        x: 1,\n        y: 2,\n    };\n    g(\n        p,\n        x,\n    )\n}\n";
+        let report = verify_round_trip(source, Rust2NuOptions::default()).unwrap();
+        assert!(report.is_equivalent(), "{:?}", report.failure);
+
+        let mut with_commas: syn::File =
+            syn::parse_str("fn f() { match x { 1 => {}, _ => {}, } }").unwrap();
+        let mut without: syn::File =
+            syn::parse_str("fn f() { match x { 1 => {} _ => {} } }").unwrap();
+        Normalizer.visit_file_mut(&mut with_commas);
+        Normalizer.visit_file_mut(&mut without);
+        assert_eq!(
+            normalize(with_commas.to_token_stream()),
+            normalize(without.to_token_stream())
+        );
+    }
+
+    #[test]
+    fn test_first_mismatch() {
+        // impl 中的私有方法在 Nu 中会变成 pub
+        let source = "fn helper() -> i32 {\n    1\n}\n\nimpl Counter {\n    /// Get.\n    fn get(&self) -> i32 {\n        let a = 1;\n        self.n + a\n    }\n}\n\nstruct Counter {\n    n: i32,\n}\n";
+        let report = verify_round_trip(source, Rust2NuOptions::default()).unwrap();
+        let Some(RoundTripFailure::Mismatch {
+            path,
+            span,
+            original,
+            ..
+        }) = &report.failure
+        else {
+            panic!("{:?}", report.failure);
+        };
+        assert_eq!(path, "impl Counter › fn get");
+        assert_eq!(span.as_ref().map(|s| s.line), Some(7));
+        assert!(original.starts_with("fn get"));
+        assert!(report
+            .render(source, "a.rs")
+            .starts_with("error: 往返后不一致：impl Counter › fn get\n --> a.rs:7:5\n"));
+    }
+}
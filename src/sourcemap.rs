@@ -0,0 +1,334 @@
+// Source Map v3
+// 各后端共用的标准 Source Map（https://sourcemaps.info/spec.html）编码器
+// 从任意后端的映射表生成 version/sources/sourcesContent/mappings(base64 VLQ)
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Component, Path};
+
+/// 一条映射：生成代码位置 -> Nu 源码位置（1-based，列按字符计数）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Mapping {
+    pub generated_line: usize,
+    pub generated_col: usize,
+    pub source_line: usize,
+    pub source_col: usize,
+}
+
+/// 后端映射表：能列出 (生成位置, 源码位置) 的 source map 实现
+pub trait MappingTable {
+    /// Nu 源文件名
+    fn source_file(&self) -> &str;
+    /// 生成的目标文件名
+    fn generated_file(&self) -> &str;
+    /// 全部映射，无需排序
+    fn mappings(&self) -> Vec<Mapping>;
+}
+
+/// Source Map v3 JSON（单一源文件）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceMapV3 {
+    pub version: u32,
+    pub file: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub source_root: String,
+    pub sources: Vec<String>,
+    #[serde(default)]
+    pub sources_content: Vec<Option<String>>,
+    #[serde(default)]
+    pub names: Vec<String>,
+    pub mappings: String,
+}
+
+impl SourceMapV3 {
+    /// 由映射列表创建；source 为相对 .map 文件所在目录的 Nu 源文件路径
+    pub fn new(file: impl Into<String>, source: impl Into<String>, mappings: &[Mapping]) -> Self {
+        Self {
+            version: 3,
+            file: file.into(),
+            source_root: String::new(),
+            sources: vec![source.into()],
+            sources_content: Vec::new(),
+            names: Vec::new(),
+            mappings: encode_mappings(mappings),
+        }
+    }
+
+    /// 由后端映射表创建
+    pub fn from_table(table: &impl MappingTable) -> Self {
+        Self::new(
+            table.generated_file(),
+            table.source_file(),
+            &table.mappings(),
+        )
+    }
+
+    /// 覆盖 sources 中的源文件路径
+    pub fn with_source(mut self, source: impl Into<String>) -> Self {
+        self.sources = vec![source.into()];
+        self
+    }
+
+    /// 内嵌 Nu 源码（sourcesContent），调试器无需再读取源文件
+    pub fn with_content(mut self, content: impl Into<String>) -> Self {
+        self.sources_content = vec![Some(content.into())];
+        self
+    }
+
+    /// 解码 mappings（单一源文件）
+    pub fn decode(&self) -> Result<Vec<Mapping>> {
+        decode_mappings(&self.mappings)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        let map: Self = serde_json::from_str(json)?;
+        if map.version != 3 {
+            bail!("不支持的 source map 版本: {}", map.version);
+        }
+        Ok(map)
+    }
+
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+}
+
+/// 生成代码末尾的 `sourceMappingURL` 注释（JS/TS 使用 `//#` 形式）
+pub fn source_mapping_url(map_file: &str) -> String {
+    format!("//# sourceMappingURL={}", map_file)
+}
+
+/// 计算 `to` 相对于目录 `from_dir` 的路径（使用 `/` 分隔，用于 sources 字段）
+pub fn relative_path(from_dir: &Path, to: &Path) -> String {
+    let absolute = |p: &Path| {
+        p.canonicalize().unwrap_or_else(|_| {
+            std::env::current_dir()
+                .map(|cwd| cwd.join(p))
+                .unwrap_or_else(|_| p.to_path_buf())
+        })
+    };
+    let from = absolute(from_dir);
+    let to = absolute(to);
+    let from: Vec<Component> = from.components().collect();
+    let to: Vec<Component> = to.components().collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+
+    let mut parts: Vec<String> = vec!["..".to_string(); from.len() - common];
+    parts.extend(
+        to[common..]
+            .iter()
+            .map(|c| c.as_os_str().to_string_lossy().to_string()),
+    );
+    parts.join("/")
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// base64 VLQ 编码一个有符号整数
+fn encode_vlq(value: i64, out: &mut String) {
+    let mut vlq = if value < 0 {
+        ((-value) << 1) | 1
+    } else {
+        value << 1
+    };
+    loop {
+        let mut digit = vlq & 0b11111;
+        vlq >>= 5;
+        if vlq > 0 {
+            digit |= 0b100000;
+        }
+        out.push(BASE64[digit as usize] as char);
+        if vlq == 0 {
+            break;
+        }
+    }
+}
+
+/// 编码 mappings 字段：各行以 `;` 分隔，行内片段以 `,` 分隔，
+/// 每个片段为 [生成列, 源索引, 源行, 源列]，均为相对上一个片段的增量（0-based）
+pub fn encode_mappings(mappings: &[Mapping]) -> String {
+    let mut sorted = mappings.to_vec();
+    sorted.sort();
+    sorted.dedup_by_key(|m| (m.generated_line, m.generated_col));
+
+    let mut out = String::new();
+    let mut line = 1;
+    let (mut prev_source_line, mut prev_source_col) = (0i64, 0i64);
+    let mut prev_col = 0i64;
+    let mut first_in_line = true;
+    for m in sorted.iter().filter(|m| m.generated_line >= 1) {
+        while line < m.generated_line {
+            out.push(';');
+            line += 1;
+            prev_col = 0;
+            first_in_line = true;
+        }
+        if !first_in_line {
+            out.push(',');
+        }
+        let col = m.generated_col.saturating_sub(1) as i64;
+        let source_line = m.source_line.saturating_sub(1) as i64;
+        let source_col = m.source_col.saturating_sub(1) as i64;
+        encode_vlq(col - prev_col, &mut out);
+        encode_vlq(0, &mut out);
+        encode_vlq(source_line - prev_source_line, &mut out);
+        encode_vlq(source_col - prev_source_col, &mut out);
+        prev_col = col;
+        prev_source_line = source_line;
+        prev_source_col = source_col;
+        first_in_line = false;
+    }
+    out
+}
+
+/// 解码 mappings 字段；只有生成列的片段（无源位置）会被忽略
+pub fn decode_mappings(mappings: &str) -> Result<Vec<Mapping>> {
+    let mut result = Vec::new();
+    let (mut source_line, mut source_col) = (0i64, 0i64);
+    for (line_idx, line) in mappings.split(';').enumerate() {
+        let mut col = 0i64;
+        for segment in line.split(',').filter(|s| !s.is_empty()) {
+            let fields = decode_vlq_segment(segment)?;
+            col += fields[0];
+            if fields.len() < 4 {
+                continue;
+            }
+            source_line += fields[2];
+            source_col += fields[3];
+            if col < 0 || source_line < 0 || source_col < 0 {
+                bail!("无效的 mappings 片段: {}", segment);
+            }
+            result.push(Mapping {
+                generated_line: line_idx + 1,
+                generated_col: col as usize + 1,
+                source_line: source_line as usize + 1,
+                source_col: source_col as usize + 1,
+            });
+        }
+    }
+    Ok(result)
+}
+
+fn decode_vlq_segment(segment: &str) -> Result<Vec<i64>> {
+    let mut fields = Vec::new();
+    let (mut value, mut shift) = (0i64, 0);
+    for c in segment.bytes() {
+        let Some(digit) = BASE64.iter().position(|&b| b == c) else {
+            bail!("无效的 base64 VLQ 字符: {}", c as char);
+        };
+        let digit = digit as i64;
+        value |= (digit & 0b11111) << shift;
+        if digit & 0b100000 != 0 {
+            shift += 5;
+            if shift > 60 {
+                bail!("VLQ 数值溢出: {}", segment);
+            }
+            continue;
+        }
+        let negative = value & 1 == 1;
+        value >>= 1;
+        fields.push(if negative { -value } else { value });
+        value = 0;
+        shift = 0;
+    }
+    if shift != 0 {
+        bail!("不完整的 VLQ 片段: {}", segment);
+    }
+    Ok(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapping(gl: usize, gc: usize, sl: usize, sc: usize) -> Mapping {
+        Mapping {
+            generated_line: gl,
+            generated_col: gc,
+            source_line: sl,
+            source_col: sc,
+        }
+    }
+
+    #[test]
+    fn test_vlq_encoding() {
+        let encode = |v| {
+            let mut s = String::new();
+            encode_vlq(v, &mut s);
+            s
+        };
+        assert_eq!(encode(0), "A");
+        assert_eq!(encode(1), "C");
+        assert_eq!(encode(-1), "D");
+        assert_eq!(encode(15), "e");
+        assert_eq!(encode(16), "gB");
+        assert_eq!(encode(-1000), "x+B");
+        assert_eq!(
+            decode_vlq_segment("AAgBx+B").unwrap(),
+            vec![0, 0, 16, -1000]
+        );
+    }
+
+    #[test]
+    fn test_mappings_roundtrip() {
+        let mappings = vec![
+            mapping(1, 1, 1, 1),
+            mapping(1, 5, 1, 3),
+            mapping(3, 5, 2, 5),
+            mapping(4, 1, 10, 1),
+        ];
+        let encoded = encode_mappings(&mappings);
+        assert_eq!(encoded, "AAAA,IAAE;;IACE;AAQJ");
+        assert_eq!(decode_mappings(&encoded).unwrap(), mappings);
+    }
+
+    #[test]
+    fn test_source_map_json() {
+        let map = SourceMapV3::new("main.ts", "../src/main.nu", &[mapping(1, 1, 1, 1)])
+            .with_content("F main() {}\n");
+        let json = map.to_json().unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["version"], 3);
+        assert_eq!(value["sources"][0], "../src/main.nu");
+        assert_eq!(value["sourcesContent"][0], "F main() {}\n");
+        assert_eq!(value["mappings"], "AAAA");
+        assert!(value.get("sourceRoot").is_none());
+
+        let loaded = SourceMapV3::from_json(&json).unwrap();
+        assert_eq!(loaded, map);
+        assert!(
+            SourceMapV3::from_json(r#"{"version":2,"file":"","sources":[],"mappings":""}"#)
+                .is_err()
+        );
+        assert_eq!(
+            source_mapping_url("main.ts.map"),
+            "//# sourceMappingURL=main.ts.map"
+        );
+    }
+
+    #[test]
+    fn test_relative_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        let out = dir.path().join("out/gen");
+        fs::create_dir_all(&src).unwrap();
+        fs::create_dir_all(&out).unwrap();
+        fs::write(src.join("main.nu"), "").unwrap();
+        assert_eq!(
+            relative_path(&out, &src.join("main.nu")),
+            "../../src/main.nu"
+        );
+        assert_eq!(relative_path(&src, &src.join("main.nu")), "main.nu");
+    }
+}
@@ -94,7 +94,7 @@ impl CargoParser {
     }
 
     /// 解析字符串数组（支持单行和多行格式）
-    fn parse_string_array(value: &Value) -> Result<Vec<String>, WorkspaceError> {
+    pub(crate) fn parse_string_array(value: &Value) -> Result<Vec<String>, WorkspaceError> {
         match value {
             Value::Array(arr) => arr
                 .iter()
@@ -116,7 +116,9 @@ impl CargoParser {
     }
 
     /// 解析依赖表
-    fn parse_dependencies(deps: &Value) -> Result<HashMap<String, DependencySpec>, WorkspaceError> {
+    pub(crate) fn parse_dependencies(
+        deps: &Value,
+    ) -> Result<HashMap<String, DependencySpec>, WorkspaceError> {
         let mut result = HashMap::new();
 
         if let Some(table) = deps.as_table() {
@@ -204,7 +206,7 @@ impl CargoParser {
     }
 
     /// 解析 workspace.package
-    fn parse_workspace_package(pkg: &Value) -> Result<WorkspacePackage, WorkspaceError> {
+    pub(crate) fn parse_workspace_package(pkg: &Value) -> Result<WorkspacePackage, WorkspaceError> {
         let mut result = WorkspacePackage::default();
 
         if let Some(table) = pkg.as_table() {
@@ -1,9 +1,11 @@
 // Incremental Conversion Logic
 // Handles file timestamp comparison and incremental conversion decisions
 
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::SystemTime;
+use walkdir::WalkDir;
 
 /// 增量转换决策
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -102,6 +104,86 @@ fn get_modified_time(path: &Path) -> Option<SystemTime> {
     fs::metadata(path).ok().and_then(|m| m.modified().ok())
 }
 
+/// 轮询检测到的文件变化
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileChange {
+    Added(PathBuf),
+    Modified(PathBuf),
+    Removed(PathBuf),
+}
+
+impl FileChange {
+    pub fn path(&self) -> &Path {
+        match self {
+            FileChange::Added(p) | FileChange::Modified(p) | FileChange::Removed(p) => p,
+        }
+    }
+}
+
+/// 按修改时间和大小轮询目录中指定扩展名的文件（不依赖平台文件通知）
+pub struct FileWatcher {
+    root: PathBuf,
+    extension: String,
+    recursive: bool,
+    snapshot: BTreeMap<PathBuf, (Option<SystemTime>, u64)>,
+}
+
+impl FileWatcher {
+    /// 创建监视器并记录当前文件状态
+    pub fn new(root: impl Into<PathBuf>, extension: &str, recursive: bool) -> Self {
+        let mut watcher = Self {
+            root: root.into(),
+            extension: extension.trim_start_matches('.').to_string(),
+            recursive,
+            snapshot: BTreeMap::new(),
+        };
+        watcher.snapshot = watcher.scan();
+        watcher
+    }
+
+    /// 当前已知的文件
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.snapshot.keys().map(PathBuf::as_path)
+    }
+
+    /// 与上次扫描比较，返回新增、修改和删除的文件（按路径排序）
+    pub fn poll(&mut self) -> Vec<FileChange> {
+        let current = self.scan();
+        let mut changes = Vec::new();
+        for (path, state) in &current {
+            match self.snapshot.get(path) {
+                None => changes.push(FileChange::Added(path.clone())),
+                Some(old) if old != state => changes.push(FileChange::Modified(path.clone())),
+                Some(_) => {}
+            }
+        }
+        for path in self.snapshot.keys() {
+            if !current.contains_key(path) {
+                changes.push(FileChange::Removed(path.clone()));
+            }
+        }
+        changes.sort_by(|a, b| a.path().cmp(b.path()));
+        self.snapshot = current;
+        changes
+    }
+
+    fn scan(&self) -> BTreeMap<PathBuf, (Option<SystemTime>, u64)> {
+        let max_depth = if self.recursive { usize::MAX } else { 1 };
+        WalkDir::new(&self.root)
+            .max_depth(max_depth)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter(|e| e.path().extension().and_then(|s| s.to_str()) == Some(&self.extension))
+            .filter_map(|e| {
+                let metadata = e.metadata().ok()?;
+                let state = (metadata.modified().ok(), metadata.len());
+                Some((e.into_path(), state))
+            })
+            .collect()
+    }
+}
+
 /// 配置文件处理器
 pub struct ConfigFileHandler;
 
@@ -196,14 +278,18 @@ impl ConfigFileHandler {
 
         if src_gitignore.exists() {
             let content = fs::read_to_string(&src_gitignore)?;
-            let converted = convert_gitignore_extensions(&content, ".rs", ".nu");
-            fs::write(&dst_gitignore, converted)?;
+            fs::write(&dst_gitignore, Self::gitignore_cargo_to_nu(&content))?;
             return Ok(true);
         }
 
         Ok(false)
     }
 
+    /// 转换 .gitignore 内容（.rs -> .nu）
+    pub fn gitignore_cargo_to_nu(content: &str) -> String {
+        convert_gitignore_extensions(content, ".rs", ".nu")
+    }
+
     /// 转换 .gitignore 中的扩展名（.nu -> .rs）
     pub fn convert_gitignore_nu_to_cargo(src_dir: &Path, dst_dir: &Path) -> std::io::Result<bool> {
         let src_gitignore = src_dir.join(".gitignore");
@@ -262,7 +348,7 @@ impl ConfigFileHandler {
 
 /// 转换 gitignore 中的扩展名
 fn convert_gitignore_extensions(content: &str, from_ext: &str, to_ext: &str) -> String {
-    content
+    let mut converted = content
         .lines()
         .map(|line| {
             let trimmed = line.trim();
@@ -281,7 +367,12 @@ fn convert_gitignore_extensions(content: &str, from_ext: &str, to_ext: &str) ->
             }
         })
         .collect::<Vec<_>>()
-        .join("\n")
+        .join("\n");
+    // lines() 会丢掉结尾换行
+    if content.ends_with('\n') {
+        converted.push('\n');
+    }
+    converted
 }
 
 #[cfg(test)]
@@ -323,6 +414,7 @@ target/
 src/*.nu"#;
         let result = convert_gitignore_extensions(input, ".rs", ".nu");
         assert_eq!(result.trim(), expected.trim());
+        assert!(result.ends_with("src/*.nu\n"));
     }
 
     #[test]
@@ -362,4 +454,38 @@ target/"#;
         let decision = converter.should_convert(&src, &tgt);
         assert_eq!(decision, ConversionDecision::Convert);
     }
+
+    #[test]
+    fn test_file_watcher() {
+        let dir = tempdir().unwrap();
+        let a = dir.path().join("a.nu");
+        let nested = dir.path().join("sub").join("b.nu");
+        fs::create_dir_all(nested.parent().unwrap()).unwrap();
+        fs::write(&a, "F a() {}").unwrap();
+        fs::write(&nested, "F b() {}").unwrap();
+        fs::write(dir.path().join("notes.txt"), "x").unwrap();
+
+        let mut watcher = FileWatcher::new(dir.path(), "nu", true);
+        assert_eq!(watcher.files().count(), 2);
+        assert!(watcher.poll().is_empty());
+        assert_eq!(
+            FileWatcher::new(dir.path(), ".nu", false).files().count(),
+            1
+        );
+
+        // 大小变化即视为修改，不依赖时间戳精度
+        fs::write(&a, "F a() { < 1 }").unwrap();
+        let c = dir.path().join("c.nu");
+        fs::write(&c, "F c() {}").unwrap();
+        fs::remove_file(&nested).unwrap();
+        assert_eq!(
+            watcher.poll(),
+            vec![
+                FileChange::Modified(a),
+                FileChange::Added(c),
+                FileChange::Removed(nested),
+            ]
+        );
+        assert!(watcher.poll().is_empty());
+    }
 }
@@ -8,6 +8,7 @@ mod incremental;
 mod mapping;
 mod nu_analyzer;
 mod nu_parser;
+mod project_converter;
 mod toml_converter;
 mod types;
 
@@ -21,5 +22,6 @@ pub use incremental::*;
 pub use mapping::*;
 pub use nu_analyzer::*;
 pub use nu_parser::*;
+pub use project_converter::*;
 pub use toml_converter::*;
 pub use types::*;
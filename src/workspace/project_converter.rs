@@ -0,0 +1,379 @@
+// Nu Project Converter
+// Converts a whole Nu project (single package or workspace) into a Cargo project
+// Shared by the nu2cargo binary and `nuc build`
+
+use super::{
+    ConfigFileHandler, ConversionDecision, ConvertOptions, ConvertReport, FileConversion,
+    FileOutcome, IncrementalConverter, Nu2CargoConverter, NuWorkspaceAnalyzer, WorkspaceError,
+    WorkspaceType,
+};
+use crate::nu2rust::{LazySourceMap, Nu2RustConverter};
+use std::fs;
+use std::path::Path;
+
+/// package 中需要转换的目录
+const PACKAGE_DIRS: &[&str] = &["src", "tests", "examples", "benches"];
+
+/// Nu 项目 -> Cargo 项目转换器
+pub struct NuProjectConverter {
+    options: ConvertOptions,
+    incremental: IncrementalConverter,
+}
+
+impl NuProjectConverter {
+    pub fn new(options: ConvertOptions) -> Self {
+        let incremental = IncrementalConverter::new()
+            .force(options.force)
+            .incremental(options.incremental);
+        Self {
+            options,
+            incremental,
+        }
+    }
+
+    /// 转换整个项目
+    pub fn convert(&self, input: &Path, output: &Path) -> Result<ConvertReport, WorkspaceError> {
+        if !input.is_dir() {
+            return Err(WorkspaceError::DirectoryNotFound {
+                path: input.to_path_buf(),
+            });
+        }
+        self.create_dir(output)?;
+
+        let nu_toml = input.join("Nu.toml");
+        let workspace_type = if nu_toml.exists() {
+            WorkspaceType::from_nu_toml(&read(&nu_toml)?)
+        } else {
+            WorkspaceType::Single
+        };
+        let mut report = ConvertReport::new(workspace_type.clone());
+
+        if !workspace_type.is_workspace() {
+            self.create_dir(&output.join("src"))?;
+            self.convert_package(input, output, None, true, &mut report)?;
+        } else {
+            // 根 Nu.toml -> Cargo.toml
+            self.convert_toml(input, output, None, &mut report)?;
+
+            // Mixed workspace 的根目录同时是 package
+            if workspace_type == WorkspaceType::Mixed {
+                self.convert_package(input, output, None, false, &mut report)?;
+            }
+
+            let mut analyzer = NuWorkspaceAnalyzer::from_dir(input)?;
+            let members: Vec<String> = analyzer
+                .expand_members()?
+                .iter()
+                .map(|p| p.to_string_lossy().to_string())
+                .filter(|m| self.member_selected(m))
+                .collect();
+            report.members_total = members.len();
+
+            for member in members {
+                let member_input = input.join(&member);
+                if !member_input.exists() {
+                    report.add_warning(format!("成员目录不存在: {}", member_input.display()));
+                    continue;
+                }
+                let member_output = output.join(&member);
+                let result = self.create_dir(&member_output.join("src")).and_then(|_| {
+                    self.convert_package(
+                        &member_input,
+                        &member_output,
+                        Some(&member),
+                        true,
+                        &mut report,
+                    )
+                });
+                match result {
+                    Ok(()) => report.members_converted += 1,
+                    Err(e) => report.add_warning(format!("成员 {} 转换失败: {}", member, e)),
+                }
+            }
+        }
+
+        if !self.options.dry_run {
+            ConfigFileHandler::process_all_nu_to_cargo(input, output);
+        }
+        report.files_total = report.files.len();
+        Ok(report)
+    }
+
+    /// 过滤成员（--only 优先于 --exclude）
+    fn member_selected(&self, member: &str) -> bool {
+        if !self.options.only.is_empty() {
+            return self
+                .options
+                .only
+                .iter()
+                .any(|o| member.contains(o.as_str()));
+        }
+        !self
+            .options
+            .exclude
+            .iter()
+            .any(|e| member.contains(e.as_str()))
+    }
+
+    /// 转换一个 package：Nu.toml、build.nu 以及 src/tests/examples/benches
+    fn convert_package(
+        &self,
+        input: &Path,
+        output: &Path,
+        member: Option<&str>,
+        with_toml: bool,
+        report: &mut ConvertReport,
+    ) -> Result<(), WorkspaceError> {
+        if with_toml {
+            self.convert_toml(input, output, member, report)?;
+        }
+
+        let build_nu = input.join("build.nu");
+        if build_nu.exists() {
+            self.convert_nu_file(
+                &build_nu,
+                &output.join("build.rs"),
+                "build.rs",
+                member,
+                report,
+            )?;
+        }
+
+        for dir in PACKAGE_DIRS {
+            let src_dir = input.join(dir);
+            if src_dir.is_dir() {
+                self.convert_dir(&src_dir, &output.join(dir), &src_dir, member, report)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn convert_toml(
+        &self,
+        input: &Path,
+        output: &Path,
+        member: Option<&str>,
+        report: &mut ConvertReport,
+    ) -> Result<(), WorkspaceError> {
+        let nu_toml = input.join("Nu.toml");
+        if !nu_toml.exists() {
+            return Ok(());
+        }
+        let cargo_toml = output.join("Cargo.toml");
+        let outcome =
+            if self.incremental.should_convert(&nu_toml, &cargo_toml) == ConversionDecision::Skip {
+                report.files_skipped += 1;
+                FileOutcome::Skipped
+            } else {
+                let content = Nu2CargoConverter::new().convert(&read(&nu_toml)?);
+                self.write(&cargo_toml, &content)?;
+                report.files_converted += 1;
+                FileOutcome::Converted
+            };
+        report.files.push(FileConversion {
+            member: member.map(str::to_string),
+            source: nu_toml,
+            target: cargo_toml,
+            display: "Cargo.toml".to_string(),
+            outcome,
+        });
+        Ok(())
+    }
+
+    /// 递归转换目录中的所有 .nu 文件
+    fn convert_dir(
+        &self,
+        src_dir: &Path,
+        output_dir: &Path,
+        base_dir: &Path,
+        member: Option<&str>,
+        report: &mut ConvertReport,
+    ) -> Result<(), WorkspaceError> {
+        self.create_dir(output_dir)?;
+
+        let mut entries: Vec<_> = fs::read_dir(src_dir)
+            .map_err(|source| WorkspaceError::FileReadError {
+                path: src_dir.to_path_buf(),
+                source,
+            })?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .collect();
+        entries.sort();
+
+        for path in entries {
+            if path.is_dir() {
+                let sub_output = output_dir.join(path.file_name().unwrap_or_default());
+                self.convert_dir(&path, &sub_output, base_dir, member, report)?;
+            } else if path.extension().and_then(|s| s.to_str()) == Some("nu") {
+                let target = output_dir.join(path.with_extension("rs").file_name().unwrap());
+                let relative = path.strip_prefix(base_dir).unwrap_or(&path);
+                let display = relative.with_extension("rs").display().to_string();
+                self.convert_nu_file(&path, &target, &display, member, report)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn convert_nu_file(
+        &self,
+        source: &Path,
+        target: &Path,
+        display: &str,
+        member: Option<&str>,
+        report: &mut ConvertReport,
+    ) -> Result<(), WorkspaceError> {
+        let outcome = if self.incremental.should_convert(source, target) == ConversionDecision::Skip
+        {
+            report.files_skipped += 1;
+            FileOutcome::Skipped
+        } else {
+            match self.translate(source, target) {
+                Ok(()) => {
+                    report.files_converted += 1;
+                    FileOutcome::Converted
+                }
+                Err(message) => {
+                    report.files_failed += 1;
+                    FileOutcome::Failed(message)
+                }
+            }
+        };
+        report.files.push(FileConversion {
+            member: member.map(str::to_string),
+            source: source.to_path_buf(),
+            target: target.to_path_buf(),
+            display: display.to_string(),
+            outcome,
+        });
+        Ok(())
+    }
+
+    /// 转换单个 .nu 文件；失败时返回错误信息（不中断整个项目的转换）
+    fn translate(&self, source: &Path, target: &Path) -> Result<(), String> {
+        let nu_code = fs::read_to_string(source).map_err(|e| format!("无法读取: {}", e))?;
+        let converter = Nu2RustConverter::new();
+
+        let file_name = |p: &Path| {
+            p.file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default()
+        };
+        let mut sourcemap = LazySourceMap::new(file_name(source), file_name(target));
+        let rust_code = if self.options.sourcemaps {
+            converter.convert_with_sourcemap(&nu_code, Some(&mut sourcemap))
+        } else {
+            converter.convert(&nu_code)
+        }
+        .map_err(|e| e.to_string())?;
+
+        self.write(target, &rust_code).map_err(|e| e.to_string())?;
+        if self.options.sourcemaps && !self.options.dry_run {
+            sourcemap
+                .save_to_file(target.with_extension("rs.map"))
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    fn create_dir(&self, dir: &Path) -> Result<(), WorkspaceError> {
+        if self.options.dry_run {
+            return Ok(());
+        }
+        fs::create_dir_all(dir).map_err(|source| WorkspaceError::FileWriteError {
+            path: dir.to_path_buf(),
+            source,
+        })
+    }
+
+    fn write(&self, path: &Path, content: &str) -> Result<(), WorkspaceError> {
+        if self.options.dry_run {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            self.create_dir(parent)?;
+        }
+        fs::write(path, content).map_err(|source| WorkspaceError::FileWriteError {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+}
+
+fn read(path: &Path) -> Result<String, WorkspaceError> {
+    fs::read_to_string(path).map_err(|source| WorkspaceError::FileReadError {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write_file(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_convert_single_project_with_sourcemaps() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("app");
+        write_file(
+            &input.join("Nu.toml"),
+            "[P]\nid = \"app\"\nv = \"0.1.0\"\ned = \"2021\"\n",
+        );
+        write_file(
+            &input.join("src/main.nu"),
+            "f main() {\n    println!(\"hi\");\n}\n",
+        );
+        write_file(&input.join("src/util/mod.nu"), "F one() -> i32 { 1 }\n");
+
+        let output = dir.path().join("out");
+        let options = ConvertOptions {
+            sourcemaps: true,
+            ..Default::default()
+        };
+        let converter = NuProjectConverter::new(options);
+        let report = converter.convert(&input, &output).unwrap();
+
+        assert_eq!(report.files_converted, 3);
+        assert!(fs::read_to_string(output.join("Cargo.toml"))
+            .unwrap()
+            .contains("[package]"));
+        assert!(output.join("src/main.rs").exists());
+        assert!(output.join("src/main.rs.map").exists());
+        assert!(output.join("src/util/mod.rs").exists());
+        let displays: Vec<&str> = report.files.iter().map(|f| f.display.as_str()).collect();
+        assert_eq!(displays, vec!["Cargo.toml", "main.rs", "util/mod.rs"]);
+    }
+
+    #[test]
+    fn test_convert_workspace_incrementally() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("ws");
+        write_file(&input.join("Nu.toml"), "[W]\nm = [\"crates/a\"]\n");
+        write_file(
+            &input.join("crates/a/Nu.toml"),
+            "[P]\nid = \"a\"\nv = \"0.1.0\"\n",
+        );
+        write_file(&input.join("crates/a/src/lib.nu"), "F a() {}\n");
+
+        let output = dir.path().join("out");
+        let converter = NuProjectConverter::new(ConvertOptions {
+            incremental: true,
+            ..Default::default()
+        });
+        let report = converter.convert(&input, &output).unwrap();
+        assert_eq!(report.members_converted, 1);
+        assert!(output.join("crates/a/src/lib.rs").exists());
+        assert_eq!(report.files[1].member.as_deref(), Some("crates/a"));
+
+        // 第二次转换：目标均比源文件新，全部跳过
+        let report = converter.convert(&input, &output).unwrap();
+        assert_eq!(report.files_converted, 0);
+        assert_eq!(report.files_skipped, 3);
+    }
+}
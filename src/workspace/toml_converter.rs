@@ -161,8 +161,20 @@ fn extract_section(line: &str) -> String {
     trimmed.to_string()
 }
 
+/// 点号键的首段：依赖表中是依赖名，保持不变；其余节（如 [P] 的 `version.workspace`）是键名
+fn dotted_key_head(head: &str, current_section: &str, convert: fn(&str) -> String) -> String {
+    let section = current_section.trim_matches(|c| c == '[' || c == ']');
+    let is_dependency_table =
+        section.ends_with("dependencies") || matches!(section, "D" | "DD" | "BD" | "W.D");
+    if is_dependency_table {
+        head.to_string()
+    } else {
+        convert(head)
+    }
+}
+
 /// 转换键值对行：Cargo -> Nu
-fn convert_kv_line_cargo_to_nu(line: &str, _current_section: &str) -> String {
+fn convert_kv_line_cargo_to_nu(line: &str, current_section: &str) -> String {
     let trimmed = line.trim();
 
     if let Some(eq_pos) = trimmed.find('=') {
@@ -174,10 +186,9 @@ fn convert_kv_line_cargo_to_nu(line: &str, _current_section: &str) -> String {
         if key.contains('.') {
             let parts: Vec<&str> = key.splitn(2, '.').collect();
             if parts.len() == 2 {
-                let dep_name = parts[0];
-                let sub_key = parts[1];
-                let converted_sub_key = convert_key_cargo_to_nu(sub_key);
-                return format!("{}{}.{} = {}", indent, dep_name, converted_sub_key, value);
+                let head = dotted_key_head(parts[0], current_section, convert_key_cargo_to_nu);
+                let converted_sub_key = convert_key_cargo_to_nu(parts[1]);
+                return format!("{}{}.{} = {}", indent, head, converted_sub_key, value);
             }
         }
 
@@ -197,7 +208,7 @@ fn convert_kv_line_cargo_to_nu(line: &str, _current_section: &str) -> String {
 }
 
 /// 转换键值对行：Nu -> Cargo
-fn convert_kv_line_nu_to_cargo(line: &str, _current_section: &str) -> String {
+fn convert_kv_line_nu_to_cargo(line: &str, current_section: &str) -> String {
     let trimmed = line.trim();
 
     if let Some(eq_pos) = trimmed.find('=') {
@@ -209,10 +220,9 @@ fn convert_kv_line_nu_to_cargo(line: &str, _current_section: &str) -> String {
         if key.contains('.') {
             let parts: Vec<&str> = key.splitn(2, '.').collect();
             if parts.len() == 2 {
-                let dep_name = parts[0];
-                let sub_key = parts[1];
-                let converted_sub_key = convert_key_nu_to_cargo(sub_key);
-                return format!("{}{}.{} = {}", indent, dep_name, converted_sub_key, value);
+                let head = dotted_key_head(parts[0], current_section, convert_key_nu_to_cargo);
+                let converted_sub_key = convert_key_nu_to_cargo(parts[1]);
+                return format!("{}{}.{} = {}", indent, head, converted_sub_key, value);
             }
         }
 
@@ -683,4 +693,23 @@ serde.workspace = true
         let restored = nu2cargo.convert(&nu);
         assert!(restored.contains("serde.workspace = true"));
     }
+
+    #[test]
+    fn test_roundtrip_inherited_package_field() {
+        let original = r#"[package]
+name = "member"
+version.workspace = true
+edition.workspace = true
+
+[dev-dependencies]
+version.workspace = true
+"#;
+        let nu = Cargo2NuConverter::new().convert(original);
+        assert!(nu.contains("[P]\nid = \"member\"\nv.w = true\ned.w = true"));
+        // 依赖表中的首段是依赖名，不做转换
+        assert!(nu.contains("[DD]\nversion.w = true"));
+
+        let restored = Nu2CargoConverter::new().convert(&nu);
+        assert_eq!(restored, original);
+    }
 }
@@ -238,6 +238,28 @@ pub struct ConvertOptions {
     pub exclude: Vec<String>,
     /// 仅包含成员
     pub only: Vec<String>,
+    /// 为每个生成的 .rs 写出 .rs.map 行映射
+    pub sourcemaps: bool,
+}
+
+/// 单个文件的转换结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileOutcome {
+    Converted,
+    Skipped,
+    Failed(String),
+}
+
+/// 单个文件的转换记录
+#[derive(Debug, Clone)]
+pub struct FileConversion {
+    /// 所属 workspace 成员（根 package 为 None）
+    pub member: Option<String>,
+    pub source: PathBuf,
+    pub target: PathBuf,
+    /// 相对于所在 package 的显示路径
+    pub display: String,
+    pub outcome: FileOutcome,
 }
 
 /// 转换报告
@@ -261,6 +283,8 @@ pub struct ConvertReport {
     pub warnings: Vec<String>,
     /// 错误列表
     pub errors: Vec<String>,
+    /// 逐文件记录
+    pub files: Vec<FileConversion>,
 }
 
 impl ConvertReport {